@@ -0,0 +1,20 @@
+-- Advance bookings of items for a future time window
+CREATE TABLE IF NOT EXISTS reservations (
+    id BIGSERIAL PRIMARY KEY,
+    item_id UUID NOT NULL REFERENCES items(id) ON DELETE CASCADE,
+    student_number TEXT NOT NULL,
+    student_name TEXT NOT NULL,
+    organization TEXT,
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ NOT NULL,
+    status TEXT NOT NULL DEFAULT 'reserved', -- reserved, fulfilled, cancelled
+    loan_id BIGINT REFERENCES loans(id) ON DELETE SET NULL,
+    remarks TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (start_time < end_time)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reservations_item_window ON reservations(item_id, start_time, end_time);
+CREATE INDEX IF NOT EXISTS idx_reservations_status ON reservations(status);
+CREATE INDEX IF NOT EXISTS idx_reservations_student_number ON reservations(student_number);
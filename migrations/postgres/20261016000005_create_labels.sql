@@ -0,0 +1,41 @@
+-- Issued label ids and their lifecycle
+CREATE TABLE IF NOT EXISTS label_batches (
+    id BIGSERIAL PRIMARY KEY,
+    quantity INTEGER NOT NULL,
+    first_label TEXT NOT NULL,
+    last_label TEXT NOT NULL,
+    record_type TEXT, -- qr, barcode, nothing
+    generated_by_id BIGINT,
+    generated_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS labels (
+    id TEXT PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'generated', -- generated, printed, attached, voided, reassigned
+    batch_id BIGINT REFERENCES label_batches(id) ON DELETE SET NULL,
+    item_id UUID REFERENCES items(id) ON DELETE SET NULL,
+    container_id TEXT REFERENCES containers(id) ON DELETE SET NULL,
+    generated_at TIMESTAMPTZ,
+    printed_at TIMESTAMPTZ,
+    attached_at TIMESTAMPTZ,
+    voided_at TIMESTAMPTZ,
+    voided_by TEXT,
+    void_reason TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_labels_status ON labels(status);
+CREATE INDEX IF NOT EXISTS idx_labels_batch_id ON labels(batch_id);
+
+-- 既に物品・コンテナに貼られているラベルは attached として取り込む
+INSERT INTO labels (id, status, item_id, attached_at, updated_at)
+SELECT label_id, 'attached', id, created_at, CURRENT_TIMESTAMP
+FROM items
+WHERE label_id IS NOT NULL AND label_id != ''
+ON CONFLICT (id) DO NOTHING;
+
+INSERT INTO labels (id, status, container_id, attached_at, updated_at)
+SELECT id, 'attached', id, created_at, CURRENT_TIMESTAMP
+FROM containers
+ON CONFLICT (id) DO NOTHING;
@@ -0,0 +1,24 @@
+-- Named label id sequences, each with its own prefix, width and alphabet.
+-- 'default' continues label_counter, which is no longer updated.
+CREATE TABLE IF NOT EXISTS label_sequences (
+    name TEXT PRIMARY KEY,
+    prefix TEXT NOT NULL DEFAULT '',
+    width INTEGER NOT NULL,
+    alphabet TEXT NOT NULL,
+    check_digit BOOLEAN NOT NULL DEFAULT FALSE,
+    current_value BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO label_sequences (name, prefix, width, alphabet, check_digit, current_value)
+SELECT 'default', '', 4, '0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ', FALSE, current_value
+FROM label_counter
+WHERE id = 1
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO label_sequences (name, prefix, width, alphabet, check_digit, current_value)
+VALUES ('default', '', 4, '0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ', FALSE, 0)
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE label_batches ADD COLUMN IF NOT EXISTS sequence TEXT NOT NULL DEFAULT 'default';
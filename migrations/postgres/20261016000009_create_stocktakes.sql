@@ -0,0 +1,35 @@
+-- Physical inventory checks. A session covers one location or a set of containers.
+CREATE TABLE IF NOT EXISTS stocktake_sessions (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    location TEXT, -- NULL when scoped to containers
+    status TEXT NOT NULL DEFAULT 'open', -- open, closed
+    opened_by_id BIGINT,
+    opened_by TEXT NOT NULL,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_by TEXT,
+    closed_at TIMESTAMPTZ,
+    corrections_applied INTEGER NOT NULL DEFAULT 0,
+    final_report TEXT -- JSON reconciliation report taken when the session was closed
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_session_containers (
+    session_id BIGINT NOT NULL REFERENCES stocktake_sessions(id) ON DELETE CASCADE,
+    container_id TEXT NOT NULL,
+    PRIMARY KEY (session_id, container_id)
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_scans (
+    id BIGSERIAL PRIMARY KEY,
+    session_id BIGINT NOT NULL REFERENCES stocktake_sessions(id) ON DELETE CASCADE,
+    label_id TEXT NOT NULL,
+    item_id UUID REFERENCES items(id) ON DELETE SET NULL, -- NULL for labels not registered to an item
+    location TEXT, -- where the label was found, if given
+    container_id TEXT,
+    scanned_by_id BIGINT,
+    scanned_by TEXT NOT NULL,
+    scanned_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_stocktake_sessions_status ON stocktake_sessions(status);
+CREATE INDEX IF NOT EXISTS idx_stocktake_scans_session ON stocktake_scans(session_id, scanned_at);
@@ -0,0 +1,5 @@
+-- Containers can sit inside other containers (a case in a rack in a storeroom).
+-- A nested container's location column mirrors the location of its outermost container.
+ALTER TABLE containers ADD COLUMN IF NOT EXISTS parent_container_id TEXT REFERENCES containers(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_containers_parent_container_id ON containers(parent_container_id);
@@ -0,0 +1,4 @@
+-- Expected return date for loans (NULL = no deadline)
+ALTER TABLE loans ADD COLUMN due_date TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_loans_due_date ON loans(due_date);
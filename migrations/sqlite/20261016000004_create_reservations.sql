@@ -0,0 +1,21 @@
+-- Advance bookings of items for a future time window
+CREATE TABLE IF NOT EXISTS reservations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    item_id TEXT NOT NULL,
+    student_number TEXT NOT NULL,
+    student_name TEXT NOT NULL,
+    organization TEXT,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'reserved', -- reserved, fulfilled, cancelled
+    loan_id INTEGER,
+    remarks TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+    FOREIGN KEY (loan_id) REFERENCES loans(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_reservations_item_window ON reservations(item_id, start_time, end_time);
+CREATE INDEX IF NOT EXISTS idx_reservations_status ON reservations(status);
+CREATE INDEX IF NOT EXISTS idx_reservations_student_number ON reservations(student_number);
@@ -0,0 +1,42 @@
+-- Issued label ids and their lifecycle
+CREATE TABLE IF NOT EXISTS label_batches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    quantity INTEGER NOT NULL,
+    first_label TEXT NOT NULL,
+    last_label TEXT NOT NULL,
+    record_type TEXT, -- qr, barcode, nothing
+    generated_by_id INTEGER,
+    generated_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS labels (
+    id TEXT PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'generated', -- generated, printed, attached, voided, reassigned
+    batch_id INTEGER,
+    item_id TEXT,
+    container_id TEXT,
+    generated_at TEXT,
+    printed_at TEXT,
+    attached_at TEXT,
+    voided_at TEXT,
+    voided_by TEXT,
+    void_reason TEXT,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (batch_id) REFERENCES label_batches(id) ON DELETE SET NULL,
+    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE SET NULL,
+    FOREIGN KEY (container_id) REFERENCES containers(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_labels_status ON labels(status);
+CREATE INDEX IF NOT EXISTS idx_labels_batch_id ON labels(batch_id);
+
+-- 既に物品・コンテナに貼られているラベルは attached として取り込む
+INSERT OR IGNORE INTO labels (id, status, item_id, attached_at, updated_at)
+SELECT label_id, 'attached', id, created_at, CURRENT_TIMESTAMP
+FROM items
+WHERE label_id IS NOT NULL AND label_id != '';
+
+INSERT OR IGNORE INTO labels (id, status, container_id, attached_at, updated_at)
+SELECT id, 'attached', id, created_at, CURRENT_TIMESTAMP
+FROM containers;
@@ -0,0 +1,12 @@
+-- Retired labels of relabeled items, so that old stickers and printed lists still resolve
+CREATE TABLE IF NOT EXISTS label_aliases (
+    label_id TEXT PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    replaced_by TEXT NOT NULL,
+    reason TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_label_aliases_item_id ON label_aliases(item_id);
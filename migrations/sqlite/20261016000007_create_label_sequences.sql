@@ -0,0 +1,22 @@
+-- Named label id sequences, each with its own prefix, width and alphabet.
+-- 'default' continues label_counter, which is no longer updated.
+CREATE TABLE IF NOT EXISTS label_sequences (
+    name TEXT PRIMARY KEY,
+    prefix TEXT NOT NULL DEFAULT '',
+    width INTEGER NOT NULL,
+    alphabet TEXT NOT NULL,
+    check_digit BOOLEAN NOT NULL DEFAULT 0,
+    current_value INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO label_sequences (name, prefix, width, alphabet, check_digit, current_value)
+SELECT 'default', '', 4, '0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ', 0, current_value
+FROM label_counter
+WHERE id = 1;
+
+INSERT OR IGNORE INTO label_sequences (name, prefix, width, alphabet, check_digit, current_value)
+VALUES ('default', '', 4, '0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ', 0, 0);
+
+ALTER TABLE label_batches ADD COLUMN sequence TEXT NOT NULL DEFAULT 'default';
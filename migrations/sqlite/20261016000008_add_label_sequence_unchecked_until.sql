@@ -0,0 +1,3 @@
+-- Label ids issued before check characters were enabled on a sequence.
+-- Numbers up to unchecked_until stay valid without a check character.
+ALTER TABLE label_sequences ADD COLUMN unchecked_until INTEGER;
@@ -0,0 +1,38 @@
+-- Physical inventory checks. A session covers one location or a set of containers.
+CREATE TABLE IF NOT EXISTS stocktake_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    location TEXT, -- NULL when scoped to containers
+    status TEXT NOT NULL DEFAULT 'open', -- open, closed
+    opened_by_id INTEGER,
+    opened_by TEXT NOT NULL,
+    opened_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_by TEXT,
+    closed_at TEXT,
+    corrections_applied INTEGER NOT NULL DEFAULT 0,
+    final_report TEXT -- JSON reconciliation report taken when the session was closed
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_session_containers (
+    session_id INTEGER NOT NULL,
+    container_id TEXT NOT NULL,
+    PRIMARY KEY (session_id, container_id),
+    FOREIGN KEY (session_id) REFERENCES stocktake_sessions(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL,
+    label_id TEXT NOT NULL,
+    item_id TEXT, -- NULL for labels not registered to an item
+    location TEXT, -- where the label was found, if given
+    container_id TEXT,
+    scanned_by_id INTEGER,
+    scanned_by TEXT NOT NULL,
+    scanned_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (session_id) REFERENCES stocktake_sessions(id) ON DELETE CASCADE,
+    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_stocktake_sessions_status ON stocktake_sessions(status);
+CREATE INDEX IF NOT EXISTS idx_stocktake_scans_session ON stocktake_scans(session_id, scanned_at);
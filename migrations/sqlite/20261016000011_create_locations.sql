@@ -0,0 +1,43 @@
+-- Master table of storage places (building > room > shelf).
+-- items.storage_location and containers.location keep the full path of the referenced row.
+CREATE TABLE IF NOT EXISTS locations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'room', -- building, room, shelf
+    parent_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (parent_id) REFERENCES locations(id) ON DELETE RESTRICT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_locations_parent_name ON locations(IFNULL(parent_id, 0), name);
+
+-- Every distinct free-text location becomes a top-level row
+INSERT OR IGNORE INTO locations (name)
+SELECT TRIM(storage_location) FROM items
+WHERE storage_location IS NOT NULL AND TRIM(storage_location) <> ''
+UNION
+SELECT TRIM(location) FROM containers
+WHERE location IS NOT NULL AND TRIM(location) <> '';
+
+ALTER TABLE items ADD COLUMN location_id INTEGER REFERENCES locations(id) ON DELETE SET NULL;
+ALTER TABLE containers ADD COLUMN location_id INTEGER REFERENCES locations(id) ON DELETE SET NULL;
+
+UPDATE items SET
+    storage_location = TRIM(storage_location),
+    location_id = (
+        SELECT l.id FROM locations l
+        WHERE l.parent_id IS NULL AND l.name = TRIM(items.storage_location)
+    )
+WHERE storage_location IS NOT NULL AND TRIM(storage_location) <> '';
+
+UPDATE containers SET
+    location = TRIM(location),
+    location_id = (
+        SELECT l.id FROM locations l
+        WHERE l.parent_id IS NULL AND l.name = TRIM(containers.location)
+    )
+WHERE location IS NOT NULL AND TRIM(location) <> '';
+
+CREATE INDEX IF NOT EXISTS idx_items_location_id ON items(location_id);
+CREATE INDEX IF NOT EXISTS idx_containers_location_id ON containers(location_id);
@@ -0,0 +1,19 @@
+-- Movement trail of items and containers. Rows written by one move operation share batch_id.
+CREATE TABLE IF NOT EXISTS transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    batch_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL, -- item, container
+    entity_id TEXT NOT NULL,
+    from_location TEXT,
+    from_container_id TEXT,
+    to_location TEXT,
+    to_container_id TEXT,
+    via_container_id TEXT, -- set when the entity moved along with this container
+    note TEXT,
+    moved_by_id INTEGER,
+    moved_by TEXT NOT NULL,
+    moved_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfers_entity ON transfers(entity_type, entity_id, moved_at);
+CREATE INDEX IF NOT EXISTS idx_transfers_batch ON transfers(batch_id);
@@ -0,0 +1,18 @@
+-- cable_colors.id was declared BIGSERIAL, which SQLite does not treat as a rowid alias,
+-- so every row had a NULL id. Rebuild the table with an autoincrementing id.
+CREATE TABLE cable_colors_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    hex_code TEXT,
+    description TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO cable_colors_new (id, name, hex_code, description, created_at, updated_at)
+SELECT COALESCE(id, rowid), name, hex_code, description, created_at, updated_at
+FROM cable_colors
+ORDER BY rowid;
+
+DROP TABLE cable_colors;
+ALTER TABLE cable_colors_new RENAME TO cable_colors;
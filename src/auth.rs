@@ -0,0 +1,99 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::{header, request::Parts, HeaderMap, Method},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CurrentUser, Role};
+use crate::AppState;
+
+/// 認証不要なエンドポイント（/api/v1 からの相対パス）
+const PUBLIC_ROUTES: &[(Method, &str)] = &[(Method::POST, "/auth/login")];
+
+/// リクエストに必要な最小の権限を返す。
+/// 参照系は viewer、貸出・返却・予約は lender、それ以外の更新系は admin とする。
+pub fn required_role(method: &Method, path: &str) -> Role {
+    if path.starts_with("/users") || path.starts_with("/audit") || path.starts_with("/admin") {
+        return Role::Admin;
+    }
+    if path.starts_with("/auth/") {
+        return Role::Viewer;
+    }
+    if matches!(*method, Method::GET | Method::HEAD) {
+        return Role::Viewer;
+    }
+    if path.starts_with("/loans") || path.starts_with("/reservations") {
+        return Role::Lender;
+    }
+    // 棚卸しのスキャンは貸出係も行う（開始・終了は admin）
+    if path.starts_with("/stocktakes") && path.ends_with("/scans") {
+        return Role::Lender;
+    }
+    Role::Admin
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// /api/v1 配下の全ルートに適用する認証・認可ミドルウェア
+pub async fn require_auth(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> AppResult<Response> {
+    let auth_service = &state.auth_service;
+
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().trim_start_matches("/api/v1").to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    if PUBLIC_ROUTES
+        .iter()
+        .any(|(method, route)| method == req.method() && *route == path)
+    {
+        return Ok(next.run(req).await);
+    }
+
+    let user = if auth_service.is_enabled() {
+        let token = bearer_token(req.headers())
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+        auth_service.authenticate(token).await?
+    } else {
+        CurrentUser::anonymous()
+    };
+
+    let required = required_role(req.method(), &path);
+    if user.role < required {
+        return Err(AppError::Forbidden(format!(
+            "This operation requires the '{}' role",
+            required.as_str()
+        )));
+    }
+
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+    }
+}
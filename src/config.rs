@@ -7,6 +7,10 @@ pub struct Config {
     pub database: DatabaseConfig,
     pub server: ServerConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub loan: LoanConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -18,6 +22,71 @@ pub struct DatabaseConfig {
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// 空の場合は全オリジンを許可する
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AuthConfig {
+    /// falseにすると全リクエストを匿名の管理者として扱う（ローカル開発用）
+    #[serde(default = "default_auth_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_session_ttl_hours")]
+    pub session_ttl_hours: i64,
+    /// ユーザーが1人もいない場合に起動時に作成する管理者
+    pub admin_username: Option<String>,
+    pub admin_password: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_auth_enabled(),
+            session_ttl_hours: default_session_ttl_hours(),
+            admin_username: None,
+            admin_password: None,
+        }
+    }
+}
+
+// 起動時に設定をログ出力するため、パスワードは伏せる
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("enabled", &self.enabled)
+            .field("session_ttl_hours", &self.session_ttl_hours)
+            .field("admin_username", &self.admin_username)
+            .field("admin_password", &self.admin_password.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+fn default_auth_enabled() -> bool {
+    true
+}
+
+fn default_session_ttl_hours() -> i64 {
+    24 * 7
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoanConfig {
+    /// 返却期限を指定せずに貸し出したときの貸出期間（日）。0なら期限を設けない
+    #[serde(default = "default_loan_period_days")]
+    pub default_period_days: i64,
+}
+
+impl Default for LoanConfig {
+    fn default() -> Self {
+        Self {
+            default_period_days: default_loan_period_days(),
+        }
+    }
+}
+
+fn default_loan_period_days() -> i64 {
+    7
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -28,12 +97,19 @@ pub struct StorageConfig {
     pub s3: Option<S3Config>,
     #[serde(default = "default_max_file_size")]
     pub max_file_size_mb: u64,
+    /// `POST /admin/restore` で受け取るバックアップの上限
+    #[serde(default = "default_max_restore_size")]
+    pub max_restore_size_mb: u64,
 }
 
 fn default_max_file_size() -> u64 {
     20 // Default 20MB
 }
 
+fn default_max_restore_size() -> u64 {
+    1024 // Default 1GB
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum StorageType {
@@ -97,6 +173,16 @@ impl Config {
             .parse::<u16>()
             .unwrap_or(8080);
 
+        let cors_allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
+            .map(|s| {
+                s.split([',', ' '])
+                    .map(str::trim)
+                    .filter(|o| !o.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
         let storage_type = env::var("STORAGE_TYPE").unwrap_or_else(|_| "local".to_string());
 
         let max_file_size_mb = env::var("STORAGE_MAX_FILE_SIZE_MB")
@@ -104,6 +190,11 @@ impl Config {
             .and_then(|s| s.parse().ok())
             .unwrap_or(5);
 
+        let max_restore_size_mb = env::var("STORAGE_MAX_RESTORE_SIZE_MB")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_max_restore_size);
+
         let storage = match storage_type.to_lowercase().as_str() {
             "s3" => {
                 let bucket_name = env::var("S3_BUCKET_NAME")
@@ -124,6 +215,7 @@ impl Config {
                         secret_access_key,
                     }),
                     max_file_size_mb,
+                    max_restore_size_mb,
                 }
             }
             _ => {
@@ -135,17 +227,41 @@ impl Config {
                     local: Some(LocalStorageConfig { path }),
                     s3: None,
                     max_file_size_mb,
+                    max_restore_size_mb,
                 }
             }
         };
 
+        let auth = AuthConfig {
+            enabled: env::var("AUTH_ENABLED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(default_auth_enabled),
+            session_ttl_hours: env::var("AUTH_SESSION_TTL_HOURS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(default_session_ttl_hours),
+            admin_username: env::var("AUTH_ADMIN_USERNAME").ok(),
+            admin_password: env::var("AUTH_ADMIN_PASSWORD").ok(),
+        };
+
+        let loan = LoanConfig {
+            default_period_days: env::var("LOAN_DEFAULT_PERIOD_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(default_loan_period_days),
+        };
+
         Ok(Config {
             database: DatabaseConfig { url: database_url },
             server: ServerConfig {
                 host: server_host,
                 port: server_port,
+                cors_allowed_origins,
             },
             storage,
+            auth,
+            loan,
         })
     }
 }
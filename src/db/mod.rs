@@ -6,6 +6,8 @@ use sqlx::migrate::Migrator;
 use crate::config::Config;
 use crate::error::AppResult;
 
+pub mod tables;
+
 #[derive(Clone)]
 pub enum DatabasePool {
     Postgres(PgPool),
@@ -14,8 +16,10 @@ pub enum DatabasePool {
 
 impl DatabasePool {
     pub async fn new(config: &Config) -> AppResult<Self> {
-        let database_url = &config.database.url;
+        Self::connect(&config.database.url).await
+    }
 
+    pub async fn connect(database_url: &str) -> AppResult<Self> {
         if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
             let pool = PgPoolOptions::new()
                 .max_connections(10)
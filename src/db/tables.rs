@@ -0,0 +1,589 @@
+//! バックエンドに依存しない形で全テーブルを読み書きする（データ移行用）。
+//!
+//! 値は JSON で持つ。UUID と時刻は文字列、真偽値は bool にそろえるので、
+//! SQLite（UUID は TEXT、真偽値は 0/1）と Postgres の間でそのまま受け渡せる。
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use sqlx::{Postgres, Row as _, Sqlite};
+use uuid::Uuid;
+
+use super::DatabasePool;
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnKind {
+    Text,
+    /// Postgres では UUID、SQLite では TEXT
+    Uuid,
+    /// Postgres の INTEGER（それ以外の整数は BIGINT）
+    Int,
+    BigInt,
+    /// Postgres の REAL
+    Real,
+    Bool,
+    Timestamp,
+}
+
+#[derive(Debug)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnKind,
+}
+
+#[derive(Debug)]
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [Column],
+    /// 主キー（読み出す順番にも使う）
+    pub key: &'static [&'static str],
+    /// 同じテーブルを指す列。親より子が先に来ることがあるので、全行を入れてから埋める
+    pub self_reference: Option<&'static str>,
+    /// `id` が連番（Postgres ではシーケンスを進め直す）
+    pub serial: bool,
+    /// マイグレーションが初期データを入れるテーブル
+    pub seeded: bool,
+}
+
+/// 1行分の値。並びは `Table::columns` と同じ
+pub type Row = Vec<Value>;
+
+macro_rules! columns {
+    ($($name:ident: $kind:ident),* $(,)?) => {
+        &[$(Column { name: stringify!($name), kind: ColumnKind::$kind }),*]
+    };
+}
+
+const fn table(name: &'static str, columns: &'static [Column]) -> Table {
+    Table {
+        name,
+        columns,
+        key: &["id"],
+        self_reference: None,
+        serial: true,
+        seeded: false,
+    }
+}
+
+/// 外部キーの参照先が先に来る順に並べた全テーブル
+pub const TABLES: &[Table] = &[
+    table(
+        "users",
+        columns!(
+            id: BigInt, username: Text, display_name: Text, password_hash: Text, role: Text,
+            is_active: Bool, created_at: Timestamp, updated_at: Timestamp,
+        ),
+    ),
+    table(
+        "sessions",
+        columns!(
+            id: BigInt, user_id: BigInt, token_hash: Text, expires_at: Timestamp,
+            created_at: Timestamp,
+        ),
+    ),
+    table(
+        "api_tokens",
+        columns!(
+            id: BigInt, user_id: BigInt, name: Text, token_hash: Text, last_used_at: Timestamp,
+            expires_at: Timestamp, created_at: Timestamp,
+        ),
+    ),
+    table(
+        "audit_events",
+        columns!(
+            id: BigInt, entity_type: Text, entity_id: Text, action: Text, actor_id: BigInt,
+            actor: Text, changes: Text, created_at: Timestamp,
+        ),
+    ),
+    Table {
+        seeded: true,
+        ..table(
+            "cable_colors",
+            columns!(
+                id: BigInt, name: Text, hex_code: Text, description: Text,
+                created_at: Timestamp, updated_at: Timestamp,
+            ),
+        )
+    },
+    table(
+        "connectors",
+        columns!(
+            id: BigInt, name: Text, gender: Text, description: Text, created_at: Timestamp,
+            updated_at: Timestamp,
+        ),
+    ),
+    table(
+        "tags",
+        columns!(
+            id: BigInt, name: Text, color: Text, description: Text, created_at: Timestamp,
+            updated_at: Timestamp,
+        ),
+    ),
+    Table {
+        self_reference: Some("parent_id"),
+        ..table(
+            "locations",
+            columns!(
+                id: BigInt, name: Text, kind: Text, parent_id: BigInt, created_at: Timestamp,
+                updated_at: Timestamp,
+            ),
+        )
+    },
+    Table {
+        self_reference: Some("parent_container_id"),
+        serial: false,
+        ..table(
+            "containers",
+            columns!(
+                id: Text, name: Text, description: Text, location: Text, created_at: Timestamp,
+                updated_at: Timestamp, is_disposed: Bool, image_url: Text,
+                parent_container_id: Text, location_id: BigInt,
+            ),
+        )
+    },
+    Table {
+        serial: false,
+        ..table(
+            "items",
+            columns!(
+                id: Uuid, name: Text, label_id: Text, model_number: Text, remarks: Text,
+                purchase_year: Int, purchase_amount: Real, durability_years: Int,
+                is_depreciation_target: Bool, connection_names: Text, cable_color_pattern: Text,
+                storage_location: Text, container_id: Text, storage_type: Text, is_on_loan: Bool,
+                qr_code_type: Text, is_disposed: Bool, image_url: Text, created_at: Timestamp,
+                updated_at: Timestamp, location_id: BigInt,
+            ),
+        )
+    },
+    Table {
+        key: &["item_id", "tag_id"],
+        serial: false,
+        ..table(
+            "item_tags",
+            columns!(item_id: Uuid, tag_id: BigInt, created_at: Timestamp),
+        )
+    },
+    table(
+        "loans",
+        columns!(
+            id: BigInt, item_id: Uuid, student_number: Text, student_name: Text,
+            organization: Text, loan_date: Timestamp, return_date: Timestamp, remarks: Text,
+            created_at: Timestamp, updated_at: Timestamp, due_date: Timestamp,
+        ),
+    ),
+    table(
+        "reservations",
+        columns!(
+            id: BigInt, item_id: Uuid, student_number: Text, student_name: Text,
+            organization: Text, start_time: Timestamp, end_time: Timestamp, status: Text,
+            loan_id: BigInt, remarks: Text, created_at: Timestamp, updated_at: Timestamp,
+        ),
+    ),
+    Table {
+        serial: false,
+        seeded: true,
+        ..table("label_counter", columns!(id: BigInt, current_value: BigInt))
+    },
+    Table {
+        key: &["name"],
+        serial: false,
+        seeded: true,
+        ..table(
+            "label_sequences",
+            columns!(
+                name: Text, prefix: Text, width: Int, alphabet: Text, check_digit: Bool,
+                current_value: BigInt, created_at: Timestamp, updated_at: Timestamp,
+                unchecked_until: BigInt,
+            ),
+        )
+    },
+    table(
+        "label_batches",
+        columns!(
+            id: BigInt, quantity: Int, first_label: Text, last_label: Text, record_type: Text,
+            generated_by_id: BigInt, generated_by: Text, created_at: Timestamp, sequence: Text,
+        ),
+    ),
+    Table {
+        serial: false,
+        ..table(
+            "labels",
+            columns!(
+                id: Text, status: Text, batch_id: BigInt, item_id: Uuid, container_id: Text,
+                generated_at: Timestamp, printed_at: Timestamp, attached_at: Timestamp,
+                voided_at: Timestamp, voided_by: Text, void_reason: Text, updated_at: Timestamp,
+            ),
+        )
+    },
+    Table {
+        key: &["label_id"],
+        serial: false,
+        ..table(
+            "label_aliases",
+            columns!(
+                label_id: Text, item_id: Uuid, replaced_by: Text, reason: Text,
+                created_by: Text, created_at: Timestamp,
+            ),
+        )
+    },
+    table(
+        "stocktake_sessions",
+        columns!(
+            id: BigInt, name: Text, location: Text, status: Text, opened_by_id: BigInt,
+            opened_by: Text, opened_at: Timestamp, closed_by: Text, closed_at: Timestamp,
+            corrections_applied: Int, final_report: Text,
+        ),
+    ),
+    Table {
+        key: &["session_id", "container_id"],
+        serial: false,
+        ..table(
+            "stocktake_session_containers",
+            columns!(session_id: BigInt, container_id: Text),
+        )
+    },
+    table(
+        "stocktake_scans",
+        columns!(
+            id: BigInt, session_id: BigInt, label_id: Text, item_id: Uuid, location: Text,
+            container_id: Text, scanned_by_id: BigInt, scanned_by: Text, scanned_at: Timestamp,
+        ),
+    ),
+    table(
+        "transfers",
+        columns!(
+            id: BigInt, batch_id: Uuid, entity_type: Text, entity_id: Text,
+            from_location: Text, from_container_id: Text, to_location: Text,
+            to_container_id: Text, via_container_id: Text, note: Text, moved_by_id: BigInt,
+            moved_by: Text, moved_at: Timestamp,
+        ),
+    ),
+];
+
+impl Table {
+    fn select_sql(&self) -> String {
+        let columns: Vec<_> = self.columns.iter().map(|column| column.name).collect();
+        format!(
+            "SELECT {} FROM {} ORDER BY {}",
+            columns.join(", "),
+            self.name,
+            self.key.join(", ")
+        )
+    }
+
+    /// `placeholder` は 1 始まりの番号からプレースホルダーを作る
+    fn insert_sql(&self, placeholder: fn(usize) -> String) -> String {
+        let columns: Vec<_> = self.columns.iter().map(|column| column.name).collect();
+        let values: Vec<_> = (1..=columns.len()).map(placeholder).collect();
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.name,
+            columns.join(", "),
+            values.join(", ")
+        )
+    }
+
+    fn update_self_reference_sql(&self, column: &str, placeholder: fn(usize) -> String) -> String {
+        format!(
+            "UPDATE {} SET {} = {} WHERE id = {}",
+            self.name,
+            column,
+            placeholder(1),
+            placeholder(2)
+        )
+    }
+
+    fn position(&self, column: &str) -> usize {
+        self.columns
+            .iter()
+            .position(|c| c.name == column)
+            .expect("column is listed in the table")
+    }
+}
+
+fn pg_placeholder(n: usize) -> String {
+    format!("${}", n)
+}
+
+fn sqlite_placeholder(n: usize) -> String {
+    format!("?{}", n)
+}
+
+impl DatabasePool {
+    pub async fn count_rows(&self, table: &Table) -> AppResult<i64> {
+        let sql = format!("SELECT COUNT(*) FROM {}", table.name);
+        let count = match self {
+            DatabasePool::Postgres(pool) => sqlx::query_scalar(&sql).fetch_one(pool).await?,
+            DatabasePool::Sqlite(pool) => sqlx::query_scalar(&sql).fetch_one(pool).await?,
+        };
+        Ok(count)
+    }
+
+    pub async fn read_rows(&self, table: &Table) -> AppResult<Vec<Row>> {
+        let sql = table.select_sql();
+        match self {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(&sql).fetch_all(pool).await?;
+                rows.iter()
+                    .map(|row| {
+                        table
+                            .columns
+                            .iter()
+                            .map(|column| read_pg(row, column))
+                            .collect()
+                    })
+                    .collect()
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(&sql).fetch_all(pool).await?;
+                rows.iter()
+                    .map(|row| {
+                        table
+                            .columns
+                            .iter()
+                            .map(|column| read_sqlite(table, row, column))
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 全テーブルの中身を入れ替える。途中で失敗したら何も変えない
+    pub async fn replace_rows(&self, data: &[(&Table, Vec<Row>)]) -> AppResult<()> {
+        match self {
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                for (table, _) in data.iter().rev() {
+                    sqlx::query(&format!("DELETE FROM {}", table.name))
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for (table, rows) in data {
+                    let insert = table.insert_sql(pg_placeholder);
+                    let deferred = table.self_reference.map(|column| table.position(column));
+                    for row in rows {
+                        let mut query = sqlx::query(&insert);
+                        for (i, (column, value)) in table.columns.iter().zip(row).enumerate() {
+                            let value = if Some(i) == deferred {
+                                &Value::Null
+                            } else {
+                                value
+                            };
+                            query = bind_pg(query, table, column, value)?;
+                        }
+                        query.execute(&mut *tx).await?;
+                    }
+                    if let (Some(column), Some(position)) = (table.self_reference, deferred) {
+                        let update = table.update_self_reference_sql(column, pg_placeholder);
+                        let id = &table.columns[0];
+                        for row in rows.iter().filter(|row| !row[position].is_null()) {
+                            let query = bind_pg(
+                                sqlx::query(&update),
+                                table,
+                                &table.columns[position],
+                                &row[position],
+                            )?;
+                            bind_pg(query, table, id, &row[0])?
+                                .execute(&mut *tx)
+                                .await?;
+                        }
+                    }
+                    if table.serial {
+                        // 値を指定して入れた ID の続きから採番させる
+                        sqlx::query(&format!(
+                            "SELECT setval(pg_get_serial_sequence('{0}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {0}",
+                            table.name
+                        ))
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                }
+                tx.commit().await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                // AUTOINCREMENT の採番位置（sqlite_sequence）は INSERT で自動的に進む
+                let mut tx = pool.begin().await?;
+                for (table, _) in data.iter().rev() {
+                    sqlx::query(&format!("DELETE FROM {}", table.name))
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for (table, rows) in data {
+                    let insert = table.insert_sql(sqlite_placeholder);
+                    let deferred = table.self_reference.map(|column| table.position(column));
+                    for row in rows {
+                        let mut query = sqlx::query(&insert);
+                        for (i, (column, value)) in table.columns.iter().zip(row).enumerate() {
+                            let value = if Some(i) == deferred {
+                                &Value::Null
+                            } else {
+                                value
+                            };
+                            query = bind_sqlite(query, table, column, value)?;
+                        }
+                        query.execute(&mut *tx).await?;
+                    }
+                    if let (Some(column), Some(position)) = (table.self_reference, deferred) {
+                        let update = table.update_self_reference_sql(column, sqlite_placeholder);
+                        let id = &table.columns[0];
+                        for row in rows.iter().filter(|row| !row[position].is_null()) {
+                            let query = bind_sqlite(
+                                sqlx::query(&update),
+                                table,
+                                &table.columns[position],
+                                &row[position],
+                            )?;
+                            bind_sqlite(query, table, id, &row[0])?
+                                .execute(&mut *tx)
+                                .await?;
+                        }
+                    }
+                }
+                tx.commit().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_pg(row: &PgRow, column: &Column) -> AppResult<Value> {
+    let name = column.name;
+    let value = match column.kind {
+        ColumnKind::Text => row.try_get::<Option<String>, _>(name)?.map(Value::from),
+        ColumnKind::Uuid => row
+            .try_get::<Option<Uuid>, _>(name)?
+            .map(|id| Value::from(id.to_string())),
+        ColumnKind::Int => row.try_get::<Option<i32>, _>(name)?.map(Value::from),
+        ColumnKind::BigInt => row.try_get::<Option<i64>, _>(name)?.map(Value::from),
+        // f32 をそのまま広げると 0.1 が 0.10000000149... になるので、10進の表記を経由する
+        ColumnKind::Real => row
+            .try_get::<Option<f32>, _>(name)?
+            .and_then(|v| v.to_string().parse::<f64>().ok())
+            .map(Value::from),
+        ColumnKind::Bool => row.try_get::<Option<bool>, _>(name)?.map(Value::from),
+        ColumnKind::Timestamp => row
+            .try_get::<Option<DateTime<Utc>>, _>(name)?
+            .map(timestamp_value),
+    };
+    Ok(value.unwrap_or(Value::Null))
+}
+
+fn read_sqlite(table: &Table, row: &SqliteRow, column: &Column) -> AppResult<Value> {
+    let name = column.name;
+    let value = match column.kind {
+        ColumnKind::Text => row.try_get::<Option<String>, _>(name)?.map(Value::from),
+        ColumnKind::Uuid => match row.try_get::<Option<String>, _>(name)? {
+            Some(text) => Some(Value::from(parse_uuid(table, column, &text)?.to_string())),
+            None => None,
+        },
+        ColumnKind::Int | ColumnKind::BigInt => {
+            row.try_get::<Option<i64>, _>(name)?.map(Value::from)
+        }
+        ColumnKind::Real => row.try_get::<Option<f64>, _>(name)?.map(Value::from),
+        ColumnKind::Bool => row.try_get::<Option<bool>, _>(name)?.map(Value::from),
+        ColumnKind::Timestamp => row
+            .try_get::<Option<DateTime<Utc>>, _>(name)?
+            .map(timestamp_value),
+    };
+    Ok(value.unwrap_or(Value::Null))
+}
+
+fn timestamp_value(at: DateTime<Utc>) -> Value {
+    Value::from(at.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+fn invalid(table: &Table, column: &Column, value: impl std::fmt::Display) -> AppError {
+    AppError::ValidationError(format!(
+        "{}.{}: unexpected value {} for {:?} column",
+        table.name, column.name, value, column.kind
+    ))
+}
+
+fn parse_uuid(table: &Table, column: &Column, text: &str) -> AppResult<Uuid> {
+    Uuid::parse_str(text).map_err(|_| invalid(table, column, format!("{:?}", text)))
+}
+
+/// JSON の値を列の型として取り出す。null は None
+enum Datum {
+    Text(Option<String>),
+    Uuid(Option<Uuid>),
+    Int(Option<i64>),
+    Real(Option<f64>),
+    Bool(Option<bool>),
+    Timestamp(Option<DateTime<Utc>>),
+}
+
+impl Datum {
+    fn decode(table: &Table, column: &Column, value: &Value) -> AppResult<Self> {
+        let datum = match (column.kind, value) {
+            (ColumnKind::Text, Value::Null) => Datum::Text(None),
+            (ColumnKind::Uuid, Value::Null) => Datum::Uuid(None),
+            (ColumnKind::Int | ColumnKind::BigInt, Value::Null) => Datum::Int(None),
+            (ColumnKind::Real, Value::Null) => Datum::Real(None),
+            (ColumnKind::Bool, Value::Null) => Datum::Bool(None),
+            (ColumnKind::Timestamp, Value::Null) => Datum::Timestamp(None),
+            (ColumnKind::Text, Value::String(text)) => Datum::Text(Some(text.clone())),
+            (ColumnKind::Uuid, Value::String(text)) => {
+                Datum::Uuid(Some(parse_uuid(table, column, text)?))
+            }
+            (ColumnKind::Int | ColumnKind::BigInt, Value::Number(n)) if n.is_i64() => {
+                Datum::Int(n.as_i64())
+            }
+            (ColumnKind::Real, Value::Number(n)) => Datum::Real(n.as_f64()),
+            (ColumnKind::Bool, Value::Bool(b)) => Datum::Bool(Some(*b)),
+            (ColumnKind::Timestamp, Value::String(text)) => Datum::Timestamp(Some(
+                DateTime::parse_from_rfc3339(text)
+                    .map_err(|_| invalid(table, column, value))?
+                    .with_timezone(&Utc),
+            )),
+            _ => return Err(invalid(table, column, value)),
+        };
+        Ok(datum)
+    }
+}
+
+type PgQuery<'q> = Query<'q, Postgres, PgArguments>;
+type SqliteQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+fn bind_pg<'q>(
+    query: PgQuery<'q>,
+    table: &Table,
+    column: &Column,
+    value: &Value,
+) -> AppResult<PgQuery<'q>> {
+    Ok(match Datum::decode(table, column, value)? {
+        Datum::Text(v) => query.bind(v),
+        Datum::Uuid(v) => query.bind(v),
+        Datum::Int(v) => match column.kind {
+            ColumnKind::Int => query.bind(
+                v.map(i32::try_from)
+                    .transpose()
+                    .map_err(|_| invalid(table, column, value))?,
+            ),
+            _ => query.bind(v),
+        },
+        Datum::Real(v) => query.bind(v.map(|v| v as f32)),
+        Datum::Bool(v) => query.bind(v),
+        Datum::Timestamp(v) => query.bind(v),
+    })
+}
+
+fn bind_sqlite<'q>(
+    query: SqliteQuery<'q>,
+    table: &Table,
+    column: &Column,
+    value: &Value,
+) -> AppResult<SqliteQuery<'q>> {
+    Ok(match Datum::decode(table, column, value)? {
+        Datum::Text(v) => query.bind(v),
+        Datum::Uuid(v) => query.bind(v.map(|id| id.to_string())),
+        Datum::Int(v) => query.bind(v),
+        Datum::Real(v) => query.bind(v),
+        Datum::Bool(v) => query.bind(v),
+        Datum::Timestamp(v) => query.bind(v),
+    })
+}
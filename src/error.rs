@@ -11,6 +11,12 @@ use sqlx::migrate::MigrateError;
 pub enum AppError {
     NotFound(String),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// 他の操作と競合した（貸出中の物品を貸し出そうとした、など）
+    Conflict(String),
+    /// ラベルIDのチェック文字が合わない（読み取り・入力の誤り）
+    InvalidChecksum(String),
     InternalServerError(String),
     DatabaseError(sqlx::Error),
     ConfigError(config::ConfigError),
@@ -25,6 +31,10 @@ impl fmt::Display for AppError {
         match self {
             AppError::NotFound(msg) => write!(f, "Not found: {msg}"),
             AppError::BadRequest(msg) => write!(f, "Bad request: {msg}"),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {msg}"),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {msg}"),
+            AppError::Conflict(msg) => write!(f, "Conflict: {msg}"),
+            AppError::InvalidChecksum(msg) => write!(f, "Invalid checksum: {msg}"),
             AppError::InternalServerError(msg) => write!(f, "Internal server error: {msg}"),
             AppError::DatabaseError(err) => write!(f, "Database error: {err}"),
             AppError::ConfigError(err) => write!(f, "Configuration error: {err}"),
@@ -43,6 +53,10 @@ impl IntoResponse for AppError {
         let (status, error_message) = match self {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::InvalidChecksum(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
             AppError::ValidationError(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::DatabaseError(ref err) => {
@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::error::AppResult;
+use crate::models::{AuditEventsListResponse, AuditFilters};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    50
+}
+
+pub async fn list_audit_events(
+    State(AppState { audit_service, .. }): State<AppState>,
+    Query(params): Query<AuditQuery>,
+) -> AppResult<Json<AuditEventsListResponse>> {
+    let filters = AuditFilters {
+        entity_type: params.entity_type,
+        entity_id: params.entity_id,
+        action: params.action,
+        actor: params.actor,
+        from: params.from,
+        to: params.to,
+    };
+
+    let response = audit_service
+        .list_events(&filters, params.page, params.per_page)
+        .await?;
+    Ok(Json(response))
+}
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use validator::Validate;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    ApiToken, CreateApiTokenRequest, CreateApiTokenResponse, CurrentUser, LoginRequest,
+    LoginResponse, User,
+};
+use crate::AppState;
+
+pub async fn login(
+    State(AppState { auth_service, .. }): State<AppState>,
+    Json(req): Json<LoginRequest>,
+) -> AppResult<Json<LoginResponse>> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let response = auth_service.login(req).await?;
+    Ok(Json(response))
+}
+
+pub async fn logout(
+    State(AppState { auth_service, .. }): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    if let Some(token) = crate::auth::bearer_token(&headers) {
+        auth_service.logout(token).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_me(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<User>> {
+    if !auth_service.is_enabled() {
+        return Err(AppError::NotFound(
+            "Authentication is disabled on this server".to_string(),
+        ));
+    }
+
+    let user = auth_service.get_user(current_user.id).await?;
+    Ok(Json(user))
+}
+
+pub async fn list_api_tokens(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<Vec<ApiToken>>> {
+    let tokens = auth_service.list_api_tokens(current_user.id).await?;
+    Ok(Json(tokens))
+}
+
+pub async fn create_api_token(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> AppResult<(StatusCode, Json<CreateApiTokenResponse>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    if !auth_service.is_enabled() {
+        return Err(AppError::BadRequest(
+            "Authentication is disabled on this server".to_string(),
+        ));
+    }
+
+    let response = auth_service
+        .create_api_token(current_user.id, req)
+        .await?;
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn delete_api_token(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<StatusCode> {
+    auth_service.delete_api_token(current_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,39 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+
+use crate::error::AppResult;
+use crate::services::RestoreSummary;
+use crate::AppState;
+
+pub async fn download_backup(
+    State(AppState { backup_service, .. }): State<AppState>,
+) -> AppResult<Response> {
+    let archive = backup_service.backup().await?;
+    let filename = format!("hyperdashi-backup-{}.tar", Utc::now().format("%Y%m%d-%H%M%S"));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(archive),
+    )
+        .into_response())
+}
+
+/// 本文はバックアップの tar アーカイブそのもの
+pub async fn restore_backup(
+    State(AppState { backup_service, .. }): State<AppState>,
+    archive: Bytes,
+) -> AppResult<Json<RestoreSummary>> {
+    Ok(Json(backup_service.restore(&archive).await?))
+}
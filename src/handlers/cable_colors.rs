@@ -8,8 +8,10 @@ use validator::Validate;
 
 use crate::error::AppResult;
 use crate::models::{
-    CableColor, CableColorsListResponse, CreateCableColorRequest, UpdateCableColorRequest,
+    CableColor, CableColorsListResponse, CreateCableColorRequest, CurrentUser,
+    UpdateCableColorRequest,
 };
+use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct CableColorsQuery {
@@ -28,15 +30,7 @@ fn default_per_page() -> u32 {
 }
 
 pub async fn list_cable_colors(
-    State((
-        _storage_service,
-        cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { cable_color_service, .. }): State<AppState>,
     Query(params): Query<CableColorsQuery>,
 ) -> AppResult<Json<CableColorsListResponse>> {
     let response = cable_color_service
@@ -47,15 +41,7 @@ pub async fn list_cable_colors(
 }
 
 pub async fn get_cable_color(
-    State((
-        _storage_service,
-        cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { cable_color_service, .. }): State<AppState>,
     Path(id): Path<i64>,
 ) -> AppResult<Json<CableColor>> {
     let cable_color = cable_color_service.get_cable_color(id).await?;
@@ -63,56 +49,35 @@ pub async fn get_cable_color(
 }
 
 pub async fn create_cable_color(
-    State((
-        _storage_service,
-        cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { cable_color_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<CreateCableColorRequest>,
 ) -> AppResult<(StatusCode, Json<CableColor>)> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let cable_color = cable_color_service.create_cable_color(req).await?;
+    let cable_color = cable_color_service.create_cable_color(req, &current_user).await?;
     Ok((StatusCode::CREATED, Json(cable_color)))
 }
 
 pub async fn update_cable_color(
-    State((
-        _storage_service,
-        cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { cable_color_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
     Json(req): Json<UpdateCableColorRequest>,
 ) -> AppResult<Json<CableColor>> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let cable_color = cable_color_service.update_cable_color(id, req).await?;
+    let cable_color = cable_color_service.update_cable_color(id, req, &current_user).await?;
     Ok(Json(cable_color))
 }
 
 pub async fn delete_cable_color(
-    State((
-        _storage_service,
-        cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { cable_color_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
 ) -> AppResult<StatusCode> {
-    cable_color_service.delete_cable_color(id).await?;
+    cable_color_service.delete_cable_color(id, &current_user).await?;
     Ok(StatusCode::NO_CONTENT)
 }
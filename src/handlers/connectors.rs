@@ -8,8 +8,10 @@ use validator::Validate;
 
 use crate::error::AppResult;
 use crate::models::{
-    Connector, ConnectorsListResponse, CreateConnectorRequest, UpdateConnectorRequest,
+    Connector, ConnectorsListResponse, CreateConnectorRequest, CurrentUser,
+    UpdateConnectorRequest,
 };
+use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct ConnectorsQuery {
@@ -28,15 +30,7 @@ fn default_per_page() -> u32 {
 }
 
 pub async fn list_connectors(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { connector_service, .. }): State<AppState>,
     Query(params): Query<ConnectorsQuery>,
 ) -> AppResult<Json<ConnectorsListResponse>> {
     let response = connector_service
@@ -47,15 +41,7 @@ pub async fn list_connectors(
 }
 
 pub async fn get_connector(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { connector_service, .. }): State<AppState>,
     Path(id): Path<i64>,
 ) -> AppResult<Json<Connector>> {
     let connector = connector_service.get_connector(id).await?;
@@ -63,56 +49,35 @@ pub async fn get_connector(
 }
 
 pub async fn create_connector(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { connector_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<CreateConnectorRequest>,
 ) -> AppResult<(StatusCode, Json<Connector>)> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let connector = connector_service.create_connector(req).await?;
+    let connector = connector_service.create_connector(req, &current_user).await?;
     Ok((StatusCode::CREATED, Json(connector)))
 }
 
 pub async fn update_connector(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { connector_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
     Json(req): Json<UpdateConnectorRequest>,
 ) -> AppResult<Json<Connector>> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let connector = connector_service.update_connector(id, req).await?;
+    let connector = connector_service.update_connector(id, req, &current_user).await?;
     Ok(Json(connector))
 }
 
 pub async fn delete_connector(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { connector_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
 ) -> AppResult<StatusCode> {
-    connector_service.delete_connector(id).await?;
+    connector_service.delete_connector(id, &current_user).await?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -6,10 +6,12 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use crate::error::AppError;
+use crate::error::{AppError, AppResult};
 use crate::models::{
-    Container, ContainerWithItemCount, ContainersListResponse, CreateContainerRequest, UpdateContainerRequest,
+    Container, ContainerTreeNode, ContainersListResponse, CreateContainerRequest, CurrentUser,
+    MoveContainerRequest, MoveContainerResponse, Transfer, UpdateContainerRequest,
 };
+use crate::AppState;
 
 
 #[derive(Debug, Deserialize)]
@@ -50,24 +52,26 @@ pub struct UpdateContainerResponse {
 }
 
 pub async fn create_container(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(request): Json<CreateContainerRequest>,
-) -> Result<(StatusCode, Json<CreateContainerResponse>), StatusCode> {
-    if request.validate().is_err() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+) -> AppResult<(StatusCode, Json<CreateContainerResponse>)> {
+    request
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    match container_service.create_container(request).await {
-        Ok(container) => Ok((
-            StatusCode::CREATED,
-            Json(CreateContainerResponse { container }),
-        )),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    // ラベルの形式が合わない場合などは、理由がわかるようにエラーをそのまま返す
+    let container = container_service
+        .create_container(request, &current_user)
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateContainerResponse { container }),
+    ))
 }
 
 pub async fn get_container(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<Json<GetContainerResponse>, StatusCode> {
     match container_service.get_container(&id).await {
@@ -77,7 +81,7 @@ pub async fn get_container(
 }
 
 pub async fn list_containers(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
     Query(query): Query<ListContainersQuery>,
 ) -> Result<Json<ContainersListResponse>, StatusCode> {
     let location_filter = query.location.as_deref();
@@ -107,28 +111,61 @@ pub async fn list_containers(
 }
 
 pub async fn update_container(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<String>,
     Json(request): Json<UpdateContainerRequest>,
-) -> Result<Json<UpdateContainerResponse>, StatusCode> {
-    if request.validate().is_err() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+) -> AppResult<Json<UpdateContainerResponse>> {
+    request
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    match container_service.update_container(&id, request).await {
-        Ok(container) => Ok(Json(UpdateContainerResponse { container })),
-        Err(_) => Err(StatusCode::NOT_FOUND),
-    }
+    // 入れ子の循環などは理由がわかるようにエラーをそのまま返す
+    let container = container_service
+        .update_container(&id, request, &current_user)
+        .await?;
+    Ok(Json(UpdateContainerResponse { container }))
 }
 
 pub async fn delete_container(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<String>,
-) -> Result<StatusCode, StatusCode> {
-    match container_service.delete_container(&id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(_) => Err(StatusCode::NOT_FOUND),
-    }
+) -> AppResult<StatusCode> {
+    container_service.delete_container(&id, &current_user).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// コンテナと、その中に入っているコンテナを入れ子のまま返す
+pub async fn get_container_tree(
+    State(AppState { container_service, .. }): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<ContainerTreeNode>> {
+    Ok(Json(container_service.get_container_tree(&id).await?))
+}
+
+pub async fn move_container(
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<String>,
+    Json(request): Json<MoveContainerRequest>,
+) -> AppResult<Json<MoveContainerResponse>> {
+    request
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    Ok(Json(
+        container_service
+            .move_container(&id, request, &current_user)
+            .await?,
+    ))
+}
+
+pub async fn list_container_transfers(
+    State(AppState { container_service, .. }): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<Transfer>>> {
+    Ok(Json(container_service.list_transfers(&id).await?))
 }
 
 #[derive(Debug, Serialize)]
@@ -137,7 +174,7 @@ pub struct CheckContainerIdResponse {
 }
 
 pub async fn check_container_id(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<Json<CheckContainerIdResponse>, StatusCode> {
     match container_service.check_container_id_exists(&id).await {
@@ -152,7 +189,7 @@ pub struct GetContainersByLocationResponse {
 }
 
 pub async fn get_containers_by_location(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
     Path(location): Path<String>,
 ) -> Result<Json<GetContainersByLocationResponse>, StatusCode> {
     match container_service.get_containers_by_location(&location).await {
@@ -167,10 +204,11 @@ pub struct BulkDeleteContainersRequest {
 }
 
 pub async fn bulk_delete_containers(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(request): Json<BulkDeleteContainersRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    match container_service.bulk_delete_containers(&request.ids).await {
+    match container_service.bulk_delete_containers(&request.ids, &current_user).await {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(AppError::BadRequest(msg)) => {
             tracing::warn!("Bad request in bulk_delete_containers: {}", msg);
@@ -187,11 +225,12 @@ pub struct BulkUpdateContainersDisposedStatusRequest {
 }
 
 pub async fn bulk_update_containers_disposed_status(
-    State((_storage, _cable, _item_service, _loan, container_service, _connector, _tag)): State<crate::AppState>,
+    State(AppState { container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(request): Json<BulkUpdateContainersDisposedStatusRequest>,
 ) -> Result<StatusCode, StatusCode> {
     match container_service
-        .bulk_update_disposed_status(&request.ids, request.is_disposed)
+        .bulk_update_disposed_status(&request.ids, request.is_disposed, &current_user)
         .await
     {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
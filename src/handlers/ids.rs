@@ -4,7 +4,8 @@ use axum::{
 };
 use serde::Serialize;
 
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
+use crate::AppState;
 
 #[derive(Serialize)]
 pub struct IdCheckResponse {
@@ -21,16 +22,17 @@ pub struct DuplicateItem {
 
 pub async fn check_global_id(
     Path(id): Path<String>,
-    State((
-        _storage_service,
-        _cable_color_service,
-        item_service,
-        _loan_service,
-        container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { item_service, container_service, label_service, .. }): State<AppState>,
 ) -> AppResult<Json<IdCheckResponse>> {
+    // 読み間違えたIDを「未使用」と答えないよう、チェック文字が合わなければエラーにする。
+    // ただしコンテナIDがラベルの形式に見えてチェック文字が合わないこともあるので、
+    // チェック文字の誤りはコンテナも見つからなかったときに返す
+    let label_error = match label_service.verify_label(&id).await {
+        Ok(()) => None,
+        Err(e @ AppError::InvalidChecksum(_)) => Some(e),
+        Err(e) => return Err(e),
+    };
+
     let mut found_in = Vec::new();
     let mut duplicates = Vec::new();
     let mut exists = false;
@@ -55,6 +57,10 @@ pub async fn check_global_id(
         exists = true;
     }
 
+    if let Some(e) = label_error.filter(|_| !exists) {
+        return Err(e);
+    }
+
     Ok(Json(IdCheckResponse {
         exists,
         found_in,
@@ -7,6 +7,7 @@ use axum::{
 use serde::{Deserialize, Serialize};
 
 use crate::error::AppResult;
+use crate::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImageUploadResponse {
@@ -16,15 +17,7 @@ pub struct ImageUploadResponse {
 }
 
 pub async fn upload_image(
-    State((
-        storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        _tag_service,
-    )): State<crate::AppState>,
+    State(AppState { storage_service, .. }): State<AppState>,
     mut multipart: Multipart,
 ) -> AppResult<(StatusCode, Json<ImageUploadResponse>)> {
     tracing::info!("Starting image upload process");
@@ -135,7 +128,7 @@ pub async fn upload_image(
 }
 
 pub async fn delete_image(
-    State((storage_service, _, _, _, _, _, _)): State<crate::AppState>,
+    State(AppState { storage_service, .. }): State<AppState>,
     Path(filename): Path<String>,
 ) -> AppResult<StatusCode> {
     tracing::info!("Attempting to delete image: {}", filename);
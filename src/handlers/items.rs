@@ -1,4 +1,5 @@
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
     Json,
@@ -8,7 +9,13 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::error::AppResult;
-use crate::models::{CreateItemRequest, Item, ItemsListResponse, UpdateItemRequest};
+use crate::models::{
+    BulkMoveItemsRequest, BulkMoveItemsResponse, CreateItemRequest, CurrentUser, Item,
+    ItemByLabel, ItemHistoryResponse, ItemImportMode, ItemImportReport, ItemsListResponse,
+    RelabelItemRequest, Transfer, UpdateItemRequest,
+};
+use crate::services::item_csv::{self, CsvEncoding};
+use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct ItemsQuery {
@@ -32,7 +39,7 @@ fn default_per_page() -> u32 {
 }
 
 pub async fn list_items(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
     Query(params): Query<ItemsQuery>,
 ) -> AppResult<Json<ItemsListResponse>> {
     let response = item_service
@@ -51,7 +58,7 @@ pub async fn list_items(
 }
 
 pub async fn export_items_csv(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
     Query(params): Query<ItemsQuery>,
 ) -> AppResult<(HeaderMap, String)> {
     let items = item_service
@@ -79,66 +86,131 @@ pub async fn export_items_csv(
     Ok((headers, csv))
 }
 
+#[derive(Deserialize)]
+pub struct ImportItemsQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub mode: ItemImportMode,
+    #[serde(default)]
+    pub encoding: CsvEncoding,
+}
+
+/// 本文は `export_items_csv` と同じ形式の CSV。
+/// エラーのある行があれば何も書き込まず、dry run でなければ 422 を返す
+pub async fn import_items_csv(
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Query(params): Query<ImportItemsQuery>,
+    body: Bytes,
+) -> AppResult<(StatusCode, Json<ItemImportReport>)> {
+    let text = item_csv::decode(&body, params.encoding)?;
+    let csv = item_csv::parse(&text)?;
+    let report = item_service
+        .import_items(csv, params.mode, params.dry_run, &current_user)
+        .await?;
+
+    let status = if report.errors.is_empty() || report.dry_run {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok((status, Json(report)))
+}
+
 pub async fn get_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Item>> {
     let item = item_service.get_item(id).await?;
     Ok(Json(item))
 }
 
+pub async fn get_item_history(
+    State(AppState { item_service, .. }): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ItemHistoryResponse>> {
+    let history = item_service.get_item_history(id).await?;
+    Ok(Json(history))
+}
+
+pub async fn list_item_transfers(
+    State(AppState { item_service, .. }): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Vec<Transfer>>> {
+    Ok(Json(item_service.list_item_transfers(id).await?))
+}
+
 pub async fn get_item_by_label(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
     Path(label_id): Path<String>,
-) -> AppResult<Json<Item>> {
-    let item = item_service.get_item_by_label(&label_id).await?;
+) -> AppResult<Json<ItemByLabel>> {
+    let item = item_service.lookup_label(&label_id).await?;
     Ok(Json(item))
 }
 
 pub async fn create_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<CreateItemRequest>,
 ) -> AppResult<(StatusCode, Json<Item>)> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let item = item_service.create_item(req).await?;
+    let item = item_service.create_item(req, &current_user).await?;
     Ok((StatusCode::CREATED, Json(item)))
 }
 
 pub async fn update_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
     Json(req): Json<UpdateItemRequest>,
 ) -> AppResult<Json<Item>> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let item = item_service.update_item(id, req).await?;
+    let item = item_service.update_item(id, req, &current_user).await?;
     Ok(Json(item))
 }
 
 pub async fn delete_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> AppResult<StatusCode> {
-    item_service.delete_item(id).await?;
+    item_service.delete_item(id, &current_user).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn dispose_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Item>> {
-    let item = item_service.dispose_item(id).await?;
+    let item = item_service.dispose_item(id, &current_user).await?;
     Ok(Json(item))
 }
 
 pub async fn undispose_item(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Item>> {
-    let item = item_service.undispose_item(id).await?;
+    let item = item_service.undispose_item(id, &current_user).await?;
+    Ok(Json(item))
+}
+
+pub async fn relabel_item(
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<RelabelItemRequest>,
+) -> AppResult<Json<Item>> {
+    req.validate()
+        .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
+
+    let item = item_service.relabel_item(id, req, &current_user).await?;
     Ok(Json(item))
 }
 
@@ -148,14 +220,14 @@ pub struct SuggestionsResponse {
 }
 
 pub async fn get_connection_names_suggestions(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
 ) -> AppResult<Json<SuggestionsResponse>> {
     let suggestions = item_service.get_connection_names_suggestions().await?;
     Ok(Json(SuggestionsResponse { suggestions }))
 }
 
 pub async fn get_storage_locations_suggestions(
-    State((_storage_service, _cable_color_service, item_service, _loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
 ) -> AppResult<Json<SuggestionsResponse>> {
     let suggestions = item_service.get_storage_locations_suggestions().await?;
     Ok(Json(SuggestionsResponse { suggestions }))
@@ -164,7 +236,8 @@ pub async fn get_storage_locations_suggestions(
 use axum::extract::Multipart;
 
 pub async fn add_item_image(
-    State((storage, _cable, item_service, _loan, _container, _connector, _tag)): State<crate::AppState>,
+    State(AppState { storage_service: storage, item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<String>,
     mut multipart: Multipart,
 ) -> Result<Json<Item>, StatusCode> {
@@ -177,7 +250,7 @@ pub async fn add_item_image(
             
             match storage.upload(data.to_vec(), &file_name, &content_type).await {
                 Ok(image_url) => {
-                    match item_service.update_item_image(&id, &image_url).await {
+                    match item_service.update_item_image(&id, &image_url, &current_user).await {
                         Ok(item) => return Ok(Json(item)),
                         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
                     }
@@ -196,10 +269,11 @@ pub struct BulkDeleteItemsRequest {
 }
 
 pub async fn bulk_delete_items(
-    State((_storage, _cable, item_service, _loan, _container, _connector, _tag)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(request): Json<BulkDeleteItemsRequest>,
 ) -> AppResult<StatusCode> {
-    item_service.bulk_delete_items(&request.ids).await?;
+    item_service.bulk_delete_items(&request.ids, &current_user).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -210,15 +284,28 @@ pub struct BulkUpdateItemsDisposedStatusRequest {
 }
 
 pub async fn bulk_update_items_disposed_status(
-    State((_storage, _cable, item_service, _loan, _container, _connector, _tag)): State<crate::AppState>,
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(request): Json<BulkUpdateItemsDisposedStatusRequest>,
 ) -> AppResult<StatusCode> {
     item_service
-        .bulk_update_disposed_status(&request.ids, request.is_disposed)
+        .bulk_update_disposed_status(&request.ids, request.is_disposed, &current_user)
         .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub async fn bulk_move_items(
+    State(AppState { item_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<BulkMoveItemsRequest>,
+) -> AppResult<Json<BulkMoveItemsResponse>> {
+    request
+        .validate()
+        .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
+
+    Ok(Json(item_service.bulk_move_items(request, &current_user).await?))
+}
+
 fn csv_escape(value: &str) -> String {
     let needs_quotes =
         value.contains(',') || value.contains('"') || value.contains('\n') || value.contains('\r');
@@ -231,7 +318,8 @@ fn csv_escape(value: &str) -> String {
 }
 
 fn items_to_csv(items: &[Item]) -> String {
-    // dashi互換: dashi-client/src/components/csv/ItemCsvButton.tsx の列・並びに合わせる
+    // dashi互換: dashi-client/src/components/csv/ItemCsvButton.tsx の列・並びに合わせる。
+    // 末尾の ラベルID は dashi にはなく、取り込み（mode=upsert）で同じ物品を更新するためのもの
     let headers = [
         "型番",
         "物品名",
@@ -242,6 +330,7 @@ fn items_to_csv(items: &[Item]) -> String {
         "使用時期",
         "年間必要数",
         "備考",
+        "ラベルID",
     ];
 
     let mut lines: Vec<String> = Vec::with_capacity(items.len() + 1);
@@ -273,6 +362,8 @@ fn items_to_csv(items: &[Item]) -> String {
             "1".to_string(),
             // 備考
             "".to_string(),
+            // ラベルID
+            item.label_id.clone(),
         ];
 
         let escaped_row: Vec<String> = fields.iter().map(|v| csv_escape(v)).collect();
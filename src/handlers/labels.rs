@@ -1,21 +1,40 @@
 use crate::error::AppError;
+use crate::models::{
+    CreateLabelSequenceRequest, CurrentUser, Item, LabelBatch, LabelFilters, LabelRecord,
+    LabelSequence, LabelStatus, LabelsListResponse, VoidLabelRequest,
+};
+use crate::services::label_codes;
+use crate::services::label_printer::{self, TapePreset};
+use crate::services::label_scheme::DEFAULT_SEQUENCE;
+use crate::services::label_sheet::{self, SheetLabel, SheetLayout};
 use crate::AppState;
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use validator::Validate;
 
 #[derive(Debug, Deserialize)]
 pub struct GenerateLabelsRequest {
     pub quantity: u32,
     pub record_type: String, // "qr", "barcode", or "nothing"
+    /// 採番ルールの名前（省略時は "default"）
+    pub sequence: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct GenerateLabelsResponse {
     pub visible_ids: Vec<String>,
+    pub batch_id: i64,
 }
 
 pub async fn generate_labels(
     State(state): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<GenerateLabelsRequest>,
 ) -> Result<Json<GenerateLabelsResponse>, AppError> {
     // Validate quantity
@@ -32,13 +51,20 @@ pub async fn generate_labels(
     }
 
     // Generate sequential label IDs
-    let visible_ids = state
-        .2
-        .generate_label_ids(req.quantity)
-        .await
-        .map_err(|e| AppError::InternalServer(e.to_string()))?;
+    let (batch, visible_ids) = state
+        .label_service
+        .generate_batch(
+            req.sequence.as_deref().unwrap_or(DEFAULT_SEQUENCE),
+            req.quantity,
+            Some(&req.record_type),
+            &current_user,
+        )
+        .await?;
 
-    Ok(Json(GenerateLabelsResponse { visible_ids }))
+    Ok(Json(GenerateLabelsResponse {
+        visible_ids,
+        batch_id: batch.id,
+    }))
 }
 
 #[derive(Debug, Serialize)]
@@ -46,16 +72,429 @@ pub struct LabelInfo {
     pub id: String,
     pub used: bool,
     pub item_name: Option<String>,
+    /// 発行記録があるラベルの状態
+    pub status: Option<LabelStatus>,
 }
 
 pub async fn get_label_info(
     State(state): State<AppState>,
 ) -> Result<Json<Vec<LabelInfo>>, AppError> {
     let labels = state
-        .2
+        .label_service
         .get_all_labels()
         .await
         .map_err(|e| AppError::InternalServer(e.to_string()))?;
 
     Ok(Json(labels))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct LabelCodeQuery {
+    /// QRコードの一辺のピクセル数
+    pub size: Option<u32>,
+    /// バーコードのバーの高さ（ピクセル）
+    pub height: Option<u32>,
+}
+
+const DEFAULT_QR_SIZE: u32 = 256;
+const DEFAULT_BARCODE_HEIGHT: u32 = 80;
+const BARCODE_MODULE_WIDTH: u32 = 2;
+
+pub async fn get_label_qr_svg(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LabelCodeQuery>,
+) -> Result<Response, AppError> {
+    ensure_code_type(&state, &id, "qr").await?;
+    let size = bounded(query.size, DEFAULT_QR_SIZE, 64, 2048, "size")?;
+    let svg = label_codes::qr_svg(&id, size)?;
+
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+pub async fn get_label_qr_png(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LabelCodeQuery>,
+) -> Result<Response, AppError> {
+    ensure_code_type(&state, &id, "qr").await?;
+    let size = bounded(query.size, DEFAULT_QR_SIZE, 64, 2048, "size")?;
+    let png = label_codes::qr_png(&id, size)?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+pub async fn get_label_barcode_svg(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LabelCodeQuery>,
+) -> Result<Response, AppError> {
+    ensure_code_type(&state, &id, "barcode").await?;
+    let height = bounded(query.height, DEFAULT_BARCODE_HEIGHT, 20, 600, "height")?;
+    let svg = label_codes::code128_svg(&id, BARCODE_MODULE_WIDTH, height)?;
+
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelPrintQuery {
+    /// "zpl" または "brother"
+    pub format: Option<String>,
+    /// "12mm" または "24mm"
+    pub tape: Option<String>,
+    /// "qr", "barcode", "nothing"。省略時は物品の qr_code_type に従う
+    pub code: Option<String>,
+}
+
+/// 1枚のラベルをプリンタにそのまま送れるバイト列で返す。ラベルの状態は変えない
+pub async fn print_label(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LabelPrintQuery>,
+) -> Result<Response, AppError> {
+    state
+        .label_service
+        .ensure_not_voided(std::slice::from_ref(&id))
+        .await?;
+    printer_data(&state, &id, query).await
+}
+
+/// [`print_label`] と同じバイト列を返し、ラベルを印刷済みとして記録する
+pub async fn record_label_print(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<LabelPrintQuery>,
+) -> Result<Response, AppError> {
+    let response = printer_data(&state, &id, req).await?;
+    state.label_service.mark_printed(std::slice::from_ref(&id)).await?;
+    Ok(response)
+}
+
+async fn printer_data(
+    state: &AppState,
+    id: &str,
+    query: LabelPrintQuery,
+) -> Result<Response, AppError> {
+    label_printer::ensure_printable(id)?;
+
+    let tape_name = query.tape.as_deref().unwrap_or("24mm");
+    let tape = TapePreset::from_name(tape_name).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Unknown tape '{}' (available: 12mm, 24mm)",
+            tape_name
+        ))
+    })?;
+
+    let code_type = match query.code {
+        Some(code) => {
+            if !["qr", "barcode", "nothing"].contains(&code.as_str()) {
+                return Err(AppError::BadRequest("Invalid code type".to_string()));
+            }
+            if code != "nothing" {
+                ensure_code_type(state, id, &code).await?;
+            }
+            code
+        }
+        None => match state.item_service.get_item_by_label(id).await {
+            Ok(item) => match item.qr_code_type.as_deref() {
+                Some("barcode") => "barcode".to_string(),
+                Some("none") => "nothing".to_string(),
+                _ => "qr".to_string(),
+            },
+            Err(AppError::NotFound(_)) => "qr".to_string(),
+            Err(e) => return Err(e),
+        },
+    };
+
+    let (body, extension) = match query.format.as_deref().unwrap_or("zpl") {
+        "zpl" => (
+            label_printer::zpl(id, &code_type, tape)?.into_bytes(),
+            "zpl",
+        ),
+        "brother" => (label_printer::brother_raster(id, &code_type, tape)?, "bin"),
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown format '{}' (available: zpl, brother)",
+                other
+            )))
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", id, extension),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// ラベルが物品に使われている場合、その物品の qr_code_type と要求された形式が合っているか確認する。
+/// 未使用のラベルはどの形式でも生成できる
+async fn ensure_code_type(
+    state: &AppState,
+    label_id: &str,
+    requested: &str,
+) -> Result<(), AppError> {
+    match state.item_service.get_item_by_label(label_id).await {
+        Ok(item) => match item.qr_code_type.as_deref() {
+            Some("none") => Err(AppError::BadRequest(format!(
+                "Label {} is configured without a code",
+                label_id
+            ))),
+            Some(code_type) if code_type != requested => Err(AppError::BadRequest(format!(
+                "Label {} is configured for '{}'",
+                label_id, code_type
+            ))),
+            _ => Ok(()),
+        },
+        Err(AppError::NotFound(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn bounded(
+    value: Option<u32>,
+    default: u32,
+    min: u32,
+    max: u32,
+    name: &str,
+) -> Result<u32, AppError> {
+    let value = value.unwrap_or(default);
+    if !(min..=max).contains(&value) {
+        return Err(AppError::BadRequest(format!(
+            "{} must be between {} and {}",
+            name, min, max
+        )));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelSheetRequest {
+    /// 印刷するラベルID。省略した場合は `quantity` 件を新しく発行する
+    #[serde(default)]
+    pub label_ids: Vec<String>,
+    pub quantity: Option<u32>,
+    /// `quantity` で発行するときの採番ルール
+    pub sequence: Option<String>,
+    /// 台紙のプリセット名（a4_12, a4_24, a4_44, a4_65）
+    pub layout: Option<String>,
+    /// プリセットの代わりに寸法を直接指定する
+    pub custom_layout: Option<SheetLayout>,
+    /// 物品に qr_code_type が設定されていないラベルに使う形式（"qr", "barcode", "nothing"）
+    #[serde(default = "default_sheet_record_type")]
+    pub record_type: String,
+    #[serde(default)]
+    pub include_item_name: bool,
+    /// 使用済みの面を飛ばして、途中から印刷する
+    #[serde(default)]
+    pub skip: u32,
+}
+
+fn default_sheet_record_type() -> String {
+    "qr".to_string()
+}
+
+pub async fn generate_label_sheet_pdf(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<LabelSheetRequest>,
+) -> Result<Response, AppError> {
+    let valid_types = ["qr", "barcode", "nothing"];
+    if !valid_types.contains(&req.record_type.as_str()) {
+        return Err(AppError::BadRequest("Invalid record type".to_string()));
+    }
+
+    let layout = match (req.custom_layout, req.layout.as_deref()) {
+        (Some(layout), _) => layout,
+        (None, name) => {
+            let name = name.unwrap_or("a4_24");
+            SheetLayout::preset(name).ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "Unknown layout '{}' (available: {})",
+                    name,
+                    SheetLayout::preset_names().join(", ")
+                ))
+            })?
+        }
+    };
+    layout.validate()?;
+    if req.skip as usize >= layout.cells_per_page() {
+        return Err(AppError::BadRequest(
+            "skip must be smaller than the number of cells on a page".to_string(),
+        ));
+    }
+
+    let label_ids = match (req.label_ids.is_empty(), req.quantity) {
+        (false, None) => {
+            if req.label_ids.len() > 1000 {
+                return Err(AppError::BadRequest(
+                    "At most 1000 labels can be printed at once".to_string(),
+                ));
+            }
+            req.label_ids
+        }
+        (true, Some(quantity)) => {
+            if quantity == 0 || quantity > 1000 {
+                return Err(AppError::BadRequest(
+                    "Quantity must be between 1 and 1000".to_string(),
+                ));
+            }
+            state
+                .label_service
+                .generate_batch(
+                    req.sequence.as_deref().unwrap_or(DEFAULT_SEQUENCE),
+                    quantity,
+                    Some(&req.record_type),
+                    &current_user,
+                )
+                .await?
+                .1
+        }
+        _ => {
+            return Err(AppError::BadRequest(
+                "Specify either label_ids or quantity".to_string(),
+            ))
+        }
+    };
+
+    let items: HashMap<String, Item> = state
+        .item_service
+        .list_items_by_labels(&label_ids)
+        .await?
+        .into_iter()
+        .map(|item| (item.label_id.clone(), item))
+        .collect();
+
+    let labels: Vec<SheetLabel> = label_ids
+        .into_iter()
+        .map(|id| {
+            let item = items.get(&id);
+            let code_type = match item.and_then(|item| item.qr_code_type.as_deref()) {
+                Some("none") => "nothing".to_string(),
+                Some(code_type) => code_type.to_string(),
+                None => req.record_type.clone(),
+            };
+            let name = item
+                .filter(|_| req.include_item_name)
+                .map(|item| item.name.clone());
+            SheetLabel {
+                id,
+                code_type,
+                name,
+            }
+        })
+        .collect();
+
+    let pdf = label_sheet::render_sheet_pdf(&layout, &labels, req.skip as usize)?;
+    let printed: Vec<String> = labels.into_iter().map(|label| label.id).collect();
+    state.label_service.mark_printed(&printed).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (header::CONTENT_DISPOSITION, "inline; filename=\"labels.pdf\""),
+        ],
+        pdf,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelRecordsQuery {
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+    pub status: Option<LabelStatus>,
+    pub batch_id: Option<i64>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    100
+}
+
+pub async fn list_label_records(
+    State(state): State<AppState>,
+    Query(params): Query<LabelRecordsQuery>,
+) -> Result<Json<LabelsListResponse>, AppError> {
+    let filters = LabelFilters {
+        status: params.status,
+        batch_id: params.batch_id,
+    };
+    let page = params.page.max(1);
+    let per_page = params.per_page.clamp(1, 1000);
+
+    Ok(Json(state.label_service.list_labels(&filters, page, per_page).await?))
+}
+
+pub async fn get_label_record(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<LabelRecord>, AppError> {
+    Ok(Json(state.label_service.get_label(&id).await?))
+}
+
+pub async fn list_label_batches(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LabelBatch>>, AppError> {
+    Ok(Json(state.label_service.list_batches().await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnattachedLabelsQuery {
+    pub batch_id: Option<i64>,
+}
+
+/// 発行したまま一度も物品・コンテナに貼られていないラベル
+pub async fn list_unattached_labels(
+    State(state): State<AppState>,
+    Query(params): Query<UnattachedLabelsQuery>,
+) -> Result<Json<Vec<LabelRecord>>, AppError> {
+    Ok(Json(state.label_service.list_unattached(params.batch_id).await?))
+}
+
+pub async fn void_label(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<String>,
+    body: Option<Json<VoidLabelRequest>>,
+) -> Result<Json<LabelRecord>, AppError> {
+    let reason = body.and_then(|Json(req)| req.reason);
+    Ok(Json(state.label_service.void_label(&id, reason, &current_user).await?))
+}
+
+pub async fn list_label_sequences(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LabelSequence>>, AppError> {
+    Ok(Json(state.label_service.list_sequences().await?))
+}
+
+pub async fn create_label_sequence(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateLabelSequenceRequest>,
+) -> Result<(StatusCode, Json<LabelSequence>), AppError> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let sequence = state.label_service.create_sequence(req, &current_user).await?;
+    Ok((StatusCode::CREATED, Json(sequence)))
+}
+
+pub async fn enable_label_check_digit(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<Json<LabelSequence>, AppError> {
+    Ok(Json(state.label_service.enable_check_digit(&name, &current_user).await?))
+}
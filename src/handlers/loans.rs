@@ -1,13 +1,24 @@
+use std::collections::HashMap;
+
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use uuid::Uuid;
 use validator::Validate;
 
-use crate::error::AppResult;
-use crate::models::{CreateLoanRequest, Loan, LoansListResponse, ReturnLoanRequest};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    BatchLoanRequest, BatchLoanResponse, BatchReturnRequest, Container, CreateLoanRequest,
+    CurrentUser, Item, LabelLoanRequest, Loan, LoanFilters, LoansListResponse, OverdueLoansResponse,
+    ReturnLoanRequest,
+};
+use crate::services::{ContainerService, ItemService};
+use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct LoansQuery {
@@ -15,9 +26,11 @@ pub struct LoansQuery {
     pub page: u32,
     #[serde(default = "default_per_page")]
     pub per_page: u32,
-    pub item_id: Option<i64>,
+    pub item_id: Option<Uuid>,
     pub student_number: Option<String>,
     pub active_only: Option<bool>,
+    pub overdue: Option<bool>,
+    pub due_before: Option<DateTime<Utc>>,
 }
 
 fn default_page() -> u32 {
@@ -29,24 +42,33 @@ fn default_per_page() -> u32 {
 }
 
 pub async fn list_loans(
-    State((_storage_service, _cable_color_service, _item_service, loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { loan_service, .. }): State<AppState>,
     Query(params): Query<LoansQuery>,
 ) -> AppResult<Json<LoansListResponse>> {
+    let filters = LoanFilters {
+        item_id: params.item_id,
+        student_number: params.student_number,
+        active_only: params.active_only,
+        overdue: params.overdue,
+        due_before: params.due_before,
+    };
+
     let response = loan_service
-        .list_loans(
-            params.page,
-            params.per_page,
-            params.item_id,
-            params.student_number,
-            params.active_only,
-        )
+        .list_loans(params.page, params.per_page, &filters)
         .await?;
 
     Ok(Json(response))
 }
 
+pub async fn list_overdue_loans(
+    State(AppState { loan_service, .. }): State<AppState>,
+) -> AppResult<Json<OverdueLoansResponse>> {
+    let response = loan_service.list_overdue_loans().await?;
+    Ok(Json(response))
+}
+
 pub async fn get_loan(
-    State((_storage_service, _cable_color_service, _item_service, loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { loan_service, .. }): State<AppState>,
     Path(id): Path<i64>,
 ) -> AppResult<Json<Loan>> {
     let loan = loan_service.get_loan(id).await?;
@@ -54,32 +76,223 @@ pub async fn get_loan(
 }
 
 pub async fn create_loan(
-    State((_storage_service, _cable_color_service, _item_service, loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { loan_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<CreateLoanRequest>,
 ) -> AppResult<(StatusCode, Json<Loan>)> {
     req.validate()
-        .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    let loan = loan_service.create_loan(req).await?;
+    let loan = loan_service.create_loan(req, &current_user).await?;
     Ok((StatusCode::CREATED, Json(loan)))
 }
 
 pub async fn return_loan(
-    State((_storage_service, _cable_color_service, _item_service, loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+    State(AppState { loan_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
     Json(req): Json<ReturnLoanRequest>,
 ) -> AppResult<Json<Loan>> {
     req.validate()
-        .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    let loan = loan_service.return_loan(id, req).await?;
+    let loan = loan_service.return_loan(id, req, &current_user).await?;
     Ok(Json(loan))
 }
 
 pub async fn get_active_loan_for_item(
-   State((_storage_service, _cable_color_service, _item_service, loan_service, _container_service, _connector_service, _tag_service)): State<crate::AppState>,
+   State(AppState { loan_service, .. }): State<AppState>,
    Path(item_id): Path<String>,
 ) -> AppResult<Json<Option<Loan>>> {
    let loan = loan_service.get_active_loan_for_item(&item_id).await?;
    Ok(Json(loan))
 }
+
+/// 一括処理が取り消された場合も物品ごとの結果を返すため、ステータスコードだけで成否を表す
+fn batch_status(response: &BatchLoanResponse, success: StatusCode) -> StatusCode {
+    if response.committed {
+        success
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+pub async fn create_loans_batch(
+    State(AppState { item_service, loan_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<BatchLoanRequest>,
+) -> AppResult<(StatusCode, Json<BatchLoanResponse>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let labels = item_service.resolve_labels(&req.label_ids).await?;
+    let response = loan_service
+        .create_loans_batch(req, &labels, &current_user)
+        .await?;
+    Ok((batch_status(&response, StatusCode::CREATED), Json(response)))
+}
+
+pub async fn return_loans_batch(
+    State(AppState { item_service, loan_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<BatchReturnRequest>,
+) -> AppResult<(StatusCode, Json<BatchLoanResponse>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let labels = item_service.resolve_labels(&req.label_ids).await?;
+    let response = loan_service
+        .return_loans_batch(req, &labels, &current_user)
+        .await?;
+    Ok((batch_status(&response, StatusCode::OK), Json(response)))
+}
+
+/// ラベルIDで貸し出す。コンテナIDが読み取られた場合は、収納されている物品のうち
+/// 貸出中でないものをまとめて貸し出す（その場合のレスポンスは一括貸出と同じ形式になる）
+pub async fn create_loan_by_label(
+    State(AppState { item_service, loan_service, container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(label_id): Path<String>,
+    Json(req): Json<LabelLoanRequest>,
+) -> AppResult<Response> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    match find_label_target(&item_service, &container_service, &label_id).await? {
+        LabelTarget::Item(item) => {
+            let loan_req = CreateLoanRequest {
+                item_id: item.id,
+                student_number: req.student_number,
+                student_name: req.student_name,
+                organization: req.organization,
+                due_date: req.due_date,
+                remarks: req.remarks,
+            };
+            let loan = loan_service.create_loan(loan_req, &current_user).await?;
+            Ok((StatusCode::CREATED, Json(loan)).into_response())
+        }
+        LabelTarget::Container(container) => {
+            if container.is_disposed {
+                return Err(AppError::BadRequest(
+                    "Container is disposed and cannot be loaned".to_string(),
+                ));
+            }
+
+            let items = item_service.list_items_in_container(&container.id).await?;
+            if items.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "Container {} has no items",
+                    container.id
+                )));
+            }
+            // 一括貸出は1件でも貸し出せないと何も反映しないので、貸出中の物品は除いて残りを貸し出す
+            let item_ids: Vec<Uuid> = items
+                .into_iter()
+                .filter(|item| !item.is_on_loan.unwrap_or(false))
+                .map(|item| item.id)
+                .collect();
+            if item_ids.is_empty() {
+                return Err(AppError::Conflict(format!(
+                    "All items in container {} are already on loan",
+                    container.id
+                )));
+            }
+
+            let batch_req = BatchLoanRequest {
+                item_ids,
+                label_ids: Vec::new(),
+                student_number: req.student_number,
+                student_name: req.student_name,
+                organization: req.organization,
+                due_date: req.due_date,
+                remarks: req.remarks,
+            };
+            let response = loan_service
+                .create_loans_batch(batch_req, &HashMap::new(), &current_user)
+                .await?;
+            Ok((batch_status(&response, StatusCode::CREATED), Json(response)).into_response())
+        }
+    }
+}
+
+/// ラベルIDで返却する。コンテナIDが読み取られた場合は、収納されている貸出中の物品をまとめて返却する
+pub async fn return_loan_by_label(
+    State(AppState { item_service, loan_service, container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(label_id): Path<String>,
+    Json(req): Json<ReturnLoanRequest>,
+) -> AppResult<Response> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    match find_label_target(&item_service, &container_service, &label_id).await? {
+        LabelTarget::Item(item) => {
+            let active = loan_service
+                .get_active_loan_for_item(&item.id.to_string())
+                .await?
+                .ok_or_else(|| AppError::Conflict("Item is not on loan".to_string()))?;
+            let loan = loan_service
+                .return_loan(active.id, req, &current_user)
+                .await?;
+            Ok(Json(loan).into_response())
+        }
+        LabelTarget::Container(container) => {
+            let item_ids: Vec<Uuid> = item_service
+                .list_items_in_container(&container.id)
+                .await?
+                .into_iter()
+                .filter(|item| item.is_on_loan.unwrap_or(false))
+                .map(|item| item.id)
+                .collect();
+            if item_ids.is_empty() {
+                return Err(AppError::Conflict(format!(
+                    "No items in container {} are on loan",
+                    container.id
+                )));
+            }
+
+            let batch_req = BatchReturnRequest {
+                item_ids,
+                label_ids: Vec::new(),
+                return_date: req.return_date,
+                remarks: req.remarks,
+            };
+            let response = loan_service
+                .return_loans_batch(batch_req, &HashMap::new(), &current_user)
+                .await?;
+            Ok((batch_status(&response, StatusCode::OK), Json(response)).into_response())
+        }
+    }
+}
+
+/// 読み取ったラベルIDが指す物品またはコンテナ
+enum LabelTarget {
+    Item(Item),
+    Container(Container),
+}
+
+/// ラベルIDを物品、見つからなければコンテナとして探す。コンテナIDがラベルの形式に見えて
+/// チェック文字が合わないこともあるので、チェック文字の誤りはコンテナも見つからなかったときに返す
+async fn find_label_target(
+    item_service: &ItemService,
+    container_service: &ContainerService,
+    label_id: &str,
+) -> AppResult<LabelTarget> {
+    let label_error = match item_service.get_item_by_label(label_id).await {
+        Ok(item) => return Ok(LabelTarget::Item(item)),
+        Err(AppError::NotFound(_)) => None,
+        Err(e @ AppError::InvalidChecksum(_)) => Some(e),
+        Err(e) => return Err(e),
+    };
+
+    match container_service.get_container(label_id).await {
+        Ok(container) => Ok(LabelTarget::Container(container)),
+        Err(AppError::NotFound(_)) => Err(label_error.unwrap_or_else(|| {
+            AppError::NotFound(format!(
+                "No item or container with label_id {} found",
+                label_id
+            ))
+        })),
+        Err(e) => Err(e),
+    }
+}
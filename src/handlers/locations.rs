@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use validator::Validate;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CreateLocationRequest, CurrentUser, Location, MergeLocationsRequest, MergeLocationsResponse,
+    UpdateLocationRequest,
+};
+use crate::AppState;
+
+pub async fn list_locations(State(state): State<AppState>) -> AppResult<Json<Vec<Location>>> {
+    Ok(Json(state.location_service.list_locations().await?))
+}
+
+pub async fn create_location(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateLocationRequest>,
+) -> AppResult<(StatusCode, Json<Location>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let location = state.location_service.create_location(req, &current_user).await?;
+    Ok((StatusCode::CREATED, Json(location)))
+}
+
+pub async fn get_location(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<Location>> {
+    Ok(Json(state.location_service.get_location(id).await?))
+}
+
+pub async fn update_location(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdateLocationRequest>,
+) -> AppResult<Json<Location>> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    Ok(Json(state.location_service.update_location(id, req, &current_user).await?))
+}
+
+pub async fn delete_location(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<StatusCode> {
+    state.location_service.delete_location(id, &current_user).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn merge_locations(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<MergeLocationsRequest>,
+) -> AppResult<Json<MergeLocationsResponse>> {
+    Ok(Json(state.location_service.merge_locations(req, &current_user).await?))
+}
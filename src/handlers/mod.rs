@@ -1,3 +1,6 @@
+pub mod audit;
+pub mod auth;
+pub mod backup;
 pub mod cable_colors;
 pub mod connectors;
 pub mod containers;
@@ -6,8 +9,16 @@ pub mod images;
 pub mod items;
 pub mod labels;
 pub mod loans;
+pub mod locations;
+pub mod reservations;
+pub mod scan;
+pub mod stocktakes;
 pub mod tags;
+pub mod users;
 
+pub use audit::*;
+pub use auth::*;
+pub use backup::*;
 pub use cable_colors::*;
 pub use connectors::*;
 pub use containers::*;
@@ -16,4 +27,9 @@ pub use images::*;
 pub use items::*;
 pub use labels::*;
 pub use loans::*;
+pub use locations::*;
+pub use reservations::*;
+pub use scan::*;
+pub use stocktakes::*;
 pub use tags::*;
+pub use users::*;
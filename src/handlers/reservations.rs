@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CreateReservationRequest, CurrentUser, ItemAvailability, Loan, Reservation,
+    ReservationFilters, ReservationStatus, ReservationsListResponse,
+};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct ReservationsQuery {
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+    pub item_id: Option<Uuid>,
+    pub student_number: Option<String>,
+    pub status: Option<ReservationStatus>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct AvailabilityQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    20
+}
+
+pub async fn list_reservations(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    Query(params): Query<ReservationsQuery>,
+) -> AppResult<Json<ReservationsListResponse>> {
+    let filters = ReservationFilters {
+        item_id: params.item_id,
+        student_number: params.student_number,
+        status: params.status,
+        from: params.from,
+        to: params.to,
+    };
+
+    let response = reservation_service
+        .list_reservations(&filters, params.page, params.per_page)
+        .await?;
+
+    Ok(Json(response))
+}
+
+pub async fn get_reservation(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<Reservation>> {
+    let reservation = reservation_service.get_reservation(id).await?;
+    Ok(Json(reservation))
+}
+
+pub async fn create_reservation(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateReservationRequest>,
+) -> AppResult<(StatusCode, Json<Reservation>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let reservation = reservation_service
+        .create_reservation(req, &current_user)
+        .await?;
+    Ok((StatusCode::CREATED, Json(reservation)))
+}
+
+pub async fn cancel_reservation(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<Json<Reservation>> {
+    let reservation = reservation_service
+        .cancel_reservation(id, &current_user)
+        .await?;
+    Ok(Json(reservation))
+}
+
+pub async fn checkout_reservation(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<(StatusCode, Json<Loan>)> {
+    let loan = reservation_service
+        .checkout_reservation(id, &current_user)
+        .await?;
+    Ok((StatusCode::CREATED, Json(loan)))
+}
+
+pub async fn get_item_availability(
+    State(AppState { reservation_service, .. }): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<AvailabilityQuery>,
+) -> AppResult<Json<ItemAvailability>> {
+    let availability = reservation_service
+        .get_availability(id, params.from, params.to)
+        .await?;
+    Ok(Json(availability))
+}
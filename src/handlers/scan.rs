@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Container, CurrentUser, Item, Loan, Role};
+use crate::services::{ContainerService, ItemService};
+use crate::AppState;
+
+/// スキャン結果に対して行える操作（ユーザーの権限で実行できるものだけを返す）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanAction {
+    View,
+    History,
+    Checkout,
+    Return,
+    Edit,
+    Dispose,
+    Undispose,
+    /// コンテナの中の貸出中でない物品をまとめて貸し出す
+    CheckoutAll,
+    /// コンテナの中の貸出中の物品をまとめて返却する
+    ReturnAll,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScanResult {
+    Item {
+        code: String,
+        item: Box<Item>,
+        /// 貼り替え前の古いラベルが読み取られた
+        superseded: bool,
+        /// 収納先のコンテナ（コンテナに入っている場合）
+        container: Option<Box<Container>>,
+        /// 収納先のコンテナを外側から順に並べたもの
+        container_path: Vec<Container>,
+        /// 現在の保管場所。コンテナに入っている場合は一番外側のコンテナの場所
+        location: Option<String>,
+        active_loan: Option<Loan>,
+        actions: Vec<ScanAction>,
+    },
+    Container {
+        code: String,
+        container: Container,
+        items: Vec<Item>,
+        actions: Vec<ScanAction>,
+    },
+}
+
+/// 読み取った文字列（ラベルID・コンテナID・物品UUID・QRのURL）を物品かコンテナに解決する
+pub async fn scan_code(
+    State(AppState { item_service, loan_service, container_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(code): Path<String>,
+) -> AppResult<Json<ScanResult>> {
+    let normalized = normalize_code(&code);
+    if normalized.is_empty() {
+        return Err(AppError::BadRequest("Scanned code is empty".to_string()));
+    }
+
+    // コンテナIDがラベルの形式に見えてチェック文字が合わないこともあるので、
+    // チェック文字の誤りはコンテナも見つからなかったときに返す
+    let (found, label_error) = match find_item(&item_service, &normalized).await {
+        Ok(found) => (found, None),
+        Err(e @ AppError::InvalidChecksum(_)) => (None, Some(e)),
+        Err(e) => return Err(e),
+    };
+
+    if let Some((item, superseded)) = found {
+        let container_path = match item.container_id.as_deref() {
+            Some(container_id) if item.storage_type == "container" => {
+                container_service.container_path(container_id).await?
+            }
+            _ => Vec::new(),
+        };
+        let container = container_path.last().cloned().map(Box::new);
+        let location = container_service.effective_item_location(&item).await?;
+        let active_loan = loan_service
+            .get_active_loan_for_item(&item.id.to_string())
+            .await?;
+        let actions = item_actions(&item, current_user.role);
+
+        return Ok(Json(ScanResult::Item {
+            code: normalized,
+            item: Box::new(item),
+            superseded,
+            container,
+            container_path,
+            location,
+            active_loan,
+            actions,
+        }));
+    }
+
+    if let Some(container) = find_container(&container_service, &normalized).await? {
+        let items = item_service.list_items_in_container(&container.id).await?;
+        let actions = container_actions(&container, &items, current_user.role);
+
+        return Ok(Json(ScanResult::Container {
+            code: normalized,
+            container,
+            items,
+            actions,
+        }));
+    }
+
+    if let Some(e) = label_error {
+        return Err(e);
+    }
+    Err(AppError::NotFound(format!(
+        "No item or container matches '{}'",
+        normalized
+    )))
+}
+
+/// QRコードにURLが埋め込まれている場合は、最後のパス要素をIDとして扱う
+fn normalize_code(code: &str) -> String {
+    let code = code.trim();
+    if !code.contains('/') {
+        return code.to_string();
+    }
+
+    let path = code.split(['?', '#']).next().unwrap_or(code);
+    path.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// 物品と、古いラベルで見つかったかどうか
+async fn find_item(item_service: &ItemService, code: &str) -> AppResult<Option<(Item, bool)>> {
+    let result = match Uuid::parse_str(code) {
+        Ok(id) => item_service.get_item(id).await.map(|item| (item, false)),
+        Err(_) => match item_service.lookup_label(code).await {
+            // ラベルIDは大文字で発行されるので、小文字で読み取られた場合も探す
+            Err(AppError::NotFound(_)) if code != code.to_uppercase() => {
+                item_service.lookup_label(&code.to_uppercase()).await
+            }
+            result => result,
+        }
+        .map(|found| (found.item, found.superseded)),
+    };
+
+    match result {
+        Ok(found) => Ok(Some(found)),
+        Err(AppError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+async fn find_container(
+    container_service: &ContainerService,
+    id: &str,
+) -> AppResult<Option<Container>> {
+    match container_service.get_container(id).await {
+        Ok(container) => Ok(Some(container)),
+        Err(AppError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn item_actions(item: &Item, role: Role) -> Vec<ScanAction> {
+    let is_disposed = item.is_disposed.unwrap_or(false);
+    let is_on_loan = item.is_on_loan.unwrap_or(false);
+
+    let mut actions = vec![ScanAction::View, ScanAction::History];
+    if role >= Role::Lender && !is_disposed {
+        actions.push(if is_on_loan {
+            ScanAction::Return
+        } else {
+            ScanAction::Checkout
+        });
+    }
+    if role >= Role::Admin {
+        actions.push(ScanAction::Edit);
+        actions.push(if is_disposed {
+            ScanAction::Undispose
+        } else {
+            ScanAction::Dispose
+        });
+    }
+    actions
+}
+
+fn container_actions(container: &Container, items: &[Item], role: Role) -> Vec<ScanAction> {
+    let mut actions = vec![ScanAction::View];
+    if role >= Role::Lender && !container.is_disposed {
+        if items.iter().any(|item| !item.is_on_loan.unwrap_or(false)) {
+            actions.push(ScanAction::CheckoutAll);
+        }
+        if items.iter().any(|item| item.is_on_loan.unwrap_or(false)) {
+            actions.push(ScanAction::ReturnAll);
+        }
+    }
+    if role >= Role::Admin {
+        actions.push(ScanAction::Edit);
+        actions.push(if container.is_disposed {
+            ScanAction::Undispose
+        } else {
+            ScanAction::Dispose
+        });
+    }
+    actions
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    CloseStocktakeRequest, CloseStocktakeResponse, CreateStocktakeRequest, CurrentUser,
+    RecordStocktakeScanRequest, StocktakeReport, StocktakeScan, StocktakeSession, StocktakeStatus,
+};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct StocktakesQuery {
+    pub status: Option<StocktakeStatus>,
+}
+
+pub async fn list_stocktakes(
+    State(state): State<AppState>,
+    Query(params): Query<StocktakesQuery>,
+) -> AppResult<Json<Vec<StocktakeSession>>> {
+    Ok(Json(state.stocktake_service.list_sessions(params.status).await?))
+}
+
+pub async fn create_stocktake(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateStocktakeRequest>,
+) -> AppResult<(StatusCode, Json<StocktakeSession>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let session = state.stocktake_service.create_session(req, &current_user).await?;
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+pub async fn get_stocktake(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<StocktakeSession>> {
+    Ok(Json(state.stocktake_service.get_session(id).await?))
+}
+
+pub async fn list_stocktake_scans(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<Vec<StocktakeScan>>> {
+    Ok(Json(state.stocktake_service.list_scans(id).await?))
+}
+
+pub async fn record_stocktake_scan(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+    Json(req): Json<RecordStocktakeScanRequest>,
+) -> AppResult<(StatusCode, Json<StocktakeScan>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let scan = state.stocktake_service.record_scan(id, req, &current_user).await?;
+    Ok((StatusCode::CREATED, Json(scan)))
+}
+
+pub async fn get_stocktake_report(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<StocktakeReport>> {
+    Ok(Json(state.stocktake_service.get_report(id).await?))
+}
+
+pub async fn close_stocktake(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+    body: Option<Json<CloseStocktakeRequest>>,
+) -> AppResult<Json<CloseStocktakeResponse>> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    Ok(Json(state.stocktake_service.close_session(id, req, &current_user).await?))
+}
@@ -7,7 +7,10 @@ use serde::Deserialize;
 use validator::Validate;
 
 use crate::error::AppResult;
-use crate::models::{CreateTagRequest, ItemTagsRequest, Tag, TagsListResponse, UpdateTagRequest};
+use crate::models::{
+    CreateTagRequest, CurrentUser, ItemTagsRequest, Tag, TagsListResponse, UpdateTagRequest,
+};
+use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct TagsQuery {
@@ -26,15 +29,7 @@ fn default_per_page() -> u32 {
 }
 
 pub async fn list_tags(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
     Query(params): Query<TagsQuery>,
 ) -> AppResult<Json<TagsListResponse>> {
     let response = tag_service.list_tags(params.page, params.per_page).await?;
@@ -43,15 +38,7 @@ pub async fn list_tags(
 }
 
 pub async fn get_tag(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
     Path(id): Path<i64>,
 ) -> AppResult<Json<Tag>> {
     let tag = tag_service.get_tag(id).await?;
@@ -59,71 +46,42 @@ pub async fn get_tag(
 }
 
 pub async fn create_tag(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Json(req): Json<CreateTagRequest>,
 ) -> AppResult<(StatusCode, Json<Tag>)> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let tag = tag_service.create_tag(req).await?;
+    let tag = tag_service.create_tag(req, &current_user).await?;
     Ok((StatusCode::CREATED, Json(tag)))
 }
 
 pub async fn update_tag(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
     Json(req): Json<UpdateTagRequest>,
 ) -> AppResult<Json<Tag>> {
     req.validate()
         .map_err(|e| crate::error::AppError::ValidationError(e.to_string()))?;
 
-    let tag = tag_service.update_tag(id, req).await?;
+    let tag = tag_service.update_tag(id, req, &current_user).await?;
     Ok(Json(tag))
 }
 
 pub async fn delete_tag(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(id): Path<i64>,
 ) -> AppResult<StatusCode> {
-    tag_service.delete_tag(id).await?;
+    tag_service.delete_tag(id, &current_user).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 // Item-tag association endpoints
 pub async fn get_item_tags(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
     Path(item_id): Path<String>,
 ) -> AppResult<Json<Vec<Tag>>> {
     let tags = tag_service.get_item_tags(&item_id).await?;
@@ -131,18 +89,11 @@ pub async fn get_item_tags(
 }
 
 pub async fn set_item_tags(
-    State((
-        _storage_service,
-        _cable_color_service,
-        _item_service,
-        _loan_service,
-        _container_service,
-        _connector_service,
-        tag_service,
-    )): State<crate::AppState>,
+    State(AppState { tag_service, .. }): State<AppState>,
+    current_user: CurrentUser,
     Path(item_id): Path<String>,
     Json(req): Json<ItemTagsRequest>,
 ) -> AppResult<Json<Vec<Tag>>> {
-    let tags = tag_service.set_item_tags(&item_id, req.tag_ids).await?;
+    let tags = tag_service.set_item_tags(&item_id, req.tag_ids, &current_user).await?;
     Ok(Json(tags))
 }
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateUserRequest, CurrentUser, UpdateUserRequest, User, UsersListResponse};
+use crate::AppState;
+
+#[derive(Deserialize)]
+pub struct UsersQuery {
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    50
+}
+
+pub async fn list_users(
+    State(AppState { auth_service, .. }): State<AppState>,
+    Query(params): Query<UsersQuery>,
+) -> AppResult<Json<UsersListResponse>> {
+    let response = auth_service.list_users(params.page, params.per_page).await?;
+    Ok(Json(response))
+}
+
+pub async fn get_user(
+    State(AppState { auth_service, .. }): State<AppState>,
+    Path(id): Path<i64>,
+) -> AppResult<Json<User>> {
+    let user = auth_service.get_user(id).await?;
+    Ok(Json(user))
+}
+
+pub async fn create_user(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<CreateUserRequest>,
+) -> AppResult<(StatusCode, Json<User>)> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user = auth_service.create_user(req, &current_user).await?;
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+pub async fn update_user(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdateUserRequest>,
+) -> AppResult<Json<User>> {
+    req.validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user = auth_service.update_user(id, req, &current_user).await?;
+    Ok(Json(user))
+}
+
+pub async fn delete_user(
+    State(AppState { auth_service, .. }): State<AppState>,
+    current_user: CurrentUser,
+    Path(id): Path<i64>,
+) -> AppResult<StatusCode> {
+    if current_user.id == id {
+        return Err(AppError::BadRequest(
+            "Cannot delete your own account".to_string(),
+        ));
+    }
+
+    auth_service.delete_user(id, &current_user).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,39 +1,55 @@
 use axum::{
     extract::DefaultBodyLimit,
+    http::HeaderValue,
+    middleware,
     routing::{get, post},
     Router,
 };
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod auth;
 mod config;
 mod db;
 mod error;
 mod handlers;
 mod models;
+mod repositories;
 mod services;
 
+#[cfg(test)]
+mod tests;
+
 use crate::config::{Config, StorageType};
 use crate::db::DatabasePool;
 use crate::services::{
-    CableColorService, ConnectorService, ContainerService, ItemService, LoanService,
-    StorageService, TagService,
+    AuditService, AuthService, BackupService, CableColorService, ConnectorService,
+    ContainerService, DataMigrationService, ItemService, LabelService, LoanService, LocationService,
+    ReservationService, StocktakeService, StorageService, TagService,
 };
 
-pub type AppState = (
-    Arc<StorageService>,
-    Arc<CableColorService>,
-    Arc<ItemService>,
-    Arc<LoanService>,
-    Arc<ContainerService>,
-    Arc<ConnectorService>,
-    Arc<TagService>,
-);
+#[derive(Clone)]
+pub struct AppState {
+    pub storage_service: Arc<StorageService>,
+    pub cable_color_service: Arc<CableColorService>,
+    pub item_service: Arc<ItemService>,
+    pub loan_service: Arc<LoanService>,
+    pub container_service: Arc<ContainerService>,
+    pub connector_service: Arc<ConnectorService>,
+    pub tag_service: Arc<TagService>,
+    pub auth_service: Arc<AuthService>,
+    pub audit_service: Arc<AuditService>,
+    pub reservation_service: Arc<ReservationService>,
+    pub label_service: Arc<LabelService>,
+    pub stocktake_service: Arc<StocktakeService>,
+    pub location_service: Arc<LocationService>,
+    pub backup_service: Arc<BackupService>,
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -46,6 +62,13 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("migrate-data") => return migrate_data(&args[1..]).await,
+        Some(_) => anyhow::bail!("{}", USAGE),
+    }
+
     info!("Starting HyperDashi server...");
 
     // Load configuration
@@ -60,35 +83,116 @@ async fn main() -> anyhow::Result<()> {
     db_pool.migrate().await?;
     info!("Database migrations completed");
 
+    let app = app(&config, db_pool).await?;
+
+    // Start server
+    let addr = SocketAddr::from((
+        config.server.host.parse::<std::net::IpAddr>()?,
+        config.server.port,
+    ));
+    info!("Server listening on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+const USAGE: &str = "usage: hyperdashi-server [migrate-data <SOURCE_DATABASE_URL> <TARGET_DATABASE_URL>]";
+
+/// 全データを別のデータベースへ写す（例: `migrate-data sqlite://hyperdashi.db postgres://...`）
+async fn migrate_data(args: &[String]) -> anyhow::Result<()> {
+    let [source_url, target_url] = args else {
+        anyhow::bail!("{}", USAGE);
+    };
+    let source = DatabasePool::connect(source_url).await?;
+    let target = DatabasePool::connect(target_url).await?;
+
+    let counts = DataMigrationService::new(source, target).run().await?;
+    for count in &counts {
+        println!("{:<30} {:>8} rows", count.table, count.target);
+    }
+    println!("Migrated {} tables; row counts match", counts.len());
+    Ok(())
+}
+
+/// サービスを組み立ててルーターを作る（統合テストからも使う）
+async fn app(config: &Config, db_pool: DatabasePool) -> anyhow::Result<Router> {
     // Initialize storage
-    let storage = Arc::new(StorageService::new(&config).await?);
+    let storage = Arc::new(StorageService::new(config).await?);
     info!("Storage initialized");
 
     // Initialize services
     let cable_color_service = Arc::new(CableColorService::new(db_pool.clone()));
     let item_service = Arc::new(ItemService::new(db_pool.clone()));
-    let loan_service = Arc::new(LoanService::new(db_pool.clone()));
+    let loan_service = Arc::new(LoanService::new(db_pool.clone(), config.loan.clone()));
     let container_service = Arc::new(ContainerService::new(db_pool.clone()));
     let connector_service = Arc::new(ConnectorService::new(db_pool.clone()));
     let tag_service = Arc::new(TagService::new(db_pool.clone()));
+    let auth_service = Arc::new(AuthService::new(db_pool.clone(), config.auth.clone()));
+    let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+    let reservation_service = Arc::new(ReservationService::new(
+        db_pool.clone(),
+        config.loan.clone(),
+    ));
+    let label_service = Arc::new(LabelService::new(db_pool.clone()));
+    let stocktake_service = Arc::new(StocktakeService::new(db_pool.clone()));
+    let location_service = Arc::new(LocationService::new(db_pool.clone()));
+    let backup_service = Arc::new(BackupService::new(db_pool.clone(), storage.clone()));
+
+    auth_service.ensure_admin_user().await?;
+    if !auth_service.is_enabled() {
+        tracing::warn!("Authentication is disabled; all requests are treated as admin");
+    }
 
     // Create app states
-    let app_state = (
-        storage.clone(),
+    let app_state = AppState {
+        storage_service: storage.clone(),
         cable_color_service,
-        item_service.clone(),
+        item_service: item_service.clone(),
         loan_service,
         container_service,
         connector_service,
         tag_service,
-    );
+        auth_service,
+        audit_service,
+        reservation_service,
+        label_service,
+        stocktake_service,
+        location_service,
+        backup_service,
+    };
     let api_routes = Router::new()
+        // Auth routes
+        .route("/auth/login", post(handlers::login))
+        .route("/auth/logout", post(handlers::logout))
+        .route("/auth/me", get(handlers::get_me))
+        .route(
+            "/auth/tokens",
+            get(handlers::list_api_tokens).post(handlers::create_api_token),
+        )
+        .route(
+            "/auth/tokens/:id",
+            axum::routing::delete(handlers::delete_api_token),
+        )
+        // User management routes
+        .route(
+            "/users",
+            get(handlers::list_users).post(handlers::create_user),
+        )
+        .route(
+            "/users/:id",
+            get(handlers::get_user)
+                .put(handlers::update_user)
+                .delete(handlers::delete_user),
+        )
         // Item routes
         .route(
             "/items",
             get(handlers::list_items).post(handlers::create_item),
         )
         .route("/items/csv", get(handlers::export_items_csv))
+        .route("/items/import", post(handlers::import_items_csv))
         .route(
             "/items/:id",
             get(handlers::get_item)
@@ -97,7 +201,14 @@ async fn main() -> anyhow::Result<()> {
         )
         .route("/items/:id/dispose", post(handlers::dispose_item))
         .route("/items/:id/undispose", post(handlers::undispose_item))
+        .route("/items/:id/relabel", post(handlers::relabel_item))
         .route("/items/:id/image", post(handlers::add_item_image))
+        .route("/items/:id/history", get(handlers::get_item_history))
+        .route("/items/:id/transfers", get(handlers::list_item_transfers))
+        .route(
+            "/items/:id/availability",
+            get(handlers::get_item_availability),
+        )
         .route(
             "/items/by-label/:label_id",
             get(handlers::get_item_by_label),
@@ -122,6 +233,7 @@ async fn main() -> anyhow::Result<()> {
             "/items/bulk/disposed",
             axum::routing::put(handlers::bulk_update_items_disposed_status),
         )
+        .route("/items/bulk/move", post(handlers::bulk_move_items))
         // Cable color routes
         .route(
             "/cable_colors",
@@ -138,14 +250,86 @@ async fn main() -> anyhow::Result<()> {
             "/loans",
             get(handlers::list_loans).post(handlers::create_loan),
         )
+        .route("/loans/overdue", get(handlers::list_overdue_loans))
+        .route("/loans/batch", post(handlers::create_loans_batch))
+        .route("/loans/batch/return", post(handlers::return_loans_batch))
+        .route(
+            "/loans/by-label/:label_id",
+            post(handlers::create_loan_by_label),
+        )
+        .route(
+            "/loans/by-label/:label_id/return",
+            post(handlers::return_loan_by_label),
+        )
         .route("/loans/:id", get(handlers::get_loan))
         .route("/loans/:id/return", post(handlers::return_loan))
         .route("/loans/history", get(handlers::list_loans))
+        // Reservation routes
+        .route(
+            "/reservations",
+            get(handlers::list_reservations).post(handlers::create_reservation),
+        )
+        .route("/reservations/:id", get(handlers::get_reservation))
+        .route(
+            "/reservations/:id/cancel",
+            post(handlers::cancel_reservation),
+        )
+        .route(
+            "/reservations/:id/checkout",
+            post(handlers::checkout_reservation),
+        )
+        // Stocktake routes
+        .route(
+            "/stocktakes",
+            get(handlers::list_stocktakes).post(handlers::create_stocktake),
+        )
+        .route("/stocktakes/:id", get(handlers::get_stocktake))
+        .route(
+            "/stocktakes/:id/scans",
+            get(handlers::list_stocktake_scans).post(handlers::record_stocktake_scan),
+        )
+        .route("/stocktakes/:id/report", get(handlers::get_stocktake_report))
+        .route("/stocktakes/:id/close", post(handlers::close_stocktake))
+        // Location routes
+        .route(
+            "/locations",
+            get(handlers::list_locations).post(handlers::create_location),
+        )
+        .route("/locations/merge", post(handlers::merge_locations))
+        .route(
+            "/locations/:id",
+            get(handlers::get_location)
+                .put(handlers::update_location)
+                .delete(handlers::delete_location),
+        )
         // Label routes
         .route("/labels/generate", post(handlers::generate_labels))
         .route("/labels", get(handlers::get_label_info))
+        .route("/labels/sheet.pdf", post(handlers::generate_label_sheet_pdf))
+        .route("/labels/:id/qr.svg", get(handlers::get_label_qr_svg))
+        .route("/labels/:id/qr.png", get(handlers::get_label_qr_png))
+        .route("/labels/:id/barcode.svg", get(handlers::get_label_barcode_svg))
+        .route(
+            "/labels/:id/print",
+            get(handlers::print_label).post(handlers::record_label_print),
+        )
+        .route("/labels/records", get(handlers::list_label_records))
+        .route("/labels/unattached", get(handlers::list_unattached_labels))
+        .route("/labels/batches", get(handlers::list_label_batches))
+        .route(
+            "/labels/sequences",
+            get(handlers::list_label_sequences).post(handlers::create_label_sequence),
+        )
+        .route(
+            "/labels/sequences/:name/check-digit",
+            post(handlers::enable_label_check_digit),
+        )
+        .route("/labels/:id/record", get(handlers::get_label_record))
+        .route("/labels/:id/void", post(handlers::void_label))
         // ID Check routes
         .route("/ids/check/:id", get(handlers::check_global_id))
+        // Scan routes
+        .route("/scan/:code", get(handlers::scan_code))
         // Container routes
         .route(
             "/containers",
@@ -165,6 +349,12 @@ async fn main() -> anyhow::Result<()> {
             "/containers/bulk/disposed",
             axum::routing::put(handlers::bulk_update_containers_disposed_status),
         )
+        .route("/containers/:id/tree", get(handlers::get_container_tree))
+        .route("/containers/:id/move", post(handlers::move_container))
+        .route(
+            "/containers/:id/transfers",
+            get(handlers::list_container_transfers),
+        )
         .route("/containers/check/:id", get(handlers::check_container_id))
         .route(
             "/containers/by-location/:location",
@@ -194,6 +384,16 @@ async fn main() -> anyhow::Result<()> {
             "/items/:item_id/tags",
             get(handlers::get_item_tags).put(handlers::set_item_tags),
         )
+        // Audit log routes
+        .route("/audit", get(handlers::list_audit_events))
+        // Backup routes
+        .route("/admin/backup", get(handlers::download_backup))
+        .route(
+            "/admin/restore",
+            post(handlers::restore_backup).layer(DefaultBodyLimit::max(
+                config.storage.max_restore_size_mb as usize * 1024 * 1024,
+            )),
+        )
         // Image routes - larger body limit for file uploads
         .route(
             "/images/upload",
@@ -205,8 +405,25 @@ async fn main() -> anyhow::Result<()> {
             "/images/:filename",
             axum::routing::delete(handlers::delete_image),
         )
+        // 全ルートで認証し、ロールに応じて認可する
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::require_auth,
+        ))
         .with_state(app_state);
 
+    let allow_origin = if config.server.cors_allowed_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .server
+            .cors_allowed_origins
+            .iter()
+            .map(|origin| origin.parse::<HeaderValue>())
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
     let mut app = Router::new()
         .route("/", get(root))
         .route("/api/v1/health", get(health_check))
@@ -217,7 +434,7 @@ async fn main() -> anyhow::Result<()> {
         ))
         .layer(
             CorsLayer::new()
-                .allow_origin(Any)
+                .allow_origin(allow_origin)
                 .allow_methods(Any)
                 .allow_headers(Any)
                 .allow_credentials(false),
@@ -235,17 +452,7 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    // Start server
-    let addr = SocketAddr::from((
-        config.server.host.parse::<std::net::IpAddr>()?,
-        config.server.port,
-    ));
-    info!("Server listening on {}", addr);
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+    Ok(app)
 }
 
 async fn root() -> &'static str {
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Item,
+    Container,
+    Loan,
+    Reservation,
+    Label,
+    LabelSequence,
+    Stocktake,
+    Location,
+    CableColor,
+    Connector,
+    Tag,
+    User,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Item => "item",
+            AuditEntity::Container => "container",
+            AuditEntity::Loan => "loan",
+            AuditEntity::Reservation => "reservation",
+            AuditEntity::Label => "label",
+            AuditEntity::LabelSequence => "label_sequence",
+            AuditEntity::Stocktake => "stocktake",
+            AuditEntity::Location => "location",
+            AuditEntity::CableColor => "cable_color",
+            AuditEntity::Connector => "connector",
+            AuditEntity::Tag => "tag",
+            AuditEntity::User => "user",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Dispose,
+    Undispose,
+    Loan,
+    Return,
+    SetTags,
+    Cancel,
+    Checkout,
+    Void,
+    Relabel,
+    Close,
+    Merge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Dispose => "dispose",
+            AuditAction::Undispose => "undispose",
+            AuditAction::Loan => "loan",
+            AuditAction::Return => "return",
+            AuditAction::SetTags => "set_tags",
+            AuditAction::Cancel => "cancel",
+            AuditAction::Checkout => "checkout",
+            AuditAction::Void => "void",
+            AuditAction::Relabel => "relabel",
+            AuditAction::Close => "close",
+            AuditAction::Merge => "merge",
+        }
+    }
+}
+
+/// 1件の変更記録。`changes` は変更されたフィールドごとの `{"before": .., "after": ..}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub action: String,
+    pub actor_id: Option<i64>,
+    pub actor: String,
+    pub changes: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditFilters {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventsListResponse {
+    pub events: Vec<AuditEvent>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
@@ -1,17 +1,21 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Container {
     pub id: String,
     pub name: String,
     pub description: Option<String>,
+    /// 入れ子になっている場合は一番外側のコンテナの場所
     pub location: String,
     pub image_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_disposed: bool,
+    /// このコンテナが入っているコンテナ
+    pub parent_container_id: Option<String>,
+    pub location_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -21,9 +25,15 @@ pub struct CreateContainerRequest {
     #[validate(length(min = 1, max = 100))]
     pub name: String,
     pub description: Option<String>,
+    /// `parent_container_id` を指定した場合は省略できる（親コンテナの場所になる）
     #[validate(length(min = 1, max = 100))]
-    pub location: String,
+    pub location: Option<String>,
+    /// 場所マスタのID。指定した場合は `location` より優先する
+    pub location_id: Option<i64>,
     pub image_url: Option<String>,
+    pub parent_container_id: Option<String>,
+    /// `id` を省略したときに使う採番ルール
+    pub label_sequence: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -33,15 +43,40 @@ pub struct UpdateContainerRequest {
     pub description: Option<String>,
     #[validate(length(min = 1, max = 100))]
     pub location: Option<String>,
+    pub location_id: Option<i64>,
     pub is_disposed: Option<bool>,
     pub image_url: Option<String>,
+    /// `null` で親コンテナから出す
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_container_id: Option<Option<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// 省略（None）と `null`（Some(None)）を区別する
+pub(crate) fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ContainerWithItemCount {
     #[serde(flatten)]
     pub container: Container,
     pub item_count: i64,
+    /// 中に入っているコンテナの物品も含めた数
+    pub total_item_count: i64,
+}
+
+/// `GET /containers/:id/tree` の各ノード
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContainerTreeNode {
+    #[serde(flatten)]
+    pub container: Container,
+    pub item_count: i64,
+    pub total_item_count: i64,
+    pub children: Vec<ContainerTreeNode>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Item {
     pub id: Uuid,
     pub name: String,
@@ -17,6 +17,8 @@ pub struct Item {
     pub connection_names: Option<Vec<String>>,
     pub cable_color_pattern: Option<Vec<String>>,
     pub storage_location: Option<String>,
+    /// 保管場所マスタのID（`storage_location` はその場所の名前）
+    pub location_id: Option<i64>,
     pub container_id: Option<String>,
     pub storage_type: String, // "location" or "container"
     pub is_on_loan: Option<bool>,
@@ -56,6 +58,9 @@ pub struct CreateItemRequest {
 
     pub storage_location: Option<String>,
 
+    /// 指定した場合は `storage_location` より優先する
+    pub location_id: Option<i64>,
+
     pub container_id: Option<String>,
 
     pub storage_type: Option<String>, // "location" or "container"
@@ -66,7 +71,7 @@ pub struct CreateItemRequest {
     pub image_url: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
 pub struct UpdateItemRequest {
     #[validate(length(min = 1, max = 255))]
     pub name: Option<String>,
@@ -95,6 +100,9 @@ pub struct UpdateItemRequest {
 
     pub storage_location: Option<String>,
 
+    /// 指定した場合は `storage_location` より優先する
+    pub location_id: Option<i64>,
+
     pub container_id: Option<String>,
 
     pub storage_type: Option<String>, // "location" or "container"
@@ -109,6 +117,107 @@ pub struct UpdateItemRequest {
     pub image_url: Option<String>,
 }
 
+/// ラベルIDから引いた物品。貼り替え前の古いラベルで見つかった場合は `superseded` が true
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemByLabel {
+    #[serde(flatten)]
+    pub item: Item,
+    pub superseded: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct RelabelItemRequest {
+    /// 新しいラベルID。省略した場合は新しく発行する
+    #[validate(length(min = 1, max = 50))]
+    pub label_id: Option<String>,
+    /// 新しく発行するときの採番ルール。省略時は古いラベルと同じもの
+    pub sequence: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// 貼り替えで使われなくなったラベルと、その物品
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelAlias {
+    pub label_id: String,
+    pub item_id: Uuid,
+    pub replaced_by: String,
+    pub reason: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// CSV 取り込みで、既にある物品と同じラベルの行をどう扱うか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemImportMode {
+    /// 新しい物品だけを登録する（使用中のラベルはエラー）
+    #[default]
+    Create,
+    /// 同じラベルの物品があれば更新する
+    Upsert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemImportAction {
+    Create,
+    Update,
+}
+
+/// CSV の1行（行番号はヘッダーを1行目として数える）
+#[derive(Debug, Clone)]
+pub struct ItemCsvRow {
+    pub row: usize,
+    pub name: String,
+    /// 空なら取り込むときに発行する
+    pub label_id: Option<String>,
+    pub model_number: Option<String>,
+    pub remarks: Option<String>,
+    pub quantity: u32,
+    /// コンテナIDまたは保管場所のパス
+    pub place: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemImportError {
+    pub row: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemImportRowResult {
+    pub row: usize,
+    pub action: ItemImportAction,
+    pub name: String,
+    /// CSV で指定されたラベル。None なら取り込むときに発行する
+    pub label_id: Option<String>,
+    pub quantity: u32,
+    pub container_id: Option<String>,
+    pub storage_location: Option<String>,
+    /// 保管場所マスタにない場所（取り込むときに作る）
+    pub new_location: bool,
+    /// 登録・更新した物品。dry run では空
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemImportReport {
+    pub dry_run: bool,
+    pub mode: ItemImportMode,
+    /// エラーがなく、すべての行を書き込んだか
+    pub applied: bool,
+    /// 書き込みを終えた行の数。途中の行で失敗した場合は、それより前の行だけが書き込まれている
+    pub applied_rows: usize,
+    pub total_rows: usize,
+    /// 登録する（した）物品の数（個数を含む）
+    pub created: usize,
+    pub updated: usize,
+    pub rows: Vec<ItemImportRowResult>,
+    pub errors: Vec<ItemImportError>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemsListResponse {
     pub items: Vec<Item>,
@@ -116,3 +225,37 @@ pub struct ItemsListResponse {
     pub page: u32,
     pub per_page: u32,
 }
+
+/// 物品の履歴に現れるイベントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemHistoryKind {
+    Created,
+    Updated,
+    ImageChanged,
+    TagsChanged,
+    Moved,
+    Disposed,
+    Undisposed,
+    Relabeled,
+    Loaned,
+    Returned,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemHistoryEntry {
+    pub kind: ItemHistoryKind,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: Option<String>,
+    /// 変更されたフィールドごとの `{"before": .., "after": ..}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loan: Option<crate::models::Loan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemHistoryResponse {
+    pub item_id: Uuid,
+    pub entries: Vec<ItemHistoryEntry>,
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelStatus {
+    /// 発行済み（まだ印刷していない）
+    Generated,
+    Printed,
+    /// 物品またはコンテナに貼られている
+    Attached,
+    Voided,
+    /// 貼り替えなどで使われなくなった
+    Reassigned,
+}
+
+impl LabelStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelStatus::Generated => "generated",
+            LabelStatus::Printed => "printed",
+            LabelStatus::Attached => "attached",
+            LabelStatus::Voided => "voided",
+            LabelStatus::Reassigned => "reassigned",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "generated" => Some(LabelStatus::Generated),
+            "printed" => Some(LabelStatus::Printed),
+            "attached" => Some(LabelStatus::Attached),
+            "voided" => Some(LabelStatus::Voided),
+            "reassigned" => Some(LabelStatus::Reassigned),
+            _ => None,
+        }
+    }
+}
+
+/// 1回の発行でまとめて確保したラベルIDの範囲
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelBatch {
+    pub id: i64,
+    pub sequence: String,
+    pub quantity: i32,
+    pub first_label: String,
+    pub last_label: String,
+    pub record_type: Option<String>,
+    pub generated_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelRecord {
+    pub id: String,
+    pub status: LabelStatus,
+    pub batch_id: Option<i64>,
+    /// 発行した人（発行記録のないラベルは None）
+    pub generated_by: Option<String>,
+    pub item_id: Option<Uuid>,
+    pub container_id: Option<String>,
+    pub generated_at: Option<DateTime<Utc>>,
+    pub printed_at: Option<DateTime<Utc>>,
+    pub attached_at: Option<DateTime<Utc>>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub voided_by: Option<String>,
+    pub void_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LabelFilters {
+    pub status: Option<LabelStatus>,
+    pub batch_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LabelsListResponse {
+    pub labels: Vec<LabelRecord>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VoidLabelRequest {
+    pub reason: Option<String>,
+}
+
+/// ラベルIDの採番ルール。`prefix` に続けて `alphabet` の文字で `width` 桁の連番を振る
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelSequence {
+    pub name: String,
+    pub prefix: String,
+    pub width: i32,
+    pub alphabet: String,
+    /// 末尾にチェック文字を付ける
+    pub check_digit: bool,
+    /// チェック文字を付ける前に発行した番号の上限。これ以下の番号はチェック文字なしでも有効
+    pub unchecked_until: Option<i64>,
+    /// 最後に発行した番号
+    pub current_value: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateLabelSequenceRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+    #[serde(default)]
+    #[validate(length(max = 10))]
+    pub prefix: String,
+    #[validate(range(min = 1, max = 12))]
+    pub width: i32,
+    /// 省略時は 0-9・A-Z
+    pub alphabet: Option<String>,
+    #[serde(default)]
+    pub check_digit: bool,
+}
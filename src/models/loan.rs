@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Loan {
     pub id: i64,
     pub item_id: Uuid,
@@ -12,6 +12,7 @@ pub struct Loan {
     pub organization: Option<String>,
     pub loan_date: DateTime<Utc>,
     pub return_date: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
     pub remarks: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -30,6 +31,26 @@ pub struct CreateLoanRequest {
     #[validate(length(max = 255))]
     pub organization: Option<String>,
 
+    /// 省略時は設定された標準の貸出期間から決める
+    pub due_date: Option<DateTime<Utc>>,
+
+    pub remarks: Option<String>,
+}
+
+/// ラベルIDを読み取って貸し出す場合のリクエスト（物品は URL のラベルIDで指定する）
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct LabelLoanRequest {
+    #[validate(length(min = 1, max = 20))]
+    pub student_number: String,
+
+    #[validate(length(min = 1, max = 100))]
+    pub student_name: String,
+
+    #[validate(length(max = 255))]
+    pub organization: Option<String>,
+
+    pub due_date: Option<DateTime<Utc>>,
+
     pub remarks: Option<String>,
 }
 
@@ -39,7 +60,62 @@ pub struct ReturnLoanRequest {
     pub remarks: Option<String>,
 }
 
+/// 複数の物品を1人の借り手にまとめて貸し出す。`item_ids` と `label_ids` は併用できる
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BatchLoanRequest {
+    #[serde(default)]
+    pub item_ids: Vec<Uuid>,
+
+    #[serde(default)]
+    pub label_ids: Vec<String>,
+
+    #[validate(length(min = 1, max = 20))]
+    pub student_number: String,
+
+    #[validate(length(min = 1, max = 100))]
+    pub student_name: String,
+
+    #[validate(length(max = 255))]
+    pub organization: Option<String>,
+
+    pub due_date: Option<DateTime<Utc>>,
+
+    pub remarks: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BatchReturnRequest {
+    #[serde(default)]
+    pub item_ids: Vec<Uuid>,
+
+    #[serde(default)]
+    pub label_ids: Vec<String>,
+
+    pub return_date: Option<DateTime<Utc>>,
+
+    pub remarks: Option<String>,
+}
+
+/// 一括処理の物品ごとの結果
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchLoanItemResult {
+    pub item_id: Option<Uuid>,
+    pub label_id: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loan: Option<Loan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 一括処理の結果。1件でも失敗した場合は何も反映されず `committed` が false になる
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchLoanResponse {
+    pub committed: bool,
+    pub results: Vec<BatchLoanItemResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LoanWithItem {
     pub id: i64,
     pub item_id: Uuid,
@@ -50,6 +126,8 @@ pub struct LoanWithItem {
     pub organization: Option<String>,
     pub loan_date: DateTime<Utc>,
     pub return_date: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub is_overdue: bool,
     pub remarks: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -63,11 +141,35 @@ pub struct LoansListResponse {
     pub per_page: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LoanFilters {
     pub item_id: Option<Uuid>,
     pub student_number: Option<String>,
     pub active_only: Option<bool>,
-    pub page: Option<u32>,
-    pub per_page: Option<u32>,
+    pub overdue: Option<bool>,
+    pub due_before: Option<DateTime<Utc>>,
+}
+
+/// 借り手（学籍番号・団体）ごとにまとめた延滞中の貸出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverdueLoanGroup {
+    pub student_number: String,
+    pub student_name: String,
+    pub organization: Option<String>,
+    pub loans: Vec<LoanWithItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverdueLoansResponse {
+    pub groups: Vec<OverdueLoanGroup>,
+    pub total: i64,
+}
+
+/// 未返却のまま返却期限を過ぎているか
+pub fn is_overdue(
+    due_date: Option<DateTime<Utc>>,
+    return_date: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> bool {
+    return_date.is_none() && due_date.is_some_and(|due| due < now)
 }
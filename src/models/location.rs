@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::container::double_option;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocationKind {
+    Building,
+    Room,
+    Shelf,
+}
+
+impl LocationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LocationKind::Building => "building",
+            LocationKind::Room => "room",
+            LocationKind::Shelf => "shelf",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "building" => Some(LocationKind::Building),
+            "room" => Some(LocationKind::Room),
+            "shelf" => Some(LocationKind::Shelf),
+            _ => None,
+        }
+    }
+}
+
+/// 保管場所。`path` は建物から順に " > " でつないだ名前で、物品・コンテナの場所の文字列になる
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub id: i64,
+    pub name: String,
+    pub kind: LocationKind,
+    pub parent_id: Option<i64>,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateLocationRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    pub kind: LocationKind,
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateLocationRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    pub kind: Option<LocationKind>,
+    /// `null` で最上位に移す
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_id: Option<Option<i64>>,
+}
+
+/// `source_ids` の場所をすべて `target_id` にまとめる
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeLocationsRequest {
+    pub source_ids: Vec<i64>,
+    pub target_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeLocationsResponse {
+    pub location: Location,
+    pub merged_ids: Vec<i64>,
+    pub items_moved: u64,
+    pub containers_moved: u64,
+}
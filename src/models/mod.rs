@@ -1,13 +1,27 @@
+pub mod audit;
 pub mod cable_color;
 pub mod connector;
 pub mod container;
 pub mod item;
+pub mod label;
 pub mod loan;
+pub mod location;
+pub mod reservation;
+pub mod stocktake;
 pub mod tag;
+pub mod transfer;
+pub mod user;
 
+pub use audit::*;
 pub use cable_color::*;
 pub use connector::*;
 pub use container::*;
 pub use item::*;
+pub use label::*;
 pub use loan::*;
+pub use location::*;
+pub use reservation::*;
+pub use stocktake::*;
 pub use tag::*;
+pub use transfer::*;
+pub use user::*;
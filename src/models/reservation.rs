@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReservationStatus {
+    /// 予約中（まだ受け取られていない）
+    Reserved,
+    /// 受け取り済み（貸出に変換された）
+    Fulfilled,
+    Cancelled,
+}
+
+impl ReservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationStatus::Reserved => "reserved",
+            ReservationStatus::Fulfilled => "fulfilled",
+            ReservationStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reserved" => Some(ReservationStatus::Reserved),
+            "fulfilled" => Some(ReservationStatus::Fulfilled),
+            "cancelled" => Some(ReservationStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: i64,
+    pub item_id: Uuid,
+    pub student_number: String,
+    pub student_name: String,
+    pub organization: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: ReservationStatus,
+    pub loan_id: Option<i64>,
+    pub remarks: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateReservationRequest {
+    pub item_id: Uuid,
+
+    #[validate(length(min = 1, max = 20))]
+    pub student_number: String,
+
+    #[validate(length(min = 1, max = 100))]
+    pub student_name: String,
+
+    #[validate(length(max = 255))]
+    pub organization: Option<String>,
+
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+
+    pub remarks: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReservationFilters {
+    pub item_id: Option<Uuid>,
+    pub student_number: Option<String>,
+    pub status: Option<ReservationStatus>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationsListResponse {
+    pub reservations: Vec<Reservation>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+/// 指定期間に物品を使えない理由
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AvailabilityConflict {
+    Disposed,
+    Reservation {
+        id: i64,
+        student_number: String,
+        organization: Option<String>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    },
+    Loan {
+        id: i64,
+        student_number: String,
+        organization: Option<String>,
+        loan_date: DateTime<Utc>,
+        due_date: Option<DateTime<Utc>>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemAvailability {
+    pub item_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub available: bool,
+    pub conflicts: Vec<AvailabilityConflict>,
+}
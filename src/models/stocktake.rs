@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StocktakeStatus {
+    /// スキャンを受け付けている
+    Open,
+    Closed,
+}
+
+impl StocktakeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StocktakeStatus::Open => "open",
+            StocktakeStatus::Closed => "closed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(StocktakeStatus::Open),
+            "closed" => Some(StocktakeStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// 棚卸しの1回分。`location` か `container_ids` のどちらかを対象にする
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeSession {
+    pub id: i64,
+    pub name: String,
+    pub location: Option<String>,
+    pub container_ids: Vec<String>,
+    pub status: StocktakeStatus,
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+    pub closed_by: Option<String>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// 終了時に保管場所を修正した物品の数
+    pub corrections_applied: i32,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateStocktakeRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, max = 100))]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub container_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeScan {
+    pub id: i64,
+    pub session_id: i64,
+    pub label_id: String,
+    /// 物品として登録されていないラベルは None
+    pub item_id: Option<Uuid>,
+    pub location: Option<String>,
+    pub container_id: Option<String>,
+    pub scanned_by: String,
+    pub scanned_at: DateTime<Utc>,
+}
+
+/// `location`・`container_id` は見つけた場所。省略時はセッションの対象場所とみなす
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct RecordStocktakeScanRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub label_id: String,
+    #[validate(length(min = 1, max = 100))]
+    pub location: Option<String>,
+    pub container_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CloseStocktakeRequest {
+    /// 別の場所で見つかった物品の保管場所を、見つかった場所に修正する
+    #[serde(default)]
+    pub apply_corrections: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeItemEntry {
+    pub item_id: Uuid,
+    pub label_id: String,
+    pub name: String,
+    pub storage_type: String,
+    pub storage_location: Option<String>,
+    pub container_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeMisplacedEntry {
+    #[serde(flatten)]
+    pub item: StocktakeItemEntry,
+    /// 見つかった場所（分からない場合は None）
+    pub found_location: Option<String>,
+    pub found_container_id: Option<String>,
+    pub scanned_by: String,
+    pub scanned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeUnexpectedEntry {
+    pub label_id: String,
+    pub item_id: Option<Uuid>,
+    /// "unknown_label" または "disposed"
+    pub reason: String,
+    pub scanned_by: String,
+    pub scanned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeLoanEntry {
+    #[serde(flatten)]
+    pub item: StocktakeItemEntry,
+    /// 貸出中のはずなのにスキャンされた
+    pub scanned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeReport {
+    pub session_id: i64,
+    pub generated_at: DateTime<Utc>,
+    pub expected: i64,
+    pub found: i64,
+    pub scans: i64,
+    pub missing: Vec<StocktakeItemEntry>,
+    pub wrong_location: Vec<StocktakeMisplacedEntry>,
+    pub unexpected: Vec<StocktakeUnexpectedEntry>,
+    pub on_loan: Vec<StocktakeLoanEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseStocktakeResponse {
+    pub session: StocktakeSession,
+    pub report: StocktakeReport,
+    pub corrected_item_ids: Vec<Uuid>,
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::container::{double_option, Container};
+use super::item::Item;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferEntity {
+    Item,
+    Container,
+}
+
+impl TransferEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferEntity::Item => "item",
+            TransferEntity::Container => "container",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "item" => Some(TransferEntity::Item),
+            "container" => Some(TransferEntity::Container),
+            _ => None,
+        }
+    }
+}
+
+/// 物品・コンテナの移動の記録。1回の操作で記録したものは同じ `batch_id` になる
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: i64,
+    pub batch_id: Uuid,
+    pub entity_type: TransferEntity,
+    pub entity_id: String,
+    pub from_location: Option<String>,
+    pub from_container_id: Option<String>,
+    pub to_location: Option<String>,
+    pub to_container_id: Option<String>,
+    /// コンテナごと移動した場合の、移動したコンテナ
+    pub via_container_id: Option<String>,
+    pub note: Option<String>,
+    pub moved_by: String,
+    pub moved_at: DateTime<Utc>,
+}
+
+/// 記録する前の移動
+#[derive(Debug, Clone)]
+pub struct NewTransfer {
+    pub entity_type: TransferEntity,
+    pub entity_id: String,
+    pub from_location: Option<String>,
+    pub from_container_id: Option<String>,
+    pub to_location: Option<String>,
+    pub to_container_id: Option<String>,
+    pub via_container_id: Option<String>,
+}
+
+/// 場所を指定して親コンテナを省略した場合は、親コンテナから出してその場所に置く
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct MoveContainerRequest {
+    pub location_id: Option<i64>,
+    #[validate(length(min = 1, max = 100))]
+    pub location: Option<String>,
+    /// `null` で親コンテナから出す
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_container_id: Option<Option<String>>,
+    #[validate(length(max = 500))]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveContainerResponse {
+    pub container: Container,
+    pub batch_id: Uuid,
+    pub transfers: Vec<Transfer>,
+}
+
+/// `container_id` か、`location_id`・`location` のどちらかを移動先にする
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct BulkMoveItemsRequest {
+    pub ids: Vec<String>,
+    pub container_id: Option<String>,
+    pub location_id: Option<i64>,
+    #[validate(length(min = 1, max = 100))]
+    pub location: Option<String>,
+    #[validate(length(max = 500))]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkMoveItemsResponse {
+    pub items: Vec<Item>,
+    pub batch_id: Uuid,
+    pub transfers: Vec<Transfer>,
+}
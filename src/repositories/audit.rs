@@ -0,0 +1,128 @@
+//! 監査ログ（`audit_events`）の書き込み。
+//!
+//! 呼び出し側のトランザクションの中で使えるよう、接続を受け取る関数として置く。
+//! 一覧や検索は `AuditService` が持つ。
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::AppResult;
+use crate::models::{AuditAction, AuditEntity, CurrentUser};
+
+/// 差分から除外するフィールド（変更のたびに必ず変わるため）
+const IGNORED_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+/// 監査ログ用にエンティティをJSONへ変換する
+pub fn snapshot<T: Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
+
+/// 変更を1件記録する。作成時は `before`、削除時は `after` を `None` にする。
+/// 更新で実際に変わったフィールドがなければ何も記録しない。
+pub async fn insert_audit_postgres(
+    conn: &mut sqlx::PgConnection,
+    entity: AuditEntity,
+    entity_id: &str,
+    action: AuditAction,
+    actor: &CurrentUser,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> AppResult<()> {
+    let Some(changes) = event_changes(action, before, after) else {
+        return Ok(());
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO audit_events (entity_type, entity_id, action, actor_id, actor, changes, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(entity.as_str())
+    .bind(entity_id)
+    .bind(action.as_str())
+    .bind((actor.id != 0).then_some(actor.id))
+    .bind(&actor.username)
+    .bind(&changes)
+    .bind(Utc::now())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn insert_audit_sqlite(
+    conn: &mut sqlx::SqliteConnection,
+    entity: AuditEntity,
+    entity_id: &str,
+    action: AuditAction,
+    actor: &CurrentUser,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> AppResult<()> {
+    let Some(changes) = event_changes(action, before, after) else {
+        return Ok(());
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO audit_events (entity_type, entity_id, action, actor_id, actor, changes, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(entity.as_str())
+    .bind(entity_id)
+    .bind(action.as_str())
+    .bind((actor.id != 0).then_some(actor.id))
+    .bind(&actor.username)
+    .bind(&changes)
+    .bind(Utc::now())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// 記録する差分（JSON）。更新で変わったフィールドがなければ None
+fn event_changes(
+    action: AuditAction,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> Option<String> {
+    let changes = diff(before, after);
+    if action == AuditAction::Update && changes.is_empty() {
+        return None;
+    }
+    Some(Value::Object(changes).to_string())
+}
+
+/// 変更のあったフィールドだけを `{"field": {"before": .., "after": ..}}` の形にまとめる
+fn diff(before: Option<Value>, after: Option<Value>) -> Map<String, Value> {
+    let before = match before {
+        Some(Value::Object(map)) => map,
+        Some(other) => Map::from_iter([("value".to_string(), other)]),
+        None => Map::new(),
+    };
+    let after = match after {
+        Some(Value::Object(map)) => map,
+        Some(other) => Map::from_iter([("value".to_string(), other)]),
+        None => Map::new(),
+    };
+
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut changes = Map::new();
+    for key in keys {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let old = before.get(key).cloned().unwrap_or(Value::Null);
+        let new = after.get(key).cloned().unwrap_or(Value::Null);
+        if old != new {
+            changes.insert(
+                key.clone(),
+                serde_json::json!({ "before": old, "after": new }),
+            );
+        }
+    }
+    changes
+}
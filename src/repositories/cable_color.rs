@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use super::{map_unique_violation, Page};
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{CableColor, CreateCableColorRequest, UpdateCableColorRequest};
+
+pub(super) const KIND: &str = "Cable color";
+
+#[async_trait]
+pub trait CableColorRepository: Send + Sync {
+    async fn insert(
+        &self,
+        req: &CreateCableColorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<CableColor>;
+
+    async fn find(&self, id: i64) -> AppResult<Option<CableColor>>;
+
+    /// 登録の新しい順。2つ目は全件数
+    async fn list(&self, page: Page) -> AppResult<(Vec<CableColor>, i64)>;
+
+    /// 指定されたフィールドだけを書き換える。見つからなければ None
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateCableColorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<CableColor>>;
+
+    /// 削除したかどうか
+    async fn delete(&self, id: i64) -> AppResult<bool>;
+}
+
+pub fn cable_color_repository(db: &DatabasePool) -> Arc<dyn CableColorRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresCableColorRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteCableColorRepository::new(pool.clone())),
+    }
+}
+
+mod postgres {
+    pub const INSERT: &str = r#"
+        INSERT INTO cable_colors (name, hex_code, description, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        RETURNING id, name, hex_code, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, hex_code, description, created_at, updated_at
+        FROM cable_colors WHERE id = $1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, hex_code, description, created_at, updated_at
+        FROM cable_colors ORDER BY created_at DESC, id DESC LIMIT $1 OFFSET $2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE cable_colors SET
+            name = COALESCE($2, name),
+            hex_code = COALESCE($3, hex_code),
+            description = COALESCE($4, description),
+            updated_at = $5
+        WHERE id = $1
+        RETURNING id, name, hex_code, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM cable_colors WHERE id = $1";
+}
+
+mod sqlite {
+    pub const INSERT: &str = r#"
+        INSERT INTO cable_colors (name, hex_code, description, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?4)
+        RETURNING id, name, hex_code, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, hex_code, description, created_at, updated_at
+        FROM cable_colors WHERE id = ?1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, hex_code, description, created_at, updated_at
+        FROM cable_colors ORDER BY created_at DESC, id DESC LIMIT ?1 OFFSET ?2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE cable_colors SET
+            name = COALESCE(?2, name),
+            hex_code = COALESCE(?3, hex_code),
+            description = COALESCE(?4, description),
+            updated_at = ?5
+        WHERE id = ?1
+        RETURNING id, name, hex_code, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM cable_colors WHERE id = ?1";
+}
+
+macro_rules! sql_cable_color_repository {
+    ($name:ident, $pool:ty, $row:ty, $sql:ident) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_cable_color(row: $row) -> CableColor {
+                CableColor {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    hex_code: row.get("hex_code"),
+                    description: row.get("description"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl CableColorRepository for $name {
+            async fn insert(
+                &self,
+                req: &CreateCableColorRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<CableColor> {
+                let row = sqlx::query($sql::INSERT)
+                    .bind(&req.name)
+                    .bind(&req.hex_code)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| map_unique_violation(e, KIND, &req.name))?;
+                Ok(Self::row_to_cable_color(row))
+            }
+
+            async fn find(&self, id: i64) -> AppResult<Option<CableColor>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_cable_color))
+            }
+
+            async fn list(&self, page: Page) -> AppResult<(Vec<CableColor>, i64)> {
+                let rows = sqlx::query($sql::LIST)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cable_colors")
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok((rows.into_iter().map(Self::row_to_cable_color).collect(), total))
+            }
+
+            async fn update(
+                &self,
+                id: i64,
+                req: &UpdateCableColorRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<CableColor>> {
+                let row = sqlx::query($sql::UPDATE)
+                    .bind(id)
+                    .bind(&req.name)
+                    .bind(&req.hex_code)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        map_unique_violation(e, KIND, req.name.as_deref().unwrap_or_default())
+                    })?;
+                Ok(row.map(Self::row_to_cable_color))
+            }
+
+            async fn delete(&self, id: i64) -> AppResult<bool> {
+                let result = sqlx::query($sql::DELETE)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+    };
+}
+
+sql_cable_color_repository!(
+    PostgresCableColorRepository,
+    sqlx::PgPool,
+    sqlx::postgres::PgRow,
+    postgres
+);
+sql_cable_color_repository!(
+    SqliteCableColorRepository,
+    sqlx::SqlitePool,
+    sqlx::sqlite::SqliteRow,
+    sqlite
+);
@@ -0,0 +1,951 @@
+//! すべての実装に同じ操作をして、同じ結果になることを確かめる。
+//!
+//! メモリ上の実装と SQLite（`sqlite::memory:`）は常に、Postgres は
+//! `TEST_DATABASE_URL` が設定されているときだけ対象にする。
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use uuid::Uuid;
+
+use super::memory::{
+    InMemoryCableColorRepository, InMemoryConnectorRepository, InMemoryInventory,
+    InMemoryTagRepository,
+};
+use super::*;
+use crate::db::DatabasePool;
+use crate::error::AppError;
+use crate::models::{
+    BatchLoanItemResult, Container, ContainerWithItemCount, CreateCableColorRequest,
+    CreateConnectorRequest, CreateItemRequest, CreateTagRequest, CurrentUser, LabelAlias,
+    LoanFilters, UpdateCableColorRequest, UpdateConnectorRequest, UpdateContainerRequest,
+    UpdateItemRequest, UpdateTagRequest,
+};
+use crate::tests::TestDatabase;
+
+struct Backend {
+    name: &'static str,
+    cable_colors: Arc<dyn CableColorRepository>,
+    connectors: Arc<dyn ConnectorRepository>,
+    tags: Arc<dyn TagRepository>,
+    items: Arc<dyn ItemRepository>,
+    loans: Arc<dyn LoanRepository>,
+    containers: Arc<dyn ContainerRepository>,
+    db: Option<TestDatabase>,
+}
+
+impl Backend {
+    fn memory() -> Self {
+        let inventory = Arc::new(InMemoryInventory::default());
+        Self {
+            name: "memory",
+            cable_colors: Arc::new(InMemoryCableColorRepository::default()),
+            connectors: Arc::new(InMemoryConnectorRepository::default()),
+            tags: Arc::new(InMemoryTagRepository::default()),
+            items: inventory.clone(),
+            loans: inventory.clone(),
+            containers: inventory,
+            db: None,
+        }
+    }
+
+    async fn sql(db: TestDatabase) -> Self {
+        // マイグレーションで入る初期データを消して、メモリ上の実装と同じ空の状態から始める
+        db.execute("DELETE FROM item_tags; DELETE FROM tags; DELETE FROM connectors; DELETE FROM cable_colors")
+            .await;
+        Self {
+            name: db.backend(),
+            cable_colors: cable_color_repository(&db.pool),
+            connectors: connector_repository(&db.pool),
+            tags: tag_repository(&db.pool),
+            items: item_repository(&db.pool),
+            loans: loan_repository(&db.pool),
+            containers: container_repository(&db.pool),
+            db: Some(db),
+        }
+    }
+
+    /// `item_tags` の外部キーを満たすための最小限の物品
+    async fn new_item(&self, label_id: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        match self.db.as_ref().map(|db| &db.pool) {
+            None => {}
+            Some(DatabasePool::Postgres(pool)) => {
+                sqlx::query("INSERT INTO items (id, name, label_id) VALUES ($1, $2, $3)")
+                    .bind(id)
+                    .bind(label_id)
+                    .bind(label_id)
+                    .execute(pool)
+                    .await
+                    .unwrap();
+            }
+            Some(DatabasePool::Sqlite(pool)) => {
+                sqlx::query("INSERT INTO items (id, name, label_id) VALUES (?1, ?2, ?3)")
+                    .bind(id.to_string())
+                    .bind(label_id)
+                    .bind(label_id)
+                    .execute(pool)
+                    .await
+                    .unwrap();
+            }
+        }
+        id
+    }
+
+    async fn teardown(self) {
+        if let Some(db) = self.db {
+            db.drop().await;
+        }
+    }
+}
+
+async fn backends() -> Vec<Backend> {
+    let mut backends = vec![
+        Backend::memory(),
+        Backend::sql(TestDatabase::sqlite().await).await,
+    ];
+    if let Some(db) = TestDatabase::postgres().await {
+        backends.push(Backend::sql(db).await);
+    }
+    backends
+}
+
+/// 秒単位に丸めた時刻（どの実装でも往復で変わらない）
+fn at(seconds: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 1, 9, 0, 0).unwrap() + Duration::seconds(seconds)
+}
+
+fn is_conflict(result: Result<impl std::fmt::Debug, AppError>) -> bool {
+    matches!(result, Err(AppError::Conflict(_)))
+}
+
+fn is_bad_request(result: Result<impl std::fmt::Debug, AppError>) -> bool {
+    matches!(result, Err(AppError::BadRequest(_)))
+}
+
+fn new_item(label_id: &str, name: &str) -> CreateItemRequest {
+    CreateItemRequest {
+        name: name.to_string(),
+        label_id: label_id.to_string(),
+        model_number: None,
+        remarks: None,
+        purchase_year: None,
+        purchase_amount: None,
+        durability_years: None,
+        is_depreciation_target: None,
+        connection_names: None,
+        cable_color_pattern: None,
+        storage_location: Some("Room 1".to_string()),
+        location_id: None,
+        container_id: None,
+        storage_type: None,
+        qr_code_type: None,
+        image_url: None,
+    }
+}
+
+fn new_container(id: &str, name: &str, location: &str, created_at: DateTime<Utc>) -> Container {
+    Container {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: None,
+        location: location.to_string(),
+        image_url: None,
+        created_at,
+        updated_at: created_at,
+        is_disposed: false,
+        parent_container_id: None,
+        location_id: None,
+    }
+}
+
+fn batch_at(actor: &CurrentUser, seconds: i64) -> TransferBatch<'_> {
+    TransferBatch {
+        moved_at: at(seconds),
+        ..TransferBatch::new(None, actor)
+    }
+}
+
+fn batch_item(item_id: Uuid) -> BatchLoanItemResult {
+    BatchLoanItemResult {
+        item_id: Some(item_id),
+        label_id: None,
+        success: false,
+        loan: None,
+        error: None,
+    }
+}
+
+#[tokio::test]
+async fn cable_colors_behave_the_same_on_every_backend() {
+    for backend in backends().await {
+        let repo = &backend.cable_colors;
+        let name = backend.name;
+        let create = |name: &str, hex: &str| CreateCableColorRequest {
+            name: name.to_string(),
+            hex_code: Some(hex.to_string()),
+            description: None,
+        };
+
+        let red = repo.insert(&create("赤", "#FF0000"), at(0)).await.unwrap();
+        let blue = repo.insert(&create("青", "#0000FF"), at(1)).await.unwrap();
+        assert_eq!(red.name, "赤", "{name}");
+        assert_eq!(red.created_at, at(0), "{name}");
+        assert_eq!(
+            repo.find(red.id).await.unwrap(),
+            Some(red.clone()),
+            "{name}"
+        );
+        assert!(
+            is_conflict(repo.insert(&create("赤", "#FF0001"), at(2)).await),
+            "{name}"
+        );
+
+        // 新しい順
+        let (rows, total) = repo.list(Page::new(1, 10)).await.unwrap();
+        assert_eq!(total, 2, "{name}");
+        assert_eq!(rows, vec![blue.clone(), red.clone()], "{name}");
+        let (rows, total) = repo.list(Page::new(2, 1)).await.unwrap();
+        assert_eq!((rows, total), (vec![red.clone()], 2), "{name}");
+
+        let update = UpdateCableColorRequest {
+            name: None,
+            hex_code: None,
+            description: Some("電源用".to_string()),
+        };
+        let updated = repo.update(red.id, &update, at(5)).await.unwrap().unwrap();
+        assert_eq!(updated.hex_code.as_deref(), Some("#FF0000"), "{name}");
+        assert_eq!(updated.description.as_deref(), Some("電源用"), "{name}");
+        assert_eq!(updated.updated_at, at(5), "{name}");
+        assert_eq!(updated.created_at, at(0), "{name}");
+        assert_eq!(
+            repo.update(9999, &update, at(6)).await.unwrap(),
+            None,
+            "{name}"
+        );
+        let rename = UpdateCableColorRequest {
+            name: Some("青".to_string()),
+            hex_code: None,
+            description: None,
+        };
+        assert!(
+            is_conflict(repo.update(red.id, &rename, at(7)).await),
+            "{name}"
+        );
+
+        assert!(repo.delete(red.id).await.unwrap(), "{name}");
+        assert!(!repo.delete(red.id).await.unwrap(), "{name}");
+        assert_eq!(repo.find(red.id).await.unwrap(), None, "{name}");
+        assert_eq!(repo.list(Page::new(1, 10)).await.unwrap().1, 1, "{name}");
+
+        backend.teardown().await;
+    }
+}
+
+#[tokio::test]
+async fn connectors_behave_the_same_on_every_backend() {
+    for backend in backends().await {
+        let repo = &backend.connectors;
+        let name = backend.name;
+        let create = |name: &str, gender: Option<&str>| CreateConnectorRequest {
+            name: name.to_string(),
+            gender: gender.map(str::to_string),
+            description: None,
+        };
+
+        let xlr = repo
+            .insert(&create("XLR", Some("male")), at(0))
+            .await
+            .unwrap();
+        let bnc = repo.insert(&create("BNC", None), at(1)).await.unwrap();
+        let rca = repo
+            .insert(&create("RCA", Some("female")), at(2))
+            .await
+            .unwrap();
+        assert_eq!(bnc.gender, None, "{name}");
+        assert_eq!(
+            repo.find(xlr.id).await.unwrap(),
+            Some(xlr.clone()),
+            "{name}"
+        );
+        assert!(
+            is_conflict(repo.insert(&create("XLR", None), at(3)).await),
+            "{name}"
+        );
+
+        // 名前順
+        let (rows, total) = repo.list(Page::new(1, 10)).await.unwrap();
+        assert_eq!(total, 3, "{name}");
+        assert_eq!(rows, vec![bnc.clone(), rca.clone(), xlr.clone()], "{name}");
+        let (rows, _) = repo.list(Page::new(2, 2)).await.unwrap();
+        assert_eq!(rows, vec![xlr.clone()], "{name}");
+        let (rows, _) = repo.list(Page::new(3, 2)).await.unwrap();
+        assert!(rows.is_empty(), "{name}");
+
+        let update = UpdateConnectorRequest {
+            name: Some("XLR3".to_string()),
+            gender: None,
+            description: None,
+        };
+        let updated = repo.update(xlr.id, &update, at(5)).await.unwrap().unwrap();
+        assert_eq!(updated.name, "XLR3", "{name}");
+        assert_eq!(updated.gender.as_deref(), Some("male"), "{name}");
+        assert_eq!(
+            repo.update(9999, &update, at(6)).await.unwrap(),
+            None,
+            "{name}"
+        );
+        let rename = UpdateConnectorRequest {
+            name: Some("BNC".to_string()),
+            gender: None,
+            description: None,
+        };
+        assert!(
+            is_conflict(repo.update(xlr.id, &rename, at(7)).await),
+            "{name}"
+        );
+
+        assert!(repo.delete(bnc.id).await.unwrap(), "{name}");
+        assert!(!repo.delete(bnc.id).await.unwrap(), "{name}");
+        assert_eq!(repo.find(bnc.id).await.unwrap(), None, "{name}");
+
+        backend.teardown().await;
+    }
+}
+
+#[tokio::test]
+async fn tags_behave_the_same_on_every_backend() {
+    for backend in backends().await {
+        let repo = &backend.tags;
+        let name = backend.name;
+        let create = |name: &str| CreateTagRequest {
+            name: name.to_string(),
+            color: None,
+            description: None,
+        };
+
+        let lighting = repo.insert(&create("照明"), at(0)).await.unwrap();
+        let audio = repo.insert(&create("音響"), at(1)).await.unwrap();
+        let video = repo.insert(&create("映像"), at(2)).await.unwrap();
+        assert!(
+            is_conflict(repo.insert(&create("音響"), at(3)).await),
+            "{name}"
+        );
+
+        let (rows, total) = repo.list(Page::new(1, 10)).await.unwrap();
+        assert_eq!(total, 3, "{name}");
+        let names: Vec<_> = rows.iter().map(|tag| tag.name.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted, "{name}");
+
+        let update = UpdateTagRequest {
+            name: None,
+            color: Some("#FFCC00".to_string()),
+            description: None,
+        };
+        let updated = repo
+            .update(lighting.id, &update, at(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.name, "照明", "{name}");
+        assert_eq!(updated.color.as_deref(), Some("#FFCC00"), "{name}");
+        assert_eq!(
+            repo.update(9999, &update, at(6)).await.unwrap(),
+            None,
+            "{name}"
+        );
+
+        // 物品のタグは置き換えで、重複は1つにまとまる
+        let item = backend.new_item("CONF1").await;
+        let other = backend.new_item("CONF2").await;
+        assert!(repo.item_tags(item).await.unwrap().is_empty(), "{name}");
+        repo.set_item_tags(item, &[video.id, audio.id, video.id])
+            .await
+            .unwrap();
+        repo.set_item_tags(other, &[audio.id]).await.unwrap();
+        let mut expected = vec![audio.clone(), video.clone()];
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(repo.item_tags(item).await.unwrap(), expected, "{name}");
+
+        repo.set_item_tags(item, &[updated.id]).await.unwrap();
+        assert_eq!(
+            repo.item_tags(item).await.unwrap(),
+            vec![updated.clone()],
+            "{name}"
+        );
+
+        // タグを消すと物品からも外れる
+        assert!(repo.delete(audio.id).await.unwrap(), "{name}");
+        assert!(repo.item_tags(other).await.unwrap().is_empty(), "{name}");
+        repo.set_item_tags(item, &[]).await.unwrap();
+        assert!(repo.item_tags(item).await.unwrap().is_empty(), "{name}");
+        assert!(!repo.delete(audio.id).await.unwrap(), "{name}");
+
+        backend.teardown().await;
+    }
+}
+
+#[tokio::test]
+async fn items_behave_the_same_on_every_backend() {
+    let actor = CurrentUser::system();
+    for backend in backends().await {
+        let repo = &backend.items;
+        let name = backend.name;
+
+        let mic = repo
+            .insert(
+                Uuid::new_v4(),
+                &CreateItemRequest {
+                    model_number: Some("SM58".to_string()),
+                    connection_names: Some(vec!["XLR".to_string(), "USB".to_string()]),
+                    ..new_item("MIC1", "Microphone")
+                },
+                at(0),
+            )
+            .await
+            .unwrap();
+        let cable = repo
+            .insert(
+                Uuid::new_v4(),
+                &CreateItemRequest {
+                    connection_names: Some(vec!["XLR".to_string()]),
+                    ..new_item("CBL1", "Cable")
+                },
+                at(1),
+            )
+            .await
+            .unwrap();
+        let amp = repo
+            .insert(Uuid::new_v4(), &new_item("AMP1", "Amplifier"), at(2))
+            .await
+            .unwrap();
+        assert_eq!(mic.storage_type, "location", "{name}");
+        assert_eq!(mic.is_on_loan, Some(false), "{name}");
+        assert_eq!(mic.is_disposed, Some(false), "{name}");
+        assert_eq!(mic.created_at, at(0), "{name}");
+        assert_eq!(repo.find(mic.id).await.unwrap(), Some(mic.clone()), "{name}");
+        assert_eq!(repo.find(Uuid::new_v4()).await.unwrap(), None, "{name}");
+        assert_eq!(
+            repo.find_by_label("CBL1").await.unwrap(),
+            Some(cable.clone()),
+            "{name}"
+        );
+        let labels = ["CBL1".to_string(), "AMP1".to_string(), "NONE".to_string()];
+        assert_eq!(
+            repo.find_by_labels(&labels).await.unwrap(),
+            vec![amp.clone(), cable.clone()],
+            "{name}"
+        );
+
+        // 新しい順。検索は大文字と小文字を区別しない
+        let all = ItemFilter::default();
+        let (rows, total) = repo.list(&all, Page::new(1, 2)).await.unwrap();
+        assert_eq!((rows, total), (vec![amp.clone(), cable.clone()], 3), "{name}");
+        let search = ItemFilter {
+            search: Some("sm5".to_string()),
+            ..ItemFilter::default()
+        };
+        let (rows, total) = repo.list(&search, Page::new(1, 10)).await.unwrap();
+        assert_eq!((rows, total), (vec![mic.clone()], 1), "{name}");
+        assert_eq!(
+            repo.connection_names().await.unwrap(),
+            vec!["USB".to_string(), "XLR".to_string()],
+            "{name}"
+        );
+
+        let update = UpdateItemRequest {
+            remarks: Some("Vocal".to_string()),
+            ..UpdateItemRequest::default()
+        };
+        let updated = repo.update(mic.id, &update, at(5)).await.unwrap().unwrap();
+        assert_eq!(updated.name, "Microphone", "{name}");
+        assert_eq!(updated.remarks.as_deref(), Some("Vocal"), "{name}");
+        assert_eq!(updated.updated_at, at(5), "{name}");
+        assert_eq!(
+            repo.update(Uuid::new_v4(), &update, at(5)).await.unwrap(),
+            None,
+            "{name}"
+        );
+        let image_url = "https://example.com/cable.jpg";
+        let with_image = repo.set_image(cable.id, image_url, at(6)).await.unwrap();
+        assert_eq!(
+            with_image.and_then(|item| item.image_url).as_deref(),
+            Some(image_url),
+            "{name}"
+        );
+
+        // コンテナに移す
+        backend
+            .containers
+            .insert(&new_container("CONF-BOX", "Box", "Room 2", at(0)))
+            .await
+            .unwrap();
+        let placement = ItemPlacement {
+            storage_type: "container",
+            container_id: Some("CONF-BOX".to_string()),
+            storage_location: None,
+            location_id: None,
+        };
+        repo.move_items(&[mic.id, amp.id], &placement, &batch_at(&actor, 7), &[])
+            .await
+            .unwrap();
+        let stored = repo.list_in_container("CONF-BOX").await.unwrap();
+        let stored: Vec<_> = stored.iter().map(|item| item.label_id.as_str()).collect();
+        assert_eq!(stored, vec!["AMP1", "MIC1"], "{name}");
+        let moved = repo.find(amp.id).await.unwrap().unwrap();
+        assert_eq!(moved.storage_location, None, "{name}");
+        assert_eq!(moved.updated_at, at(7), "{name}");
+
+        // 廃棄した物品はコンテナの中身に数えない
+        assert_eq!(
+            repo.set_disposed(&[amp.id, Uuid::new_v4()], true, at(8))
+                .await
+                .unwrap(),
+            1,
+            "{name}"
+        );
+        let stored = repo.list_in_container("CONF-BOX").await.unwrap();
+        assert_eq!(stored.len(), 1, "{name}");
+        let disposed = ItemFilter {
+            is_disposed: Some(true),
+            ..ItemFilter::default()
+        };
+        let rows = repo.list_all(&disposed).await.unwrap();
+        assert_eq!(rows.len(), 1, "{name}");
+        assert_eq!(rows[0].id, amp.id, "{name}");
+        assert!(!repo.any_on_loan(&[mic.id, amp.id]).await.unwrap(), "{name}");
+
+        // 貼り替えた古いラベルは別名として残り、元のラベルに戻すと消える
+        let relabel = LabelAlias {
+            label_id: "MIC1".to_string(),
+            item_id: mic.id,
+            replaced_by: "MIC2".to_string(),
+            reason: Some("peeled".to_string()),
+            created_by: actor.username.clone(),
+            created_at: at(9),
+        };
+        assert!(repo.relabel(&relabel).await.unwrap(), "{name}");
+        assert!(!repo.relabel(&relabel).await.unwrap(), "{name}");
+        let relabeled = repo.find_by_label("MIC2").await.unwrap().unwrap();
+        assert_eq!(relabeled.id, mic.id, "{name}");
+        assert_eq!(relabeled.updated_at, at(9), "{name}");
+        assert_eq!(
+            repo.find_label_alias("MIC1").await.unwrap(),
+            Some(relabel.clone()),
+            "{name}"
+        );
+        let restore = LabelAlias {
+            label_id: "MIC2".to_string(),
+            replaced_by: "MIC1".to_string(),
+            reason: None,
+            created_at: at(10),
+            ..relabel.clone()
+        };
+        assert!(repo.relabel(&restore).await.unwrap(), "{name}");
+        assert_eq!(repo.find_label_alias("MIC1").await.unwrap(), None, "{name}");
+        assert_eq!(
+            repo.find_label_alias("MIC2").await.unwrap(),
+            Some(restore.clone()),
+            "{name}"
+        );
+
+        assert_eq!(repo.delete(&[cable.id, cable.id]).await.unwrap(), 1, "{name}");
+        assert_eq!(repo.delete(&[cable.id]).await.unwrap(), 0, "{name}");
+        assert_eq!(repo.find(cable.id).await.unwrap(), None, "{name}");
+
+        backend.teardown().await;
+    }
+}
+
+#[tokio::test]
+async fn loans_behave_the_same_on_every_backend() {
+    let actor = CurrentUser::system();
+    for backend in backends().await {
+        let repo = &backend.loans;
+        let items = &backend.items;
+        let name = backend.name;
+        let mic = items
+            .insert(Uuid::new_v4(), &new_item("MIC1", "Microphone"), at(0))
+            .await
+            .unwrap();
+        let cable = items
+            .insert(Uuid::new_v4(), &new_item("CBL1", "Cable"), at(0))
+            .await
+            .unwrap();
+        let broken = items
+            .insert(Uuid::new_v4(), &new_item("BRK1", "Broken"), at(0))
+            .await
+            .unwrap();
+        items.set_disposed(&[broken.id], true, at(0)).await.unwrap();
+
+        let loan = NewLoan {
+            student_number: "S001".to_string(),
+            student_name: "Student One".to_string(),
+            organization: Some("Club".to_string()),
+            remarks: None,
+            loan_date: at(10),
+            due_date: Some(at(100)),
+        };
+        let first = repo.insert(mic.id, &loan, None, &actor).await.unwrap().unwrap();
+        assert_eq!(first.item_id, mic.id, "{name}");
+        assert_eq!(first.created_at, at(10), "{name}");
+        assert_eq!(first.return_date, None, "{name}");
+        let taken = items.find(mic.id).await.unwrap().unwrap();
+        assert_eq!(taken.is_on_loan, Some(true), "{name}");
+        assert_eq!(taken.updated_at, at(10), "{name}");
+        assert!(items.any_on_loan(&[cable.id, mic.id]).await.unwrap(), "{name}");
+
+        // 貸出中・廃棄済み・存在しない物品は貸し出さない
+        assert_eq!(repo.insert(mic.id, &loan, None, &actor).await.unwrap(), None, "{name}");
+        assert_eq!(repo.insert(broken.id, &loan, None, &actor).await.unwrap(), None, "{name}");
+        assert_eq!(
+            repo.insert(Uuid::new_v4(), &loan, None, &actor).await.unwrap(),
+            None,
+            "{name}"
+        );
+
+        let second = repo
+            .insert(
+                cable.id,
+                &NewLoan {
+                    student_number: "S002".to_string(),
+                    loan_date: at(20),
+                    due_date: None,
+                    ..loan.clone()
+                },
+                None,
+                &actor,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(repo.find(first.id).await.unwrap(), Some(first.clone()), "{name}");
+        assert_eq!(
+            repo.find_active(mic.id).await.unwrap(),
+            Some(first.clone()),
+            "{name}"
+        );
+
+        // 新しい順。延滞は `now` で判定する
+        let all = LoanFilters::default();
+        let (rows, total) = repo.list(&all, at(200), Page::new(1, 10)).await.unwrap();
+        let ids: Vec<_> = rows.iter().map(|row| row.id).collect();
+        assert_eq!((ids, total), (vec![second.id, first.id], 2), "{name}");
+        assert_eq!(rows[1].item_label_id, "MIC1", "{name}");
+        assert!(rows[1].is_overdue, "{name}");
+        assert!(!rows[0].is_overdue, "{name}");
+        let overdue = LoanFilters {
+            overdue: Some(true),
+            ..LoanFilters::default()
+        };
+        let (rows, total) = repo.list(&overdue, at(200), Page::new(1, 10)).await.unwrap();
+        assert_eq!((rows[0].id, total), (first.id, 1), "{name}");
+        let (_, total) = repo.list(&overdue, at(50), Page::new(1, 10)).await.unwrap();
+        assert_eq!(total, 0, "{name}");
+        let student = LoanFilters {
+            student_number: Some("S002".to_string()),
+            ..LoanFilters::default()
+        };
+        let (rows, _) = repo.list(&student, at(200), Page::new(1, 10)).await.unwrap();
+        assert_eq!(rows.len(), 1, "{name}");
+        assert_eq!(rows[0].id, second.id, "{name}");
+        let overdue: Vec<_> = repo
+            .list_overdue(at(200))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.id)
+            .collect();
+        assert_eq!(overdue, vec![first.id], "{name}");
+
+        let returned = repo
+            .mark_returned(first.id, at(150), Some("OK"), at(151))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(returned.return_date, Some(at(150)), "{name}");
+        assert_eq!(returned.remarks.as_deref(), Some("OK"), "{name}");
+        assert_eq!(returned.updated_at, at(151), "{name}");
+        assert_eq!(
+            items.find(mic.id).await.unwrap().unwrap().is_on_loan,
+            Some(false),
+            "{name}"
+        );
+        assert_eq!(
+            repo.mark_returned(first.id, at(152), None, at(152))
+                .await
+                .unwrap(),
+            None,
+            "{name}"
+        );
+        assert_eq!(
+            repo.list_for_item(mic.id).await.unwrap(),
+            vec![returned.clone()],
+            "{name}"
+        );
+
+        // 1件でも貸し出せなければ何も書き込まない
+        let batch = NewLoan {
+            loan_date: at(300),
+            ..loan.clone()
+        };
+        let mut results = vec![batch_item(mic.id), batch_item(broken.id)];
+        assert!(
+            !repo.insert_batch(&batch, &mut results, &actor).await.unwrap(),
+            "{name}"
+        );
+        assert_eq!(results[0].error, None, "{name}");
+        assert_eq!(
+            results[1].error.as_deref(),
+            Some("Item is disposed and cannot be loaned"),
+            "{name}"
+        );
+        assert_eq!(
+            items.find(mic.id).await.unwrap().unwrap().is_on_loan,
+            Some(false),
+            "{name}"
+        );
+        let mut results = vec![batch_item(mic.id)];
+        assert!(
+            repo.insert_batch(&batch, &mut results, &actor).await.unwrap(),
+            "{name}"
+        );
+        let batched = results[0].loan.clone().unwrap();
+        assert_eq!(batched.loan_date, at(300), "{name}");
+        assert_eq!(
+            repo.find_active(mic.id).await.unwrap(),
+            Some(batched.clone()),
+            "{name}"
+        );
+
+        let mut results = vec![batch_item(mic.id), batch_item(cable.id)];
+        assert!(
+            repo.return_batch(at(400), None, at(401), &mut results, &actor)
+                .await
+                .unwrap(),
+            "{name}"
+        );
+        let returned: Vec<_> = results
+            .iter()
+            .map(|result| result.loan.as_ref().and_then(|loan| loan.return_date))
+            .collect();
+        assert_eq!(returned, vec![Some(at(400)), Some(at(400))], "{name}");
+        assert!(!items.any_on_loan(&[mic.id, cable.id]).await.unwrap(), "{name}");
+        let mut results = vec![batch_item(mic.id)];
+        assert!(
+            !repo.return_batch(at(500), None, at(500), &mut results, &actor)
+                .await
+                .unwrap(),
+            "{name}"
+        );
+        assert_eq!(results[0].error.as_deref(), Some("Item is not on loan"), "{name}");
+
+        backend.teardown().await;
+    }
+}
+
+#[tokio::test]
+async fn containers_behave_the_same_on_every_backend() {
+    let actor = CurrentUser::system();
+    for backend in backends().await {
+        let repo = &backend.containers;
+        let name = backend.name;
+        let box_a = new_container("CONF-A", "Box A", "Room 1", at(0));
+        let box_b = new_container("CONF-B", "Box B", "Room 2", at(1));
+        let case_c = Container {
+            parent_container_id: Some("CONF-A".to_string()),
+            ..new_container("CONF-C", "Case C", "Room 1", at(2))
+        };
+        for container in [&box_a, &box_b, &case_c] {
+            repo.insert(container).await.unwrap();
+        }
+        assert_eq!(repo.find("CONF-A").await.unwrap(), Some(box_a.clone()), "{name}");
+        assert_eq!(repo.find("CONF-X").await.unwrap(), None, "{name}");
+        backend
+            .items
+            .insert(
+                Uuid::new_v4(),
+                &CreateItemRequest {
+                    container_id: Some("CONF-B".to_string()),
+                    storage_type: Some("container".to_string()),
+                    ..new_item("MIC1", "Microphone")
+                },
+                at(3),
+            )
+            .await
+            .unwrap();
+
+        let all = ContainerFilter::default();
+        let ids = |rows: &[ContainerWithItemCount]| -> Vec<String> {
+            rows.iter().map(|row| row.container.id.clone()).collect()
+        };
+        let (rows, total) = repo
+            .list(&all, ContainerSort::Name, false, Page::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!(total, 3, "{name}");
+        assert_eq!(ids(&rows), vec!["CONF-A", "CONF-B", "CONF-C"], "{name}");
+        assert_eq!(rows[1].item_count, 1, "{name}");
+        assert_eq!(rows[0].container, box_a, "{name}");
+        // 同じ値ならIDの昇順
+        let (rows, _) = repo
+            .list(&all, ContainerSort::ItemCount, true, Page::new(1, 2))
+            .await
+            .unwrap();
+        assert_eq!(ids(&rows), vec!["CONF-B", "CONF-A"], "{name}");
+        let search = ContainerFilter {
+            search: Some("case".to_string()),
+            ..ContainerFilter::default()
+        };
+        let (rows, total) = repo
+            .list(&search, ContainerSort::default(), false, Page::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!((ids(&rows), total), (vec!["CONF-C".to_string()], 1), "{name}");
+        let room = ContainerFilter {
+            location: Some("Room 1".to_string()),
+            ..ContainerFilter::default()
+        };
+        let (rows, _) = repo
+            .list(&room, ContainerSort::CreatedAt, true, Page::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!(ids(&rows), vec!["CONF-C", "CONF-A"], "{name}");
+        assert_eq!(
+            repo.list_at_location("Room 1").await.unwrap(),
+            vec![box_a.clone(), case_c.clone()],
+            "{name}"
+        );
+        assert_eq!(
+            repo.direct_item_counts().await.unwrap(),
+            [("CONF-B".to_string(), 1)].into_iter().collect(),
+            "{name}"
+        );
+        assert!(!repo.any_has_items(&["CONF-A".to_string()]).await.unwrap(), "{name}");
+        assert!(
+            repo.any_has_items(&["CONF-A".to_string(), "CONF-B".to_string()])
+                .await
+                .unwrap(),
+            "{name}"
+        );
+
+        let update = UpdateContainerRequest {
+            name: None,
+            description: Some("Shelf".to_string()),
+            location: None,
+            location_id: None,
+            is_disposed: None,
+            image_url: None,
+            parent_container_id: Some(None),
+        };
+        let updated = repo.update("CONF-C", &update, at(5)).await.unwrap().unwrap();
+        assert_eq!(updated.description.as_deref(), Some("Shelf"), "{name}");
+        assert_eq!(updated.parent_container_id, None, "{name}");
+        assert_eq!(updated.updated_at, at(5), "{name}");
+        assert_eq!(
+            repo.update("CONF-X", &update, at(5)).await.unwrap(),
+            None,
+            "{name}"
+        );
+
+        // 中のコンテナごと移動し、自分の中には入れない
+        let into_c = ContainerDestination::Parent {
+            parent_id: "CONF-C".to_string(),
+            location: None,
+        };
+        assert!(
+            repo.move_container("CONF-B", &into_c, &batch_at(&actor, 10))
+                .await
+                .unwrap(),
+            "{name}"
+        );
+        let moved = repo.find("CONF-B").await.unwrap().unwrap();
+        assert_eq!(moved.location, "Room 1", "{name}");
+        assert_eq!(moved.parent_container_id.as_deref(), Some("CONF-C"), "{name}");
+        assert_eq!(moved.updated_at, at(10), "{name}");
+        let into_b = ContainerDestination::Parent {
+            parent_id: "CONF-B".to_string(),
+            location: None,
+        };
+        assert!(
+            is_bad_request(
+                repo.move_container("CONF-C", &into_b, &batch_at(&actor, 11))
+                    .await
+            ),
+            "{name}"
+        );
+        let elsewhere = ContainerDestination::Parent {
+            parent_id: "CONF-A".to_string(),
+            location: Some("Room 9".to_string()),
+        };
+        assert!(
+            is_bad_request(
+                repo.move_container("CONF-C", &elsewhere, &batch_at(&actor, 11))
+                    .await
+            ),
+            "{name}"
+        );
+        let to_room_3 = UpdateContainerRequest {
+            location: Some("Room 3".to_string()),
+            description: None,
+            parent_container_id: None,
+            ..update
+        };
+        repo.update("CONF-A", &to_room_3, at(12)).await.unwrap();
+        let into_a = ContainerDestination::Parent {
+            parent_id: "CONF-A".to_string(),
+            location: None,
+        };
+        assert!(
+            repo.move_container("CONF-C", &into_a, &batch_at(&actor, 13))
+                .await
+                .unwrap(),
+            "{name}"
+        );
+        let hierarchy = repo.hierarchy().await.unwrap();
+        assert_eq!(hierarchy.chain("CONF-B"), vec!["CONF-B", "CONF-C", "CONF-A"], "{name}");
+        assert_eq!(
+            repo.find("CONF-B").await.unwrap().unwrap().location,
+            "Room 3",
+            "{name}"
+        );
+        assert!(
+            repo.move_container("CONF-C", &ContainerDestination::Unnest, &batch_at(&actor, 14))
+                .await
+                .unwrap(),
+            "{name}"
+        );
+        let unnested = repo.find("CONF-C").await.unwrap().unwrap();
+        assert_eq!(unnested.parent_container_id, None, "{name}");
+        assert_eq!(unnested.location, "Room 3", "{name}");
+        assert!(
+            !repo.move_container("CONF-X", &ContainerDestination::Unnest, &batch_at(&actor, 15))
+                .await
+                .unwrap(),
+            "{name}"
+        );
+
+        let ids_ab = ["CONF-A".to_string(), "CONF-X".to_string()];
+        assert_eq!(repo.set_disposed(&ids_ab, true, at(16)).await.unwrap(), 1, "{name}");
+        let (_, total) = repo
+            .list(&all, ContainerSort::default(), false, Page::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!(total, 2, "{name}");
+        let disposed = ContainerFilter {
+            include_disposed: true,
+            ..ContainerFilter::default()
+        };
+        let (_, total) = repo
+            .list(&disposed, ContainerSort::IsDisposed, false, Page::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!(total, 3, "{name}");
+        assert_eq!(repo.delete(&ids_ab).await.unwrap(), 1, "{name}");
+        assert_eq!(repo.find("CONF-A").await.unwrap(), None, "{name}");
+
+        backend.teardown().await;
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use super::{map_unique_violation, Page};
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{Connector, CreateConnectorRequest, UpdateConnectorRequest};
+
+pub(super) const KIND: &str = "Connector";
+
+#[async_trait]
+pub trait ConnectorRepository: Send + Sync {
+    async fn insert(
+        &self,
+        req: &CreateConnectorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Connector>;
+
+    async fn find(&self, id: i64) -> AppResult<Option<Connector>>;
+
+    /// 名前順。2つ目は全件数
+    async fn list(&self, page: Page) -> AppResult<(Vec<Connector>, i64)>;
+
+    /// 指定されたフィールドだけを書き換える。見つからなければ None
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateConnectorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Connector>>;
+
+    /// 削除したかどうか
+    async fn delete(&self, id: i64) -> AppResult<bool>;
+}
+
+pub fn connector_repository(db: &DatabasePool) -> Arc<dyn ConnectorRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresConnectorRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteConnectorRepository::new(pool.clone())),
+    }
+}
+
+mod postgres {
+    pub const INSERT: &str = r#"
+        INSERT INTO connectors (name, gender, description, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        RETURNING id, name, gender, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, gender, description, created_at, updated_at
+        FROM connectors WHERE id = $1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, gender, description, created_at, updated_at
+        FROM connectors ORDER BY name ASC, id ASC LIMIT $1 OFFSET $2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE connectors SET
+            name = COALESCE($2, name),
+            gender = COALESCE($3, gender),
+            description = COALESCE($4, description),
+            updated_at = $5
+        WHERE id = $1
+        RETURNING id, name, gender, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM connectors WHERE id = $1";
+}
+
+mod sqlite {
+    pub const INSERT: &str = r#"
+        INSERT INTO connectors (name, gender, description, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?4)
+        RETURNING id, name, gender, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, gender, description, created_at, updated_at
+        FROM connectors WHERE id = ?1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, gender, description, created_at, updated_at
+        FROM connectors ORDER BY name ASC, id ASC LIMIT ?1 OFFSET ?2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE connectors SET
+            name = COALESCE(?2, name),
+            gender = COALESCE(?3, gender),
+            description = COALESCE(?4, description),
+            updated_at = ?5
+        WHERE id = ?1
+        RETURNING id, name, gender, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM connectors WHERE id = ?1";
+}
+
+macro_rules! sql_connector_repository {
+    ($name:ident, $pool:ty, $row:ty, $sql:ident) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_connector(row: $row) -> Connector {
+                Connector {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    gender: row.get("gender"),
+                    description: row.get("description"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl ConnectorRepository for $name {
+            async fn insert(
+                &self,
+                req: &CreateConnectorRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Connector> {
+                let row = sqlx::query($sql::INSERT)
+                    .bind(&req.name)
+                    .bind(&req.gender)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| map_unique_violation(e, KIND, &req.name))?;
+                Ok(Self::row_to_connector(row))
+            }
+
+            async fn find(&self, id: i64) -> AppResult<Option<Connector>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_connector))
+            }
+
+            async fn list(&self, page: Page) -> AppResult<(Vec<Connector>, i64)> {
+                let rows = sqlx::query($sql::LIST)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM connectors")
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok((rows.into_iter().map(Self::row_to_connector).collect(), total))
+            }
+
+            async fn update(
+                &self,
+                id: i64,
+                req: &UpdateConnectorRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Connector>> {
+                let row = sqlx::query($sql::UPDATE)
+                    .bind(id)
+                    .bind(&req.name)
+                    .bind(&req.gender)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        map_unique_violation(e, KIND, req.name.as_deref().unwrap_or_default())
+                    })?;
+                Ok(row.map(Self::row_to_connector))
+            }
+
+            async fn delete(&self, id: i64) -> AppResult<bool> {
+                let result = sqlx::query($sql::DELETE)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+    };
+}
+
+sql_connector_repository!(
+    PostgresConnectorRepository,
+    sqlx::PgPool,
+    sqlx::postgres::PgRow,
+    postgres
+);
+sql_connector_repository!(
+    SqliteConnectorRepository,
+    sqlx::SqlitePool,
+    sqlx::sqlite::SqliteRow,
+    sqlite
+);
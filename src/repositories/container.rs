@@ -0,0 +1,820 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use super::{
+    insert_transfer_postgres, insert_transfer_sqlite, postgres_list, postgres_uuid_column,
+    sqlite_list, sqlite_uuid_column, Page, TransferBatch,
+};
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Container, ContainerWithItemCount, Item, NewTransfer, TransferEntity, UpdateContainerRequest,
+};
+
+/// 一覧の並び順
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerSort {
+    Name,
+    Location,
+    ItemCount,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    IsDisposed,
+}
+
+impl ContainerSort {
+    /// `sort_by` の値から。知らない値は登録日時順
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "name" => Self::Name,
+            "location" => Self::Location,
+            "item_count" => Self::ItemCount,
+            "updated_at" => Self::UpdatedAt,
+            "is_disposed" => Self::IsDisposed,
+            _ => Self::CreatedAt,
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "c.name",
+            Self::Location => "c.location",
+            Self::ItemCount => "item_count",
+            Self::CreatedAt => "c.created_at",
+            Self::UpdatedAt => "c.updated_at",
+            Self::IsDisposed => "c.is_disposed",
+        }
+    }
+}
+
+/// 一覧の絞り込み
+#[derive(Debug, Clone, Default)]
+pub struct ContainerFilter {
+    pub location: Option<String>,
+    pub include_disposed: bool,
+    /// 名前・説明・場所の部分一致（大文字と小文字は区別しない）
+    pub search: Option<String>,
+}
+
+/// コンテナの移動先
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerDestination {
+    /// 親コンテナから出して場所に置く
+    Location { path: String, location_id: i64 },
+    /// 親コンテナに入れる。`location` を指定するなら親コンテナの場所と同じでなければならない
+    Parent {
+        parent_id: String,
+        location: Option<String>,
+    },
+    /// 親コンテナから出して、今の場所にそのまま置く
+    Unnest,
+}
+
+#[async_trait]
+pub trait ContainerRepository: Send + Sync {
+    async fn insert(&self, container: &Container) -> AppResult<()>;
+
+    async fn find(&self, id: &str) -> AppResult<Option<Container>>;
+
+    /// 中に直接入っている物品の数つき。`total_item_count` は 0 のまま返す。2つ目は全件数
+    async fn list(
+        &self,
+        filter: &ContainerFilter,
+        sort: ContainerSort,
+        descending: bool,
+        page: Page,
+    ) -> AppResult<(Vec<ContainerWithItemCount>, i64)>;
+
+    /// 場所にある（廃棄済みを除く）コンテナを名前順に返す
+    async fn list_at_location(&self, location: &str) -> AppResult<Vec<Container>>;
+
+    /// 指定されたフィールドだけを書き換える。見つからなければ None
+    async fn update(
+        &self,
+        id: &str,
+        req: &UpdateContainerRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Container>>;
+
+    /// 書き換えた件数
+    async fn set_disposed(
+        &self,
+        ids: &[String],
+        is_disposed: bool,
+        now: DateTime<Utc>,
+    ) -> AppResult<u64>;
+
+    /// 削除した件数
+    async fn delete(&self, ids: &[String]) -> AppResult<u64>;
+
+    async fn hierarchy(&self) -> AppResult<ContainerHierarchy>;
+
+    /// コンテナに直接入っている（廃棄されていない）物品の数
+    async fn direct_item_counts(&self) -> AppResult<HashMap<String, i64>>;
+
+    /// どれかに（廃棄されていない）物品が入っているか
+    async fn any_has_items(&self, ids: &[String]) -> AppResult<bool>;
+
+    /// コンテナを中身ごと移動し、コンテナ・中のコンテナ・中の物品それぞれの移動を
+    /// `batch` として記録する。入れ子の確認から記録までを1つのトランザクションで行う。
+    /// コンテナが見つからなければ何もせず false
+    async fn move_container(
+        &self,
+        id: &str,
+        destination: &ContainerDestination,
+        batch: &TransferBatch<'_>,
+    ) -> AppResult<bool>;
+}
+
+pub fn container_repository(db: &DatabasePool) -> Arc<dyn ContainerRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresContainerRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteContainerRepository::new(pool.clone())),
+    }
+}
+
+/// コンテナの入れ子関係（ID → 親コンテナ）
+#[derive(Debug, Default)]
+pub struct ContainerHierarchy {
+    parents: HashMap<String, Option<String>>,
+    locations: HashMap<String, String>,
+}
+
+impl ContainerHierarchy {
+    /// (ID, 場所, 親コンテナ) の一覧から作る
+    pub(crate) fn from_rows(rows: Vec<(String, Option<String>, Option<String>)>) -> Self {
+        let mut hierarchy = Self::default();
+        for (id, location, parent) in rows {
+            hierarchy
+                .locations
+                .insert(id.clone(), location.unwrap_or_default());
+            hierarchy.parents.insert(id, parent);
+        }
+        hierarchy
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.parents.contains_key(id)
+    }
+
+    /// 自分から一番外側のコンテナまでのID。親子関係が循環していても途中で止まる
+    pub fn chain(&self, id: &str) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut current = Some(id.to_string());
+        while let Some(id) = current {
+            if chain.contains(&id) {
+                break;
+            }
+            let Some(parent) = self.parents.get(&id) else {
+                break;
+            };
+            current = parent.clone();
+            chain.push(id);
+        }
+        chain
+    }
+
+    /// 一番外側のコンテナの場所
+    pub fn effective_location(&self, id: &str) -> Option<&str> {
+        self.chain(id)
+            .last()
+            .and_then(|root| self.locations.get(root))
+            .map(String::as_str)
+    }
+
+    pub fn parent(&self, id: &str) -> Option<&str> {
+        self.parents.get(id).and_then(|parent| parent.as_deref())
+    }
+
+    /// 物品が実際に置かれている場所。コンテナが見つからなければ物品の保管場所
+    pub fn item_location(&self, item: &Item) -> Option<String> {
+        match item.container_id.as_deref() {
+            Some(container_id) if item.storage_type == "container" => self
+                .effective_location(container_id)
+                .map(str::to_string)
+                .or_else(|| item.storage_location.clone()),
+            _ => item.storage_location.clone(),
+        }
+    }
+
+    pub fn children(&self, id: &str) -> Vec<String> {
+        let mut children: Vec<String> = self
+            .parents
+            .iter()
+            .filter(|(_, parent)| parent.as_deref() == Some(id))
+            .map(|(child, _)| child.clone())
+            .collect();
+        children.sort();
+        children
+    }
+
+    /// 中に入っているすべてのコンテナ（自分は含まない）
+    pub fn descendants(&self, id: &str) -> Vec<String> {
+        let mut descendants = Vec::new();
+        let mut queue = vec![id.to_string()];
+        while let Some(current) = queue.pop() {
+            for child in self.children(&current) {
+                if child != id && !descendants.contains(&child) {
+                    descendants.push(child.clone());
+                    queue.push(child);
+                }
+            }
+        }
+        descendants
+    }
+
+    /// 入れようとしているコンテナが、自分自身や自分の中のコンテナでないことを確認する
+    pub fn ensure_can_nest(&self, id: &str, parent_id: &str) -> AppResult<()> {
+        if !self.contains(parent_id) {
+            return Err(AppError::NotFound(format!(
+                "Parent container {} not found",
+                parent_id
+            )));
+        }
+        if self.chain(parent_id).iter().any(|ancestor| ancestor == id) {
+            return Err(AppError::BadRequest(format!(
+                "Container {} cannot be placed inside itself or one of its own sub-containers",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 親コンテナの中に置くときの場所。指定された場所が親コンテナの場所と違えばエラー
+    pub fn nested_location(&self, parent_id: &str, location: Option<&str>) -> AppResult<String> {
+        let parent_location = self
+            .effective_location(parent_id)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Parent container {} not found", parent_id))
+            })?
+            .to_string();
+        match location {
+            Some(location) if location != parent_location => Err(AppError::BadRequest(format!(
+                "A nested container is located where its parent is ({})",
+                parent_location
+            ))),
+            _ => Ok(parent_location),
+        }
+    }
+
+    /// 移動後の場所を持っているコンテナ。親コンテナに入れるなら一番外側のコンテナ、
+    /// 場所を変えずに出すだけなら自分。場所に置くなら None
+    pub fn location_source(
+        &self,
+        id: &str,
+        destination: &ContainerDestination,
+    ) -> AppResult<Option<String>> {
+        match destination {
+            ContainerDestination::Location { .. } => Ok(None),
+            ContainerDestination::Parent {
+                parent_id,
+                location,
+            } => {
+                self.ensure_can_nest(id, parent_id)?;
+                self.nested_location(parent_id, location.as_deref())?;
+                Ok(Some(
+                    self.chain(parent_id)
+                        .pop()
+                        .unwrap_or_else(|| parent_id.to_string()),
+                ))
+            }
+            ContainerDestination::Unnest => Ok(Some(id.to_string())),
+        }
+    }
+}
+
+impl ContainerDestination {
+    pub fn parent_id(&self) -> Option<&str> {
+        match self {
+            Self::Parent { parent_id, .. } => Some(parent_id),
+            Self::Location { .. } | Self::Unnest => None,
+        }
+    }
+}
+
+/// コンテナを移動したときの、コンテナ・中のコンテナ・中の物品それぞれの移動。
+/// `hierarchy` は移動前の入れ子関係、`items` は (物品ID, 入っているコンテナID)
+pub(crate) fn move_transfers(
+    hierarchy: &ContainerHierarchy,
+    id: &str,
+    parent_id: Option<&str>,
+    location: &str,
+    items: &[(String, String)],
+) -> Vec<NewTransfer> {
+    let from_location = hierarchy.effective_location(id).map(str::to_string);
+    let moved = |entity_type, entity_id: &str, container_id: Option<String>| NewTransfer {
+        entity_type,
+        entity_id: entity_id.to_string(),
+        from_location: from_location.clone(),
+        from_container_id: container_id.clone(),
+        to_location: Some(location.to_string()),
+        to_container_id: container_id,
+        via_container_id: Some(id.to_string()),
+    };
+    let mut transfers = vec![NewTransfer {
+        entity_type: TransferEntity::Container,
+        entity_id: id.to_string(),
+        from_location: from_location.clone(),
+        from_container_id: hierarchy.parent(id).map(str::to_string),
+        to_location: Some(location.to_string()),
+        to_container_id: parent_id.map(str::to_string),
+        via_container_id: None,
+    }];
+    for descendant in hierarchy.descendants(id) {
+        let parent = hierarchy.parent(&descendant).map(str::to_string);
+        transfers.push(moved(TransferEntity::Container, &descendant, parent));
+    }
+    for (item_id, container_id) in items {
+        transfers.push(moved(TransferEntity::Item, item_id, Some(container_id.clone())));
+    }
+    transfers
+}
+
+macro_rules! container_columns {
+    () => {
+        r#"
+            id, name, description, location, image_url, created_at, updated_at, is_disposed,
+            parent_container_id, location_id
+        "#
+    };
+}
+
+macro_rules! container_with_count_columns {
+    () => {
+        r#"
+            c.id, c.name, c.description, c.location, c.image_url, c.created_at, c.updated_at,
+            c.is_disposed, c.parent_container_id, c.location_id, COUNT(i.id) AS item_count
+        "#
+    };
+}
+
+mod postgres {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE ($1 OR c.is_disposed = false)
+              AND ($2 IS NULL OR c.location = $2)
+              AND ($3 IS NULL OR c.name ILIKE $3 OR c.description ILIKE $3 OR c.location ILIKE $3)
+            "#
+        };
+    }
+
+    pub const INSERT: &str = r#"
+        INSERT INTO containers (
+            id, name, description, location, image_url, created_at, updated_at, is_disposed,
+            parent_container_id, location_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    "#;
+    pub const FIND: &str =
+        concat!("SELECT ", container_columns!(), " FROM containers WHERE id = $1");
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        container_with_count_columns!(),
+        r#"
+        FROM containers c
+        LEFT JOIN items i ON c.id = i.container_id AND i.storage_type = 'container'
+            AND (i.is_disposed IS NULL OR i.is_disposed = false)
+        "#,
+        filter!(),
+        r#"
+        GROUP BY c.id, c.name, c.description, c.location, c.image_url, c.created_at,
+            c.updated_at, c.is_disposed, c.parent_container_id, c.location_id
+        "#
+    );
+    pub const PAGE: &str = "LIMIT $4 OFFSET $5";
+    pub const COUNT: &str = concat!("SELECT COUNT(*) FROM containers c ", filter!());
+    pub const AT_LOCATION: &str = concat!(
+        "SELECT ",
+        container_columns!(),
+        " FROM containers WHERE location = $1 AND is_disposed = false ORDER BY name"
+    );
+    pub const UPDATE: &str = concat!(
+        r#"
+        UPDATE containers SET
+            name = COALESCE($2, name),
+            description = COALESCE($3, description),
+            location = COALESCE($4, location),
+            location_id = COALESCE($5, location_id),
+            image_url = COALESCE($6, image_url),
+            is_disposed = COALESCE($7, is_disposed),
+            parent_container_id = CASE WHEN $8 THEN $9 ELSE parent_container_id END,
+            updated_at = $10
+        WHERE id = $1
+        RETURNING "#,
+        container_columns!()
+    );
+    pub const RELOCATE: &str = r#"
+        UPDATE containers SET location = $1, location_id = $2, updated_at = $3
+        WHERE id = ANY($4)
+    "#;
+    pub const SET_DISPOSED: &str =
+        "UPDATE containers SET is_disposed = $1, updated_at = $2 WHERE id = ANY($3)";
+    pub const DELETE: &str = "DELETE FROM containers WHERE id = ANY($1)";
+    pub const HIERARCHY: &str = "SELECT id, location, parent_container_id FROM containers";
+    pub const ITEM_COUNTS: &str = r#"
+        SELECT container_id, COUNT(*) FROM items
+        WHERE container_id IS NOT NULL AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = false)
+        GROUP BY container_id
+    "#;
+    pub const ANY_HAS_ITEMS: &str = r#"
+        SELECT EXISTS(
+            SELECT 1 FROM items
+            WHERE container_id = ANY($1) AND storage_type = 'container'
+              AND (is_disposed IS NULL OR is_disposed = false)
+        )
+    "#;
+    /// 同時に入れ子を組み替えて循環させないよう、コンテナをすべてロックする
+    pub const LOCK: &str = "SELECT id FROM containers FOR UPDATE";
+    pub const LOCATION: &str = "SELECT location, location_id FROM containers WHERE id = $1";
+    pub const MOVE: &str = r#"
+        UPDATE containers SET location = $1, location_id = $2, parent_container_id = $3,
+            updated_at = $4
+        WHERE id = $5
+    "#;
+    pub const ITEMS_IN: &str = r#"
+        SELECT id, container_id FROM items
+        WHERE container_id = ANY($1) AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = false)
+        ORDER BY container_id, label_id
+    "#;
+}
+
+mod sqlite {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE (?1 OR c.is_disposed = 0)
+              AND (?2 IS NULL OR c.location = ?2)
+              AND (?3 IS NULL OR c.name LIKE ?3 OR c.description LIKE ?3 OR c.location LIKE ?3)
+            "#
+        };
+    }
+
+    pub const INSERT: &str = r#"
+        INSERT INTO containers (
+            id, name, description, location, image_url, created_at, updated_at, is_disposed,
+            parent_container_id, location_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+    "#;
+    pub const FIND: &str =
+        concat!("SELECT ", container_columns!(), " FROM containers WHERE id = ?1");
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        container_with_count_columns!(),
+        r#"
+        FROM containers c
+        LEFT JOIN items i ON c.id = i.container_id AND i.storage_type = 'container'
+            AND (i.is_disposed IS NULL OR i.is_disposed = 0)
+        "#,
+        filter!(),
+        r#"
+        GROUP BY c.id, c.name, c.description, c.location, c.image_url, c.created_at,
+            c.updated_at, c.is_disposed, c.parent_container_id, c.location_id
+        "#
+    );
+    pub const PAGE: &str = "LIMIT ?4 OFFSET ?5";
+    pub const COUNT: &str = concat!("SELECT COUNT(*) FROM containers c ", filter!());
+    pub const AT_LOCATION: &str = concat!(
+        "SELECT ",
+        container_columns!(),
+        " FROM containers WHERE location = ?1 AND is_disposed = 0 ORDER BY name"
+    );
+    pub const UPDATE: &str = concat!(
+        r#"
+        UPDATE containers SET
+            name = COALESCE(?2, name),
+            description = COALESCE(?3, description),
+            location = COALESCE(?4, location),
+            location_id = COALESCE(?5, location_id),
+            image_url = COALESCE(?6, image_url),
+            is_disposed = COALESCE(?7, is_disposed),
+            parent_container_id = CASE WHEN ?8 THEN ?9 ELSE parent_container_id END,
+            updated_at = ?10
+        WHERE id = ?1
+        RETURNING "#,
+        container_columns!()
+    );
+    pub const RELOCATE: &str = r#"
+        UPDATE containers SET location = ?1, location_id = ?2, updated_at = ?3
+        WHERE id IN (SELECT value FROM json_each(?4))
+    "#;
+    pub const SET_DISPOSED: &str = r#"
+        UPDATE containers SET is_disposed = ?1, updated_at = ?2
+        WHERE id IN (SELECT value FROM json_each(?3))
+    "#;
+    pub const DELETE: &str =
+        "DELETE FROM containers WHERE id IN (SELECT value FROM json_each(?1))";
+    pub const HIERARCHY: &str = "SELECT id, location, parent_container_id FROM containers";
+    pub const ITEM_COUNTS: &str = r#"
+        SELECT container_id, COUNT(*) FROM items
+        WHERE container_id IS NOT NULL AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = 0)
+        GROUP BY container_id
+    "#;
+    pub const ANY_HAS_ITEMS: &str = r#"
+        SELECT EXISTS(
+            SELECT 1 FROM items
+            WHERE container_id IN (SELECT value FROM json_each(?1)) AND storage_type = 'container'
+              AND (is_disposed IS NULL OR is_disposed = 0)
+        )
+    "#;
+    /// 最初に書き込んで書き込みロックを取り、入れ子の確認から記録までほかの書き込みを待たせる
+    pub const LOCK: &str = "UPDATE containers SET updated_at = updated_at";
+    pub const LOCATION: &str = "SELECT location, location_id FROM containers WHERE id = ?1";
+    pub const MOVE: &str = r#"
+        UPDATE containers SET location = ?1, location_id = ?2, parent_container_id = ?3,
+            updated_at = ?4
+        WHERE id = ?5
+    "#;
+    pub const ITEMS_IN: &str = r#"
+        SELECT id, container_id FROM items
+        WHERE container_id IN (SELECT value FROM json_each(?1)) AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = 0)
+        ORDER BY container_id, label_id
+    "#;
+}
+
+macro_rules! sql_container_repository {
+    (
+        $name:ident,
+        $pool:ty,
+        $row:ty,
+        $sql:ident,
+        $uuid_column:path,
+        $list:path,
+        $insert_transfer:path
+    ) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_container(row: &$row) -> Container {
+                Container {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    description: row.get("description"),
+                    location: row.get::<Option<String>, _>("location").unwrap_or_default(),
+                    image_url: row.get("image_url"),
+                    created_at: row
+                        .get::<Option<DateTime<Utc>>, _>("created_at")
+                        .unwrap_or_default(),
+                    updated_at: row
+                        .get::<Option<DateTime<Utc>>, _>("updated_at")
+                        .unwrap_or_default(),
+                    is_disposed: row.get::<Option<bool>, _>("is_disposed").unwrap_or(false),
+                    parent_container_id: row.get("parent_container_id"),
+                    location_id: row.get("location_id"),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl ContainerRepository for $name {
+            async fn insert(&self, container: &Container) -> AppResult<()> {
+                sqlx::query($sql::INSERT)
+                    .bind(&container.id)
+                    .bind(&container.name)
+                    .bind(&container.description)
+                    .bind(&container.location)
+                    .bind(&container.image_url)
+                    .bind(container.created_at)
+                    .bind(container.updated_at)
+                    .bind(container.is_disposed)
+                    .bind(&container.parent_container_id)
+                    .bind(container.location_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn find(&self, id: &str) -> AppResult<Option<Container>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.as_ref().map(Self::row_to_container))
+            }
+
+            async fn list(
+                &self,
+                filter: &ContainerFilter,
+                sort: ContainerSort,
+                descending: bool,
+                page: Page,
+            ) -> AppResult<(Vec<ContainerWithItemCount>, i64)> {
+                let search = filter.search.as_ref().map(|search| format!("%{}%", search));
+                // 並び順の列は決まった候補から選ぶので、値を埋め込まずに済む
+                let query = format!(
+                    "{} ORDER BY {} {}, c.id ASC {}",
+                    $sql::LIST,
+                    sort.column(),
+                    if descending { "DESC" } else { "ASC" },
+                    $sql::PAGE
+                );
+                let rows = sqlx::query(&query)
+                    .bind(filter.include_disposed)
+                    .bind(&filter.location)
+                    .bind(&search)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar($sql::COUNT)
+                    .bind(filter.include_disposed)
+                    .bind(&filter.location)
+                    .bind(&search)
+                    .fetch_one(&self.pool)
+                    .await?;
+                let containers = rows
+                    .iter()
+                    .map(|row| ContainerWithItemCount {
+                        container: Self::row_to_container(row),
+                        item_count: row.get("item_count"),
+                        total_item_count: 0,
+                    })
+                    .collect();
+                Ok((containers, total))
+            }
+
+            async fn list_at_location(&self, location: &str) -> AppResult<Vec<Container>> {
+                let rows = sqlx::query($sql::AT_LOCATION)
+                    .bind(location)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.iter().map(Self::row_to_container).collect())
+            }
+
+            async fn update(
+                &self,
+                id: &str,
+                req: &UpdateContainerRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Container>> {
+                let row = sqlx::query($sql::UPDATE)
+                    .bind(id)
+                    .bind(&req.name)
+                    .bind(&req.description)
+                    .bind(&req.location)
+                    .bind(req.location_id)
+                    .bind(&req.image_url)
+                    .bind(req.is_disposed)
+                    .bind(req.parent_container_id.is_some())
+                    .bind(req.parent_container_id.clone().flatten())
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.as_ref().map(Self::row_to_container))
+            }
+
+            async fn set_disposed(
+                &self,
+                ids: &[String],
+                is_disposed: bool,
+                now: DateTime<Utc>,
+            ) -> AppResult<u64> {
+                let result = sqlx::query($sql::SET_DISPOSED)
+                    .bind(is_disposed)
+                    .bind(now)
+                    .bind($list(ids))
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn delete(&self, ids: &[String]) -> AppResult<u64> {
+                let result = sqlx::query($sql::DELETE)
+                    .bind($list(ids))
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn hierarchy(&self) -> AppResult<ContainerHierarchy> {
+                let rows = sqlx::query_as($sql::HIERARCHY)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(ContainerHierarchy::from_rows(rows))
+            }
+
+            async fn direct_item_counts(&self) -> AppResult<HashMap<String, i64>> {
+                let rows: Vec<(String, i64)> = sqlx::query_as($sql::ITEM_COUNTS)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().collect())
+            }
+
+            async fn any_has_items(&self, ids: &[String]) -> AppResult<bool> {
+                let has_items: bool = sqlx::query_scalar($sql::ANY_HAS_ITEMS)
+                    .bind($list(ids))
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(has_items)
+            }
+
+            async fn move_container(
+                &self,
+                id: &str,
+                destination: &ContainerDestination,
+                batch: &TransferBatch<'_>,
+            ) -> AppResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query($sql::LOCK).execute(&mut *tx).await?;
+                let hierarchy = ContainerHierarchy::from_rows(
+                    sqlx::query_as($sql::HIERARCHY)
+                        .fetch_all(&mut *tx)
+                        .await?,
+                );
+                if !hierarchy.contains(id) {
+                    tx.rollback().await?;
+                    return Ok(false);
+                }
+                let (location, location_id) = match destination {
+                    ContainerDestination::Location { path, location_id } => {
+                        (path.clone(), Some(*location_id))
+                    }
+                    _ => {
+                        let source = hierarchy
+                            .location_source(id, destination)?
+                            .unwrap_or_else(|| id.to_string());
+                        let row: Option<(Option<String>, Option<i64>)> =
+                            sqlx::query_as($sql::LOCATION)
+                                .bind(&source)
+                                .fetch_optional(&mut *tx)
+                                .await?;
+                        let (location, location_id) = row.ok_or_else(|| {
+                            AppError::NotFound(format!("Container {} not found", source))
+                        })?;
+                        (location.unwrap_or_default(), location_id)
+                    }
+                };
+                let parent_id = destination.parent_id();
+                sqlx::query($sql::MOVE)
+                    .bind(&location)
+                    .bind(location_id)
+                    .bind(parent_id)
+                    .bind(batch.moved_at)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                let descendants = hierarchy.descendants(id);
+                sqlx::query($sql::RELOCATE)
+                    .bind(&location)
+                    .bind(location_id)
+                    .bind(batch.moved_at)
+                    .bind($list(&descendants))
+                    .execute(&mut *tx)
+                    .await?;
+
+                let mut scope = vec![id.to_string()];
+                scope.extend(descendants);
+                let items: Vec<(String, String)> = sqlx::query($sql::ITEMS_IN)
+                    .bind($list(&scope))
+                    .fetch_all(&mut *tx)
+                    .await?
+                    .iter()
+                    .map(|row| {
+                        (
+                            $uuid_column(row, "id").to_string(),
+                            row.get("container_id"),
+                        )
+                    })
+                    .collect();
+                for transfer in move_transfers(&hierarchy, id, parent_id, &location, &items) {
+                    $insert_transfer(&mut tx, batch, &transfer).await?;
+                }
+                tx.commit().await?;
+                Ok(true)
+            }
+        }
+    };
+}
+
+sql_container_repository!(
+    PostgresContainerRepository,
+    sqlx::PgPool,
+    sqlx::postgres::PgRow,
+    postgres,
+    postgres_uuid_column,
+    postgres_list,
+    insert_transfer_postgres
+);
+sql_container_repository!(
+    SqliteContainerRepository,
+    sqlx::SqlitePool,
+    sqlx::sqlite::SqliteRow,
+    sqlite,
+    sqlite_uuid_column,
+    sqlite_list,
+    insert_transfer_sqlite
+);
@@ -0,0 +1,710 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::{
+    insert_transfer_postgres, insert_transfer_sqlite, postgres_list, postgres_uuid_column,
+    sqlite_list, sqlite_uuid, sqlite_uuid_column, Page, TransferBatch,
+};
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{CreateItemRequest, Item, LabelAlias, NewTransfer, UpdateItemRequest};
+
+/// 一覧の絞り込み。指定されていない条件では絞り込まない
+#[derive(Debug, Clone, Default)]
+pub struct ItemFilter {
+    /// 名前・ラベルID・型番・備考の部分一致（大文字と小文字は区別しない）
+    pub search: Option<String>,
+    pub is_on_loan: Option<bool>,
+    pub is_disposed: Option<bool>,
+    pub container_id: Option<String>,
+    pub storage_type: Option<String>,
+}
+
+/// 物品の移動先
+#[derive(Debug, Clone)]
+pub struct ItemPlacement {
+    /// `location` または `container`
+    pub storage_type: &'static str,
+    pub container_id: Option<String>,
+    pub storage_location: Option<String>,
+    pub location_id: Option<i64>,
+}
+
+#[async_trait]
+pub trait ItemRepository: Send + Sync {
+    async fn insert(&self, id: Uuid, req: &CreateItemRequest, now: DateTime<Utc>)
+        -> AppResult<Item>;
+
+    async fn find(&self, id: Uuid) -> AppResult<Option<Item>>;
+
+    /// 現在のラベルで探す（貼り替え前のラベルは [`ItemRepository::find_label_alias`]）
+    async fn find_by_label(&self, label_id: &str) -> AppResult<Option<Item>>;
+
+    /// ラベルID順。使われていないラベルは含まれない
+    async fn find_by_labels(&self, label_ids: &[String]) -> AppResult<Vec<Item>>;
+
+    /// コンテナに収納されている（廃棄済みを除く）物品をラベルID順に返す
+    async fn list_in_container(&self, container_id: &str) -> AppResult<Vec<Item>>;
+
+    /// 登録の新しい順。2つ目は全件数
+    async fn list(&self, filter: &ItemFilter, page: Page) -> AppResult<(Vec<Item>, i64)>;
+
+    /// [`ItemRepository::list`] の全件版（CSV の書き出し用）
+    async fn list_all(&self, filter: &ItemFilter) -> AppResult<Vec<Item>>;
+
+    /// 指定されたフィールドだけを書き換える。ラベルは変えない（貼り替えは
+    /// [`ItemRepository::relabel`]）。見つからなければ None
+    async fn update(
+        &self,
+        id: Uuid,
+        req: &UpdateItemRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Item>>;
+
+    async fn set_image(
+        &self,
+        id: Uuid,
+        image_url: &str,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Item>>;
+
+    /// 書き換えた件数
+    async fn set_disposed(
+        &self,
+        ids: &[Uuid],
+        is_disposed: bool,
+        now: DateTime<Utc>,
+    ) -> AppResult<u64>;
+
+    /// 削除した件数
+    async fn delete(&self, ids: &[Uuid]) -> AppResult<u64>;
+
+    /// どれかが貸出中か
+    async fn any_on_loan(&self, ids: &[Uuid]) -> AppResult<bool>;
+
+    /// 登録されている接続名（重複なし、名前順）
+    async fn connection_names(&self) -> AppResult<Vec<String>>;
+
+    async fn find_label_alias(&self, label_id: &str) -> AppResult<Option<LabelAlias>>;
+
+    /// `alias.item_id` の物品のラベルを `alias.label_id` から `alias.replaced_by` に貼り替え、
+    /// 古いラベルを別名として残す。物品のラベルが `alias.label_id` でなくなっていれば
+    /// 何もせず false
+    async fn relabel(&self, alias: &LabelAlias) -> AppResult<bool>;
+
+    /// 物品をまとめて移動し、`transfers` を同じトランザクションで記録する
+    async fn move_items(
+        &self,
+        ids: &[Uuid],
+        placement: &ItemPlacement,
+        batch: &TransferBatch<'_>,
+        transfers: &[NewTransfer],
+    ) -> AppResult<()>;
+}
+
+pub fn item_repository(db: &DatabasePool) -> Arc<dyn ItemRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresItemRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteItemRepository::new(pool.clone())),
+    }
+}
+
+/// 接続名などの配列は JSON の文字列で持つ
+fn to_json(values: &Option<Vec<String>>) -> Option<String> {
+    values
+        .as_ref()
+        .map(|values| serde_json::to_string(values).unwrap_or_default())
+}
+
+fn from_json(value: Option<String>) -> Option<Vec<String>> {
+    value.and_then(|value| serde_json::from_str(&value).ok())
+}
+
+macro_rules! item_columns {
+    () => {
+        r#"
+            id, name, label_id, model_number, remarks, purchase_year, purchase_amount,
+            durability_years, is_depreciation_target, connection_names, cable_color_pattern,
+            storage_location, container_id, storage_type, is_on_loan, qr_code_type, is_disposed,
+            image_url, location_id, created_at, updated_at
+        "#
+    };
+}
+
+mod postgres {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE ($1 IS NULL OR name ILIKE $1 OR label_id ILIKE $1
+                   OR model_number ILIKE $1 OR remarks ILIKE $1)
+              AND ($2 IS NULL OR is_on_loan = $2)
+              AND ($3 IS NULL OR is_disposed = $3)
+              AND ($4 IS NULL OR container_id = $4)
+              AND ($5 IS NULL OR storage_type = $5)
+            "#
+        };
+    }
+
+    pub const INSERT: &str = concat!(
+        r#"
+        INSERT INTO items (
+            id, name, label_id, model_number, remarks, purchase_year, purchase_amount,
+            durability_years, is_depreciation_target, connection_names, cable_color_pattern,
+            storage_location, container_id, storage_type, qr_code_type, image_url, location_id,
+            created_at, updated_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $18)
+        RETURNING "#,
+        item_columns!()
+    );
+    pub const FIND: &str = concat!("SELECT ", item_columns!(), " FROM items WHERE id = $1");
+    pub const FIND_BY_LABEL: &str =
+        concat!("SELECT ", item_columns!(), " FROM items WHERE label_id = $1");
+    pub const FIND_BY_LABELS: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        " FROM items WHERE label_id = ANY($1) ORDER BY label_id ASC"
+    );
+    pub const IN_CONTAINER: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        r#"
+        FROM items
+        WHERE container_id = $1 AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = false)
+        ORDER BY label_id ASC
+        "#
+    );
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        " FROM items ",
+        filter!(),
+        " ORDER BY created_at DESC LIMIT $6 OFFSET $7"
+    );
+    pub const COUNT: &str = concat!("SELECT COUNT(*) FROM items ", filter!());
+    pub const LIST_ALL: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        " FROM items ",
+        filter!(),
+        " ORDER BY created_at DESC"
+    );
+    pub const UPDATE: &str = concat!(
+        r#"
+        UPDATE items SET
+            name = COALESCE($2, name),
+            model_number = COALESCE($3, model_number),
+            remarks = COALESCE($4, remarks),
+            purchase_year = COALESCE($5, purchase_year),
+            purchase_amount = COALESCE($6, purchase_amount),
+            durability_years = COALESCE($7, durability_years),
+            is_depreciation_target = COALESCE($8, is_depreciation_target),
+            connection_names = COALESCE($9, connection_names),
+            cable_color_pattern = COALESCE($10, cable_color_pattern),
+            storage_location = COALESCE($11, storage_location),
+            location_id = COALESCE($12, location_id),
+            container_id = COALESCE($13, container_id),
+            storage_type = COALESCE($14, storage_type),
+            qr_code_type = COALESCE($15, qr_code_type),
+            image_url = COALESCE($16, image_url),
+            updated_at = $17
+        WHERE id = $1
+        RETURNING "#,
+        item_columns!()
+    );
+    pub const SET_IMAGE: &str = concat!(
+        "UPDATE items SET image_url = $2, updated_at = $3 WHERE id = $1 RETURNING ",
+        item_columns!()
+    );
+    pub const SET_DISPOSED: &str =
+        "UPDATE items SET is_disposed = $1, updated_at = $2 WHERE id = ANY($3)";
+    pub const DELETE: &str = "DELETE FROM items WHERE id = ANY($1)";
+    pub const ANY_ON_LOAN: &str =
+        "SELECT EXISTS(SELECT 1 FROM items WHERE id = ANY($1) AND is_on_loan = true)";
+    pub const FIND_LABEL_ALIAS: &str = r#"
+        SELECT label_id, item_id, replaced_by, reason, created_by, created_at
+        FROM label_aliases WHERE label_id = $1
+    "#;
+    pub const RELABEL: &str =
+        "UPDATE items SET label_id = $1, updated_at = $2 WHERE id = $3 AND label_id = $4";
+    pub const DELETE_LABEL_ALIAS: &str = "DELETE FROM label_aliases WHERE label_id = $1";
+    pub const INSERT_LABEL_ALIAS: &str = r#"
+        INSERT INTO label_aliases (label_id, item_id, replaced_by, reason, created_by, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+    "#;
+    pub const REPLACE_LABEL_ALIASES: &str =
+        "UPDATE label_aliases SET replaced_by = $1 WHERE item_id = $2";
+    pub const MOVE: &str = r#"
+        UPDATE items SET storage_type = $1, container_id = $2, storage_location = $3,
+            location_id = $4, updated_at = $5
+        WHERE id = $6
+    "#;
+}
+
+mod sqlite {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE (?1 IS NULL OR name LIKE ?1 OR label_id LIKE ?1
+                   OR model_number LIKE ?1 OR remarks LIKE ?1)
+              AND (?2 IS NULL OR is_on_loan = ?2)
+              AND (?3 IS NULL OR is_disposed = ?3)
+              AND (?4 IS NULL OR container_id = ?4)
+              AND (?5 IS NULL OR storage_type = ?5)
+            "#
+        };
+    }
+
+    pub const INSERT: &str = concat!(
+        r#"
+        INSERT INTO items (
+            id, name, label_id, model_number, remarks, purchase_year, purchase_amount,
+            durability_years, is_depreciation_target, connection_names, cable_color_pattern,
+            storage_location, container_id, storage_type, qr_code_type, image_url, location_id,
+            created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?18)
+        RETURNING "#,
+        item_columns!()
+    );
+    pub const FIND: &str = concat!("SELECT ", item_columns!(), " FROM items WHERE id = ?1");
+    pub const FIND_BY_LABEL: &str =
+        concat!("SELECT ", item_columns!(), " FROM items WHERE label_id = ?1");
+    pub const FIND_BY_LABELS: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        r#"
+        FROM items WHERE label_id IN (SELECT value FROM json_each(?1))
+        ORDER BY label_id ASC
+        "#
+    );
+    pub const IN_CONTAINER: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        r#"
+        FROM items
+        WHERE container_id = ?1 AND storage_type = 'container'
+          AND (is_disposed IS NULL OR is_disposed = 0)
+        ORDER BY label_id ASC
+        "#
+    );
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        " FROM items ",
+        filter!(),
+        " ORDER BY created_at DESC LIMIT ?6 OFFSET ?7"
+    );
+    pub const COUNT: &str = concat!("SELECT COUNT(*) FROM items ", filter!());
+    pub const LIST_ALL: &str = concat!(
+        "SELECT ",
+        item_columns!(),
+        " FROM items ",
+        filter!(),
+        " ORDER BY created_at DESC"
+    );
+    pub const UPDATE: &str = concat!(
+        r#"
+        UPDATE items SET
+            name = COALESCE(?2, name),
+            model_number = COALESCE(?3, model_number),
+            remarks = COALESCE(?4, remarks),
+            purchase_year = COALESCE(?5, purchase_year),
+            purchase_amount = COALESCE(?6, purchase_amount),
+            durability_years = COALESCE(?7, durability_years),
+            is_depreciation_target = COALESCE(?8, is_depreciation_target),
+            connection_names = COALESCE(?9, connection_names),
+            cable_color_pattern = COALESCE(?10, cable_color_pattern),
+            storage_location = COALESCE(?11, storage_location),
+            location_id = COALESCE(?12, location_id),
+            container_id = COALESCE(?13, container_id),
+            storage_type = COALESCE(?14, storage_type),
+            qr_code_type = COALESCE(?15, qr_code_type),
+            image_url = COALESCE(?16, image_url),
+            updated_at = ?17
+        WHERE id = ?1
+        RETURNING "#,
+        item_columns!()
+    );
+    pub const SET_IMAGE: &str = concat!(
+        "UPDATE items SET image_url = ?2, updated_at = ?3 WHERE id = ?1 RETURNING ",
+        item_columns!()
+    );
+    pub const SET_DISPOSED: &str = r#"
+        UPDATE items SET is_disposed = ?1, updated_at = ?2
+        WHERE id IN (SELECT value FROM json_each(?3))
+    "#;
+    pub const DELETE: &str = "DELETE FROM items WHERE id IN (SELECT value FROM json_each(?1))";
+    pub const ANY_ON_LOAN: &str = r#"
+        SELECT EXISTS(
+            SELECT 1 FROM items
+            WHERE id IN (SELECT value FROM json_each(?1)) AND is_on_loan = 1
+        )
+    "#;
+    pub const FIND_LABEL_ALIAS: &str = r#"
+        SELECT label_id, item_id, replaced_by, reason, created_by, created_at
+        FROM label_aliases WHERE label_id = ?1
+    "#;
+    pub const RELABEL: &str =
+        "UPDATE items SET label_id = ?1, updated_at = ?2 WHERE id = ?3 AND label_id = ?4";
+    pub const DELETE_LABEL_ALIAS: &str = "DELETE FROM label_aliases WHERE label_id = ?1";
+    pub const INSERT_LABEL_ALIAS: &str = r#"
+        INSERT INTO label_aliases (label_id, item_id, replaced_by, reason, created_by, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+    "#;
+    pub const REPLACE_LABEL_ALIASES: &str =
+        "UPDATE label_aliases SET replaced_by = ?1 WHERE item_id = ?2";
+    pub const MOVE: &str = r#"
+        UPDATE items SET storage_type = ?1, container_id = ?2, storage_location = ?3,
+            location_id = ?4, updated_at = ?5
+        WHERE id = ?6
+    "#;
+}
+
+macro_rules! sql_item_repository {
+    (
+        $name:ident,
+        $pool:ty,
+        $row:ty,
+        $sql:ident,
+        $uuid:path,
+        $uuid_column:path,
+        $list:path,
+        $insert_transfer:path
+    ) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_item(row: $row) -> Item {
+                Item {
+                    id: $uuid_column(&row, "id"),
+                    name: row.get("name"),
+                    label_id: row.get("label_id"),
+                    model_number: row.get("model_number"),
+                    remarks: row.get("remarks"),
+                    purchase_year: row.get("purchase_year"),
+                    purchase_amount: row.get("purchase_amount"),
+                    durability_years: row.get("durability_years"),
+                    is_depreciation_target: row.get("is_depreciation_target"),
+                    connection_names: from_json(row.get("connection_names")),
+                    cable_color_pattern: from_json(row.get("cable_color_pattern")),
+                    storage_location: row.get("storage_location"),
+                    container_id: row.get("container_id"),
+                    storage_type: row
+                        .get::<Option<String>, _>("storage_type")
+                        .unwrap_or_else(|| "location".to_string()),
+                    is_on_loan: row.get("is_on_loan"),
+                    qr_code_type: row.get("qr_code_type"),
+                    is_disposed: row.get("is_disposed"),
+                    image_url: row.get("image_url"),
+                    location_id: row.get("location_id"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl ItemRepository for $name {
+            async fn insert(
+                &self,
+                id: Uuid,
+                req: &CreateItemRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Item> {
+                let row = sqlx::query($sql::INSERT)
+                    .bind($uuid(id))
+                    .bind(&req.name)
+                    .bind(&req.label_id)
+                    .bind(&req.model_number)
+                    .bind(&req.remarks)
+                    .bind(req.purchase_year)
+                    .bind(req.purchase_amount)
+                    .bind(req.durability_years)
+                    .bind(req.is_depreciation_target.unwrap_or(false))
+                    .bind(to_json(&req.connection_names))
+                    .bind(to_json(&req.cable_color_pattern))
+                    .bind(&req.storage_location)
+                    .bind(&req.container_id)
+                    .bind(req.storage_type.as_deref().unwrap_or("location"))
+                    .bind(&req.qr_code_type)
+                    .bind(&req.image_url)
+                    .bind(req.location_id)
+                    .bind(now)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(Self::row_to_item(row))
+            }
+
+            async fn find(&self, id: Uuid) -> AppResult<Option<Item>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind($uuid(id))
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_item))
+            }
+
+            async fn find_by_label(&self, label_id: &str) -> AppResult<Option<Item>> {
+                let row = sqlx::query($sql::FIND_BY_LABEL)
+                    .bind(label_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_item))
+            }
+
+            async fn find_by_labels(&self, label_ids: &[String]) -> AppResult<Vec<Item>> {
+                let rows = sqlx::query($sql::FIND_BY_LABELS)
+                    .bind($list(label_ids))
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(Self::row_to_item).collect())
+            }
+
+            async fn list_in_container(&self, container_id: &str) -> AppResult<Vec<Item>> {
+                let rows = sqlx::query($sql::IN_CONTAINER)
+                    .bind(container_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(Self::row_to_item).collect())
+            }
+
+            async fn list(&self, filter: &ItemFilter, page: Page) -> AppResult<(Vec<Item>, i64)> {
+                let search = filter.search.as_ref().map(|search| format!("%{}%", search));
+                let rows = sqlx::query($sql::LIST)
+                    .bind(&search)
+                    .bind(filter.is_on_loan)
+                    .bind(filter.is_disposed)
+                    .bind(&filter.container_id)
+                    .bind(&filter.storage_type)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar($sql::COUNT)
+                    .bind(&search)
+                    .bind(filter.is_on_loan)
+                    .bind(filter.is_disposed)
+                    .bind(&filter.container_id)
+                    .bind(&filter.storage_type)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok((rows.into_iter().map(Self::row_to_item).collect(), total))
+            }
+
+            async fn list_all(&self, filter: &ItemFilter) -> AppResult<Vec<Item>> {
+                let rows = sqlx::query($sql::LIST_ALL)
+                    .bind(filter.search.as_ref().map(|search| format!("%{}%", search)))
+                    .bind(filter.is_on_loan)
+                    .bind(filter.is_disposed)
+                    .bind(&filter.container_id)
+                    .bind(&filter.storage_type)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(Self::row_to_item).collect())
+            }
+
+            async fn update(
+                &self,
+                id: Uuid,
+                req: &UpdateItemRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Item>> {
+                let row = sqlx::query($sql::UPDATE)
+                    .bind($uuid(id))
+                    .bind(&req.name)
+                    .bind(&req.model_number)
+                    .bind(&req.remarks)
+                    .bind(req.purchase_year)
+                    .bind(req.purchase_amount)
+                    .bind(req.durability_years)
+                    .bind(req.is_depreciation_target)
+                    .bind(to_json(&req.connection_names))
+                    .bind(to_json(&req.cable_color_pattern))
+                    .bind(&req.storage_location)
+                    .bind(req.location_id)
+                    .bind(&req.container_id)
+                    .bind(&req.storage_type)
+                    .bind(&req.qr_code_type)
+                    .bind(&req.image_url)
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_item))
+            }
+
+            async fn set_image(
+                &self,
+                id: Uuid,
+                image_url: &str,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Item>> {
+                let row = sqlx::query($sql::SET_IMAGE)
+                    .bind($uuid(id))
+                    .bind(image_url)
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_item))
+            }
+
+            async fn set_disposed(
+                &self,
+                ids: &[Uuid],
+                is_disposed: bool,
+                now: DateTime<Utc>,
+            ) -> AppResult<u64> {
+                let result = sqlx::query($sql::SET_DISPOSED)
+                    .bind(is_disposed)
+                    .bind(now)
+                    .bind($list(ids))
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn delete(&self, ids: &[Uuid]) -> AppResult<u64> {
+                let result = sqlx::query($sql::DELETE)
+                    .bind($list(ids))
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn any_on_loan(&self, ids: &[Uuid]) -> AppResult<bool> {
+                let on_loan: bool = sqlx::query_scalar($sql::ANY_ON_LOAN)
+                    .bind($list(ids))
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(on_loan)
+            }
+
+            async fn connection_names(&self) -> AppResult<Vec<String>> {
+                let rows: Vec<String> = sqlx::query_scalar(
+                    r#"
+                    SELECT DISTINCT connection_names FROM items
+                    WHERE connection_names IS NOT NULL AND connection_names != ''
+                    "#,
+                )
+                .fetch_all(&self.pool)
+                .await?;
+                let mut names: Vec<String> = rows
+                    .into_iter()
+                    .filter_map(|json| from_json(Some(json)))
+                    .flatten()
+                    .collect();
+                names.sort();
+                names.dedup();
+                Ok(names)
+            }
+
+            async fn find_label_alias(&self, label_id: &str) -> AppResult<Option<LabelAlias>> {
+                let row = sqlx::query($sql::FIND_LABEL_ALIAS)
+                    .bind(label_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(|row| LabelAlias {
+                    label_id: row.get("label_id"),
+                    item_id: $uuid_column(&row, "item_id"),
+                    replaced_by: row.get("replaced_by"),
+                    reason: row.get("reason"),
+                    created_by: row.get("created_by"),
+                    created_at: row.get("created_at"),
+                }))
+            }
+
+            async fn relabel(&self, alias: &LabelAlias) -> AppResult<bool> {
+                let mut tx = self.pool.begin().await?;
+                let result = sqlx::query($sql::RELABEL)
+                    .bind(&alias.replaced_by)
+                    .bind(alias.created_at)
+                    .bind($uuid(alias.item_id))
+                    .bind(&alias.label_id)
+                    .execute(&mut *tx)
+                    .await?;
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                // 以前の古いラベルに戻す場合は、その別名を消す
+                sqlx::query($sql::DELETE_LABEL_ALIAS)
+                    .bind(&alias.replaced_by)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query($sql::INSERT_LABEL_ALIAS)
+                    .bind(&alias.label_id)
+                    .bind($uuid(alias.item_id))
+                    .bind(&alias.replaced_by)
+                    .bind(&alias.reason)
+                    .bind(&alias.created_by)
+                    .bind(alias.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+                // それ以前に貼り替えたラベルも、新しいラベルを指すようにする
+                sqlx::query($sql::REPLACE_LABEL_ALIASES)
+                    .bind(&alias.replaced_by)
+                    .bind($uuid(alias.item_id))
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn move_items(
+                &self,
+                ids: &[Uuid],
+                placement: &ItemPlacement,
+                batch: &TransferBatch<'_>,
+                transfers: &[NewTransfer],
+            ) -> AppResult<()> {
+                let mut tx = self.pool.begin().await?;
+                for id in ids {
+                    sqlx::query($sql::MOVE)
+                        .bind(placement.storage_type)
+                        .bind(&placement.container_id)
+                        .bind(&placement.storage_location)
+                        .bind(placement.location_id)
+                        .bind(batch.moved_at)
+                        .bind($uuid(*id))
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for transfer in transfers {
+                    $insert_transfer(&mut tx, batch, transfer).await?;
+                }
+                tx.commit().await?;
+                Ok(())
+            }
+        }
+    };
+}
+
+sql_item_repository!(
+    PostgresItemRepository,
+    sqlx::PgPool,
+    sqlx::postgres::PgRow,
+    postgres,
+    std::convert::identity,
+    postgres_uuid_column,
+    postgres_list,
+    insert_transfer_postgres
+);
+sql_item_repository!(
+    SqliteItemRepository,
+    sqlx::SqlitePool,
+    sqlx::sqlite::SqliteRow,
+    sqlite,
+    sqlite_uuid,
+    sqlite_uuid_column,
+    sqlite_list,
+    insert_transfer_sqlite
+);
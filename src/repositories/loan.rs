@@ -0,0 +1,714 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::{
+    insert_audit_postgres, insert_audit_sqlite, postgres_uuid_column, snapshot, sqlite_uuid,
+    sqlite_uuid_column, Page,
+};
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    is_overdue, AuditAction, AuditEntity, BatchLoanItemResult, CurrentUser, Loan, LoanFilters,
+    LoanWithItem,
+};
+
+/// 新しい貸出。物品は別に渡す
+#[derive(Debug, Clone)]
+pub struct NewLoan {
+    pub student_number: String,
+    pub student_name: String,
+    pub organization: Option<String>,
+    pub remarks: Option<String>,
+    /// 登録日時・物品の更新日時にも使う
+    pub loan_date: DateTime<Utc>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+pub trait LoanRepository: Send + Sync {
+    async fn find(&self, id: i64) -> AppResult<Option<Loan>>;
+
+    /// 物品の未返却の貸出（複数あれば最新のもの）
+    async fn find_active(&self, item_id: Uuid) -> AppResult<Option<Loan>>;
+
+    /// 物品の貸出記録（古い順）
+    async fn list_for_item(&self, item_id: Uuid) -> AppResult<Vec<Loan>>;
+
+    /// 登録の新しい順。延滞かどうかは `now` で判定する。2つ目は全件数
+    async fn list(
+        &self,
+        filters: &LoanFilters,
+        now: DateTime<Utc>,
+        page: Page,
+    ) -> AppResult<(Vec<LoanWithItem>, i64)>;
+
+    /// `now` の時点で延滞している貸出（返却期限の古い順）
+    async fn list_overdue(&self, now: DateTime<Utc>) -> AppResult<Vec<LoanWithItem>>;
+
+    /// 貸出中でも廃棄済みでもない物品だけを貸出中にして、貸出を監査ログと一緒に
+    /// 1つのトランザクションで記録する。貸し出せなければ何も書き込まず None。
+    /// 貸出期間が予約（`exclude_reservation_id` を除く）と重なっていれば Conflict
+    async fn insert(
+        &self,
+        item_id: Uuid,
+        loan: &NewLoan,
+        exclude_reservation_id: Option<i64>,
+        actor: &CurrentUser,
+    ) -> AppResult<Option<Loan>>;
+
+    /// 未返却なら返却を記録し、物品を貸出中でなくする。返却済みか見つからなければ None
+    async fn mark_returned(
+        &self,
+        id: i64,
+        return_date: DateTime<Utc>,
+        remarks: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Loan>>;
+
+    /// エラーのない `results` の物品をまとめて貸し出し、監査ログと一緒に1つのトランザクションで
+    /// 書き込む。貸し出せない物品があればその理由を `error` に入れ、何も書き込まずに false
+    async fn insert_batch(
+        &self,
+        loan: &NewLoan,
+        results: &mut [BatchLoanItemResult],
+        actor: &CurrentUser,
+    ) -> AppResult<bool>;
+
+    /// [`LoanRepository::insert_batch`] の返却版
+    async fn return_batch(
+        &self,
+        return_date: DateTime<Utc>,
+        remarks: Option<&str>,
+        now: DateTime<Utc>,
+        results: &mut [BatchLoanItemResult],
+        actor: &CurrentUser,
+    ) -> AppResult<bool>;
+}
+
+pub fn loan_repository(db: &DatabasePool) -> Arc<dyn LoanRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresLoanRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteLoanRepository::new(pool.clone())),
+    }
+}
+
+/// 物品の貸出状態 (is_on_loan, is_disposed) から貸し出せない理由を返す（`None` なら物品が存在しない）
+pub(crate) fn loan_state_error(
+    item_id: Uuid,
+    state: Option<(Option<bool>, Option<bool>)>,
+) -> Option<String> {
+    match state {
+        None => Some(format!("Item with id {} not found", item_id)),
+        Some((Some(true), _)) => Some("Item is already on loan".to_string()),
+        Some((_, Some(true))) => Some("Item is disposed and cannot be loaned".to_string()),
+        Some(_) => None,
+    }
+}
+
+/// 重なっている予約 (ID, 開始日時) を示すメッセージ
+fn reservation_conflict((id, start_time): (i64, DateTime<Utc>)) -> String {
+    format!(
+        "Item is reserved from {} (reservation {})",
+        start_time.to_rfc3339(),
+        id
+    )
+}
+
+macro_rules! loan_columns {
+    () => {
+        r#"
+            id, item_id, student_number, student_name, organization,
+            loan_date, return_date, due_date, remarks, created_at, updated_at
+        "#
+    };
+}
+
+macro_rules! loan_with_item_columns {
+    () => {
+        r#"
+            l.id, l.item_id, l.student_number, l.student_name, l.organization,
+            l.loan_date, l.return_date, l.due_date, l.remarks, l.created_at, l.updated_at,
+            i.name AS item_name, i.label_id AS item_label_id
+        "#
+    };
+}
+
+mod postgres {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE ($1 IS NULL OR l.item_id = $1)
+              AND ($2 IS NULL OR l.student_number = $2)
+              AND ($3 IS NULL OR (l.return_date IS NULL) = $3)
+              AND ($4 IS NULL
+                   OR (l.return_date IS NULL AND l.due_date IS NOT NULL AND l.due_date < $6) = $4)
+              AND ($5 IS NULL OR l.due_date < $5)
+            "#
+        };
+    }
+
+    pub const FIND: &str = concat!("SELECT ", loan_columns!(), " FROM loans WHERE id = $1");
+    pub const FIND_ACTIVE: &str = concat!(
+        "SELECT ",
+        loan_columns!(),
+        r#"
+        FROM loans
+        WHERE item_id = $1 AND return_date IS NULL
+        ORDER BY loan_date DESC, id DESC
+        LIMIT 1
+        "#
+    );
+    pub const FOR_ITEM: &str = concat!(
+        "SELECT ",
+        loan_columns!(),
+        " FROM loans WHERE item_id = $1 ORDER BY loan_date ASC, id ASC"
+    );
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        loan_with_item_columns!(),
+        " FROM loans l INNER JOIN items i ON l.item_id = i.id ",
+        filter!(),
+        " ORDER BY l.created_at DESC, l.id DESC LIMIT $7 OFFSET $8"
+    );
+    pub const COUNT: &str = concat!(
+        "SELECT COUNT(*) FROM loans l INNER JOIN items i ON l.item_id = i.id ",
+        filter!()
+    );
+    pub const OVERDUE: &str = concat!(
+        "SELECT ",
+        loan_with_item_columns!(),
+        r#"
+        FROM loans l
+        INNER JOIN items i ON l.item_id = i.id
+        WHERE l.return_date IS NULL AND l.due_date IS NOT NULL AND l.due_date < $1
+        ORDER BY l.due_date ASC, l.id ASC
+        "#
+    );
+    pub const CONFLICTING_RESERVATION: &str = r#"
+        SELECT id, start_time FROM reservations
+        WHERE item_id = $1 AND status = 'reserved' AND id <> $2
+          AND ($3 IS NULL OR start_time < $3) AND end_time > $4
+        ORDER BY start_time ASC
+        LIMIT 1
+    "#;
+    pub const TAKE_ITEM: &str = r#"
+        UPDATE items SET is_on_loan = true, updated_at = $2
+        WHERE id = $1
+          AND COALESCE(is_on_loan, false) = false
+          AND COALESCE(is_disposed, false) = false
+    "#;
+    pub const ITEM_STATE: &str = "SELECT is_on_loan, is_disposed FROM items WHERE id = $1";
+    pub const INSERT: &str = concat!(
+        r#"
+        INSERT INTO loans (
+            item_id, student_number, student_name, organization, remarks, loan_date, due_date,
+            created_at, updated_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $6, $6)
+        RETURNING "#,
+        loan_columns!()
+    );
+    pub const RELEASE_ITEM: &str =
+        "UPDATE items SET is_on_loan = false, updated_at = $2 WHERE id = $1";
+    pub const RELEASE_LOAN_ITEM: &str = r#"
+        UPDATE items SET is_on_loan = false, updated_at = $2
+        WHERE id = (SELECT item_id FROM loans WHERE id = $1 AND return_date IS NULL)
+    "#;
+    pub const RETURN: &str = concat!(
+        r#"
+        UPDATE loans SET return_date = $2, remarks = COALESCE($3, remarks), updated_at = $4
+        WHERE id = $1 AND return_date IS NULL
+        RETURNING "#,
+        loan_columns!()
+    );
+}
+
+mod sqlite {
+    macro_rules! filter {
+        () => {
+            r#"
+            WHERE (?1 IS NULL OR l.item_id = ?1)
+              AND (?2 IS NULL OR l.student_number = ?2)
+              AND (?3 IS NULL OR (l.return_date IS NULL) = ?3)
+              AND (?4 IS NULL
+                   OR (l.return_date IS NULL AND l.due_date IS NOT NULL AND l.due_date < ?6) = ?4)
+              AND (?5 IS NULL OR l.due_date < ?5)
+            "#
+        };
+    }
+
+    pub const FIND: &str = concat!("SELECT ", loan_columns!(), " FROM loans WHERE id = ?1");
+    pub const FIND_ACTIVE: &str = concat!(
+        "SELECT ",
+        loan_columns!(),
+        r#"
+        FROM loans
+        WHERE item_id = ?1 AND return_date IS NULL
+        ORDER BY loan_date DESC, id DESC
+        LIMIT 1
+        "#
+    );
+    pub const FOR_ITEM: &str = concat!(
+        "SELECT ",
+        loan_columns!(),
+        " FROM loans WHERE item_id = ?1 ORDER BY loan_date ASC, id ASC"
+    );
+    pub const LIST: &str = concat!(
+        "SELECT ",
+        loan_with_item_columns!(),
+        " FROM loans l INNER JOIN items i ON l.item_id = i.id ",
+        filter!(),
+        " ORDER BY l.created_at DESC, l.id DESC LIMIT ?7 OFFSET ?8"
+    );
+    pub const COUNT: &str = concat!(
+        "SELECT COUNT(*) FROM loans l INNER JOIN items i ON l.item_id = i.id ",
+        filter!()
+    );
+    pub const OVERDUE: &str = concat!(
+        "SELECT ",
+        loan_with_item_columns!(),
+        r#"
+        FROM loans l
+        INNER JOIN items i ON l.item_id = i.id
+        WHERE l.return_date IS NULL AND l.due_date IS NOT NULL AND l.due_date < ?1
+        ORDER BY l.due_date ASC, l.id ASC
+        "#
+    );
+    pub const CONFLICTING_RESERVATION: &str = r#"
+        SELECT id, start_time FROM reservations
+        WHERE item_id = ?1 AND status = 'reserved' AND id <> ?2
+          AND (?3 IS NULL OR start_time < ?3) AND end_time > ?4
+        ORDER BY start_time ASC
+        LIMIT 1
+    "#;
+    pub const TAKE_ITEM: &str = r#"
+        UPDATE items SET is_on_loan = 1, updated_at = ?2
+        WHERE id = ?1
+          AND COALESCE(is_on_loan, 0) = 0
+          AND COALESCE(is_disposed, 0) = 0
+    "#;
+    pub const ITEM_STATE: &str = "SELECT is_on_loan, is_disposed FROM items WHERE id = ?1";
+    pub const INSERT: &str = concat!(
+        r#"
+        INSERT INTO loans (
+            item_id, student_number, student_name, organization, remarks, loan_date, due_date,
+            created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?6, ?6)
+        RETURNING "#,
+        loan_columns!()
+    );
+    pub const RELEASE_ITEM: &str = "UPDATE items SET is_on_loan = 0, updated_at = ?2 WHERE id = ?1";
+    pub const RELEASE_LOAN_ITEM: &str = r#"
+        UPDATE items SET is_on_loan = 0, updated_at = ?2
+        WHERE id = (SELECT item_id FROM loans WHERE id = ?1 AND return_date IS NULL)
+    "#;
+    pub const RETURN: &str = concat!(
+        r#"
+        UPDATE loans SET return_date = ?2, remarks = COALESCE(?3, remarks), updated_at = ?4
+        WHERE id = ?1 AND return_date IS NULL
+        RETURNING "#,
+        loan_columns!()
+    );
+}
+
+macro_rules! sql_loan_repository {
+    (
+        $name:ident,
+        $pool:ty,
+        $conn:ty,
+        $row:ty,
+        $sql:ident,
+        $uuid:path,
+        $uuid_column:path,
+        $insert_audit:path
+    ) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_loan(row: $row) -> Loan {
+                Loan {
+                    id: row.get("id"),
+                    item_id: $uuid_column(&row, "item_id"),
+                    student_number: row.get("student_number"),
+                    student_name: row.get("student_name"),
+                    organization: row.get("organization"),
+                    loan_date: row.get("loan_date"),
+                    return_date: row.get("return_date"),
+                    due_date: row.get("due_date"),
+                    remarks: row.get("remarks"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+
+            fn row_to_loan_with_item(row: $row, now: DateTime<Utc>) -> LoanWithItem {
+                let due_date = row.get("due_date");
+                let return_date = row.get("return_date");
+                LoanWithItem {
+                    id: row.get("id"),
+                    item_id: $uuid_column(&row, "item_id"),
+                    item_name: row.get("item_name"),
+                    item_label_id: row.get("item_label_id"),
+                    student_number: row.get("student_number"),
+                    student_name: row.get("student_name"),
+                    organization: row.get("organization"),
+                    loan_date: row.get("loan_date"),
+                    return_date,
+                    due_date,
+                    is_overdue: is_overdue(due_date, return_date, now),
+                    remarks: row.get("remarks"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+
+            /// 期間が重なっている予約のうち、開始の早いものの ID と開始日時。
+            /// `until` がなければ無期限として扱う
+            async fn conflicting_reservation(
+                conn: &mut $conn,
+                item_id: Uuid,
+                from: DateTime<Utc>,
+                until: Option<DateTime<Utc>>,
+                exclude_reservation_id: Option<i64>,
+            ) -> AppResult<Option<(i64, DateTime<Utc>)>> {
+                let conflict = sqlx::query_as($sql::CONFLICTING_RESERVATION)
+                    .bind($uuid(item_id))
+                    .bind(exclude_reservation_id.unwrap_or(0))
+                    .bind(until)
+                    .bind(from)
+                    .fetch_optional(conn)
+                    .await?;
+                Ok(conflict)
+            }
+        }
+
+        #[async_trait]
+        impl LoanRepository for $name {
+            async fn find(&self, id: i64) -> AppResult<Option<Loan>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_loan))
+            }
+
+            async fn find_active(&self, item_id: Uuid) -> AppResult<Option<Loan>> {
+                let row = sqlx::query($sql::FIND_ACTIVE)
+                    .bind($uuid(item_id))
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_loan))
+            }
+
+            async fn list_for_item(&self, item_id: Uuid) -> AppResult<Vec<Loan>> {
+                let rows = sqlx::query($sql::FOR_ITEM)
+                    .bind($uuid(item_id))
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(Self::row_to_loan).collect())
+            }
+
+            async fn list(
+                &self,
+                filters: &LoanFilters,
+                now: DateTime<Utc>,
+                page: Page,
+            ) -> AppResult<(Vec<LoanWithItem>, i64)> {
+                let item_id = filters.item_id.map($uuid);
+                let rows = sqlx::query($sql::LIST)
+                    .bind(&item_id)
+                    .bind(&filters.student_number)
+                    .bind(filters.active_only)
+                    .bind(filters.overdue)
+                    .bind(filters.due_before)
+                    .bind(now)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar($sql::COUNT)
+                    .bind(&item_id)
+                    .bind(&filters.student_number)
+                    .bind(filters.active_only)
+                    .bind(filters.overdue)
+                    .bind(filters.due_before)
+                    .bind(now)
+                    .fetch_one(&self.pool)
+                    .await?;
+                let loans = rows
+                    .into_iter()
+                    .map(|row| Self::row_to_loan_with_item(row, now))
+                    .collect();
+                Ok((loans, total))
+            }
+
+            async fn list_overdue(&self, now: DateTime<Utc>) -> AppResult<Vec<LoanWithItem>> {
+                let rows = sqlx::query($sql::OVERDUE)
+                    .bind(now)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| Self::row_to_loan_with_item(row, now))
+                    .collect())
+            }
+
+            async fn insert(
+                &self,
+                item_id: Uuid,
+                loan: &NewLoan,
+                exclude_reservation_id: Option<i64>,
+                actor: &CurrentUser,
+            ) -> AppResult<Option<Loan>> {
+                let mut tx = self.pool.begin().await?;
+                // 貸出状態の確認と更新を1つのUPDATEで行い、同時に貸し出されないようにする
+                let updated = sqlx::query($sql::TAKE_ITEM)
+                    .bind($uuid(item_id))
+                    .bind(loan.loan_date)
+                    .execute(&mut *tx)
+                    .await?;
+                if updated.rows_affected() == 0 {
+                    tx.rollback().await?;
+                    return Ok(None);
+                }
+                // 物品を押さえてから確かめるので、確認の後に予約が入り込むことはない
+                let conflict = Self::conflicting_reservation(
+                    &mut tx,
+                    item_id,
+                    loan.loan_date,
+                    loan.due_date,
+                    exclude_reservation_id,
+                )
+                .await?;
+                if let Some(conflict) = conflict {
+                    tx.rollback().await?;
+                    return Err(AppError::Conflict(reservation_conflict(conflict)));
+                }
+                let row = sqlx::query($sql::INSERT)
+                    .bind($uuid(item_id))
+                    .bind(&loan.student_number)
+                    .bind(&loan.student_name)
+                    .bind(&loan.organization)
+                    .bind(&loan.remarks)
+                    .bind(loan.loan_date)
+                    .bind(loan.due_date)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                let created = Self::row_to_loan(row);
+                $insert_audit(
+                    &mut tx,
+                    AuditEntity::Loan,
+                    &created.id.to_string(),
+                    AuditAction::Loan,
+                    actor,
+                    None,
+                    snapshot(&created),
+                )
+                .await?;
+                tx.commit().await?;
+                Ok(Some(created))
+            }
+
+            async fn mark_returned(
+                &self,
+                id: i64,
+                return_date: DateTime<Utc>,
+                remarks: Option<&str>,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Loan>> {
+                let mut tx = self.pool.begin().await?;
+                // 貸出・一括返却と同じく、物品、貸出の順に書き込む
+                sqlx::query($sql::RELEASE_LOAN_ITEM)
+                    .bind(id)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                let row = sqlx::query($sql::RETURN)
+                    .bind(id)
+                    .bind(return_date)
+                    .bind(remarks)
+                    .bind(now)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                let Some(row) = row else {
+                    tx.rollback().await?;
+                    return Ok(None);
+                };
+                tx.commit().await?;
+                Ok(Some(Self::row_to_loan(row)))
+            }
+
+            async fn insert_batch(
+                &self,
+                loan: &NewLoan,
+                results: &mut [BatchLoanItemResult],
+                actor: &CurrentUser,
+            ) -> AppResult<bool> {
+                let mut tx = self.pool.begin().await?;
+
+                // 貸出状態の確認と更新を1つのUPDATEで行う（SQLite では最初の文で書き込みロックも取る）
+                for result in results.iter_mut() {
+                    let Some(item_id) = result.item_id.filter(|_| result.error.is_none()) else {
+                        continue;
+                    };
+                    let updated = sqlx::query($sql::TAKE_ITEM)
+                        .bind($uuid(item_id))
+                        .bind(loan.loan_date)
+                        .execute(&mut *tx)
+                        .await?;
+                    if updated.rows_affected() == 0 {
+                        let state = sqlx::query($sql::ITEM_STATE)
+                            .bind($uuid(item_id))
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                        result.error = loan_state_error(
+                            item_id,
+                            state.map(|row| (row.get("is_on_loan"), row.get("is_disposed"))),
+                        )
+                        .or_else(|| Some("Item is already on loan".to_string()));
+                        continue;
+                    }
+                    let conflict = Self::conflicting_reservation(
+                        &mut tx,
+                        item_id,
+                        loan.loan_date,
+                        loan.due_date,
+                        None,
+                    )
+                    .await?;
+                    result.error = conflict.map(reservation_conflict);
+                }
+                if results.iter().any(|r| r.error.is_some()) {
+                    tx.rollback().await?;
+                    return Ok(false);
+                }
+
+                for result in results.iter_mut() {
+                    let row = sqlx::query($sql::INSERT)
+                        .bind($uuid(result.item_id.unwrap_or_default()))
+                        .bind(&loan.student_number)
+                        .bind(&loan.student_name)
+                        .bind(&loan.organization)
+                        .bind(&loan.remarks)
+                        .bind(loan.loan_date)
+                        .bind(loan.due_date)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                    let created = Self::row_to_loan(row);
+                    $insert_audit(
+                        &mut tx,
+                        AuditEntity::Loan,
+                        &created.id.to_string(),
+                        AuditAction::Loan,
+                        actor,
+                        None,
+                        snapshot(&created),
+                    )
+                    .await?;
+                    result.loan = Some(created);
+                }
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn return_batch(
+                &self,
+                return_date: DateTime<Utc>,
+                remarks: Option<&str>,
+                now: DateTime<Utc>,
+                results: &mut [BatchLoanItemResult],
+                actor: &CurrentUser,
+            ) -> AppResult<bool> {
+                let mut tx = self.pool.begin().await?;
+
+                let mut returned = Vec::new();
+                for (index, result) in results.iter_mut().enumerate() {
+                    let Some(item_id) = result.item_id.filter(|_| result.error.is_none()) else {
+                        continue;
+                    };
+                    // 貸出を探す前に物品の貸出状態を戻して、物品の行を押さえる
+                    // （返却できない物品があればトランザクションごと取り消す）
+                    sqlx::query($sql::RELEASE_ITEM)
+                        .bind($uuid(item_id))
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                    let row = sqlx::query($sql::FIND_ACTIVE)
+                        .bind($uuid(item_id))
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    match row {
+                        Some(row) => returned.push((index, Self::row_to_loan(row))),
+                        None => result.error = Some("Item is not on loan".to_string()),
+                    }
+                }
+                if results.iter().any(|r| r.error.is_some()) {
+                    tx.rollback().await?;
+                    return Ok(false);
+                }
+
+                for (index, before) in returned {
+                    let row = sqlx::query($sql::RETURN)
+                        .bind(before.id)
+                        .bind(return_date)
+                        .bind(remarks)
+                        .bind(now)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    let Some(row) = row else {
+                        tx.rollback().await?;
+                        return Err(AppError::Conflict(
+                            "Loan has already been returned".to_string(),
+                        ));
+                    };
+                    let loan = Self::row_to_loan(row);
+                    $insert_audit(
+                        &mut tx,
+                        AuditEntity::Loan,
+                        &loan.id.to_string(),
+                        AuditAction::Return,
+                        actor,
+                        snapshot(&before),
+                        snapshot(&loan),
+                    )
+                    .await?;
+                    results[index].loan = Some(loan);
+                }
+
+                tx.commit().await?;
+                Ok(true)
+            }
+        }
+    };
+}
+
+sql_loan_repository!(
+    PostgresLoanRepository,
+    sqlx::PgPool,
+    sqlx::PgConnection,
+    sqlx::postgres::PgRow,
+    postgres,
+    std::convert::identity,
+    postgres_uuid_column,
+    insert_audit_postgres
+);
+sql_loan_repository!(
+    SqliteLoanRepository,
+    sqlx::SqlitePool,
+    sqlx::SqliteConnection,
+    sqlx::sqlite::SqliteRow,
+    sqlite,
+    sqlite_uuid,
+    sqlite_uuid_column,
+    insert_audit_sqlite
+);
@@ -0,0 +1,1058 @@
+//! データベースを使わない実装。`conformance` のテストで SQL の実装と突き合わせる。
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{
+    cable_color, connector, duplicate_name, loan_state_error, tag, CableColorRepository,
+    ConnectorRepository, ContainerDestination, ContainerFilter, ContainerHierarchy,
+    ContainerRepository, ContainerSort, ItemFilter, ItemPlacement, ItemRepository, LoanRepository,
+    NewLoan, Page, TagRepository, TransferBatch,
+};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    is_overdue, BatchLoanItemResult, CableColor, Connector, Container, ContainerWithItemCount,
+    CreateCableColorRequest, CreateConnectorRequest, CreateItemRequest, CreateTagRequest,
+    CurrentUser, Item, LabelAlias, Loan, LoanFilters, LoanWithItem, NewTransfer, Tag,
+    UpdateCableColorRequest, UpdateConnectorRequest, UpdateContainerRequest, UpdateItemRequest,
+    UpdateTagRequest,
+};
+
+#[derive(Default)]
+pub struct InMemoryCableColorRepository {
+    rows: Mutex<Vec<CableColor>>,
+}
+
+#[async_trait]
+impl CableColorRepository for InMemoryCableColorRepository {
+    async fn insert(
+        &self,
+        req: &CreateCableColorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<CableColor> {
+        let mut rows = self.rows.lock().unwrap();
+        if rows.iter().any(|row| row.name == req.name) {
+            return Err(duplicate_name(cable_color::KIND, &req.name));
+        }
+        let cable_color = CableColor {
+            id: rows.iter().map(|row| row.id).max().unwrap_or(0) + 1,
+            name: req.name.clone(),
+            hex_code: req.hex_code.clone(),
+            description: req.description.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        rows.push(cable_color.clone());
+        Ok(cable_color)
+    }
+
+    async fn find(&self, id: i64) -> AppResult<Option<CableColor>> {
+        let rows = self.rows.lock().unwrap();
+        Ok(rows.iter().find(|row| row.id == id).cloned())
+    }
+
+    async fn list(&self, page: Page) -> AppResult<(Vec<CableColor>, i64)> {
+        let mut rows = self.rows.lock().unwrap().clone();
+        rows.sort_by_key(|row| std::cmp::Reverse((row.created_at, row.id)));
+        Ok((page.slice(&rows), rows.len() as i64))
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateCableColorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<CableColor>> {
+        let mut rows = self.rows.lock().unwrap();
+        if !rows.iter().any(|row| row.id == id) {
+            return Ok(None);
+        }
+        if let Some(name) = &req.name {
+            if rows.iter().any(|row| row.id != id && &row.name == name) {
+                return Err(duplicate_name(cable_color::KIND, name));
+            }
+        }
+        let row = rows.iter_mut().find(|row| row.id == id).unwrap();
+        if let Some(name) = &req.name {
+            row.name = name.clone();
+        }
+        if let Some(hex_code) = &req.hex_code {
+            row.hex_code = Some(hex_code.clone());
+        }
+        if let Some(description) = &req.description {
+            row.description = Some(description.clone());
+        }
+        row.updated_at = now;
+        Ok(Some(row.clone()))
+    }
+
+    async fn delete(&self, id: i64) -> AppResult<bool> {
+        let mut rows = self.rows.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.id != id);
+        Ok(rows.len() != before)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryConnectorRepository {
+    rows: Mutex<Vec<Connector>>,
+}
+
+#[async_trait]
+impl ConnectorRepository for InMemoryConnectorRepository {
+    async fn insert(
+        &self,
+        req: &CreateConnectorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Connector> {
+        let mut rows = self.rows.lock().unwrap();
+        if rows.iter().any(|row| row.name == req.name) {
+            return Err(duplicate_name(connector::KIND, &req.name));
+        }
+        let connector = Connector {
+            id: rows.iter().map(|row| row.id).max().unwrap_or(0) + 1,
+            name: req.name.clone(),
+            gender: req.gender.clone(),
+            description: req.description.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        rows.push(connector.clone());
+        Ok(connector)
+    }
+
+    async fn find(&self, id: i64) -> AppResult<Option<Connector>> {
+        let rows = self.rows.lock().unwrap();
+        Ok(rows.iter().find(|row| row.id == id).cloned())
+    }
+
+    async fn list(&self, page: Page) -> AppResult<(Vec<Connector>, i64)> {
+        let mut rows = self.rows.lock().unwrap().clone();
+        rows.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+        Ok((page.slice(&rows), rows.len() as i64))
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateConnectorRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Connector>> {
+        let mut rows = self.rows.lock().unwrap();
+        if !rows.iter().any(|row| row.id == id) {
+            return Ok(None);
+        }
+        if let Some(name) = &req.name {
+            if rows.iter().any(|row| row.id != id && &row.name == name) {
+                return Err(duplicate_name(connector::KIND, name));
+            }
+        }
+        let row = rows.iter_mut().find(|row| row.id == id).unwrap();
+        if let Some(name) = &req.name {
+            row.name = name.clone();
+        }
+        if let Some(gender) = &req.gender {
+            row.gender = Some(gender.clone());
+        }
+        if let Some(description) = &req.description {
+            row.description = Some(description.clone());
+        }
+        row.updated_at = now;
+        Ok(Some(row.clone()))
+    }
+
+    async fn delete(&self, id: i64) -> AppResult<bool> {
+        let mut rows = self.rows.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.id != id);
+        Ok(rows.len() != before)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTagRepository {
+    rows: Mutex<Vec<Tag>>,
+    item_tags: Mutex<HashMap<Uuid, BTreeSet<i64>>>,
+}
+
+#[async_trait]
+impl TagRepository for InMemoryTagRepository {
+    async fn insert(&self, req: &CreateTagRequest, now: DateTime<Utc>) -> AppResult<Tag> {
+        let mut rows = self.rows.lock().unwrap();
+        if rows.iter().any(|row| row.name == req.name) {
+            return Err(duplicate_name(tag::KIND, &req.name));
+        }
+        let tag = Tag {
+            id: rows.iter().map(|row| row.id).max().unwrap_or(0) + 1,
+            name: req.name.clone(),
+            color: req.color.clone(),
+            description: req.description.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        rows.push(tag.clone());
+        Ok(tag)
+    }
+
+    async fn find(&self, id: i64) -> AppResult<Option<Tag>> {
+        let rows = self.rows.lock().unwrap();
+        Ok(rows.iter().find(|row| row.id == id).cloned())
+    }
+
+    async fn list(&self, page: Page) -> AppResult<(Vec<Tag>, i64)> {
+        let mut rows = self.rows.lock().unwrap().clone();
+        rows.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+        Ok((page.slice(&rows), rows.len() as i64))
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateTagRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Tag>> {
+        let mut rows = self.rows.lock().unwrap();
+        if !rows.iter().any(|row| row.id == id) {
+            return Ok(None);
+        }
+        if let Some(name) = &req.name {
+            if rows.iter().any(|row| row.id != id && &row.name == name) {
+                return Err(duplicate_name(tag::KIND, name));
+            }
+        }
+        let row = rows.iter_mut().find(|row| row.id == id).unwrap();
+        if let Some(name) = &req.name {
+            row.name = name.clone();
+        }
+        if let Some(color) = &req.color {
+            row.color = Some(color.clone());
+        }
+        if let Some(description) = &req.description {
+            row.description = Some(description.clone());
+        }
+        row.updated_at = now;
+        Ok(Some(row.clone()))
+    }
+
+    async fn delete(&self, id: i64) -> AppResult<bool> {
+        let mut rows = self.rows.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| row.id != id);
+        for tag_ids in self.item_tags.lock().unwrap().values_mut() {
+            tag_ids.remove(&id);
+        }
+        Ok(rows.len() != before)
+    }
+
+    async fn item_tags(&self, item_id: Uuid) -> AppResult<Vec<Tag>> {
+        let item_tags = self.item_tags.lock().unwrap();
+        let Some(tag_ids) = item_tags.get(&item_id) else {
+            return Ok(Vec::new());
+        };
+        let mut tags: Vec<Tag> = self
+            .rows
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|tag| tag_ids.contains(&tag.id))
+            .cloned()
+            .collect();
+        tags.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+        Ok(tags)
+    }
+
+    async fn set_item_tags(&self, item_id: Uuid, tag_ids: &[i64]) -> AppResult<()> {
+        self.item_tags
+            .lock()
+            .unwrap()
+            .insert(item_id, tag_ids.iter().copied().collect());
+        Ok(())
+    }
+}
+
+/// 物品・貸出・コンテナは互いの行を見るので、まとめて1つで持つ。
+/// 監査ログ・移動記録・予約は持たない
+#[derive(Default)]
+pub struct InMemoryInventory {
+    items: Mutex<Vec<Item>>,
+    label_aliases: Mutex<Vec<LabelAlias>>,
+    loans: Mutex<Vec<Loan>>,
+    containers: Mutex<Vec<Container>>,
+}
+
+/// `ILIKE '%search%'` と同じ（大文字と小文字は区別しない）
+fn contains_ignore_case(value: Option<&str>, search: &str) -> bool {
+    value.is_some_and(|value| value.to_lowercase().contains(&search.to_lowercase()))
+}
+
+fn item_matches(item: &Item, filter: &ItemFilter) -> bool {
+    filter.search.as_deref().is_none_or(|search| {
+        contains_ignore_case(Some(&item.name), search)
+            || contains_ignore_case(Some(&item.label_id), search)
+            || contains_ignore_case(item.model_number.as_deref(), search)
+            || contains_ignore_case(item.remarks.as_deref(), search)
+    }) && filter.is_on_loan.is_none_or(|v| item.is_on_loan == Some(v))
+        && filter.is_disposed.is_none_or(|v| item.is_disposed == Some(v))
+        && filter
+            .container_id
+            .as_ref()
+            .is_none_or(|v| item.container_id.as_ref() == Some(v))
+        && filter
+            .storage_type
+            .as_ref()
+            .is_none_or(|v| &item.storage_type == v)
+}
+
+/// コンテナに直接入っている（廃棄されていない）物品か
+fn is_stored_in(item: &Item, container_id: &str) -> bool {
+    item.storage_type == "container"
+        && item.container_id.as_deref() == Some(container_id)
+        && item.is_disposed != Some(true)
+}
+
+fn filtered_items(items: &[Item], filter: &ItemFilter) -> Vec<Item> {
+    let mut items: Vec<Item> = items
+        .iter()
+        .filter(|item| item_matches(item, filter))
+        .cloned()
+        .collect();
+    items.sort_by_key(|item| Reverse(item.created_at));
+    items
+}
+
+fn loan_with_item(loan: &Loan, item: &Item, now: DateTime<Utc>) -> LoanWithItem {
+    LoanWithItem {
+        id: loan.id,
+        item_id: loan.item_id,
+        item_name: item.name.clone(),
+        item_label_id: item.label_id.clone(),
+        student_number: loan.student_number.clone(),
+        student_name: loan.student_name.clone(),
+        organization: loan.organization.clone(),
+        loan_date: loan.loan_date,
+        return_date: loan.return_date,
+        due_date: loan.due_date,
+        is_overdue: is_overdue(loan.due_date, loan.return_date, now),
+        remarks: loan.remarks.clone(),
+        created_at: loan.created_at,
+        updated_at: loan.updated_at,
+    }
+}
+
+fn loan_matches(loan: &Loan, filters: &LoanFilters, now: DateTime<Utc>) -> bool {
+    filters.item_id.is_none_or(|v| loan.item_id == v)
+        && filters
+            .student_number
+            .as_ref()
+            .is_none_or(|v| &loan.student_number == v)
+        && filters
+            .active_only
+            .is_none_or(|v| loan.return_date.is_none() == v)
+        && filters
+            .overdue
+            .is_none_or(|v| is_overdue(loan.due_date, loan.return_date, now) == v)
+        && filters
+            .due_before
+            .is_none_or(|v| loan.due_date.is_some_and(|due| due < v))
+}
+
+/// 貸出中でも廃棄済みでもなければ貸出中にする
+fn take_item(items: &mut [Item], item_id: Uuid, now: DateTime<Utc>) -> bool {
+    match items.iter_mut().find(|item| item.id == item_id) {
+        Some(item) if item.is_on_loan != Some(true) && item.is_disposed != Some(true) => {
+            item.is_on_loan = Some(true);
+            item.updated_at = now;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn release_item(items: &mut [Item], item_id: Uuid, now: DateTime<Utc>) {
+    if let Some(item) = items.iter_mut().find(|item| item.id == item_id) {
+        item.is_on_loan = Some(false);
+        item.updated_at = now;
+    }
+}
+
+fn new_loan(loans: &[Loan], item_id: Uuid, loan: &NewLoan) -> Loan {
+    Loan {
+        id: loans.iter().map(|row| row.id).max().unwrap_or(0) + 1,
+        item_id,
+        student_number: loan.student_number.clone(),
+        student_name: loan.student_name.clone(),
+        organization: loan.organization.clone(),
+        loan_date: loan.loan_date,
+        return_date: None,
+        due_date: loan.due_date,
+        remarks: loan.remarks.clone(),
+        created_at: loan.loan_date,
+        updated_at: loan.loan_date,
+    }
+}
+
+fn active_loan(loans: &[Loan], item_id: Uuid) -> Option<&Loan> {
+    loans
+        .iter()
+        .filter(|loan| loan.item_id == item_id && loan.return_date.is_none())
+        .max_by_key(|loan| (loan.loan_date, loan.id))
+}
+
+/// 未返却なら返却を記録する
+fn return_loan(
+    loans: &mut [Loan],
+    id: i64,
+    return_date: DateTime<Utc>,
+    remarks: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<Loan> {
+    let loan = loans
+        .iter_mut()
+        .find(|loan| loan.id == id && loan.return_date.is_none())?;
+    loan.return_date = Some(return_date);
+    if let Some(remarks) = remarks {
+        loan.remarks = Some(remarks.to_string());
+    }
+    loan.updated_at = now;
+    Some(loan.clone())
+}
+
+fn compare_containers(
+    sort: ContainerSort,
+    a: &ContainerWithItemCount,
+    b: &ContainerWithItemCount,
+) -> Ordering {
+    let (a_row, b_row) = (&a.container, &b.container);
+    match sort {
+        ContainerSort::Name => a_row.name.cmp(&b_row.name),
+        ContainerSort::Location => a_row.location.cmp(&b_row.location),
+        ContainerSort::ItemCount => a.item_count.cmp(&b.item_count),
+        ContainerSort::CreatedAt => a_row.created_at.cmp(&b_row.created_at),
+        ContainerSort::UpdatedAt => a_row.updated_at.cmp(&b_row.updated_at),
+        ContainerSort::IsDisposed => a_row.is_disposed.cmp(&b_row.is_disposed),
+    }
+}
+
+fn container_hierarchy(containers: &[Container]) -> ContainerHierarchy {
+    ContainerHierarchy::from_rows(
+        containers
+            .iter()
+            .map(|row| {
+                (
+                    row.id.clone(),
+                    Some(row.location.clone()),
+                    row.parent_container_id.clone(),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[async_trait]
+impl ItemRepository for InMemoryInventory {
+    async fn insert(
+        &self,
+        id: Uuid,
+        req: &CreateItemRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Item> {
+        let item = Item {
+            id,
+            name: req.name.clone(),
+            label_id: req.label_id.clone(),
+            model_number: req.model_number.clone(),
+            remarks: req.remarks.clone(),
+            purchase_year: req.purchase_year,
+            purchase_amount: req.purchase_amount,
+            durability_years: req.durability_years,
+            is_depreciation_target: Some(req.is_depreciation_target.unwrap_or(false)),
+            connection_names: req.connection_names.clone(),
+            cable_color_pattern: req.cable_color_pattern.clone(),
+            storage_location: req.storage_location.clone(),
+            location_id: req.location_id,
+            container_id: req.container_id.clone(),
+            storage_type: req
+                .storage_type
+                .clone()
+                .unwrap_or_else(|| "location".to_string()),
+            is_on_loan: Some(false),
+            qr_code_type: req.qr_code_type.clone(),
+            is_disposed: Some(false),
+            image_url: req.image_url.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.items.lock().unwrap().push(item.clone());
+        Ok(item)
+    }
+
+    async fn find(&self, id: Uuid) -> AppResult<Option<Item>> {
+        let items = self.items.lock().unwrap();
+        Ok(items.iter().find(|item| item.id == id).cloned())
+    }
+
+    async fn find_by_label(&self, label_id: &str) -> AppResult<Option<Item>> {
+        let items = self.items.lock().unwrap();
+        Ok(items.iter().find(|item| item.label_id == label_id).cloned())
+    }
+
+    async fn find_by_labels(&self, label_ids: &[String]) -> AppResult<Vec<Item>> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| label_ids.contains(&item.label_id))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.label_id.cmp(&b.label_id));
+        Ok(items)
+    }
+
+    async fn list_in_container(&self, container_id: &str) -> AppResult<Vec<Item>> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| is_stored_in(item, container_id))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.label_id.cmp(&b.label_id));
+        Ok(items)
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: Page) -> AppResult<(Vec<Item>, i64)> {
+        let items = filtered_items(&self.items.lock().unwrap(), filter);
+        Ok((page.slice(&items), items.len() as i64))
+    }
+
+    async fn list_all(&self, filter: &ItemFilter) -> AppResult<Vec<Item>> {
+        Ok(filtered_items(&self.items.lock().unwrap(), filter))
+    }
+
+    async fn update(
+        &self,
+        id: Uuid,
+        req: &UpdateItemRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Item>> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.iter_mut().find(|item| item.id == id) else {
+            return Ok(None);
+        };
+        if let Some(name) = &req.name {
+            item.name = name.clone();
+        }
+        if let Some(model_number) = &req.model_number {
+            item.model_number = Some(model_number.clone());
+        }
+        if let Some(remarks) = &req.remarks {
+            item.remarks = Some(remarks.clone());
+        }
+        if let Some(purchase_year) = req.purchase_year {
+            item.purchase_year = Some(purchase_year);
+        }
+        if let Some(purchase_amount) = req.purchase_amount {
+            item.purchase_amount = Some(purchase_amount);
+        }
+        if let Some(durability_years) = req.durability_years {
+            item.durability_years = Some(durability_years);
+        }
+        if let Some(is_depreciation_target) = req.is_depreciation_target {
+            item.is_depreciation_target = Some(is_depreciation_target);
+        }
+        if let Some(connection_names) = &req.connection_names {
+            item.connection_names = Some(connection_names.clone());
+        }
+        if let Some(cable_color_pattern) = &req.cable_color_pattern {
+            item.cable_color_pattern = Some(cable_color_pattern.clone());
+        }
+        if let Some(storage_location) = &req.storage_location {
+            item.storage_location = Some(storage_location.clone());
+        }
+        if let Some(location_id) = req.location_id {
+            item.location_id = Some(location_id);
+        }
+        if let Some(container_id) = &req.container_id {
+            item.container_id = Some(container_id.clone());
+        }
+        if let Some(storage_type) = &req.storage_type {
+            item.storage_type = storage_type.clone();
+        }
+        if let Some(qr_code_type) = &req.qr_code_type {
+            item.qr_code_type = Some(qr_code_type.clone());
+        }
+        if let Some(image_url) = &req.image_url {
+            item.image_url = Some(image_url.clone());
+        }
+        item.updated_at = now;
+        Ok(Some(item.clone()))
+    }
+
+    async fn set_image(
+        &self,
+        id: Uuid,
+        image_url: &str,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Item>> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.iter_mut().find(|item| item.id == id) else {
+            return Ok(None);
+        };
+        item.image_url = Some(image_url.to_string());
+        item.updated_at = now;
+        Ok(Some(item.clone()))
+    }
+
+    async fn set_disposed(
+        &self,
+        ids: &[Uuid],
+        is_disposed: bool,
+        now: DateTime<Utc>,
+    ) -> AppResult<u64> {
+        let mut updated = 0;
+        for item in self.items.lock().unwrap().iter_mut() {
+            if ids.contains(&item.id) {
+                item.is_disposed = Some(is_disposed);
+                item.updated_at = now;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn delete(&self, ids: &[Uuid]) -> AppResult<u64> {
+        let mut items = self.items.lock().unwrap();
+        let before = items.len();
+        items.retain(|item| !ids.contains(&item.id));
+        Ok((before - items.len()) as u64)
+    }
+
+    async fn any_on_loan(&self, ids: &[Uuid]) -> AppResult<bool> {
+        let items = self.items.lock().unwrap();
+        Ok(items
+            .iter()
+            .any(|item| ids.contains(&item.id) && item.is_on_loan == Some(true)))
+    }
+
+    async fn connection_names(&self) -> AppResult<Vec<String>> {
+        let mut names: Vec<String> = self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|item| item.connection_names.clone())
+            .flatten()
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    async fn find_label_alias(&self, label_id: &str) -> AppResult<Option<LabelAlias>> {
+        let aliases = self.label_aliases.lock().unwrap();
+        Ok(aliases.iter().find(|alias| alias.label_id == label_id).cloned())
+    }
+
+    async fn relabel(&self, alias: &LabelAlias) -> AppResult<bool> {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items
+            .iter_mut()
+            .find(|item| item.id == alias.item_id && item.label_id == alias.label_id)
+        else {
+            return Ok(false);
+        };
+        item.label_id = alias.replaced_by.clone();
+        item.updated_at = alias.created_at;
+
+        let mut aliases = self.label_aliases.lock().unwrap();
+        aliases.retain(|row| row.label_id != alias.replaced_by);
+        aliases.push(alias.clone());
+        for row in aliases.iter_mut().filter(|row| row.item_id == alias.item_id) {
+            row.replaced_by = alias.replaced_by.clone();
+        }
+        Ok(true)
+    }
+
+    async fn move_items(
+        &self,
+        ids: &[Uuid],
+        placement: &ItemPlacement,
+        batch: &TransferBatch<'_>,
+        _transfers: &[NewTransfer],
+    ) -> AppResult<()> {
+        for item in self.items.lock().unwrap().iter_mut() {
+            if ids.contains(&item.id) {
+                item.storage_type = placement.storage_type.to_string();
+                item.container_id = placement.container_id.clone();
+                item.storage_location = placement.storage_location.clone();
+                item.location_id = placement.location_id;
+                item.updated_at = batch.moved_at;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LoanRepository for InMemoryInventory {
+    async fn find(&self, id: i64) -> AppResult<Option<Loan>> {
+        let loans = self.loans.lock().unwrap();
+        Ok(loans.iter().find(|loan| loan.id == id).cloned())
+    }
+
+    async fn find_active(&self, item_id: Uuid) -> AppResult<Option<Loan>> {
+        Ok(active_loan(&self.loans.lock().unwrap(), item_id).cloned())
+    }
+
+    async fn list_for_item(&self, item_id: Uuid) -> AppResult<Vec<Loan>> {
+        let mut loans: Vec<Loan> = self
+            .loans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|loan| loan.item_id == item_id)
+            .cloned()
+            .collect();
+        loans.sort_by_key(|loan| (loan.loan_date, loan.id));
+        Ok(loans)
+    }
+
+    async fn list(
+        &self,
+        filters: &LoanFilters,
+        now: DateTime<Utc>,
+        page: Page,
+    ) -> AppResult<(Vec<LoanWithItem>, i64)> {
+        let items = self.items.lock().unwrap();
+        let mut loans: Vec<LoanWithItem> = self
+            .loans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|loan| loan_matches(loan, filters, now))
+            .filter_map(|loan| {
+                let item = items.iter().find(|item| item.id == loan.item_id)?;
+                Some(loan_with_item(loan, item, now))
+            })
+            .collect();
+        loans.sort_by_key(|loan| Reverse((loan.created_at, loan.id)));
+        Ok((page.slice(&loans), loans.len() as i64))
+    }
+
+    async fn list_overdue(&self, now: DateTime<Utc>) -> AppResult<Vec<LoanWithItem>> {
+        let items = self.items.lock().unwrap();
+        let mut loans: Vec<LoanWithItem> = self
+            .loans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|loan| is_overdue(loan.due_date, loan.return_date, now))
+            .filter_map(|loan| {
+                let item = items.iter().find(|item| item.id == loan.item_id)?;
+                Some(loan_with_item(loan, item, now))
+            })
+            .collect();
+        loans.sort_by_key(|loan| (loan.due_date, loan.id));
+        Ok(loans)
+    }
+
+    async fn insert(
+        &self,
+        item_id: Uuid,
+        loan: &NewLoan,
+        _exclude_reservation_id: Option<i64>,
+        _actor: &CurrentUser,
+    ) -> AppResult<Option<Loan>> {
+        let mut items = self.items.lock().unwrap();
+        if !take_item(&mut items, item_id, loan.loan_date) {
+            return Ok(None);
+        }
+        let mut loans = self.loans.lock().unwrap();
+        let created = new_loan(&loans, item_id, loan);
+        loans.push(created.clone());
+        Ok(Some(created))
+    }
+
+    async fn mark_returned(
+        &self,
+        id: i64,
+        return_date: DateTime<Utc>,
+        remarks: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Loan>> {
+        let mut items = self.items.lock().unwrap();
+        let mut loans = self.loans.lock().unwrap();
+        let loan = return_loan(&mut loans, id, return_date, remarks, now);
+        if let Some(loan) = &loan {
+            release_item(&mut items, loan.item_id, now);
+        }
+        Ok(loan)
+    }
+
+    async fn insert_batch(
+        &self,
+        loan: &NewLoan,
+        results: &mut [BatchLoanItemResult],
+        _actor: &CurrentUser,
+    ) -> AppResult<bool> {
+        let mut items = self.items.lock().unwrap();
+        // 途中で貸し出せない物品が見つかったら、書き換えた物品ごと捨てる
+        let mut staged = items.clone();
+        for result in results.iter_mut() {
+            let Some(item_id) = result.item_id.filter(|_| result.error.is_none()) else {
+                continue;
+            };
+            if !take_item(&mut staged, item_id, loan.loan_date) {
+                let state = staged
+                    .iter()
+                    .find(|item| item.id == item_id)
+                    .map(|item| (item.is_on_loan, item.is_disposed));
+                result.error = loan_state_error(item_id, state)
+                    .or_else(|| Some("Item is already on loan".to_string()));
+            }
+        }
+        if results.iter().any(|r| r.error.is_some()) {
+            return Ok(false);
+        }
+
+        let mut loans = self.loans.lock().unwrap();
+        for result in results.iter_mut() {
+            let created = new_loan(&loans, result.item_id.unwrap_or_default(), loan);
+            loans.push(created.clone());
+            result.loan = Some(created);
+        }
+        *items = staged;
+        Ok(true)
+    }
+
+    async fn return_batch(
+        &self,
+        return_date: DateTime<Utc>,
+        remarks: Option<&str>,
+        now: DateTime<Utc>,
+        results: &mut [BatchLoanItemResult],
+        _actor: &CurrentUser,
+    ) -> AppResult<bool> {
+        let mut items = self.items.lock().unwrap();
+        let mut loans = self.loans.lock().unwrap();
+        let mut staged_items = items.clone();
+        let mut staged_loans = loans.clone();
+
+        let mut returned = Vec::new();
+        for (index, result) in results.iter_mut().enumerate() {
+            let Some(item_id) = result.item_id.filter(|_| result.error.is_none()) else {
+                continue;
+            };
+            release_item(&mut staged_items, item_id, now);
+            match active_loan(&staged_loans, item_id) {
+                Some(loan) => returned.push((index, loan.id)),
+                None => result.error = Some("Item is not on loan".to_string()),
+            }
+        }
+        if results.iter().any(|r| r.error.is_some()) {
+            return Ok(false);
+        }
+
+        for (index, id) in returned {
+            let loan = return_loan(&mut staged_loans, id, return_date, remarks, now)
+                .ok_or_else(|| AppError::Conflict("Loan has already been returned".to_string()))?;
+            results[index].loan = Some(loan);
+        }
+        *items = staged_items;
+        *loans = staged_loans;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl ContainerRepository for InMemoryInventory {
+    async fn insert(&self, container: &Container) -> AppResult<()> {
+        self.containers.lock().unwrap().push(container.clone());
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> AppResult<Option<Container>> {
+        let containers = self.containers.lock().unwrap();
+        Ok(containers.iter().find(|row| row.id == id).cloned())
+    }
+
+    async fn list(
+        &self,
+        filter: &ContainerFilter,
+        sort: ContainerSort,
+        descending: bool,
+        page: Page,
+    ) -> AppResult<(Vec<ContainerWithItemCount>, i64)> {
+        let items = self.items.lock().unwrap();
+        let mut rows: Vec<ContainerWithItemCount> = self
+            .containers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|row| filter.include_disposed || !row.is_disposed)
+            .filter(|row| filter.location.as_ref().is_none_or(|v| &row.location == v))
+            .filter(|row| {
+                filter.search.as_deref().is_none_or(|search| {
+                    contains_ignore_case(Some(&row.name), search)
+                        || contains_ignore_case(row.description.as_deref(), search)
+                        || contains_ignore_case(Some(&row.location), search)
+                })
+            })
+            .map(|row| ContainerWithItemCount {
+                container: row.clone(),
+                item_count: items.iter().filter(|item| is_stored_in(item, &row.id)).count()
+                    as i64,
+                total_item_count: 0,
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            let order = compare_containers(sort, a, b);
+            let order = if descending { order.reverse() } else { order };
+            order.then_with(|| a.container.id.cmp(&b.container.id))
+        });
+        Ok((page.slice(&rows), rows.len() as i64))
+    }
+
+    async fn list_at_location(&self, location: &str) -> AppResult<Vec<Container>> {
+        let mut rows: Vec<Container> = self
+            .containers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|row| row.location == location && !row.is_disposed)
+            .cloned()
+            .collect();
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(rows)
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        req: &UpdateContainerRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Container>> {
+        let mut containers = self.containers.lock().unwrap();
+        let Some(row) = containers.iter_mut().find(|row| row.id == id) else {
+            return Ok(None);
+        };
+        if let Some(name) = &req.name {
+            row.name = name.clone();
+        }
+        if let Some(description) = &req.description {
+            row.description = Some(description.clone());
+        }
+        if let Some(location) = &req.location {
+            row.location = location.clone();
+        }
+        if let Some(location_id) = req.location_id {
+            row.location_id = Some(location_id);
+        }
+        if let Some(image_url) = &req.image_url {
+            row.image_url = Some(image_url.clone());
+        }
+        if let Some(is_disposed) = req.is_disposed {
+            row.is_disposed = is_disposed;
+        }
+        if let Some(parent_container_id) = &req.parent_container_id {
+            row.parent_container_id = parent_container_id.clone();
+        }
+        row.updated_at = now;
+        Ok(Some(row.clone()))
+    }
+
+    async fn set_disposed(
+        &self,
+        ids: &[String],
+        is_disposed: bool,
+        now: DateTime<Utc>,
+    ) -> AppResult<u64> {
+        let mut updated = 0;
+        for row in self.containers.lock().unwrap().iter_mut() {
+            if ids.contains(&row.id) {
+                row.is_disposed = is_disposed;
+                row.updated_at = now;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn delete(&self, ids: &[String]) -> AppResult<u64> {
+        let mut containers = self.containers.lock().unwrap();
+        let before = containers.len();
+        containers.retain(|row| !ids.contains(&row.id));
+        Ok((before - containers.len()) as u64)
+    }
+
+    async fn hierarchy(&self) -> AppResult<ContainerHierarchy> {
+        Ok(container_hierarchy(&self.containers.lock().unwrap()))
+    }
+
+    async fn direct_item_counts(&self) -> AppResult<HashMap<String, i64>> {
+        let mut counts = HashMap::new();
+        for item in self.items.lock().unwrap().iter() {
+            if let Some(container_id) = &item.container_id {
+                if is_stored_in(item, container_id) {
+                    *counts.entry(container_id.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn any_has_items(&self, ids: &[String]) -> AppResult<bool> {
+        let items = self.items.lock().unwrap();
+        Ok(items
+            .iter()
+            .any(|item| ids.iter().any(|id| is_stored_in(item, id))))
+    }
+
+    async fn move_container(
+        &self,
+        id: &str,
+        destination: &ContainerDestination,
+        batch: &TransferBatch<'_>,
+    ) -> AppResult<bool> {
+        let mut containers = self.containers.lock().unwrap();
+        let hierarchy = container_hierarchy(&containers);
+        if !hierarchy.contains(id) {
+            return Ok(false);
+        }
+        let (location, location_id) = match destination {
+            ContainerDestination::Location { path, location_id } => {
+                (path.clone(), Some(*location_id))
+            }
+            _ => {
+                let source = hierarchy
+                    .location_source(id, destination)?
+                    .unwrap_or_else(|| id.to_string());
+                let row = containers
+                    .iter()
+                    .find(|row| row.id == source)
+                    .ok_or_else(|| AppError::NotFound(format!("Container {} not found", source)))?;
+                (row.location.clone(), row.location_id)
+            }
+        };
+        let descendants = hierarchy.descendants(id);
+        for row in containers.iter_mut() {
+            if row.id == id {
+                row.parent_container_id = destination.parent_id().map(str::to_string);
+            } else if !descendants.contains(&row.id) {
+                continue;
+            }
+            row.location = location.clone();
+            row.location_id = location_id;
+            row.updated_at = batch.moved_at;
+        }
+        Ok(true)
+    }
+}
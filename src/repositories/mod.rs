@@ -0,0 +1,98 @@
+//! 集約ごとの永続化の窓口。
+//!
+//! 各トレイトに Postgres・SQLite と、テスト用のメモリ上の実装があり、どれも `conformance` の
+//! 同じテストを通す。サービスは存在確認や監査ログなどの業務ルールだけを持ち、
+//! SQL の方言の違いはこのモジュールの中に閉じ込める。
+
+pub mod audit;
+pub mod cable_color;
+pub mod connector;
+pub mod container;
+pub mod item;
+pub mod loan;
+pub mod tag;
+pub mod transfer;
+
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+mod memory;
+
+pub use audit::*;
+pub use cable_color::*;
+pub use connector::*;
+pub use container::*;
+pub use item::*;
+pub use loan::*;
+pub use tag::*;
+pub use transfer::*;
+
+use sqlx::Row;
+use uuid::Uuid;
+
+/// 一覧の取得範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Page {
+    /// 1始まりのページ番号から作る
+    pub fn new(page: u32, per_page: u32) -> Self {
+        Self {
+            limit: per_page as i64,
+            offset: (page.saturating_sub(1) as i64) * per_page as i64,
+        }
+    }
+
+    #[cfg(test)]
+    fn slice<T: Clone>(&self, rows: &[T]) -> Vec<T> {
+        rows.iter()
+            .skip(self.offset.max(0) as usize)
+            .take(self.limit.max(0) as usize)
+            .cloned()
+            .collect()
+    }
+}
+
+/// SQLite では UUID を TEXT で持つ
+fn sqlite_uuid(id: Uuid) -> String {
+    id.to_string()
+}
+
+fn sqlite_uuid_column(row: &sqlx::sqlite::SqliteRow, column: &str) -> Uuid {
+    row.get::<String, _>(column).parse().unwrap_or_default()
+}
+
+fn postgres_uuid_column(row: &sqlx::postgres::PgRow, column: &str) -> Uuid {
+    row.get(column)
+}
+
+/// SQLite には配列がないので、JSON の配列にして `json_each` で展開する
+fn sqlite_list<T: serde::Serialize>(values: &[T]) -> String {
+    serde_json::to_string(values).unwrap_or_default()
+}
+
+/// Postgres には配列のまま渡し、`= ANY(...)` で比べる
+fn postgres_list<T: Clone>(values: &[T]) -> Vec<T> {
+    values.to_vec()
+}
+
+/// 名前の重複など、一意制約に違反したときにメモリ上の実装が返すエラー。
+/// データベースの実装も同じ場合に同じエラーを返す。
+pub(crate) fn duplicate_name(kind: &str, name: &str) -> crate::error::AppError {
+    crate::error::AppError::Conflict(format!("{} named '{}' already exists", kind, name))
+}
+
+/// 一意制約違反を [`duplicate_name`] に変換する
+pub(crate) fn map_unique_violation(
+    e: sqlx::Error,
+    kind: &str,
+    name: &str,
+) -> crate::error::AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => duplicate_name(kind, name),
+        _ => e.into(),
+    }
+}
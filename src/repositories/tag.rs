@@ -0,0 +1,246 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+use super::{map_unique_violation, sqlite_uuid, Page};
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{CreateTagRequest, Tag, UpdateTagRequest};
+
+pub(super) const KIND: &str = "Tag";
+
+#[async_trait]
+pub trait TagRepository: Send + Sync {
+    async fn insert(&self, req: &CreateTagRequest, now: DateTime<Utc>) -> AppResult<Tag>;
+
+    async fn find(&self, id: i64) -> AppResult<Option<Tag>>;
+
+    /// 名前順。2つ目は全件数
+    async fn list(&self, page: Page) -> AppResult<(Vec<Tag>, i64)>;
+
+    /// 指定されたフィールドだけを書き換える。見つからなければ None
+    async fn update(
+        &self,
+        id: i64,
+        req: &UpdateTagRequest,
+        now: DateTime<Utc>,
+    ) -> AppResult<Option<Tag>>;
+
+    /// 削除したかどうか。物品からも外れる
+    async fn delete(&self, id: i64) -> AppResult<bool>;
+
+    /// 物品に付いているタグ（名前順）
+    async fn item_tags(&self, item_id: Uuid) -> AppResult<Vec<Tag>>;
+
+    /// 物品のタグを `tag_ids` に置き換える。重複したIDは1つにまとめる
+    async fn set_item_tags(&self, item_id: Uuid, tag_ids: &[i64]) -> AppResult<()>;
+}
+
+pub fn tag_repository(db: &DatabasePool) -> Arc<dyn TagRepository> {
+    match db {
+        DatabasePool::Postgres(pool) => Arc::new(PostgresTagRepository::new(pool.clone())),
+        DatabasePool::Sqlite(pool) => Arc::new(SqliteTagRepository::new(pool.clone())),
+    }
+}
+
+mod postgres {
+    pub const INSERT: &str = r#"
+        INSERT INTO tags (name, color, description, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        RETURNING id, name, color, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, color, description, created_at, updated_at
+        FROM tags WHERE id = $1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, color, description, created_at, updated_at
+        FROM tags ORDER BY name ASC, id ASC LIMIT $1 OFFSET $2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE tags SET
+            name = COALESCE($2, name),
+            color = COALESCE($3, color),
+            description = COALESCE($4, description),
+            updated_at = $5
+        WHERE id = $1
+        RETURNING id, name, color, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM tags WHERE id = $1";
+    pub const ITEM_TAGS: &str = r#"
+        SELECT t.id, t.name, t.color, t.description, t.created_at, t.updated_at
+        FROM tags t
+        INNER JOIN item_tags it ON t.id = it.tag_id
+        WHERE it.item_id = $1
+        ORDER BY t.name ASC, t.id ASC
+    "#;
+    pub const CLEAR_ITEM_TAGS: &str = "DELETE FROM item_tags WHERE item_id = $1";
+    pub const ADD_ITEM_TAG: &str = "INSERT INTO item_tags (item_id, tag_id) VALUES ($1, $2)";
+}
+
+mod sqlite {
+    pub const INSERT: &str = r#"
+        INSERT INTO tags (name, color, description, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?4)
+        RETURNING id, name, color, description, created_at, updated_at
+    "#;
+    pub const FIND: &str = r#"
+        SELECT id, name, color, description, created_at, updated_at
+        FROM tags WHERE id = ?1
+    "#;
+    pub const LIST: &str = r#"
+        SELECT id, name, color, description, created_at, updated_at
+        FROM tags ORDER BY name ASC, id ASC LIMIT ?1 OFFSET ?2
+    "#;
+    pub const UPDATE: &str = r#"
+        UPDATE tags SET
+            name = COALESCE(?2, name),
+            color = COALESCE(?3, color),
+            description = COALESCE(?4, description),
+            updated_at = ?5
+        WHERE id = ?1
+        RETURNING id, name, color, description, created_at, updated_at
+    "#;
+    pub const DELETE: &str = "DELETE FROM tags WHERE id = ?1";
+    pub const ITEM_TAGS: &str = r#"
+        SELECT t.id, t.name, t.color, t.description, t.created_at, t.updated_at
+        FROM tags t
+        INNER JOIN item_tags it ON t.id = it.tag_id
+        WHERE it.item_id = ?1
+        ORDER BY t.name ASC, t.id ASC
+    "#;
+    pub const CLEAR_ITEM_TAGS: &str = "DELETE FROM item_tags WHERE item_id = ?1";
+    pub const ADD_ITEM_TAG: &str = "INSERT INTO item_tags (item_id, tag_id) VALUES (?1, ?2)";
+}
+
+macro_rules! sql_tag_repository {
+    ($name:ident, $pool:ty, $row:ty, $sql:ident, $item_id:expr) => {
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                Self { pool }
+            }
+
+            fn row_to_tag(row: $row) -> Tag {
+                Tag {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    color: row.get("color"),
+                    description: row.get("description"),
+                    created_at: row.get("created_at"),
+                    updated_at: row.get("updated_at"),
+                }
+            }
+        }
+
+        #[async_trait]
+        impl TagRepository for $name {
+            async fn insert(&self, req: &CreateTagRequest, now: DateTime<Utc>) -> AppResult<Tag> {
+                let row = sqlx::query($sql::INSERT)
+                    .bind(&req.name)
+                    .bind(&req.color)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| map_unique_violation(e, KIND, &req.name))?;
+                Ok(Self::row_to_tag(row))
+            }
+
+            async fn find(&self, id: i64) -> AppResult<Option<Tag>> {
+                let row = sqlx::query($sql::FIND)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.map(Self::row_to_tag))
+            }
+
+            async fn list(&self, page: Page) -> AppResult<(Vec<Tag>, i64)> {
+                let rows = sqlx::query($sql::LIST)
+                    .bind(page.limit)
+                    .bind(page.offset)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tags")
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok((rows.into_iter().map(Self::row_to_tag).collect(), total))
+            }
+
+            async fn update(
+                &self,
+                id: i64,
+                req: &UpdateTagRequest,
+                now: DateTime<Utc>,
+            ) -> AppResult<Option<Tag>> {
+                let row = sqlx::query($sql::UPDATE)
+                    .bind(id)
+                    .bind(&req.name)
+                    .bind(&req.color)
+                    .bind(&req.description)
+                    .bind(now)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        map_unique_violation(e, KIND, req.name.as_deref().unwrap_or_default())
+                    })?;
+                Ok(row.map(Self::row_to_tag))
+            }
+
+            async fn delete(&self, id: i64) -> AppResult<bool> {
+                let result = sqlx::query($sql::DELETE)
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn item_tags(&self, item_id: Uuid) -> AppResult<Vec<Tag>> {
+                let rows = sqlx::query($sql::ITEM_TAGS)
+                    .bind($item_id(item_id))
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(Self::row_to_tag).collect())
+            }
+
+            async fn set_item_tags(&self, item_id: Uuid, tag_ids: &[i64]) -> AppResult<()> {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query($sql::CLEAR_ITEM_TAGS)
+                    .bind($item_id(item_id))
+                    .execute(&mut *tx)
+                    .await?;
+                for tag_id in tag_ids.iter().collect::<BTreeSet<_>>() {
+                    sqlx::query($sql::ADD_ITEM_TAG)
+                        .bind($item_id(item_id))
+                        .bind(tag_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(())
+            }
+        }
+    };
+}
+
+sql_tag_repository!(
+    PostgresTagRepository,
+    sqlx::PgPool,
+    sqlx::postgres::PgRow,
+    postgres,
+    std::convert::identity
+);
+sql_tag_repository!(
+    SqliteTagRepository,
+    sqlx::SqlitePool,
+    sqlx::sqlite::SqliteRow,
+    sqlite,
+    sqlite_uuid
+);
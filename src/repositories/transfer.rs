@@ -0,0 +1,91 @@
+//! 移動履歴（`transfers`）の書き込み。
+//!
+//! 移動と同じトランザクションで記録するため、接続を受け取る関数として置く。
+//! 履歴の参照は `TransferService` が持つ。
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::sqlite_uuid;
+use crate::error::AppResult;
+use crate::models::{CurrentUser, NewTransfer};
+
+const INSERT_TRANSFER_POSTGRES: &str = r#"
+    INSERT INTO transfers (
+        batch_id, entity_type, entity_id, from_location, from_container_id,
+        to_location, to_container_id, via_container_id, note, moved_by_id, moved_by, moved_at
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+"#;
+
+const INSERT_TRANSFER_SQLITE: &str = r#"
+    INSERT INTO transfers (
+        batch_id, entity_type, entity_id, from_location, from_container_id,
+        to_location, to_container_id, via_container_id, note, moved_by_id, moved_by, moved_at
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+"#;
+
+/// 1回の移動操作。`transfers` の各行に共通する値
+pub struct TransferBatch<'a> {
+    pub id: Uuid,
+    pub note: Option<&'a str>,
+    pub actor: &'a CurrentUser,
+    pub moved_at: DateTime<Utc>,
+}
+
+impl<'a> TransferBatch<'a> {
+    pub fn new(note: Option<&'a str>, actor: &'a CurrentUser) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            note,
+            actor,
+            moved_at: Utc::now(),
+        }
+    }
+}
+
+/// 呼び出し側のトランザクションの中で1件記録する
+pub async fn insert_transfer_postgres(
+    conn: &mut sqlx::PgConnection,
+    batch: &TransferBatch<'_>,
+    transfer: &NewTransfer,
+) -> AppResult<()> {
+    sqlx::query(INSERT_TRANSFER_POSTGRES)
+        .bind(batch.id)
+        .bind(transfer.entity_type.as_str())
+        .bind(&transfer.entity_id)
+        .bind(&transfer.from_location)
+        .bind(&transfer.from_container_id)
+        .bind(&transfer.to_location)
+        .bind(&transfer.to_container_id)
+        .bind(&transfer.via_container_id)
+        .bind(batch.note)
+        .bind((batch.actor.id != 0).then_some(batch.actor.id))
+        .bind(&batch.actor.username)
+        .bind(batch.moved_at)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_transfer_sqlite(
+    conn: &mut sqlx::SqliteConnection,
+    batch: &TransferBatch<'_>,
+    transfer: &NewTransfer,
+) -> AppResult<()> {
+    sqlx::query(INSERT_TRANSFER_SQLITE)
+        .bind(sqlite_uuid(batch.id))
+        .bind(transfer.entity_type.as_str())
+        .bind(&transfer.entity_id)
+        .bind(&transfer.from_location)
+        .bind(&transfer.from_container_id)
+        .bind(&transfer.to_location)
+        .bind(&transfer.to_container_id)
+        .bind(&transfer.via_container_id)
+        .bind(batch.note)
+        .bind((batch.actor.id != 0).then_some(batch.actor.id))
+        .bind(&batch.actor.username)
+        .bind(batch.moved_at)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
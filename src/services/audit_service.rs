@@ -0,0 +1,280 @@
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{
+    AuditAction, AuditEntity, AuditEvent, AuditEventsListResponse, AuditFilters, CurrentUser,
+};
+use crate::repositories::{insert_audit_postgres, insert_audit_sqlite};
+use serde_json::Value;
+use sqlx::Row;
+
+pub use crate::repositories::snapshot;
+
+pub struct AuditService {
+    db: DatabasePool,
+}
+
+impl AuditService {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// 変更を記録する。作成時は `before`、削除時は `after` を `None` にする。
+    /// 更新で実際に変わったフィールドがなければ何も記録しない。
+    pub async fn record(
+        &self,
+        entity: AuditEntity,
+        entity_id: &str,
+        action: AuditAction,
+        actor: &CurrentUser,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AppResult<()> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut conn = pool.acquire().await?;
+                insert_audit_postgres(&mut conn, entity, entity_id, action, actor, before, after)
+                    .await
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut conn = pool.acquire().await?;
+                insert_audit_sqlite(&mut conn, entity, entity_id, action, actor, before, after)
+                    .await
+            }
+        }
+    }
+
+    pub async fn list_events(
+        &self,
+        filters: &AuditFilters,
+        page: u32,
+        per_page: u32,
+    ) -> AppResult<AuditEventsListResponse> {
+        let offset = ((page - 1) * per_page) as i64;
+        let limit = per_page as i64;
+
+        let string_filters: Vec<(&str, &String)> = [
+            ("entity_type", filters.entity_type.as_ref()),
+            ("entity_id", filters.entity_id.as_ref()),
+            ("action", filters.action.as_ref()),
+            ("actor", filters.actor.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(column, value)| value.map(|v| (column, v)))
+        .collect();
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut where_conditions = Vec::new();
+                let mut param_index = 1;
+
+                for (column, _) in &string_filters {
+                    where_conditions.push(format!("{} = ${}", column, param_index));
+                    param_index += 1;
+                }
+                if filters.from.is_some() {
+                    where_conditions.push(format!("created_at >= ${}", param_index));
+                    param_index += 1;
+                }
+                if filters.to.is_some() {
+                    where_conditions.push(format!("created_at <= ${}", param_index));
+                    param_index += 1;
+                }
+
+                let where_clause = if where_conditions.is_empty() {
+                    String::new()
+                } else {
+                    format!("WHERE {}", where_conditions.join(" AND "))
+                };
+
+                let query_str = format!(
+                    r#"
+                    SELECT id, entity_type, entity_id, action, actor_id, actor, changes, created_at
+                    FROM audit_events
+                    {}
+                    ORDER BY created_at DESC, id DESC
+                    LIMIT ${} OFFSET ${}
+                    "#,
+                    where_clause,
+                    param_index,
+                    param_index + 1
+                );
+                let count_query_str =
+                    format!("SELECT COUNT(*) as count FROM audit_events {}", where_clause);
+
+                let mut query = sqlx::query(&query_str);
+                let mut count_query = sqlx::query(&count_query_str);
+
+                for (_, value) in &string_filters {
+                    query = query.bind(*value);
+                    count_query = count_query.bind(*value);
+                }
+                if let Some(from) = filters.from {
+                    query = query.bind(from);
+                    count_query = count_query.bind(from);
+                }
+                if let Some(to) = filters.to {
+                    query = query.bind(to);
+                    count_query = count_query.bind(to);
+                }
+                query = query.bind(limit).bind(offset);
+
+                let rows = query.fetch_all(pool).await?;
+                let events = rows
+                    .into_iter()
+                    .map(|row| self.row_to_event_postgres(row))
+                    .collect();
+
+                let total: i64 = count_query.fetch_one(pool).await?.get("count");
+
+                Ok(AuditEventsListResponse {
+                    events,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut where_conditions = Vec::new();
+
+                for (column, _) in &string_filters {
+                    where_conditions.push(format!("{} = ?", column));
+                }
+                if filters.from.is_some() {
+                    where_conditions.push("created_at >= ?".to_string());
+                }
+                if filters.to.is_some() {
+                    where_conditions.push("created_at <= ?".to_string());
+                }
+
+                let where_clause = if where_conditions.is_empty() {
+                    String::new()
+                } else {
+                    format!("WHERE {}", where_conditions.join(" AND "))
+                };
+
+                let query_str = format!(
+                    r#"
+                    SELECT id, entity_type, entity_id, action, actor_id, actor, changes, created_at
+                    FROM audit_events
+                    {}
+                    ORDER BY created_at DESC, id DESC
+                    LIMIT ? OFFSET ?
+                    "#,
+                    where_clause
+                );
+                let count_query_str =
+                    format!("SELECT COUNT(*) as count FROM audit_events {}", where_clause);
+
+                let mut query = sqlx::query(&query_str);
+                let mut count_query = sqlx::query(&count_query_str);
+
+                for (_, value) in &string_filters {
+                    query = query.bind(*value);
+                    count_query = count_query.bind(*value);
+                }
+                if let Some(from) = filters.from {
+                    query = query.bind(from);
+                    count_query = count_query.bind(from);
+                }
+                if let Some(to) = filters.to {
+                    query = query.bind(to);
+                    count_query = count_query.bind(to);
+                }
+                query = query.bind(limit).bind(offset);
+
+                let rows = query.fetch_all(pool).await?;
+                let events = rows
+                    .into_iter()
+                    .map(|row| self.row_to_event(row))
+                    .collect();
+
+                let total: i64 = count_query.fetch_one(pool).await?.get("count");
+
+                Ok(AuditEventsListResponse {
+                    events,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+        }
+    }
+
+    /// 指定したエンティティの記録を古い順に全件返す
+    pub async fn list_entity_events(
+        &self,
+        entity: AuditEntity,
+        entity_ids: &[String],
+    ) -> AppResult<Vec<AuditEvent>> {
+        if entity_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, entity_type, entity_id, action, actor_id, actor, changes, created_at
+                    FROM audit_events
+                    WHERE entity_type = $1 AND entity_id = ANY($2)
+                    ORDER BY created_at ASC, id ASC
+                    "#,
+                )
+                .bind(entity.as_str())
+                .bind(entity_ids)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_event_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let query_str = format!(
+                    r#"
+                    SELECT id, entity_type, entity_id, action, actor_id, actor, changes, created_at
+                    FROM audit_events
+                    WHERE entity_type = ? AND entity_id IN ({})
+                    ORDER BY created_at ASC, id ASC
+                    "#,
+                    entity_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+                );
+
+                let mut query = sqlx::query(&query_str).bind(entity.as_str());
+                for entity_id in entity_ids {
+                    query = query.bind(entity_id);
+                }
+
+                let rows = query.fetch_all(pool).await?;
+                Ok(rows.into_iter().map(|row| self.row_to_event(row)).collect())
+            }
+        }
+    }
+
+    fn row_to_event(&self, row: sqlx::sqlite::SqliteRow) -> AuditEvent {
+        AuditEvent {
+            id: row.get("id"),
+            entity_type: row.get("entity_type"),
+            entity_id: row.get("entity_id"),
+            action: row.get("action"),
+            actor_id: row.get("actor_id"),
+            actor: row.get("actor"),
+            changes: serde_json::from_str(&row.get::<String, _>("changes")).unwrap_or_default(),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn row_to_event_postgres(&self, row: sqlx::postgres::PgRow) -> AuditEvent {
+        AuditEvent {
+            id: row.get("id"),
+            entity_type: row.get("entity_type"),
+            entity_id: row.get("entity_id"),
+            action: row.get("action"),
+            actor_id: row.get("actor_id"),
+            actor: row.get("actor"),
+            changes: serde_json::from_str(&row.get::<String, _>("changes")).unwrap_or_default(),
+            created_at: row.get("created_at"),
+        }
+    }
+}
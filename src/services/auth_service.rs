@@ -0,0 +1,821 @@
+use crate::config::AuthConfig;
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    ApiToken, AuditAction, AuditEntity, CreateApiTokenRequest, CreateApiTokenResponse,
+    CreateUserRequest, CurrentUser, LoginRequest, LoginResponse, Role, UpdateUserRequest, User,
+    UsersListResponse,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{Duration, Utc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+
+pub struct AuthService {
+    db: DatabasePool,
+    config: AuthConfig,
+    audit: AuditService,
+}
+
+impl AuthService {
+    pub fn new(db: DatabasePool, config: AuthConfig) -> Self {
+        let audit = AuditService::new(db.clone());
+        Self { db, config, audit }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// ユーザーが1人もいなければ、設定された管理者アカウントを作成する
+    pub async fn ensure_admin_user(&self) -> AppResult<()> {
+        let count: i64 = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?
+            }
+        };
+
+        if count > 0 {
+            return Ok(());
+        }
+
+        match (&self.config.admin_username, &self.config.admin_password) {
+            (Some(username), Some(password)) => {
+                self.create_user(
+                    CreateUserRequest {
+                        username: username.clone(),
+                        display_name: None,
+                        password: password.clone(),
+                        role: Role::Admin,
+                    },
+                    &CurrentUser::system(),
+                )
+                .await?;
+                tracing::info!("Created initial admin user '{}'", username);
+            }
+            _ if self.config.enabled => {
+                tracing::warn!(
+                    "No users exist. Set AUTH_ADMIN_USERNAME and AUTH_ADMIN_PASSWORD to create an initial admin"
+                );
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub async fn login(&self, req: LoginRequest) -> AppResult<LoginResponse> {
+        let invalid = || AppError::Unauthorized("Invalid username or password".to_string());
+
+        let (user, password_hash) = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at, password_hash
+                    FROM users
+                    WHERE username = $1
+                    "#,
+                )
+                .bind(&req.username)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(invalid)?;
+
+                let password_hash: String = row.get("password_hash");
+                (self.row_to_user_postgres(row), password_hash)
+            }
+            DatabasePool::Sqlite(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at, password_hash
+                    FROM users
+                    WHERE username = ?1
+                    "#,
+                )
+                .bind(&req.username)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(invalid)?;
+
+                let password_hash: String = row.get("password_hash");
+                (self.row_to_user(row), password_hash)
+            }
+        };
+
+        if !verify_password(&req.password, &password_hash) || !user.is_active {
+            return Err(invalid());
+        }
+
+        let token = generate_token();
+        let token_hash = digest_token(&token);
+        let now = Utc::now();
+        let expires_at = now + Duration::hours(self.config.session_ttl_hours);
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                // 期限切れのセッションを掃除
+                sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND expires_at < $2")
+                    .bind(user.id)
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+
+                sqlx::query(
+                    "INSERT INTO sessions (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+                )
+                .bind(user.id)
+                .bind(&token_hash)
+                .bind(expires_at)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                // 期限切れのセッションを掃除
+                sqlx::query("DELETE FROM sessions WHERE user_id = ?1 AND expires_at < ?2")
+                    .bind(user.id)
+                    .bind(now)
+                    .execute(pool)
+                    .await?;
+
+                sqlx::query(
+                    "INSERT INTO sessions (user_id, token_hash, expires_at) VALUES (?1, ?2, ?3)",
+                )
+                .bind(user.id)
+                .bind(&token_hash)
+                .bind(expires_at)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(LoginResponse {
+            token,
+            expires_at,
+            user,
+        })
+    }
+
+    pub async fn logout(&self, token: &str) -> AppResult<()> {
+        let token_hash = digest_token(token);
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+                    .bind(&token_hash)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM sessions WHERE token_hash = ?1")
+                    .bind(&token_hash)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// セッショントークンまたはAPIトークンから実行者を解決する
+    pub async fn authenticate(&self, token: &str) -> AppResult<CurrentUser> {
+        let unauthorized = || AppError::Unauthorized("Invalid or expired token".to_string());
+        let token_hash = digest_token(token);
+        let now = Utc::now();
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let session = sqlx::query(
+                    r#"
+                    SELECT u.id, u.username, u.display_name, u.role, u.is_active, u.created_at, u.updated_at,
+                        s.expires_at
+                    FROM sessions s
+                    INNER JOIN users u ON s.user_id = u.id
+                    WHERE s.token_hash = $1
+                    "#,
+                )
+                .bind(&token_hash)
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some(row) = session {
+                    let expires_at: chrono::DateTime<Utc> = row.get("expires_at");
+                    let user = self.row_to_user_postgres(row);
+                    if expires_at < now || !user.is_active {
+                        return Err(unauthorized());
+                    }
+                    return Ok(CurrentUser::from(&user));
+                }
+
+                let row = sqlx::query(
+                    r#"
+                    SELECT u.id, u.username, u.display_name, u.role, u.is_active, u.created_at, u.updated_at,
+                        t.id as token_id, t.expires_at
+                    FROM api_tokens t
+                    INNER JOIN users u ON t.user_id = u.id
+                    WHERE t.token_hash = $1
+                    "#,
+                )
+                .bind(&token_hash)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(unauthorized)?;
+
+                let token_id: i64 = row.get("token_id");
+                let expires_at: Option<chrono::DateTime<Utc>> = row.get("expires_at");
+                let user = self.row_to_user_postgres(row);
+                if expires_at.is_some_and(|e| e < now) || !user.is_active {
+                    return Err(unauthorized());
+                }
+
+                sqlx::query("UPDATE api_tokens SET last_used_at = $1 WHERE id = $2")
+                    .bind(now)
+                    .bind(token_id)
+                    .execute(pool)
+                    .await?;
+
+                Ok(CurrentUser::from(&user))
+            }
+            DatabasePool::Sqlite(pool) => {
+                let session = sqlx::query(
+                    r#"
+                    SELECT u.id, u.username, u.display_name, u.role, u.is_active, u.created_at, u.updated_at,
+                        s.expires_at
+                    FROM sessions s
+                    INNER JOIN users u ON s.user_id = u.id
+                    WHERE s.token_hash = ?1
+                    "#,
+                )
+                .bind(&token_hash)
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some(row) = session {
+                    let expires_at: chrono::DateTime<Utc> = row.get("expires_at");
+                    let user = self.row_to_user(row);
+                    if expires_at < now || !user.is_active {
+                        return Err(unauthorized());
+                    }
+                    return Ok(CurrentUser::from(&user));
+                }
+
+                let row = sqlx::query(
+                    r#"
+                    SELECT u.id, u.username, u.display_name, u.role, u.is_active, u.created_at, u.updated_at,
+                        t.id as token_id, t.expires_at
+                    FROM api_tokens t
+                    INNER JOIN users u ON t.user_id = u.id
+                    WHERE t.token_hash = ?1
+                    "#,
+                )
+                .bind(&token_hash)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(unauthorized)?;
+
+                let token_id: i64 = row.get("token_id");
+                let expires_at: Option<chrono::DateTime<Utc>> = row.get("expires_at");
+                let user = self.row_to_user(row);
+                if expires_at.is_some_and(|e| e < now) || !user.is_active {
+                    return Err(unauthorized());
+                }
+
+                sqlx::query("UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2")
+                    .bind(now)
+                    .bind(token_id)
+                    .execute(pool)
+                    .await?;
+
+                Ok(CurrentUser::from(&user))
+            }
+        }
+    }
+
+    pub async fn create_user(
+        &self,
+        req: CreateUserRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<User> {
+        let password_hash = hash_password(&req.password)?;
+
+        let user = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO users (username, display_name, password_hash, role)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&req.username)
+                .bind(&req.display_name)
+                .bind(&password_hash)
+                .bind(req.role.as_str())
+                .fetch_one(pool)
+                .await
+                .map_err(|e| map_unique_violation(e, &req.username))?;
+
+                let id: i64 = result.get("id");
+                self.get_user(id).await
+            }
+            DatabasePool::Sqlite(pool) => {
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO users (username, display_name, password_hash, role)
+                    VALUES (?1, ?2, ?3, ?4)
+                    "#,
+                )
+                .bind(&req.username)
+                .bind(&req.display_name)
+                .bind(&password_hash)
+                .bind(req.role.as_str())
+                .execute(pool)
+                .await
+                .map_err(|e| map_unique_violation(e, &req.username))?;
+
+                let id = result.last_insert_rowid();
+                self.get_user(id).await
+            }
+        }?;
+        self.audit
+            .record(
+                AuditEntity::User,
+                &user.id.to_string(),
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&user),
+            )
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn get_user(&self, id: i64) -> AppResult<User> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at
+                    FROM users
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", id)))?;
+
+                Ok(self.row_to_user_postgres(row))
+            }
+            DatabasePool::Sqlite(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at
+                    FROM users
+                    WHERE id = ?1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", id)))?;
+
+                Ok(self.row_to_user(row))
+            }
+        }
+    }
+
+    pub async fn list_users(&self, page: u32, per_page: u32) -> AppResult<UsersListResponse> {
+        let offset = ((page - 1) * per_page) as i64;
+        let limit = per_page as i64;
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at
+                    FROM users
+                    ORDER BY username ASC
+                    LIMIT $1 OFFSET $2
+                    "#,
+                )
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?;
+
+                let users = rows
+                    .into_iter()
+                    .map(|row| self.row_to_user_postgres(row))
+                    .collect();
+
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?;
+
+                Ok(UsersListResponse {
+                    users,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, username, display_name, role, is_active, created_at, updated_at
+                    FROM users
+                    ORDER BY username ASC
+                    LIMIT ?1 OFFSET ?2
+                    "#,
+                )
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?;
+
+                let users = rows.into_iter().map(|row| self.row_to_user(row)).collect();
+
+                let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+                    .fetch_one(pool)
+                    .await?;
+
+                Ok(UsersListResponse {
+                    users,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+        }
+    }
+
+    pub async fn update_user(
+        &self,
+        id: i64,
+        req: UpdateUserRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<User> {
+        let existing = self.get_user(id).await?;
+
+        // 最後の有効な管理者を降格・無効化させない
+        let loses_admin = existing.role == Role::Admin
+            && existing.is_active
+            && (req.role.is_some_and(|r| r != Role::Admin) || req.is_active == Some(false));
+        if loses_admin && self.count_other_active_admins(id).await? == 0 {
+            return Err(AppError::BadRequest(
+                "Cannot demote or deactivate the last active admin".to_string(),
+            ));
+        }
+
+        let password_hash = req.password.as_deref().map(hash_password).transpose()?;
+        let role = req.role.map(|r| r.as_str());
+        let now = Utc::now();
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE users SET
+                        display_name = COALESCE($2, display_name),
+                        password_hash = COALESCE($3, password_hash),
+                        role = COALESCE($4, role),
+                        is_active = COALESCE($5, is_active),
+                        updated_at = $6
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .bind(&req.display_name)
+                .bind(&password_hash)
+                .bind(role)
+                .bind(req.is_active)
+                .bind(now)
+                .execute(pool)
+                .await?;
+
+                // パスワード変更・無効化時は既存のセッションを破棄する
+                if password_hash.is_some() || req.is_active == Some(false) {
+                    sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+                        .bind(id)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE users SET
+                        display_name = COALESCE(?2, display_name),
+                        password_hash = COALESCE(?3, password_hash),
+                        role = COALESCE(?4, role),
+                        is_active = COALESCE(?5, is_active),
+                        updated_at = ?6
+                    WHERE id = ?1
+                    "#,
+                )
+                .bind(id)
+                .bind(&req.display_name)
+                .bind(&password_hash)
+                .bind(role)
+                .bind(req.is_active)
+                .bind(now)
+                .execute(pool)
+                .await?;
+
+                // パスワード変更・無効化時は既存のセッションを破棄する
+                if password_hash.is_some() || req.is_active == Some(false) {
+                    sqlx::query("DELETE FROM sessions WHERE user_id = ?1")
+                        .bind(id)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+        }
+
+        let user = self.get_user(id).await?;
+
+        // パスワードは値を残さず、変更されたことだけを記録する
+        let mut after = snapshot(&user);
+        if let (Some(serde_json::Value::Object(fields)), Some(_)) = (after.as_mut(), &password_hash) {
+            fields.insert("password".to_string(), "(changed)".into());
+        }
+        self.audit
+            .record(
+                AuditEntity::User,
+                &id.to_string(),
+                AuditAction::Update,
+                actor,
+                snapshot(&existing),
+                after,
+            )
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn delete_user(&self, id: i64, actor: &CurrentUser) -> AppResult<()> {
+        let existing = self.get_user(id).await?;
+
+        if existing.role == Role::Admin
+            && existing.is_active
+            && self.count_other_active_admins(id).await? == 0
+        {
+            return Err(AppError::BadRequest(
+                "Cannot delete the last active admin".to_string(),
+            ));
+        }
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM users WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM users WHERE id = ?1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        self.audit
+            .record(
+                AuditEntity::User,
+                &id.to_string(),
+                AuditAction::Delete,
+                actor,
+                snapshot(&existing),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_api_token(
+        &self,
+        user_id: i64,
+        req: CreateApiTokenRequest,
+    ) -> AppResult<CreateApiTokenResponse> {
+        let token = generate_token();
+        let token_hash = digest_token(&token);
+        let expires_at = req.expires_in_days.map(|days| Utc::now() + Duration::days(days));
+
+        let id = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    INSERT INTO api_tokens (user_id, name, token_hash, expires_at)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING id
+                    "#,
+                )
+                .bind(user_id)
+                .bind(&req.name)
+                .bind(&token_hash)
+                .bind(expires_at)
+                .fetch_one(pool)
+                .await?;
+                row.get::<i64, _>("id")
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO api_tokens (user_id, name, token_hash, expires_at)
+                    VALUES (?1, ?2, ?3, ?4)
+                    "#,
+                )
+                .bind(user_id)
+                .bind(&req.name)
+                .bind(&token_hash)
+                .bind(expires_at)
+                .execute(pool)
+                .await?
+                .last_insert_rowid()
+            }
+        };
+
+        let api_token = self
+            .list_api_tokens(user_id)
+            .await?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| {
+                AppError::InternalServerError("Failed to load created API token".to_string())
+            })?;
+
+        Ok(CreateApiTokenResponse { token, api_token })
+    }
+
+    pub async fn list_api_tokens(&self, user_id: i64) -> AppResult<Vec<ApiToken>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, user_id, name, last_used_at, expires_at, created_at
+                    FROM api_tokens
+                    WHERE user_id = $1
+                    ORDER BY created_at DESC
+                    "#,
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|row| ApiToken {
+                        id: row.get("id"),
+                        user_id: row.get("user_id"),
+                        name: row.get("name"),
+                        last_used_at: row.get("last_used_at"),
+                        expires_at: row.get("expires_at"),
+                        created_at: row.get("created_at"),
+                    })
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, user_id, name, last_used_at, expires_at, created_at
+                    FROM api_tokens
+                    WHERE user_id = ?1
+                    ORDER BY created_at DESC
+                    "#,
+                )
+                .bind(user_id)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|row| ApiToken {
+                        id: row.get("id"),
+                        user_id: row.get("user_id"),
+                        name: row.get("name"),
+                        last_used_at: row.get("last_used_at"),
+                        expires_at: row.get("expires_at"),
+                        created_at: row.get("created_at"),
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    pub async fn delete_api_token(&self, user_id: i64, id: i64) -> AppResult<()> {
+        let result = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2")
+                    .bind(id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM api_tokens WHERE id = ?1 AND user_id = ?2")
+                    .bind(id)
+                    .bind(user_id)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+        };
+
+        if result == 0 {
+            return Err(AppError::NotFound(format!(
+                "API token with id {} not found",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn count_other_active_admins(&self, excluding_id: i64) -> AppResult<i64> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => Ok(sqlx::query_scalar(
+                "SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = true AND id <> $1",
+            )
+            .bind(excluding_id)
+            .fetch_one(pool)
+            .await?),
+            DatabasePool::Sqlite(pool) => Ok(sqlx::query_scalar(
+                "SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = 1 AND id <> ?1",
+            )
+            .bind(excluding_id)
+            .fetch_one(pool)
+            .await?),
+        }
+    }
+
+    fn row_to_user(&self, row: sqlx::sqlite::SqliteRow) -> User {
+        User {
+            id: row.get("id"),
+            username: row.get("username"),
+            display_name: row.get("display_name"),
+            role: Role::parse(&row.get::<String, _>("role")).unwrap_or(Role::Viewer),
+            is_active: row.get("is_active"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_user_postgres(&self, row: sqlx::postgres::PgRow) -> User {
+        User {
+            id: row.get("id"),
+            username: row.get("username"),
+            display_name: row.get("display_name"),
+            role: Role::parse(&row.get::<String, _>("role")).unwrap_or(Role::Viewer),
+            is_active: row.get("is_active"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+fn hash_password(password: &str) -> AppResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::InternalServerError(format!("Failed to hash password: {}", e)))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// 256bitのランダムなトークンを16進文字列で生成する
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn digest_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn map_unique_violation(err: sqlx::Error, username: &str) -> AppError {
+    match &err {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            AppError::BadRequest(format!("Username '{}' is already taken", username))
+        }
+        _ => AppError::DatabaseError(err),
+    }
+}
@@ -0,0 +1,375 @@
+//! 全テーブルと画像を1つの tar アーカイブにまとめるバックアップとリストア。
+//!
+//! アーカイブの先頭は `backup.json`（全テーブルの行と参照されている画像の一覧）で、
+//! その後に画像が `images/<ストレージのキー>` として続く。テーブルの値は
+//! `db::tables` と同じ JSON 表現なので、SQLite・Postgres、ローカル・S3 の
+//! どの組み合わせの間でも戻せる。
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read as _};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::tables::{Row, Table, TABLES};
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::services::StorageService;
+
+pub const BACKUP_FORMAT: &str = "hyperdashi-backup";
+pub const BACKUP_VERSION: u32 = 1;
+
+const DUMP_ENTRY: &str = "backup.json";
+const IMAGE_DIR: &str = "images/";
+
+/// `image_url` 列を持つテーブル
+const IMAGE_TABLES: &[&str] = &["items", "containers"];
+
+/// サーバー起動時の管理者作成や、リストアする人のログインで行ができるテーブル。
+/// リストア先が空かどうかの判定には含めない（中身はバックアップで置き換わる）
+const ACCOUNT_TABLES: &[&str] = &["users", "sessions", "api_tokens", "audit_events"];
+
+/// `backup.json` の中身
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupDump {
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub tables: Vec<TableDump>,
+    /// 参照されている画像。ストレージから読めたものだけがアーカイブに入る
+    pub images: Vec<BackupImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableDump {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupImage {
+    /// バックアップ元での URL（`image_url` の値）
+    pub url: String,
+    pub key: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoredTable {
+    pub table: &'static str,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreSummary {
+    pub created_at: DateTime<Utc>,
+    pub tables: Vec<RestoredTable>,
+    pub images: usize,
+    /// アーカイブに入っていなかった画像の URL（`image_url` はそのまま残す）
+    pub missing_images: Vec<String>,
+}
+
+pub struct BackupService {
+    db: DatabasePool,
+    storage: Arc<StorageService>,
+}
+
+impl BackupService {
+    pub fn new(db: DatabasePool, storage: Arc<StorageService>) -> Self {
+        Self { db, storage }
+    }
+
+    /// テーブルを読み終えてから、アーカイブを先頭から順に返すストリームを作る。
+    /// 画像はストリームを読み進めたときに1枚ずつストレージから取り出す
+    pub async fn backup(&self) -> AppResult<impl Stream<Item = io::Result<Bytes>>> {
+        let mut tables = Vec::with_capacity(TABLES.len());
+        let mut images = Vec::new();
+        let mut seen = HashSet::new();
+        for table in TABLES {
+            let rows = self.db.read_rows(table).await?;
+            if let Some(position) = image_column(table) {
+                for row in &rows {
+                    let Value::String(url) = &row[position] else {
+                        continue;
+                    };
+                    match self.storage.key_for_url(url) {
+                        Some(key) if seen.insert(url.clone()) => images.push(BackupImage {
+                            url: url.clone(),
+                            key,
+                        }),
+                        Some(_) => {}
+                        None => tracing::warn!("Skipping image outside of storage: {}", url),
+                    }
+                }
+            }
+            tables.push(TableDump {
+                name: table.name.to_string(),
+                columns: table.columns.iter().map(|c| c.name.to_string()).collect(),
+                rows,
+            });
+        }
+
+        let dump = BackupDump {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at: Utc::now(),
+            tables,
+            images,
+        };
+        let json = serde_json::to_vec(&dump).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to serialize backup: {}", e))
+        })?;
+        let head = tar_entry(DUMP_ENTRY, &json)?;
+
+        let storage = self.storage.clone();
+        let files = stream::iter(dump.images)
+            .then(move |image| {
+                let storage = storage.clone();
+                async move {
+                    match storage.fetch(&image.key).await {
+                        Ok(Some(data)) => {
+                            tar_entry(&format!("{}{}", IMAGE_DIR, image.key), &data).map(Some)
+                        }
+                        Ok(None) => {
+                            tracing::warn!("Image missing from storage: {}", image.key);
+                            Ok(None)
+                        }
+                        Err(e) => Err(io::Error::other(e.to_string())),
+                    }
+                }
+            })
+            .filter_map(|entry| async move { entry.transpose() });
+
+        // tar の終わりは 512 バイトのゼロ埋めブロック2つ
+        Ok(stream::once(async move { Ok(head) })
+            .chain(files)
+            .chain(stream::once(async { Ok(Bytes::from(vec![0; 1024])) })))
+    }
+
+    /// 空のデータベースとストレージにバックアップを戻す。
+    /// データベースへの書き込みに失敗したら、戻した画像も消す
+    pub async fn restore(&self, archive: &[u8]) -> AppResult<RestoreSummary> {
+        let (dump, mut files) = read_archive(archive)?;
+        let mut data = table_rows(dump.tables)?;
+        self.ensure_empty().await?;
+
+        let mut urls = HashMap::new();
+        let mut missing_images = Vec::new();
+        for image in &dump.images {
+            let Some(bytes) = files.remove(&image.key) else {
+                missing_images.push(image.url.clone());
+                continue;
+            };
+            match self.storage.put(&image.key, bytes, content_type(&image.key)).await {
+                Ok(url) => {
+                    urls.insert(image.url.clone(), url);
+                }
+                Err(e) => {
+                    self.discard(urls.values()).await;
+                    return Err(e);
+                }
+            }
+        }
+
+        for (table, rows) in &mut data {
+            let Some(position) = image_column(table) else {
+                continue;
+            };
+            for row in rows.iter_mut() {
+                if let Some(url) = row[position].as_str().and_then(|url| urls.get(url)) {
+                    row[position] = Value::from(url.clone());
+                }
+            }
+        }
+
+        if let Err(e) = self.db.replace_rows(&data).await {
+            self.discard(urls.values()).await;
+            return Err(e);
+        }
+
+        Ok(RestoreSummary {
+            created_at: dump.created_at,
+            tables: data
+                .iter()
+                .map(|(table, rows)| RestoredTable {
+                    table: table.name,
+                    rows: rows.len(),
+                })
+                .collect(),
+            images: urls.len(),
+            missing_images,
+        })
+    }
+
+    async fn ensure_empty(&self) -> AppResult<()> {
+        let mut non_empty = Vec::new();
+        for table in TABLES
+            .iter()
+            .filter(|table| !table.seeded && !ACCOUNT_TABLES.contains(&table.name))
+        {
+            let count = self.db.count_rows(table).await?;
+            if count > 0 {
+                non_empty.push(format!("{} ({} rows)", table.name, count));
+            }
+        }
+        if !non_empty.is_empty() {
+            return Err(AppError::Conflict(format!(
+                "Database is not empty: {}",
+                non_empty.join(", ")
+            )));
+        }
+        if !self.storage.is_empty().await? {
+            return Err(AppError::Conflict("Storage is not empty".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn discard(&self, urls: impl Iterator<Item = &String>) {
+        for url in urls {
+            if let Err(e) = self.storage.delete(url).await {
+                tracing::warn!("Failed to remove restored image {}: {}", url, e);
+            }
+        }
+    }
+}
+
+fn image_column(table: &Table) -> Option<usize> {
+    if !IMAGE_TABLES.contains(&table.name) {
+        return None;
+    }
+    table.columns.iter().position(|column| column.name == "image_url")
+}
+
+fn tar_entry(path: &str, data: &[u8]) -> io::Result<Bytes> {
+    let mut header = tar::Header::new_ustar();
+    header.set_path(path)?;
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_cksum();
+
+    let padding = (512 - data.len() % 512) % 512;
+    let mut bytes = Vec::with_capacity(512 + data.len() + padding);
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    bytes.resize(bytes.len() + padding, 0);
+    Ok(bytes.into())
+}
+
+fn invalid_archive(e: io::Error) -> AppError {
+    AppError::BadRequest(format!("Invalid backup archive: {}", e))
+}
+
+/// `backup.json` と、キーごとの画像を取り出す
+fn read_archive(archive: &[u8]) -> AppResult<(BackupDump, HashMap<String, Vec<u8>>)> {
+    let mut dump = None;
+    let mut files = HashMap::new();
+    let mut tar = tar::Archive::new(archive);
+    for entry in tar.entries().map_err(invalid_archive)? {
+        let mut entry = entry.map_err(invalid_archive)?;
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(invalid_archive)?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(invalid_archive)?;
+        if path == DUMP_ENTRY {
+            dump = Some(serde_json::from_slice::<BackupDump>(&data).map_err(|e| {
+                AppError::BadRequest(format!("Invalid {}: {}", DUMP_ENTRY, e))
+            })?);
+        } else if let Some(key) = path.strip_prefix(IMAGE_DIR) {
+            files.insert(key.to_string(), data);
+        }
+    }
+
+    let dump = dump
+        .ok_or_else(|| AppError::BadRequest(format!("{} not found in archive", DUMP_ENTRY)))?;
+    if dump.format != BACKUP_FORMAT || dump.version != BACKUP_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported backup format {} version {} (expected {} version {})",
+            dump.format, dump.version, BACKUP_FORMAT, BACKUP_VERSION
+        )));
+    }
+    Ok((dump, files))
+}
+
+/// 列を名前で今のスキーマに合わせる。バックアップにない列は null、ないテーブルは空にする
+fn table_rows(dumps: Vec<TableDump>) -> AppResult<Vec<(&'static Table, Vec<Row>)>> {
+    let mut dumps: HashMap<String, TableDump> =
+        dumps.into_iter().map(|dump| (dump.name.clone(), dump)).collect();
+    let mut data = Vec::with_capacity(TABLES.len());
+    for table in TABLES {
+        let rows = match dumps.remove(table.name) {
+            Some(dump) => align_rows(table, dump)?,
+            None => Vec::new(),
+        };
+        data.push((table, rows));
+    }
+    if let Some(name) = dumps.keys().next() {
+        return Err(AppError::BadRequest(format!(
+            "Unknown table in backup: {}",
+            name
+        )));
+    }
+    Ok(data)
+}
+
+fn align_rows(table: &Table, dump: TableDump) -> AppResult<Vec<Row>> {
+    if let Some(unknown) = dump
+        .columns
+        .iter()
+        .find(|name| !table.columns.iter().any(|column| column.name == name.as_str()))
+    {
+        return Err(AppError::BadRequest(format!(
+            "Unknown column in backup: {}.{}",
+            table.name, unknown
+        )));
+    }
+    let positions: Vec<Option<usize>> = table
+        .columns
+        .iter()
+        .map(|column| dump.columns.iter().position(|name| name == column.name))
+        .collect();
+
+    dump.rows
+        .into_iter()
+        .map(|row| {
+            if row.len() != dump.columns.len() {
+                return Err(AppError::BadRequest(format!(
+                    "{}: expected {} values per row, got {}",
+                    table.name,
+                    dump.columns.len(),
+                    row.len()
+                )));
+            }
+            Ok(positions
+                .iter()
+                .map(|position| position.map_or(Value::Null, |i| row[i].clone()))
+                .collect())
+        })
+        .collect()
+}
+
+fn content_type(key: &str) -> &'static str {
+    let extension = std::path::Path::new(key)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
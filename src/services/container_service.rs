@@ -1,170 +1,129 @@
 use crate::db::DatabasePool;
 use crate::error::{AppError, AppResult};
 use crate::models::{
-    Container, ContainerWithItemCount, ContainersListResponse, CreateContainerRequest, UpdateContainerRequest,
+    AuditAction, AuditEntity, Container, ContainerTreeNode, ContainerWithItemCount,
+    ContainersListResponse, CreateContainerRequest, CurrentUser, Item, LabelStatus,
+    MoveContainerRequest, MoveContainerResponse, Transfer, TransferEntity, UpdateContainerRequest,
 };
-use crate::services::item_service::ItemService;
-use sqlx::Row;
+use crate::repositories::{
+    container_repository, ContainerDestination, ContainerFilter, ContainerHierarchy,
+    ContainerRepository, ContainerSort, Page, TransferBatch,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use crate::services::label_scheme;
+use crate::services::label_service::LabelService;
+use crate::services::location_service::LocationService;
+use crate::services::transfer_service::TransferService;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 pub struct ContainerService {
-    db: DatabasePool,
-    item_service: ItemService,
+    repo: Arc<dyn ContainerRepository>,
+    labels: LabelService,
+    audit: AuditService,
+    locations: LocationService,
+    transfers: TransferService,
 }
 
 impl ContainerService {
     pub fn new(db: DatabasePool) -> Self {
-        let item_service = ItemService::new(db.clone());
-        Self { db, item_service }
+        Self {
+            repo: container_repository(&db),
+            labels: LabelService::new(db.clone()),
+            audit: AuditService::new(db.clone()),
+            locations: LocationService::new(db.clone()),
+            transfers: TransferService::new(db),
+        }
     }
 
-    pub async fn create_container(&self, request: CreateContainerRequest) -> AppResult<Container> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // Use provided ID or generate one
-                let container_id = if let Some(id) = &request.id {
-                    id.clone()
-                } else {
-                    let container_ids = self.item_service.generate_label_ids(1).await?;
-                    container_ids.into_iter().next().ok_or_else(|| {
-                        AppError::InternalServerError("Failed to generate container ID".to_string())
-                    })?
-                };
-
-                let now = chrono::Utc::now();
-
-                sqlx::query(
-                    r#"
-                    INSERT INTO containers (id, name, description, location, image_url, created_at, updated_at, is_disposed)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                    "#,
-                )
-                .bind(&container_id)
-                .bind(&request.name)
-                .bind(&request.description)
-                .bind(&request.location)
-                .bind(&request.image_url)
-                .bind(now)
-                .bind(now)
-                .bind(false)
-                .execute(pool)
-                .await?;
-
-                // Return the created container
-                self.get_container(&container_id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                // Use provided ID or generate one
-                let container_id = if let Some(id) = &request.id {
-                    id.clone()
-                } else {
-                    let container_ids = self.item_service.generate_label_ids(1).await?;
-                    container_ids.into_iter().next().ok_or_else(|| {
-                        AppError::InternalServerError("Failed to generate container ID".to_string())
-                    })?
-                };
-
-                let now = chrono::Utc::now();
-
-                let result = sqlx::query(
-                    r#"
-                    INSERT INTO containers (id, name, description, location, image_url, created_at, updated_at, is_disposed)
-                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
-                    "#
+    pub async fn create_container(
+        &self,
+        request: CreateContainerRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Container> {
+        if let Some(id) = &request.id {
+            self.labels.ensure_attachable(id).await?;
+        }
+        let requested = self
+            .requested_location(request.location_id, request.location.as_deref())
+            .await?;
+        let location = match &request.parent_container_id {
+            Some(parent_id) => self
+                .hierarchy()
+                .await?
+                .nested_location(parent_id, requested.as_deref())?,
+            None => requested.ok_or_else(|| {
+                AppError::BadRequest(
+                    "location is required unless parent_container_id is given".to_string(),
                 )
-                .bind(&container_id)
-                .bind(&request.name)
-                .bind(&request.description)
-                .bind(&request.location)
-                .bind(&request.image_url)
-                .bind(now)
-                .bind(now)
-                .bind(false)
-                .execute(pool)
-                .await?;
+            })?,
+        };
+        let location = self
+            .locations
+            .resolve(None, Some(&location), actor)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("location must not be blank".to_string()))?;
+
+        // Use provided ID or generate one
+        let container_id = self.new_container_id(&request, actor).await?;
+        let now = chrono::Utc::now();
+        self.repo
+            .insert(&Container {
+                id: container_id.clone(),
+                name: request.name,
+                description: request.description,
+                location: location.path,
+                image_url: request.image_url,
+                created_at: now,
+                updated_at: now,
+                is_disposed: false,
+                parent_container_id: request.parent_container_id,
+                location_id: Some(location.id),
+            })
+            .await?;
+        let container = self.get_container(&container_id).await?;
+
+        self.labels.attach_to_container(&container.id).await?;
+        self.audit
+            .record(
+                AuditEntity::Container,
+                &container.id,
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&container),
+            )
+            .await?;
+        Ok(container)
+    }
 
-                if result.rows_affected() == 0 {
-                    return Err(AppError::InternalServerError(
-                        "Failed to create container".to_string(),
-                    ));
-                }
-
-                Ok(Container {
-                    id: container_id.clone(),
-                    name: request.name,
-                    description: request.description,
-                    location: request.location,
-                    image_url: request.image_url,
-                    created_at: now,
-                    updated_at: now,
-                    is_disposed: false,
-                })
-            }
+    /// IDの指定がなければラベルの発行範囲から1つ確保する
+    async fn new_container_id(
+        &self,
+        request: &CreateContainerRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<String> {
+        if let Some(id) = &request.id {
+            return Ok(id.clone());
         }
+        let sequence = request
+            .label_sequence
+            .as_deref()
+            .unwrap_or(label_scheme::DEFAULT_SEQUENCE);
+        let (_, container_ids) = self.labels.generate_batch(sequence, 1, None, actor).await?;
+        container_ids.into_iter().next().ok_or_else(|| {
+            AppError::InternalServerError("Failed to generate container ID".to_string())
+        })
     }
 
     pub async fn get_container(&self, id: &str) -> AppResult<Container> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let row = sqlx::query(
-                    "SELECT id, name, description, location, image_url, created_at, updated_at, is_disposed FROM containers WHERE id = $1"
-                )
-                .bind(id)
-                .fetch_optional(pool)
-                .await?;
-
-                match row {
-                    Some(row) => Ok(Container {
-                        id: row.get("id"),
-                        name: row.get("name"),
-                        description: row.get("description"),
-                        location: row.get("location"),
-                        image_url: row.get("image_url"),
-                        created_at: row.get("created_at"),
-                        updated_at: row.get("updated_at"),
-                        is_disposed: row.get("is_disposed"),
-                    }),
-                    None => Err(AppError::NotFound("Container not found".to_string())),
-                }
-            }
-            DatabasePool::Sqlite(pool) => {
-                let row = sqlx::query(
-                    "SELECT id, name, description, location, image_url, created_at, updated_at, is_disposed FROM containers WHERE id = ?"
-                )
-                .bind(id)
-                .fetch_optional(pool)
-                .await?;
-
-                match row {
-                    Some(row) => Ok(Container {
-                        id: row.get("id"),
-                        name: row.get("name"),
-                        description: row.get("description"),
-                        location: row.get("location"),
-                        image_url: row.get("image_url"),
-                        created_at: row.get::<chrono::NaiveDateTime, _>("created_at").and_utc(),
-                        updated_at: row.get::<chrono::NaiveDateTime, _>("updated_at").and_utc(),
-                        is_disposed: {
-                            // Handle both TEXT and INTEGER types for is_disposed
-                            if let Ok(int_val) = row.try_get::<Option<i32>, _>("is_disposed") {
-                                int_val.unwrap_or(0) != 0
-                            } else if let Ok(text_val) =
-                                row.try_get::<Option<String>, _>("is_disposed")
-                            {
-                                matches!(
-                                    text_val.as_deref(),
-                                    Some("1") | Some("true") | Some("TRUE")
-                                )
-                            } else {
-                                false
-                            }
-                        },
-                    }),
-                    None => Err(AppError::NotFound("Container not found".to_string())),
-                }
-            }
-        }
+        self.repo
+            .find(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Container not found".to_string()))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn list_containers(
         &self,
         page: u32,
@@ -175,564 +134,134 @@ impl ContainerService {
         sort_by: &str,
         sort_order: &str,
     ) -> AppResult<ContainersListResponse> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // First, get the total count
-                let mut count_query_str = String::from(
-                    r#"
-                    SELECT COUNT(DISTINCT c.id) as total
-                    FROM containers c
-                    WHERE 1=1
-                    "#,
-                );
-
-                let mut count_param_index = 1;
-
-                if !include_disposed {
-                    count_query_str.push_str(" AND c.is_disposed = false");
-                }
-
-                if location_filter.is_some() {
-                    count_query_str.push_str(&format!(" AND c.location = ${}", count_param_index));
-                    count_param_index += 1;
-                }
-
-                if search.is_some() {
-                    let search_clause = format!(
-                        " AND (c.name ILIKE ${} OR c.description ILIKE ${} OR c.location ILIKE ${})",
-                        count_param_index, count_param_index + 1, count_param_index + 2
-                    );
-                    count_query_str.push_str(&search_clause);
-                }
-
-                let mut count_query = sqlx::query(&count_query_str);
-
-                if let Some(location) = location_filter {
-                    count_query = count_query.bind(location);
-                }
-
-                if let Some(search_term) = search {
-                    let search_param = format!("%{}%", search_term);
-                    count_query = count_query.bind(search_param.clone()).bind(search_param.clone()).bind(search_param.clone());
-                }
-
-                let count_row = count_query.fetch_one(pool).await?;
-                let total: i64 = count_row.get("total");
-
-                // Now get the paginated data
-                let mut query_str = String::from(
-                    r#"
-                    SELECT
-                        c.id, c.name, c.description, c.location, c.image_url, c.created_at, c.updated_at, c.is_disposed,
-                        COUNT(i.id) as item_count
-                    FROM containers c
-                    LEFT JOIN items i ON c.id = i.container_id AND i.storage_type = 'container' AND (i.is_disposed IS NULL OR i.is_disposed = false)
-                    WHERE 1=1
-                    "#,
-                );
-
-                let mut param_index = 1;
-
-                if !include_disposed {
-                    query_str.push_str(" AND c.is_disposed = false");
-                }
-
-                if location_filter.is_some() {
-                    query_str.push_str(&format!(" AND c.location = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if search.is_some() {
-                    let search_clause = format!(
-                        " AND (c.name ILIKE ${} OR c.description ILIKE ${} OR c.location ILIKE ${})",
-                        param_index, param_index + 1, param_index + 2
-                    );
-                    query_str.push_str(&search_clause);
-                    param_index += 3;
-                }
-
-                query_str.push_str(" GROUP BY c.id, c.name, c.description, c.location, c.image_url, c.created_at, c.updated_at, c.is_disposed");
-
-                let sort_column = match sort_by {
-                    "name" => "c.name",
-                    "location" => "c.location",
-                    "item_count" => "item_count",
-                    "created_at" => "c.created_at",
-                    "updated_at" => "c.updated_at",
-                    "is_disposed" => "c.is_disposed",
-                    _ => "c.created_at",
-                };
-                let sort_direction = if sort_order.eq_ignore_ascii_case("asc") { "ASC" } else { "DESC" };
-                query_str.push_str(&format!(" ORDER BY {} {}", sort_column, sort_direction));
-
-                // Add pagination
-                let offset = ((page - 1) * per_page) as i64;
-                query_str.push_str(&format!(" LIMIT ${} OFFSET ${}", param_index, param_index + 1));
-
-                let mut query = sqlx::query(&query_str);
-
-                if let Some(location) = location_filter {
-                    query = query.bind(location);
-                }
-
-                if let Some(search_term) = search {
-                    let search_param = format!("%{}%", search_term);
-                    query = query.bind(search_param.clone()).bind(search_param.clone()).bind(search_param);
-                }
-
-                query = query.bind(per_page as i64).bind(offset);
-
-                let rows = query.fetch_all(pool).await?;
-
-                let containers = rows
-                    .into_iter()
-                    .map(|row| ContainerWithItemCount {
-                        container: Container {
-                            id: row.get("id"),
-                            name: row.get("name"),
-                            description: row.get("description"),
-                            location: row.get("location"),
-                            image_url: row.get("image_url"),
-                            created_at: row.get("created_at"),
-                            updated_at: row.get("updated_at"),
-                            is_disposed: row.get("is_disposed"),
-                        },
-                        item_count: row.get::<i64, _>("item_count"),
-                    })
-                    .collect();
-
-                Ok(ContainersListResponse {
-                    containers,
-                    total,
-                    page,
-                    per_page,
-                })
-            }
-            DatabasePool::Sqlite(pool) => {
-                // First, get the total count
-                let mut count_query = String::from(
-                    r#"
-                    SELECT COUNT(DISTINCT c.id) as total
-                    FROM containers c
-                    WHERE 1=1
-                    "#,
-                );
-
-                let mut count_params: Vec<String> = Vec::new();
-
-                if !include_disposed {
-                    count_query.push_str(" AND c.is_disposed = 0");
-                }
-
-                if let Some(location) = location_filter {
-                    count_query.push_str(" AND c.location = ?");
-                    count_params.push(location.to_string());
-                }
-
-                if let Some(search_term) = search {
-                    count_query.push_str(" AND (c.name LIKE ? OR c.description LIKE ? OR c.location LIKE ?)");
-                    let search_param = format!("%{}%", search_term);
-                    count_params.push(search_param.clone());
-                    count_params.push(search_param.clone());
-                    count_params.push(search_param);
-                }
-
-                let mut count_query_builder = sqlx::query(&count_query);
-                for param in count_params {
-                    count_query_builder = count_query_builder.bind(param);
-                }
-
-                let count_row = count_query_builder.fetch_one(pool).await?;
-                let total: i64 = count_row.get("total");
-
-                // Now get the paginated data
-                let mut query = String::from(
-                    r#"
-                    SELECT
-                        c.id, c.name, c.description, c.location, c.image_url, c.created_at, c.updated_at, c.is_disposed,
-                        COUNT(i.id) as item_count
-                    FROM containers c
-                    LEFT JOIN items i ON c.id = i.container_id AND i.storage_type = 'container' AND (i.is_disposed IS NULL OR i.is_disposed = 0)
-                    WHERE 1=1
-                    "#,
-                );
-
-                let mut params: Vec<String> = Vec::new();
-
-                if !include_disposed {
-                    query.push_str(" AND c.is_disposed = 0");
-                }
-
-                if let Some(location) = location_filter {
-                    query.push_str(" AND c.location = ?");
-                    params.push(location.to_string());
-                }
-
-                if let Some(search_term) = search {
-                    query.push_str(" AND (c.name LIKE ? OR c.description LIKE ? OR c.location LIKE ?)");
-                    let search_param = format!("%{}%", search_term);
-                    params.push(search_param.clone());
-                    params.push(search_param.clone());
-                    params.push(search_param);
-                }
-
-                query.push_str(" GROUP BY c.id, c.name, c.description, c.location, c.image_url, c.created_at, c.updated_at, c.is_disposed");
-
-                let sort_column = match sort_by {
-                    "name" => "c.name",
-                    "location" => "c.location",
-                    "item_count" => "item_count",
-                    "created_at" => "c.created_at",
-                    "updated_at" => "c.updated_at",
-                    "is_disposed" => "c.is_disposed",
-                    _ => "c.created_at",
-                };
-                let sort_direction = if sort_order.eq_ignore_ascii_case("asc") { "ASC" } else { "DESC" };
-                query.push_str(&format!(" ORDER BY {} {}", sort_column, sort_direction));
-
-                // Add pagination
-                let offset = (page - 1) * per_page;
-                query.push_str(&format!(" LIMIT {} OFFSET {}", per_page, offset));
-
-                let mut query_builder = sqlx::query(&query);
-                for param in params {
-                    query_builder = query_builder.bind(param);
-                }
-
-                let rows = query_builder.fetch_all(pool).await?;
-
-                let containers = rows
-                    .into_iter()
-                    .map(|row| {
-                        ContainerWithItemCount {
-                            container: Container {
-                                id: row.get::<Option<String>, _>("id").unwrap_or_default(),
-                                name: row.get::<Option<String>, _>("name").unwrap_or_default(),
-                                description: row.get("description"),
-                                location: row
-                                    .get::<Option<String>, _>("location")
-                                    .unwrap_or_default(),
-                                image_url: row.get("image_url"),
-                                created_at: row
-                                    .get::<Option<chrono::NaiveDateTime>, _>("created_at")
-                                    .map(|dt| {
-                                        chrono::DateTime::from_naive_utc_and_offset(dt, chrono::Utc)
-                                    })
-                                    .unwrap_or_default(),
-                                updated_at: row
-                                    .get::<Option<chrono::NaiveDateTime>, _>("updated_at")
-                                    .map(|dt| {
-                                        chrono::DateTime::from_naive_utc_and_offset(dt, chrono::Utc)
-                                    })
-                                    .unwrap_or_default(),
-                                is_disposed: {
-                                    // Handle both TEXT and INTEGER types for is_disposed
-                                    if let Ok(int_val) =
-                                        row.try_get::<Option<i32>, _>("is_disposed")
-                                    {
-                                        int_val.unwrap_or(0) != 0
-                                    } else if let Ok(text_val) =
-                                        row.try_get::<Option<String>, _>("is_disposed")
-                                    {
-                                        matches!(
-                                            text_val.as_deref(),
-                                            Some("1") | Some("true") | Some("TRUE")
-                                        )
-                                    } else {
-                                        false
-                                    }
-                                },
-                            },
-                            item_count: row.get("item_count"),
-                        }
-                    })
-                    .collect();
-
-                Ok(ContainersListResponse {
-                    containers,
-                    total,
-                    page,
-                    per_page,
-                })
-            }
-        }
+        let filter = ContainerFilter {
+            location: location_filter.map(str::to_string),
+            include_disposed,
+            search: search.map(str::to_string),
+        };
+        let (containers, total) = self
+            .repo
+            .list(
+                &filter,
+                ContainerSort::parse(sort_by),
+                !sort_order.eq_ignore_ascii_case("asc"),
+                Page::new(page, per_page),
+            )
+            .await?;
+        let containers = self.with_total_item_counts(containers).await?;
+
+        Ok(ContainersListResponse {
+            containers,
+            total,
+            page,
+            per_page,
+        })
     }
 
+    /// 親コンテナや場所の変更は [`ContainerService::move_container`] と同じく移動として扱い、
+    /// 入れ子の確認・移動・記録を1つのトランザクションで行う
     pub async fn update_container(
         &self,
         id: &str,
         request: UpdateContainerRequest,
+        actor: &CurrentUser,
     ) -> AppResult<Container> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let mut updates = Vec::new();
-                let mut param_index = 1;
-
-                if request.name.is_some() {
-                    updates.push(format!("name = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if request.description.is_some() {
-                    updates.push(format!("description = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if request.location.is_some() {
-                    updates.push(format!("location = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if request.image_url.is_some() {
-                    updates.push(format!("image_url = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if request.is_disposed.is_some() {
-                    updates.push(format!("is_disposed = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if updates.is_empty() {
-                    return Err(AppError::BadRequest("No fields to update".to_string()));
-                }
-
-                updates.push(format!("updated_at = ${}", param_index));
-                let now = chrono::Utc::now();
-                param_index += 1;
-
-                let query = format!(
-                    "UPDATE containers SET {} WHERE id = ${}",
-                    updates.join(", "),
-                    param_index
-                );
-
-                let mut query_builder = sqlx::query(&query);
-
-                if let Some(name) = &request.name {
-                    query_builder = query_builder.bind(name);
-                }
-
-                if let Some(description) = &request.description {
-                    query_builder = query_builder.bind(description);
-                }
-
-                if let Some(location) = &request.location {
-                    query_builder = query_builder.bind(location);
-                }
-
-                if let Some(image_url) = &request.image_url {
-                    query_builder = query_builder.bind(image_url);
-                }
-
-                if let Some(is_disposed) = request.is_disposed {
-                    query_builder = query_builder.bind(is_disposed);
-                }
-
-                query_builder = query_builder.bind(now).bind(id);
-
-                let result = query_builder.execute(pool).await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound("Container not found".to_string()));
-                }
+        let before = self.get_container(id).await?;
+        let requested = self
+            .requested_location(request.location_id, request.location.as_deref())
+            .await?;
+        let moves = request.parent_container_id.is_some() || requested.is_some();
+        let has_details = request.name.is_some()
+            || request.description.is_some()
+            || request.image_url.is_some()
+            || request.is_disposed.is_some();
+        if !moves && !has_details {
+            return Err(AppError::BadRequest("No fields to update".to_string()));
+        }
 
-                self.get_container(id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                let mut updates = Vec::new();
-                let mut params: Vec<String> = Vec::new();
-
-                if let Some(name) = &request.name {
-                    updates.push("name = ?");
-                    params.push(name.clone());
-                }
-
-                if let Some(description) = &request.description {
-                    updates.push("description = ?");
-                    params.push(description.clone());
-                }
-
-                if let Some(location) = &request.location {
-                    updates.push("location = ?");
-                    params.push(location.clone());
-                }
-
-                if let Some(image_url) = &request.image_url {
-                    updates.push("image_url = ?");
-                    params.push(image_url.clone());
-                }
-
-                if let Some(is_disposed) = request.is_disposed {
-                    updates.push("is_disposed = ?");
-                    // Store as text to match the current database schema
-                    params.push(if is_disposed {
-                        "1".to_string()
-                    } else {
-                        "0".to_string()
-                    });
-                }
-
-                if updates.is_empty() {
-                    return Err(AppError::BadRequest("No fields to update".to_string()));
-                }
-
-                updates.push("updated_at = ?");
-                let now = chrono::Utc::now();
-                params.push(now.to_rfc3339());
-                params.push(id.to_string());
-
-                let query = format!("UPDATE containers SET {} WHERE id = ?", updates.join(", "));
-
-                let mut query_builder = sqlx::query(&query);
-                for param in params {
-                    query_builder = query_builder.bind(param);
-                }
-
-                let result = query_builder.execute(pool).await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound("Container not found".to_string()));
-                }
-
-                self.get_container(id).await
+        if moves {
+            // 親コンテナを指定しなければ今の親コンテナに入れたまま、場所だけを確認する
+            let parent_id = match &request.parent_container_id {
+                Some(parent_id) => parent_id.clone(),
+                None => before.parent_container_id.clone(),
+            };
+            let destination = self.destination(parent_id, requested, actor).await?;
+            let batch = TransferBatch::new(None, actor);
+            if !self.repo.move_container(id, &destination, &batch).await? {
+                return Err(AppError::NotFound("Container not found".to_string()));
             }
         }
+        let container = if has_details {
+            let details = UpdateContainerRequest {
+                location: None,
+                location_id: None,
+                parent_container_id: None,
+                ..request
+            };
+            self.repo
+                .update(id, &details, chrono::Utc::now())
+                .await?
+                .ok_or_else(|| AppError::NotFound("Container not found".to_string()))?
+        } else {
+            self.get_container(id).await?
+        };
+
+        self.audit
+            .record(
+                AuditEntity::Container,
+                &container.id,
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&container),
+            )
+            .await?;
+        Ok(container)
     }
 
-    pub async fn delete_container(&self, id: &str) -> AppResult<()> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // Check if container has items
-                let items_count = sqlx::query(
-                    "SELECT COUNT(*) as count FROM items WHERE container_id = $1 AND storage_type = 'container'"
-                )
-                .bind(id)
-                .fetch_one(pool)
-                .await?;
-
-                let count: i64 = items_count.get("count");
-                if count > 0 {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete container that contains items".to_string(),
-                    ));
-                }
-
-                let result = sqlx::query("DELETE FROM containers WHERE id = $1")
-                    .bind(id)
-                    .execute(pool)
-                    .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound("Container not found".to_string()));
-                }
-
-                Ok(())
-            }
-            DatabasePool::Sqlite(pool) => {
-                // Check if container has items
-                let item_count_row = sqlx::query(
-                    "SELECT COUNT(*) as count FROM items WHERE container_id = ? AND storage_type = 'container' AND (is_disposed IS NULL OR is_disposed = 0)"
-                )
-                .bind(id)
-                .fetch_one(pool)
-                .await?;
-
-                let item_count: i64 = item_count_row.get("count");
-                if item_count > 0 {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete container with items. Move or remove items first."
-                            .to_string(),
-                    ));
-                }
-
-                let result = sqlx::query("DELETE FROM containers WHERE id = ?")
-                    .bind(id)
-                    .execute(pool)
-                    .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound("Container not found".to_string()));
-                }
-
-                Ok(())
-            }
+    pub async fn delete_container(&self, id: &str, actor: &CurrentUser) -> AppResult<()> {
+        let before = self.get_container(id).await?;
+        if !self.hierarchy().await?.children(id).is_empty() {
+            return Err(AppError::BadRequest(
+                "Cannot delete container that contains other containers".to_string(),
+            ));
         }
+        // 廃棄済みの物品は数えない（一括削除と同じ判定）
+        if self.check_containers_have_items(&[id.to_string()]).await? {
+            return Err(AppError::BadRequest(
+                "Cannot delete container with items. Move or remove items first.".to_string(),
+            ));
+        }
+        if self.repo.delete(&[id.to_string()]).await? == 0 {
+            return Err(AppError::NotFound("Container not found".to_string()));
+        }
+        self.labels.detach(&before.id, LabelStatus::Printed).await?;
+        self.audit
+            .record(
+                AuditEntity::Container,
+                &before.id,
+                AuditAction::Delete,
+                actor,
+                snapshot(&before),
+                None,
+            )
+            .await?;
+        Ok(())
     }
 
     pub async fn get_containers_by_location(&self, location: &str) -> AppResult<Vec<Container>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let rows = sqlx::query(
-                    "SELECT id, name, description, location, image_url, created_at, updated_at, is_disposed FROM containers WHERE location = $1 AND is_disposed = false ORDER BY name"
-                )
-                .bind(location)
-                .fetch_all(pool)
-                .await?;
-
-                let containers = rows
-                    .into_iter()
-                    .map(|row| Container {
-                        id: row.get("id"),
-                        name: row.get("name"),
-                        description: row.get("description"),
-                        location: row.get("location"),
-                        image_url: row.get("image_url"),
-                        created_at: row.get("created_at"),
-                        updated_at: row.get("updated_at"),
-                        is_disposed: row.get("is_disposed"),
-                    })
-                    .collect();
-
-                Ok(containers)
-            }
-            DatabasePool::Sqlite(pool) => {
-                let rows = sqlx::query(
-                    "SELECT id, name, description, location, image_url, created_at, updated_at, is_disposed FROM containers WHERE location = ? AND is_disposed = 0 ORDER BY name"
-                )
-                .bind(location)
-                .fetch_all(pool)
-                .await?;
-
-                let containers = rows
-                    .into_iter()
-                    .map(|row| Container {
-                        id: row.get("id"),
-                        name: row.get("name"),
-                        description: row.get("description"),
-                        location: row.get("location"),
-                        image_url: row.get("image_url"),
-                        created_at: row.get::<chrono::NaiveDateTime, _>("created_at").and_utc(),
-                        updated_at: row.get::<chrono::NaiveDateTime, _>("updated_at").and_utc(),
-                        is_disposed: row.get("is_disposed"),
-                    })
-                    .collect();
-
-                Ok(containers)
-            }
-        }
+        self.repo.list_at_location(location).await
     }
 
     pub async fn check_container_id_exists(&self, id: &str) -> AppResult<bool> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let result: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM containers WHERE id = $1)")
-                    .bind(id)
-                    .fetch_one(pool)
-                    .await?;
-                Ok(result.0)
-            }
-            DatabasePool::Sqlite(pool) => {
-                let result: (i32,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM containers WHERE id = ?)")
-                    .bind(id)
-                    .fetch_one(pool)
-                    .await?;
-                Ok(result.0 == 1)
-            }
-        }
+        Ok(self.repo.find(id).await?.is_some())
     }
 
-    pub async fn bulk_delete_containers(&self, ids: &[String]) -> AppResult<()> {
+    pub async fn bulk_delete_containers(
+        &self,
+        ids: &[String],
+        actor: &CurrentUser,
+    ) -> AppResult<()> {
         if ids.is_empty() {
             return Ok(());
         }
@@ -745,24 +274,33 @@ impl ContainerService {
             ));
         }
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                sqlx::query("DELETE FROM containers WHERE id = ANY($1)")
-                    .bind(ids)
-                    .execute(pool)
-                    .await?;
-            }
-            DatabasePool::Sqlite(pool) => {
-                let query = format!(
-                    "DELETE FROM containers WHERE id IN ({})",
-                    ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
-                );
-                let mut query_builder = sqlx::query(&query);
-                for id in ids {
-                    query_builder = query_builder.bind(id);
-                }
-                query_builder.execute(pool).await?;
-            }
+        let hierarchy = self.hierarchy().await?;
+        if ids
+            .iter()
+            .flat_map(|id| hierarchy.children(id))
+            .any(|child| !ids.contains(&child))
+        {
+            return Err(AppError::BadRequest(
+                "Cannot delete containers that contain other containers".to_string(),
+            ));
+        }
+
+        let deleted = self.find_existing_containers(ids).await?;
+        self.repo.delete(ids).await?;
+        for container in &deleted {
+            self.labels
+                .detach(&container.id, LabelStatus::Printed)
+                .await?;
+            self.audit
+                .record(
+                    AuditEntity::Container,
+                    &container.id,
+                    AuditAction::Delete,
+                    actor,
+                    snapshot(container),
+                    None,
+                )
+                .await?;
         }
         Ok(())
     }
@@ -771,66 +309,222 @@ impl ContainerService {
         &self,
         ids: &[String],
         is_disposed: bool,
+        actor: &CurrentUser,
     ) -> AppResult<()> {
         if ids.is_empty() {
             return Ok(());
         }
 
-        let now = chrono::Utc::now();
+        let before = self.find_existing_containers(ids).await?;
+        self.repo
+            .set_disposed(ids, is_disposed, chrono::Utc::now())
+            .await?;
+        let action = if is_disposed {
+            AuditAction::Dispose
+        } else {
+            AuditAction::Undispose
+        };
+        for old in &before {
+            let container = self.get_container(&old.id).await?;
+            self.audit
+                .record(
+                    AuditEntity::Container,
+                    &container.id,
+                    action,
+                    actor,
+                    snapshot(old),
+                    snapshot(&container),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// コンテナの入れ子関係を読み込む
+    pub async fn hierarchy(&self) -> AppResult<ContainerHierarchy> {
+        self.repo.hierarchy().await
+    }
+
+    /// 一番外側のコンテナから順に並べた、コンテナの入れ子の経路
+    pub async fn container_path(&self, id: &str) -> AppResult<Vec<Container>> {
+        let mut path = Vec::new();
+        for container_id in self.hierarchy().await?.chain(id).iter().rev() {
+            path.push(self.get_container(container_id).await?);
+        }
+        Ok(path)
+    }
+
+    /// 物品が実際に置かれている場所。コンテナに入っている場合は一番外側のコンテナの場所
+    pub async fn effective_item_location(&self, item: &Item) -> AppResult<Option<String>> {
+        Ok(self.hierarchy().await?.item_location(item))
+    }
+
+    /// コンテナを中身ごと移動し、コンテナ・中のコンテナ・中の物品それぞれの移動を記録する。
+    /// 入れ子の確認・移動・記録は1つのトランザクションで行う
+    pub async fn move_container(
+        &self,
+        id: &str,
+        request: MoveContainerRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<MoveContainerResponse> {
+        let has_location = request.location_id.is_some() || request.location.is_some();
+        if !has_location && request.parent_container_id.is_none() {
+            return Err(AppError::BadRequest(
+                "location, location_id or parent_container_id is required".to_string(),
+            ));
+        }
+        let before = self.get_container(id).await?;
+        let requested = self
+            .requested_location(request.location_id, request.location.as_deref())
+            .await?;
+        let destination = self
+            .destination(request.parent_container_id.clone().flatten(), requested, actor)
+            .await?;
+
+        let batch = TransferBatch::new(request.note.as_deref(), actor);
+        if !self.repo.move_container(id, &destination, &batch).await? {
+            return Err(AppError::NotFound("Container not found".to_string()));
+        }
+
+        let container = self.get_container(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Container,
+                &container.id,
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&container),
+            )
+            .await?;
+        Ok(MoveContainerResponse {
+            container,
+            batch_id: batch.id,
+            transfers: self.transfers.list_batch(batch.id).await?,
+        })
+    }
+
+    pub async fn list_transfers(&self, id: &str) -> AppResult<Vec<Transfer>> {
+        self.get_container(id).await?;
+        self.transfers
+            .list_for_entity(TransferEntity::Container, id)
+            .await
+    }
+
+    pub async fn get_container_tree(&self, id: &str) -> AppResult<ContainerTreeNode> {
+        let root = self.get_container(id).await?;
+        let hierarchy = self.hierarchy().await?;
+        let counts = self.repo.direct_item_counts().await?;
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                sqlx::query("UPDATE containers SET is_disposed = $1, updated_at = $2 WHERE id = ANY($3)")
-                    .bind(is_disposed)
-                    .bind(now)
-                    .bind(ids)
-                    .execute(pool)
-                    .await?;
+        let mut containers = HashMap::new();
+        for descendant in hierarchy.descendants(id) {
+            let container = self.get_container(&descendant).await?;
+            containers.insert(descendant, container);
+        }
+        Ok(build_tree(root, &hierarchy, &counts, &mut containers))
+    }
+
+    /// リクエストで指定された場所。`location_id` があればその場所のパスを使う
+    async fn requested_location(
+        &self,
+        location_id: Option<i64>,
+        location: Option<&str>,
+    ) -> AppResult<Option<String>> {
+        match location_id {
+            Some(id) => Ok(Some(self.locations.get_location(id).await?.path)),
+            None => Ok(location.map(str::to_string)),
+        }
+    }
+
+    /// 移動先。親コンテナに入れるときの場所は、トランザクションの中で親コンテナから決める
+    async fn destination(
+        &self,
+        parent_id: Option<String>,
+        location: Option<String>,
+        actor: &CurrentUser,
+    ) -> AppResult<ContainerDestination> {
+        match (parent_id, location) {
+            (Some(parent_id), location) => Ok(ContainerDestination::Parent {
+                parent_id,
+                location,
+            }),
+            (None, Some(location)) => {
+                let location = self
+                    .locations
+                    .resolve(None, Some(&location), actor)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::BadRequest("location must not be blank".to_string())
+                    })?;
+                Ok(ContainerDestination::Location {
+                    path: location.path,
+                    location_id: location.id,
+                })
             }
-            DatabasePool::Sqlite(pool) => {
-                let query = format!(
-                    "UPDATE containers SET is_disposed = ?, updated_at = ? WHERE id IN ({})",
-                    ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
-                );
-                let mut query_builder = sqlx::query(&query);
-                query_builder = query_builder.bind(is_disposed);
-                query_builder = query_builder.bind(now);
-                for id in ids {
-                    query_builder = query_builder.bind(id);
-                }
-                query_builder.execute(pool).await?;
+            (None, None) => Ok(ContainerDestination::Unnest),
+        }
+    }
+
+    async fn with_total_item_counts(
+        &self,
+        mut containers: Vec<ContainerWithItemCount>,
+    ) -> AppResult<Vec<ContainerWithItemCount>> {
+        if containers.is_empty() {
+            return Ok(containers);
+        }
+        let hierarchy = self.hierarchy().await?;
+        let counts = self.repo.direct_item_counts().await?;
+        for entry in &mut containers {
+            entry.total_item_count = total_item_count(&hierarchy, &counts, &entry.container.id);
+        }
+        Ok(containers)
+    }
+
+    /// 一括操作の監査ログ用に、存在するコンテナだけを取得する
+    async fn find_existing_containers(&self, ids: &[String]) -> AppResult<Vec<Container>> {
+        let mut containers = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(container) = self.repo.find(id).await? {
+                containers.push(container);
             }
         }
-        Ok(())
+        Ok(containers)
     }
 
     async fn check_containers_have_items(&self, ids: &[String]) -> AppResult<bool> {
         if ids.is_empty() {
             return Ok(false);
         }
+        self.repo.any_has_items(ids).await
+    }
+}
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let count: i64 = sqlx::query_scalar(
-                    "SELECT COUNT(*) FROM items WHERE container_id = ANY($1) AND storage_type = 'container' AND (is_disposed IS NULL OR is_disposed = false)"
-                )
-                .bind(ids)
-                .fetch_one(pool)
-                .await?;
-                Ok(count > 0)
-            }
-            DatabasePool::Sqlite(pool) => {
-                let query = format!(
-                    "SELECT COUNT(*) FROM items WHERE container_id IN ({}) AND storage_type = 'container' AND (is_disposed IS NULL OR is_disposed = 0)",
-                    ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
-                );
-                let mut query_builder = sqlx::query_scalar(&query);
-                for id in ids {
-                    query_builder = query_builder.bind(id);
-                }
-                let count: i64 = query_builder.fetch_one(pool).await?;
-                Ok(count > 0)
-            }
-        }
+fn total_item_count(hierarchy: &ContainerHierarchy, counts: &HashMap<String, i64>, id: &str) -> i64 {
+    std::iter::once(id.to_string())
+        .chain(hierarchy.descendants(id))
+        .map(|container_id| counts.get(&container_id).copied().unwrap_or(0))
+        .sum()
+}
+
+fn build_tree(
+    container: Container,
+    hierarchy: &ContainerHierarchy,
+    counts: &HashMap<String, i64>,
+    containers: &mut HashMap<String, Container>,
+) -> ContainerTreeNode {
+    // 取り出したコンテナは二度と現れないので、親子関係が循環していても止まる
+    let children = hierarchy
+        .children(&container.id)
+        .into_iter()
+        .filter_map(|child| containers.remove(&child))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|child| build_tree(child, hierarchy, counts, containers))
+        .collect();
+    ContainerTreeNode {
+        item_count: counts.get(&container.id).copied().unwrap_or(0),
+        total_item_count: total_item_count(hierarchy, counts, &container.id),
+        container,
+        children,
     }
 }
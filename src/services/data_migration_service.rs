@@ -0,0 +1,86 @@
+use serde::Serialize;
+
+use crate::db::tables::{Table, TABLES};
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+
+/// テーブルごとの移行元と移行先の行数
+#[derive(Debug, Clone, Serialize)]
+pub struct TableCount {
+    pub table: &'static str,
+    pub source: i64,
+    pub target: i64,
+}
+
+/// あるデータベースの全データを別のデータベースへ写す（SQLite から Postgres へ、またはその逆）。
+/// UUID・時刻・ラベルの採番位置を含めてそのまま持っていく
+pub struct DataMigrationService {
+    source: DatabasePool,
+    target: DatabasePool,
+}
+
+impl DataMigrationService {
+    pub fn new(source: DatabasePool, target: DatabasePool) -> Self {
+        Self { source, target }
+    }
+
+    pub async fn run(&self) -> AppResult<Vec<TableCount>> {
+        // 列の並びを揃えるため、どちらも最新のスキーマにしておく
+        self.source.migrate().await?;
+        self.target.migrate().await?;
+        self.ensure_target_empty().await?;
+
+        let mut data: Vec<(&Table, _)> = Vec::with_capacity(TABLES.len());
+        for table in TABLES {
+            let rows = self.source.read_rows(table).await?;
+            tracing::info!("Read {} rows from {}", rows.len(), table.name);
+            data.push((table, rows));
+        }
+        self.target.replace_rows(&data).await?;
+
+        let counts = self.count_rows().await?;
+        let mismatched: Vec<_> = counts
+            .iter()
+            .filter(|count| count.source != count.target)
+            .map(|count| format!("{} ({} -> {})", count.table, count.source, count.target))
+            .collect();
+        if !mismatched.is_empty() {
+            return Err(AppError::InternalServerError(format!(
+                "Row counts differ after migration: {}",
+                mismatched.join(", ")
+            )));
+        }
+        Ok(counts)
+    }
+
+    /// 移行先に残っていてよいのはマイグレーションが入れた初期データだけ
+    async fn ensure_target_empty(&self) -> AppResult<()> {
+        let mut non_empty = Vec::new();
+        for table in TABLES.iter().filter(|table| !table.seeded) {
+            let count = self.target.count_rows(table).await?;
+            if count > 0 {
+                non_empty.push(format!("{} ({} rows)", table.name, count));
+            }
+        }
+        if non_empty.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Conflict(format!(
+                "Target database is not empty: {}",
+                non_empty.join(", ")
+            )))
+        }
+    }
+
+    async fn count_rows(&self) -> AppResult<Vec<TableCount>> {
+        let mut counts = Vec::with_capacity(TABLES.len());
+        for table in TABLES {
+            counts.push(TableCount {
+                table: table.name,
+                source: self.source.count_rows(table).await?,
+                target: self.target.count_rows(table).await?,
+            });
+        }
+        Ok(counts)
+    }
+}
@@ -0,0 +1,183 @@
+//! dashi 互換の物品 CSV（`GET /items/csv` が書き出す形式）の読み込み。
+//!
+//! 列は見出しの名前で探すので、並びが違っても、使わない列があってもよい。
+//! `ラベルID`（または `label_id`）列があれば、そのラベルで登録する（`mode=upsert` なら更新する）。
+
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{ItemCsvRow, ItemImportError};
+
+const NAME: &str = "物品名";
+const MODEL_NUMBER: &str = "型番";
+const QUANTITY: &str = "個数";
+const DETAILS: &str = "物品詳細";
+pub const PLACE: &str = "保管場所";
+const NOTES: &str = "備考";
+pub const LABEL_ID: &[&str] = &["ラベルID", "label_id"];
+
+/// 1行で登録できる個数の上限（一度に発行できるラベルの数と同じ）
+pub const MAX_QUANTITY: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum CsvEncoding {
+    /// UTF-8 として読めなければ Shift_JIS とみなす
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    #[serde(rename = "shift_jis", alias = "sjis", alias = "cp932")]
+    ShiftJis,
+}
+
+/// 読み込んだ行と、行ごとのエラー。エラーのある行は `rows` に入らない
+#[derive(Debug, Default)]
+pub struct ItemCsv {
+    /// 空行を除いた行数
+    pub total_rows: usize,
+    pub rows: Vec<ItemCsvRow>,
+    pub errors: Vec<ItemImportError>,
+}
+
+pub fn decode(bytes: &[u8], encoding: CsvEncoding) -> AppResult<String> {
+    // Excel の「CSV UTF-8」は BOM を付ける
+    let utf8 = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match encoding {
+        CsvEncoding::Utf8 => std::str::from_utf8(utf8)
+            .map(str::to_string)
+            .map_err(|e| AppError::BadRequest(format!("CSV is not valid UTF-8: {}", e))),
+        CsvEncoding::ShiftJis => shift_jis(bytes),
+        CsvEncoding::Auto => match std::str::from_utf8(utf8) {
+            Ok(text) => Ok(text.to_string()),
+            Err(_) => shift_jis(bytes),
+        },
+    }
+}
+
+fn shift_jis(bytes: &[u8]) -> AppResult<String> {
+    let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes);
+    if had_errors {
+        return Err(AppError::BadRequest(
+            "CSV is neither valid UTF-8 nor Shift_JIS".to_string(),
+        ));
+    }
+    Ok(text.into_owned())
+}
+
+pub fn parse(text: &str) -> AppResult<ItemCsv> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::BadRequest(format!("Failed to read CSV header: {}", e)))?
+        .clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.trim()))
+    };
+    let name_column = column(&[NAME])
+        .ok_or_else(|| AppError::BadRequest(format!("CSV has no {} column", NAME)))?;
+    let label_column = column(LABEL_ID);
+    let model_number_column = column(&[MODEL_NUMBER]);
+    let quantity_column = column(&[QUANTITY]);
+    let details_column = column(&[DETAILS]);
+    let place_column = column(&[PLACE]);
+    let notes_column = column(&[NOTES]);
+
+    let mut csv = ItemCsv::default();
+    for (i, record) in reader.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                csv.total_rows += 1;
+                csv.errors.push(ItemImportError {
+                    row: e.position().map_or(i + 2, |p| p.line() as usize),
+                    column: None,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        // Excel は末尾に空の行を残すことがある
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        csv.total_rows += 1;
+        let row = record.position().map_or(i + 2, |p| p.line() as usize);
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let mut errors = Vec::new();
+        let mut error = |column: &str, message: String| {
+            errors.push(ItemImportError {
+                row,
+                column: Some(column.to_string()),
+                message,
+            })
+        };
+
+        let name = field(Some(name_column)).unwrap_or_default();
+        if name.is_empty() {
+            error(NAME, "Name is required".to_string());
+        } else if name.chars().count() > 255 {
+            error(NAME, "Name must be at most 255 characters".to_string());
+        }
+        let model_number = field(model_number_column);
+        if model_number.as_ref().is_some_and(|m| m.chars().count() > 255) {
+            error(MODEL_NUMBER, "Model number must be at most 255 characters".to_string());
+        }
+        let label_id = field(label_column);
+        if label_id.as_ref().is_some_and(|l| l.chars().count() > 50) {
+            error(LABEL_ID[0], "Label ID must be at most 50 characters".to_string());
+        }
+        let quantity = match field(quantity_column) {
+            None => 1,
+            Some(value) => match value.parse::<u32>() {
+                Ok(quantity) if (1..=MAX_QUANTITY).contains(&quantity) => quantity,
+                _ => {
+                    error(
+                        QUANTITY,
+                        format!(
+                            "Quantity must be an integer between 1 and {} (got {:?})",
+                            MAX_QUANTITY, value
+                        ),
+                    );
+                    1
+                }
+            },
+        };
+        if quantity > 1 && label_id.is_some() {
+            error(
+                QUANTITY,
+                "Quantity must be 1 when a label ID is given".to_string(),
+            );
+        }
+        let remarks = match (field(details_column), field(notes_column)) {
+            (Some(details), Some(notes)) => Some(format!("{}\n{}", details, notes)),
+            (details, notes) => details.or(notes),
+        };
+
+        if errors.is_empty() {
+            csv.rows.push(ItemCsvRow {
+                row,
+                name,
+                label_id,
+                model_number,
+                remarks,
+                quantity,
+                place: field(place_column),
+            });
+        } else {
+            csv.errors.extend(errors);
+        }
+    }
+    Ok(csv)
+}
+
@@ -1,509 +1,386 @@
+use crate::config::LoanConfig;
 use crate::db::DatabasePool;
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateItemRequest, Item, ItemsListResponse, UpdateItemRequest};
+use crate::models::{
+    AuditAction, AuditEntity, AuditEvent, BulkMoveItemsRequest, BulkMoveItemsResponse,
+    CreateItemRequest, CurrentUser, Item, ItemByLabel, ItemHistoryEntry, ItemHistoryKind,
+    ItemHistoryResponse, ItemImportAction, ItemImportError, ItemImportMode, ItemImportReport,
+    ItemImportRowResult, ItemsListResponse, LabelAlias, LabelStatus, NewTransfer,
+    RelabelItemRequest, Transfer, TransferEntity, UpdateItemRequest,
+};
+use crate::repositories::{
+    item_repository, ItemFilter, ItemPlacement, ItemRepository, Page, TransferBatch,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use crate::services::item_csv::{self, ItemCsv};
+use crate::services::label_scheme::DEFAULT_SEQUENCE;
+use crate::services::label_service::LabelService;
+use crate::services::loan_service::LoanService;
+use crate::services::container_service::ContainerService;
+use crate::services::location_service::LocationService;
+use crate::services::transfer_service::TransferService;
 use chrono::Utc;
-use sqlx::Row;
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub struct ItemService {
-    db: DatabasePool,
+    repo: Arc<dyn ItemRepository>,
+    audit: AuditService,
+    labels: LabelService,
+    loan_service: LoanService,
+    locations: LocationService,
+    containers: ContainerService,
+    transfers: TransferService,
 }
 
 impl ItemService {
     pub fn new(db: DatabasePool) -> Self {
-        Self { db }
+        let audit = AuditService::new(db.clone());
+        let labels = LabelService::new(db.clone());
+        // 履歴の参照にのみ使うので、貸出期間の設定は既定値でよい
+        let loan_service = LoanService::new(db.clone(), LoanConfig::default());
+        let locations = LocationService::new(db.clone());
+        let containers = ContainerService::new(db.clone());
+        let transfers = TransferService::new(db.clone());
+        Self {
+            repo: item_repository(&db),
+            audit,
+            labels,
+            loan_service,
+            locations,
+            containers,
+            transfers,
+        }
     }
 
-    pub async fn create_item(&self, req: CreateItemRequest) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let connection_names = req
-                    .connection_names
-                    .map(|v| serde_json::to_string(&v).unwrap_or_default());
-                let cable_color_pattern = req
-                    .cable_color_pattern
-                    .map(|v| serde_json::to_string(&v).unwrap_or_default());
-                let storage_location = req.storage_location;
-                let is_depreciation_target = req.is_depreciation_target.unwrap_or(false);
-
-                let storage_type = req
-                    .storage_type
-                    .as_ref()
-                    .unwrap_or(&"location".to_string())
-                    .clone();
-
-                let new_id = Uuid::new_v4();
-                
-                sqlx::query(
-                    r#"
-                    INSERT INTO items (
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target, connection_names,
-                        cable_color_pattern, storage_location, container_id, storage_type, qr_code_type, image_url
-                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
-                    "#,
-                )
-                .bind(new_id)
-                .bind(&req.name)
-                .bind(&req.label_id)
-                .bind(&req.model_number)
-                .bind(&req.remarks)
-                .bind(req.purchase_year)
-                .bind(req.purchase_amount)
-                .bind(req.durability_years)
-                .bind(is_depreciation_target)
-                .bind(&connection_names)
-                .bind(&cable_color_pattern)
-                .bind(&storage_location)
-                .bind(&req.container_id)
-                .bind(&storage_type)
-                .bind(&req.qr_code_type)
-                .bind(&req.image_url)
-                .execute(pool)
-                .await?;
-
-                self.get_item(new_id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                let connection_names = req
-                    .connection_names
-                    .map(|v| serde_json::to_string(&v).unwrap_or_default());
-                let cable_color_pattern = req
-                    .cable_color_pattern
-                    .map(|v| serde_json::to_string(&v).unwrap_or_default());
-                let storage_location = req.storage_location;
-                let is_depreciation_target = req.is_depreciation_target.unwrap_or(false);
-
-                let storage_type = req
-                    .storage_type
-                    .as_ref()
-                    .unwrap_or(&"location".to_string())
-                    .clone();
-
-                let new_id = Uuid::new_v4();
-                let new_id_str = new_id.to_string();
-
-                sqlx::query(
-                    r#"
-                    INSERT INTO items (
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target, connection_names,
-                        cable_color_pattern, storage_location, container_id, storage_type, qr_code_type, image_url
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
-                    "#,
-                )
-                .bind(new_id_str)
-                .bind(req.name)
-                .bind(req.label_id)
-                .bind(req.model_number)
-                .bind(req.remarks)
-                .bind(req.purchase_year)
-                .bind(req.purchase_amount)
-                .bind(req.durability_years)
-                .bind(is_depreciation_target)
-                .bind(connection_names)
-                .bind(cable_color_pattern)
-                .bind(storage_location)
-                .bind(req.container_id)
-                .bind(storage_type)
-                .bind(req.qr_code_type)
-                .bind(req.image_url)
-                .execute(pool)
-                .await?;
-
-                self.get_item(new_id).await
-            }
+    pub async fn create_item(
+        &self,
+        mut req: CreateItemRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Item> {
+        self.ensure_label_unused(&req.label_id).await?;
+        self.ensure_label_not_retired(&req.label_id, None).await?;
+        self.labels.ensure_attachable(&req.label_id).await?;
+        if let Some(location) = self
+            .locations
+            .resolve(req.location_id, req.storage_location.as_deref(), actor)
+            .await?
+        {
+            req.storage_location = Some(location.path);
+            req.location_id = Some(location.id);
         }
+        let item = self.repo.insert(Uuid::new_v4(), &req, Utc::now()).await?;
+        self.labels.attach_to_item(&item.label_id, item.id).await?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &item.id.to_string(),
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
     }
 
     pub async fn get_item(&self, id: Uuid) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    WHERE id = $1
-                    "#,
-                )
-                .bind(id)
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Item with id {} not found", id)))?;
-
-                Ok(self.row_to_item_postgres(row))
-            }
-            DatabasePool::Sqlite(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    WHERE id = ?1
-                    "#,
-                )
-                .bind(id.to_string())
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Item with id {} not found", id)))?;
-
-                Ok(self.row_to_item(row))
-            }
-        }
+        self.repo
+            .find(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Item with id {} not found", id)))
     }
 
-    pub async fn get_item_by_label(&self, label_id: &str) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    WHERE label_id = $1
-                    "#,
-                )
-                .bind(label_id)
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Item with label_id {} not found", label_id)))?;
+    /// 物品の編集・タグ変更・移動・廃棄・貸出・返却を時系列順にまとめて返す
+    pub async fn get_item_history(&self, id: Uuid) -> AppResult<ItemHistoryResponse> {
+        let item = self.get_item(id).await?;
+
+        let item_events = self
+            .audit
+            .list_entity_events(AuditEntity::Item, &[id.to_string()])
+            .await?;
+        let loans = self.loan_service.list_loans_for_item(id).await?;
+        let loan_ids: Vec<String> = loans.iter().map(|loan| loan.id.to_string()).collect();
+        let loan_events = self
+            .audit
+            .list_entity_events(AuditEntity::Loan, &loan_ids)
+            .await?;
+
+        let mut entries = Vec::new();
+
+        // 監査ログ導入前に登録された物品は作成記録がないので、登録日時から補う
+        if !item_events
+            .iter()
+            .any(|event| event.action == AuditAction::Create.as_str())
+        {
+            entries.push(ItemHistoryEntry {
+                kind: ItemHistoryKind::Created,
+                occurred_at: item.created_at,
+                actor: None,
+                changes: None,
+                loan: None,
+            });
+        }
 
-                Ok(self.row_to_item_postgres(row))
-            }
-            DatabasePool::Sqlite(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    WHERE label_id = ?1
-                    "#,
-                )
-                .bind(label_id)
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Item with label_id {} not found", label_id)))?;
+        for event in item_events {
+            entries.extend(history_entries_from_event(event));
+        }
 
-                Ok(self.row_to_item(row))
+        for loan in loans {
+            let loan_id = loan.id.to_string();
+            let actor_of = |action: AuditAction| {
+                loan_events
+                    .iter()
+                    .find(|event| event.entity_id == loan_id && event.action == action.as_str())
+                    .map(|event| event.actor.clone())
+            };
+
+            entries.push(ItemHistoryEntry {
+                kind: ItemHistoryKind::Loaned,
+                occurred_at: loan.loan_date,
+                actor: actor_of(AuditAction::Loan),
+                changes: None,
+                loan: Some(loan.clone()),
+            });
+            if let Some(return_date) = loan.return_date {
+                entries.push(ItemHistoryEntry {
+                    kind: ItemHistoryKind::Returned,
+                    occurred_at: return_date,
+                    actor: actor_of(AuditAction::Return),
+                    changes: None,
+                    loan: Some(loan),
+                });
             }
         }
-    }
 
-    pub async fn list_items(
-        &self,
-        page: u32,
-        per_page: u32,
-        search: Option<String>,
-        is_on_loan: Option<bool>,
-        is_disposed: Option<bool>,
-        container_id: Option<String>,
-        storage_type: Option<String>,
-    ) -> AppResult<ItemsListResponse> {
-        let offset = ((page - 1) * per_page) as i64;
-        let limit = per_page as i64;
-
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // 動的WHEREクエリを構築（PostgreSQL版）
-                let mut where_conditions = Vec::new();
-                let mut param_index = 1;
-
-                // 検索条件
-                if search.is_some() {
-                    where_conditions.push(format!("(name ILIKE ${} OR label_id ILIKE ${} OR model_number ILIKE ${} OR remarks ILIKE ${})", param_index, param_index+1, param_index+2, param_index+3));
-                    param_index += 4;
-                }
-
-                // 貸出状態フィルター
-                if is_on_loan.is_some() {
-                    where_conditions.push(format!("is_on_loan = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // 廃棄状態フィルター
-                if is_disposed.is_some() {
-                    where_conditions.push(format!("is_disposed = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // コンテナIDフィルター
-                if container_id.is_some() {
-                    where_conditions.push(format!("container_id = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // 保管タイプフィルター
-                if storage_type.is_some() {
-                    where_conditions.push(format!("storage_type = ${}", param_index));
-                    param_index += 1;
-                }
-
-                let where_clause = if where_conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!("WHERE {}", where_conditions.join(" AND "))
-                };
+        // コンテナごと移動した分は物品の行が変わらないので、移動の記録から補う
+        let transfers = self
+            .transfers
+            .list_for_entity(TransferEntity::Item, &id.to_string())
+            .await?;
+        for transfer in transfers {
+            let Some(via_container_id) = transfer.via_container_id else {
+                continue;
+            };
+            entries.push(ItemHistoryEntry {
+                kind: ItemHistoryKind::Moved,
+                occurred_at: transfer.moved_at,
+                actor: Some(transfer.moved_by),
+                changes: Some(serde_json::json!({
+                    "location": { "before": transfer.from_location, "after": transfer.to_location },
+                    "via_container_id": via_container_id,
+                })),
+                loan: None,
+            });
+        }
 
-                let query_str = format!(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    {}
-                    ORDER BY created_at DESC
-                    LIMIT ${} OFFSET ${}
-                    "#,
-                    where_clause,
-                    param_index,
-                    param_index + 1
-                );
-
-                let count_query_str =
-                    format!("SELECT COUNT(*) as count FROM items {}", where_clause);
-
-                // パラメーターをバインド
-                let mut query = sqlx::query(&query_str);
-                let mut count_query = sqlx::query(&count_query_str);
-
-                // 検索条件
-                if let Some(search_term) = &search {
-                    let search_pattern = format!("%{}%", search_term);
-                    query = query
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone());
-                    count_query = count_query
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern);
-                }
+        // 同時刻のイベントは追加した順を保つ
+        entries.sort_by_key(|entry| entry.occurred_at);
 
-                // 貸出状態フィルター
-                if let Some(loan_status) = is_on_loan {
-                    query = query.bind(loan_status);
-                    count_query = count_query.bind(loan_status);
-                }
+        Ok(ItemHistoryResponse {
+            item_id: id,
+            entries,
+        })
+    }
 
-                // 廃棄状態フィルター
-                if let Some(disposed_status) = is_disposed {
-                    query = query.bind(disposed_status);
-                    count_query = count_query.bind(disposed_status);
-                }
+    /// ラベルIDから物品を引く。貼り替え前の古いラベルでも見つかる
+    pub async fn get_item_by_label(&self, label_id: &str) -> AppResult<Item> {
+        Ok(self.lookup_label(label_id).await?.item)
+    }
 
-                // コンテナIDフィルター
-                if let Some(container_id_val) = &container_id {
-                    query = query.bind(container_id_val);
-                    count_query = count_query.bind(container_id_val);
-                }
+    /// 現在のラベルで見つからなければ、貼り替え前のラベルとして探す
+    pub async fn lookup_label(&self, label_id: &str) -> AppResult<ItemByLabel> {
+        self.labels.verify_label(label_id).await?;
+        match self.get_item_by_current_label(label_id).await {
+            Ok(item) => Ok(ItemByLabel {
+                item,
+                superseded: false,
+            }),
+            Err(AppError::NotFound(message)) => match self.find_label_alias(label_id).await? {
+                Some(alias) => Ok(ItemByLabel {
+                    item: self.get_item(alias.item_id).await?,
+                    superseded: true,
+                }),
+                None => Err(AppError::NotFound(message)),
+            },
+            Err(e) => Err(e),
+        }
+    }
 
-                // 保管タイプフィルター
-                if let Some(storage_type_val) = &storage_type {
-                    query = query.bind(storage_type_val);
-                    count_query = count_query.bind(storage_type_val);
+    /// 一括処理で指定されたラベルを物品IDに解決する。見つからないラベルは含めず、
+    /// チェック文字の誤りはそのまま返す
+    pub async fn resolve_labels(&self, label_ids: &[String]) -> AppResult<HashMap<String, Uuid>> {
+        let mut resolved = HashMap::new();
+        for label_id in label_ids {
+            match self.lookup_label(label_id).await {
+                Ok(found) => {
+                    resolved.insert(label_id.clone(), found.item.id);
                 }
-
-                // LIMIT と OFFSET
-                query = query.bind(limit).bind(offset);
-
-                let rows = query.fetch_all(pool).await?;
-                let items: Vec<Item> = rows
-                    .into_iter()
-                    .map(|row| self.row_to_item_postgres(row))
-                    .collect();
-
-                let count_row = count_query.fetch_one(pool).await?;
-                let total: i64 = count_row.get("count");
-
-                Ok(ItemsListResponse {
-                    items,
-                    total,
-                    page,
-                    per_page,
-                })
+                Err(AppError::NotFound(_)) => {}
+                Err(e) => return Err(e),
             }
-            DatabasePool::Sqlite(pool) => {
-                // 動的WHEREクエリを構築（簡単な方法）
-                let mut where_conditions = Vec::new();
-
-                // 検索条件
-                if search.is_some() {
-                    where_conditions.push(
-                        "(name LIKE ? OR label_id LIKE ? OR model_number LIKE ? OR remarks LIKE ?)"
-                            .to_string(),
-                    );
-                }
+        }
+        Ok(resolved)
+    }
 
-                // 貸出状態フィルター
-                if is_on_loan.is_some() {
-                    where_conditions.push("is_on_loan = ?".to_string());
-                }
+    async fn get_item_by_current_label(&self, label_id: &str) -> AppResult<Item> {
+        self.repo.find_by_label(label_id).await?.ok_or_else(|| {
+            AppError::NotFound(format!("Item with label_id {} not found", label_id))
+        })
+    }
 
-                // 廃棄状態フィルター
-                if is_disposed.is_some() {
-                    where_conditions.push("is_disposed = ?".to_string());
-                }
+    /// 貼り替えで外されたラベルとして探す。見つかれば、そのラベルを使っていた物品と
+    /// 貼り替え後のラベルが分かる
+    async fn find_label_alias(&self, label_id: &str) -> AppResult<Option<LabelAlias>> {
+        self.repo.find_label_alias(label_id).await
+    }
 
-                // コンテナIDフィルター
-                if container_id.is_some() {
-                    where_conditions.push("container_id = ?".to_string());
-                }
+    /// いま他の物品に貼られているラベルは使わせない
+    async fn ensure_label_unused(&self, label_id: &str) -> AppResult<()> {
+        match self.get_item_by_current_label(label_id).await {
+            Ok(other) => Err(AppError::Conflict(format!(
+                "Label {} is already used by item {}",
+                label_id, other.id
+            ))),
+            Err(AppError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 
-                // 保管タイプフィルター
-                if storage_type.is_some() {
-                    where_conditions.push("storage_type = ?".to_string());
-                }
+    /// 他の物品の古いラベルは、新しい物品に使わせない（古いシールの読み取りと衝突するため）
+    async fn ensure_label_not_retired(
+        &self,
+        label_id: &str,
+        item_id: Option<Uuid>,
+    ) -> AppResult<()> {
+        match self.find_label_alias(label_id).await? {
+            Some(alias) if Some(alias.item_id) != item_id => Err(AppError::Conflict(format!(
+                "Label {} is a retired label of item {}",
+                label_id, alias.item_id
+            ))),
+            _ => Ok(()),
+        }
+    }
 
-                let where_clause = if where_conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!("WHERE {}", where_conditions.join(" AND "))
+    /// 剥がれたラベルを新しいラベルに貼り替える。古いラベルは別名として残す
+    pub async fn relabel_item(
+        &self,
+        id: Uuid,
+        req: RelabelItemRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Item> {
+        let before = self.get_item(id).await?;
+        let new_label = match req.label_id {
+            Some(label_id) => label_id.trim().to_string(),
+            None => {
+                let record_type = match before.qr_code_type.as_deref() {
+                    Some("none") => Some("nothing"),
+                    other => other,
                 };
-
-                // シンプルなアプローチで実装（フィルター条件ごとに分岐）
-                let (items, total) = if search.is_none()
-                    && is_on_loan.is_none()
-                    && is_disposed.is_none()
-                    && container_id.is_none()
-                    && storage_type.is_none()
-                {
-                    // フィルターなし
-                    let rows = sqlx::query(
-                        r#"
-                        SELECT
-                            id, name, label_id, model_number, remarks, purchase_year,
-                            purchase_amount, durability_years, is_depreciation_target,
-                            connection_names, cable_color_pattern, storage_location,
-                            container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                            created_at, updated_at
-                        FROM items
-                        ORDER BY created_at DESC
-                        LIMIT ?1 OFFSET ?2
-                        "#,
-                    )
-                    .bind(limit)
-                    .bind(offset)
-                    .fetch_all(pool)
+                let sequence = match req.sequence {
+                    Some(sequence) => sequence,
+                    None => self
+                        .labels
+                        .sequence_of(&before.label_id)
+                        .await?
+                        .map_or_else(|| DEFAULT_SEQUENCE.to_string(), |sequence| sequence.name),
+                };
+                let (_, label_ids) = self
+                    .labels
+                    .generate_batch(&sequence, 1, record_type, actor)
                     .await?;
+                label_ids.into_iter().next().ok_or_else(|| {
+                    AppError::InternalServerError("Failed to generate label ID".to_string())
+                })?
+            }
+        };
+        if new_label.is_empty() {
+            return Err(AppError::BadRequest("label_id must not be empty".to_string()));
+        }
+        if new_label == before.label_id {
+            return Err(AppError::BadRequest(format!(
+                "Item already has label {}",
+                new_label
+            )));
+        }
+        self.ensure_label_unused(&new_label).await?;
+        self.ensure_label_not_retired(&new_label, Some(id)).await?;
+        self.labels.ensure_attachable(&new_label).await?;
+
+        let alias = LabelAlias {
+            label_id: before.label_id.clone(),
+            item_id: id,
+            replaced_by: new_label.clone(),
+            reason: req.reason,
+            created_by: actor.username.clone(),
+            created_at: Utc::now(),
+        };
+        if !self.repo.relabel(&alias).await? {
+            return Err(AppError::Conflict(format!(
+                "Item {} was relabeled concurrently",
+                id
+            )));
+        }
 
-                    let items: Vec<Item> =
-                        rows.into_iter().map(|row| self.row_to_item(row)).collect();
-
-                    let count_row = sqlx::query("SELECT COUNT(*) as count FROM items")
-                        .fetch_one(pool)
-                        .await?;
-                    let total: i64 = count_row.get("count");
-
-                    (items, total)
-                } else {
-                    // フィルターあり - 動的クエリを使用
-                    let query_str = format!(
-                        r#"
-                        SELECT
-                            id, name, label_id, model_number, remarks, purchase_year,
-                            purchase_amount, durability_years, is_depreciation_target,
-                            connection_names, cable_color_pattern, storage_location,
-                            container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                            created_at, updated_at
-                        FROM items
-                        {}
-                        ORDER BY created_at DESC
-                        LIMIT ? OFFSET ?
-                        "#,
-                        where_clause
-                    );
-
-                    let count_query_str =
-                        format!("SELECT COUNT(*) as count FROM items {}", where_clause);
-
-                    // パラメーターをバインドするためのヘルパー関数
-                    let mut query = sqlx::query(&query_str);
-                    let mut count_query = sqlx::query(&count_query_str);
-
-                    // 検索条件
-                    if let Some(search_term) = &search {
-                        let search_pattern = format!("%{}%", search_term);
-                        query = query
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern.clone());
-                        count_query = count_query
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern.clone())
-                            .bind(search_pattern);
-                    }
-
-                    // 貸出状態フィルター
-                    if let Some(loan_status) = is_on_loan {
-                        let loan_value = if loan_status { 1i32 } else { 0i32 };
-                        query = query.bind(loan_value);
-                        count_query = count_query.bind(loan_value);
-                    }
-
-                    // 廃棄状態フィルター
-                    if let Some(disposed_status) = is_disposed {
-                        let disposed_value = if disposed_status { 1i32 } else { 0i32 };
-                        query = query.bind(disposed_value);
-                        count_query = count_query.bind(disposed_value);
-                    }
-
-                    // コンテナIDフィルター
-                    if let Some(container_id_val) = &container_id {
-                        query = query.bind(container_id_val);
-                        count_query = count_query.bind(container_id_val);
-                    }
-
-                    // 保管タイプフィルター
-                    if let Some(storage_type_val) = &storage_type {
-                        query = query.bind(storage_type_val);
-                        count_query = count_query.bind(storage_type_val);
-                    }
-
-                    // LIMIT/OFFSETをバインド
-                    query = query.bind(limit).bind(offset);
+        self.labels
+            .detach(&before.label_id, LabelStatus::Reassigned)
+            .await?;
+        self.labels.attach_to_item(&new_label, id).await?;
+
+        let item = self.get_item(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &id.to_string(),
+                AuditAction::Relabel,
+                actor,
+                snapshot(&before),
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
+    }
 
-                    let rows = query.fetch_all(pool).await?;
-                    let items: Vec<Item> =
-                        rows.into_iter().map(|row| self.row_to_item(row)).collect();
+    pub async fn list_items_by_labels(&self, label_ids: &[String]) -> AppResult<Vec<Item>> {
+        if label_ids.is_empty() {
+            return Ok(Vec::new());
+        }
 
-                    let count_row = count_query.fetch_one(pool).await?;
-                    let total: i64 = count_row.get("count");
+        self.repo.find_by_labels(label_ids).await
+    }
 
-                    (items, total)
-                };
+    /// コンテナに収納されている（廃棄済みを除く）物品をラベルID順に返す
+    pub async fn list_items_in_container(&self, container_id: &str) -> AppResult<Vec<Item>> {
+        self.repo.list_in_container(container_id).await
+    }
 
-                Ok(ItemsListResponse {
-                    items,
-                    total,
-                    page,
-                    per_page,
-                })
-            }
-        }
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_items(
+        &self,
+        page: u32,
+        per_page: u32,
+        search: Option<String>,
+        is_on_loan: Option<bool>,
+        is_disposed: Option<bool>,
+        container_id: Option<String>,
+        storage_type: Option<String>,
+    ) -> AppResult<ItemsListResponse> {
+        let filter = ItemFilter {
+            search,
+            is_on_loan,
+            is_disposed,
+            container_id,
+            storage_type,
+        };
+        let (items, total) = self.repo.list(&filter, Page::new(page, per_page)).await?;
+
+        Ok(ItemsListResponse {
+            items,
+            total,
+            page,
+            per_page,
+        })
     }
 
     pub async fn list_items_for_csv(
@@ -514,364 +391,71 @@ impl ItemService {
         container_id: Option<String>,
         storage_type: Option<String>,
     ) -> AppResult<Vec<Item>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // 動的WHEREクエリを構築（PostgreSQL版）
-                let mut where_conditions = Vec::new();
-                let mut param_index = 1;
-
-                // 検索条件
-                if search.is_some() {
-                    where_conditions.push(format!(
-                        "(name ILIKE ${} OR label_id ILIKE ${} OR model_number ILIKE ${} OR remarks ILIKE ${})",
-                        param_index,
-                        param_index + 1,
-                        param_index + 2,
-                        param_index + 3
-                    ));
-                    param_index += 4;
-                }
-
-                // 貸出状態フィルター
-                if is_on_loan.is_some() {
-                    where_conditions.push(format!("is_on_loan = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // 廃棄状態フィルター
-                if is_disposed.is_some() {
-                    where_conditions.push(format!("is_disposed = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // コンテナIDフィルター
-                if container_id.is_some() {
-                    where_conditions.push(format!("container_id = ${}", param_index));
-                    param_index += 1;
-                }
-
-                // 保管タイプフィルター
-                if storage_type.is_some() {
-                    where_conditions.push(format!("storage_type = ${}", param_index));
-                }
-
-                let where_clause = if where_conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!("WHERE {}", where_conditions.join(" AND "))
-                };
-
-                let query_str = format!(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    {}
-                    ORDER BY created_at DESC
-                    "#,
-                    where_clause
-                );
-
-                // パラメーターをバインド
-                let mut query = sqlx::query(&query_str);
-
-                // 検索条件
-                if let Some(search_term) = &search {
-                    let search_pattern = format!("%{}%", search_term);
-                    query = query
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern);
-                }
-
-                // 貸出状態フィルター
-                if let Some(loan_status) = is_on_loan {
-                    query = query.bind(loan_status);
-                }
-
-                // 廃棄状態フィルター
-                if let Some(disposed_status) = is_disposed {
-                    query = query.bind(disposed_status);
-                }
-
-                // コンテナIDフィルター
-                if let Some(container_id_val) = &container_id {
-                    query = query.bind(container_id_val);
-                }
-
-                // 保管タイプフィルター
-                if let Some(storage_type_val) = &storage_type {
-                    query = query.bind(storage_type_val);
-                }
-
-                let rows = query.fetch_all(pool).await?;
-                Ok(rows
-                    .into_iter()
-                    .map(|row| self.row_to_item_postgres(row))
-                    .collect())
-            }
-            DatabasePool::Sqlite(pool) => {
-                // 動的WHEREクエリを構築（SQLite版）
-                let mut where_conditions = Vec::new();
-
-                // 検索条件
-                if search.is_some() {
-                    where_conditions.push(
-                        "(name LIKE ? OR label_id LIKE ? OR model_number LIKE ? OR remarks LIKE ?)"
-                            .to_string(),
-                    );
-                }
-
-                // 貸出状態フィルター
-                if is_on_loan.is_some() {
-                    where_conditions.push("is_on_loan = ?".to_string());
-                }
-
-                // 廃棄状態フィルター
-                if is_disposed.is_some() {
-                    where_conditions.push("is_disposed = ?".to_string());
-                }
-
-                // コンテナIDフィルター
-                if container_id.is_some() {
-                    where_conditions.push("container_id = ?".to_string());
-                }
-
-                // 保管タイプフィルター
-                if storage_type.is_some() {
-                    where_conditions.push("storage_type = ?".to_string());
-                }
-
-                let where_clause = if where_conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!("WHERE {}", where_conditions.join(" AND "))
-                };
-
-                let query_str = format!(
-                    r#"
-                    SELECT
-                        id, name, label_id, model_number, remarks, purchase_year,
-                        purchase_amount, durability_years, is_depreciation_target,
-                        connection_names, cable_color_pattern, storage_location,
-                        container_id, storage_type, is_on_loan, qr_code_type, is_disposed, image_url,
-                        created_at, updated_at
-                    FROM items
-                    {}
-                    ORDER BY created_at DESC
-                    "#,
-                    where_clause
-                );
-
-                // パラメーターをバインド
-                let mut query = sqlx::query(&query_str);
-
-                // 検索条件
-                if let Some(search_term) = &search {
-                    let search_pattern = format!("%{}%", search_term);
-                    query = query
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern.clone())
-                        .bind(search_pattern);
-                }
-
-                // 貸出状態フィルター
-                if let Some(loan_status) = is_on_loan {
-                    let loan_value = if loan_status { 1i32 } else { 0i32 };
-                    query = query.bind(loan_value);
-                }
-
-                // 廃棄状態フィルター
-                if let Some(disposed_status) = is_disposed {
-                    let disposed_value = if disposed_status { 1i32 } else { 0i32 };
-                    query = query.bind(disposed_value);
-                }
-
-                // コンテナIDフィルター
-                if let Some(container_id_val) = &container_id {
-                    query = query.bind(container_id_val);
-                }
-
-                // 保管タイプフィルター
-                if let Some(storage_type_val) = &storage_type {
-                    query = query.bind(storage_type_val);
-                }
-
-                let rows = query.fetch_all(pool).await?;
-                Ok(rows.into_iter().map(|row| self.row_to_item(row)).collect())
-            }
-        }
+        let filter = ItemFilter {
+            search,
+            is_on_loan,
+            is_disposed,
+            container_id,
+            storage_type,
+        };
+        self.repo.list_all(&filter).await
     }
 
-    pub async fn update_item(&self, id: Uuid, req: UpdateItemRequest) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // まず物品が存在するかチェック
-                let _existing_item = self.get_item(id).await?;
-
-                // JSON配列フィールドをシリアライズ
-                let connection_names_json = req
-                    .connection_names
-                    .as_ref()
-                    .map(serde_json::to_string)
-                    .transpose()
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!(
-                            "Failed to serialize connection_names: {}",
-                            e
-                        ))
-                    })?;
-
-                let cable_color_pattern_json = req
-                    .cable_color_pattern
-                    .as_ref()
-                    .map(serde_json::to_string)
-                    .transpose()
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!(
-                            "Failed to serialize cable_color_pattern: {}",
-                            e
-                        ))
-                    })?;
-
-                let storage_location = req.storage_location;
-
-                let now = chrono::Utc::now();
-
-                sqlx::query(
-                    r#"
-                    UPDATE items SET
-                        name = COALESCE($2, name),
-                        label_id = COALESCE($3, label_id),
-                        model_number = COALESCE($4, model_number),
-                        remarks = COALESCE($5, remarks),
-                        purchase_year = COALESCE($6, purchase_year),
-                        purchase_amount = COALESCE($7, purchase_amount),
-                        durability_years = COALESCE($8, durability_years),
-                        is_depreciation_target = COALESCE($9, is_depreciation_target),
-                        connection_names = COALESCE($10, connection_names),
-                        cable_color_pattern = COALESCE($11, cable_color_pattern),
-                        storage_location = COALESCE($12, storage_location),
-                        container_id = COALESCE($13, container_id),
-                        storage_type = COALESCE($14, storage_type),
-                        qr_code_type = COALESCE($15, qr_code_type),
-                        image_url = COALESCE($16, image_url),
-                        updated_at = $17
-                    WHERE id = $1
-                    "#,
-                )
-                .bind(id)
-                .bind(&req.name)
-                .bind(&req.label_id)
-                .bind(&req.model_number)
-                .bind(&req.remarks)
-                .bind(req.purchase_year)
-                .bind(req.purchase_amount)
-                .bind(req.durability_years)
-                .bind(req.is_depreciation_target)
-                .bind(&connection_names_json)
-                .bind(&cable_color_pattern_json)
-                .bind(&storage_location)
-                .bind(&req.container_id)
-                .bind(&req.storage_type)
-                .bind(&req.qr_code_type)
-                .bind(&req.image_url)
-                .bind(now)
-                .execute(pool)
-                .await?;
-
-                // 更新後の物品を取得して返す
-                self.get_item(id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                // まず物品が存在するかチェック
-                let _existing_item = self.get_item(id).await?;
-
-                // JSON配列フィールドをシリアライズ
-                let connection_names_json = req
-                    .connection_names
-                    .as_ref()
-                    .map(serde_json::to_string)
-                    .transpose()
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!(
-                            "Failed to serialize connection_names: {}",
-                            e
-                        ))
-                    })?;
-
-                let cable_color_pattern_json = req
-                    .cable_color_pattern
-                    .as_ref()
-                    .map(serde_json::to_string)
-                    .transpose()
-                    .map_err(|e| {
-                        AppError::InternalServerError(format!(
-                            "Failed to serialize cable_color_pattern: {}",
-                            e
-                        ))
-                    })?;
-
-                let storage_location = req.storage_location;
-
-                let now = chrono::Utc::now();
-                let id_str = id.to_string();
-
-                sqlx::query(
-                    r#"
-                    UPDATE items SET
-                        name = COALESCE(?2, name),
-                        label_id = COALESCE(?3, label_id),
-                        model_number = COALESCE(?4, model_number),
-                        remarks = COALESCE(?5, remarks),
-                        purchase_year = COALESCE(?6, purchase_year),
-                        purchase_amount = COALESCE(?7, purchase_amount),
-                        durability_years = COALESCE(?8, durability_years),
-                        is_depreciation_target = COALESCE(?9, is_depreciation_target),
-                        connection_names = COALESCE(?10, connection_names),
-                        cable_color_pattern = COALESCE(?11, cable_color_pattern),
-                        storage_location = COALESCE(?12, storage_location),
-                        container_id = COALESCE(?13, container_id),
-                        storage_type = COALESCE(?14, storage_type),
-                        qr_code_type = COALESCE(?15, qr_code_type),
-                        image_url = COALESCE(?16, image_url),
-                        updated_at = ?17
-                    WHERE id = ?1
-                    "#,
-                )
-                .bind(id_str)
-                .bind(req.name)
-                .bind(req.label_id)
-                .bind(req.model_number)
-                .bind(req.remarks)
-                .bind(req.purchase_year)
-                .bind(req.purchase_amount)
-                .bind(req.durability_years)
-                .bind(req.is_depreciation_target)
-                .bind(connection_names_json)
-                .bind(cable_color_pattern_json)
-                .bind(storage_location)
-                .bind(req.container_id)
-                .bind(req.storage_type)
-                .bind(req.qr_code_type)
-                .bind(req.image_url)
-                .bind(now)
-                .execute(pool)
-                .await?;
-
-                // 更新後の物品を取得して返す
-                self.get_item(id).await
-            }
+    pub async fn update_item(
+        &self,
+        id: Uuid,
+        mut req: UpdateItemRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Item> {
+        let before = self.get_item(id).await?;
+        // ラベルの変更は貼り替えとして扱い、古いラベルを別名として残す
+        let new_label = req
+            .label_id
+            .take()
+            .filter(|label_id| label_id.trim() != before.label_id);
+        if let Some(location) = self
+            .locations
+            .resolve(req.location_id, req.storage_location.as_deref(), actor)
+            .await?
+        {
+            req.storage_location = Some(location.path);
+            req.location_id = Some(location.id);
         }
+        let before = match new_label {
+            Some(label_id) => {
+                let relabel = RelabelItemRequest {
+                    label_id: Some(label_id),
+                    sequence: None,
+                    reason: None,
+                };
+                self.relabel_item(id, relabel, actor).await?
+            }
+            None => before,
+        };
+        let item = self
+            .repo
+            .update(id, &req, Utc::now())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Item with id {} not found", id)))?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &item.id.to_string(),
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
     }
 
-    pub async fn update_item_image(&self, id: &str, image_url: &str) -> AppResult<Item> {
+    pub async fn update_item_image(
+        &self,
+        id: &str,
+        image_url: &str,
+        actor: &CurrentUser,
+    ) -> AppResult<Item> {
         let item_id = match Uuid::parse_str(id) {
             Ok(uuid) => uuid,
             Err(_) => {
@@ -882,620 +466,598 @@ impl ItemService {
             }
         };
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let now = Utc::now();
-                let result = sqlx::query(
-                    "UPDATE items SET image_url = $1, updated_at = $2 WHERE id = $3",
-                )
-                .bind(image_url)
-                .bind(now)
-                .bind(item_id)
-                .execute(pool)
-                .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
-
-                self.get_item(item_id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                let now = Utc::now();
-                let result = sqlx::query(
-                    "UPDATE items SET image_url = ?1, updated_at = ?2 WHERE id = ?3",
-                )
-                .bind(image_url)
-                .bind(now)
-                .bind(item_id.to_string())
-                .execute(pool)
-                .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
+        let before = self.get_item(item_id).await?;
+        let item = self
+            .repo
+            .set_image(item_id, image_url, Utc::now())
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Item with id {} not found", id)))?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &item.id.to_string(),
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
+    }
 
-                self.get_item(item_id).await
-            }
+    pub async fn delete_item(&self, id: Uuid, actor: &CurrentUser) -> AppResult<()> {
+        let before = self.get_item(id).await?;
+        // 貸出中でないかチェック
+        if before.is_on_loan.unwrap_or(false) {
+            return Err(AppError::BadRequest(
+                "Cannot delete item that is currently on loan".to_string(),
+            ));
         }
+        // アクティブな貸出がないかチェック
+        if self
+            .loan_service
+            .get_active_loan_for_item(&id.to_string())
+            .await?
+            .is_some()
+        {
+            return Err(AppError::BadRequest(
+                "Cannot delete item with active loans".to_string(),
+            ));
+        }
+        if self.repo.delete(&[id]).await? == 0 {
+            return Err(AppError::NotFound(format!("Item with id {} not found", id)));
+        }
+        // 物品がなくなったラベルは別の物品に貼り直せる
+        self.labels
+            .detach(&before.label_id, LabelStatus::Printed)
+            .await?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &id.to_string(),
+                AuditAction::Delete,
+                actor,
+                snapshot(&before),
+                None,
+            )
+            .await?;
+        Ok(())
     }
 
-    pub async fn delete_item(&self, id: Uuid) -> AppResult<()> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // まず物品が存在し、貸出中でないかチェック
-                let item = self.get_item(id).await?;
+    pub async fn dispose_item(&self, id: Uuid, actor: &CurrentUser) -> AppResult<Item> {
+        let before = self.get_item(id).await?;
+        if self.repo.set_disposed(&[id], true, Utc::now()).await? == 0 {
+            return Err(AppError::NotFound(format!("Item with id {} not found", id)));
+        }
+        let item = self.get_item(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &item.id.to_string(),
+                AuditAction::Dispose,
+                actor,
+                snapshot(&before),
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
+    }
 
-                if item.is_on_loan.unwrap_or(false) {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete item that is currently on loan".to_string(),
-                    ));
-                }
+    pub async fn undispose_item(&self, id: Uuid, actor: &CurrentUser) -> AppResult<Item> {
+        let before = self.get_item(id).await?;
+        if self.repo.set_disposed(&[id], false, Utc::now()).await? == 0 {
+            return Err(AppError::NotFound(format!("Item with id {} not found", id)));
+        }
+        let item = self.get_item(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Item,
+                &item.id.to_string(),
+                AuditAction::Undispose,
+                actor,
+                snapshot(&before),
+                snapshot(&item),
+            )
+            .await?;
+        Ok(item)
+    }
 
-                // アクティブな貸出がないかチェック
-                let active_loans = sqlx::query(
-                    "SELECT COUNT(*) as count FROM loans WHERE item_id = $1 AND return_date IS NULL"
-                )
-                .bind(id)
-                .fetch_one(pool)
-                .await?;
+    pub async fn get_connection_names_suggestions(&self) -> AppResult<Vec<String>> {
+        self.repo.connection_names().await
+    }
 
-                let count: i64 = active_loans.get("count");
-                if count > 0 {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete item with active loans".to_string(),
-                    ));
-                }
+    /// 場所マスタのパス一覧（並び順もパス順）
+    pub async fn get_storage_locations_suggestions(&self) -> AppResult<Vec<String>> {
+        let locations = self.locations.list_locations().await?;
+        Ok(locations.into_iter().map(|location| location.path).collect())
+    }
 
-                let result = sqlx::query("DELETE FROM items WHERE id = $1")
-                    .bind(id)
-                    .execute(pool)
-                    .await?;
+    pub async fn bulk_delete_items(&self, ids: &[String], actor: &CurrentUser) -> AppResult<()> {
+        let item_ids: Vec<Uuid> = ids
+            .iter()
+            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
 
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
-                Ok(())
-            }
-            DatabasePool::Sqlite(pool) => {
-                // まず物品が存在し、貸出中でないかチェック
-                let item = self.get_item(id).await?;
+        // Check if any items are on loan
+        let on_loan_check = self.check_items_on_loan(&item_ids).await?;
+        if on_loan_check {
+            return Err(AppError::BadRequest(
+                "One or more items are currently on loan and cannot be deleted.".to_string(),
+            ));
+        }
 
-                if item.is_on_loan.unwrap_or(false) {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete item that is currently on loan".to_string(),
-                    ));
-                }
+        let deleted = self.find_existing_items(&item_ids).await?;
+        self.repo.delete(&item_ids).await?;
 
-                // アクティブな貸出がないかチェック
-                let id_str = id.to_string();
-                let active_loans = sqlx::query(
-                    "SELECT COUNT(*) as count FROM loans WHERE item_id = ?1 AND return_date IS NULL"
+        for item in &deleted {
+            self.labels
+                .detach(&item.label_id, LabelStatus::Printed)
+                .await?;
+            self.audit
+                .record(
+                    AuditEntity::Item,
+                    &item.id.to_string(),
+                    AuditAction::Delete,
+                    actor,
+                    snapshot(item),
+                    None,
                 )
-                .bind(id_str)
-                .fetch_one(pool)
                 .await?;
+        }
 
-                if active_loans.get::<i64, _>("count") > 0 {
-                    return Err(AppError::BadRequest(
-                        "Cannot delete item with active loans".to_string(),
-                    ));
-                }
+        Ok(())
+    }
 
-                let result = sqlx::query("DELETE FROM items WHERE id = ?1")
-                    .bind(id.to_string())
-                    .execute(pool)
-                    .await?;
+    pub async fn bulk_update_disposed_status(
+        &self,
+        ids: &[String],
+        is_disposed: bool,
+        actor: &CurrentUser,
+    ) -> AppResult<()> {
+        let item_ids: Vec<Uuid> = ids
+            .iter()
+            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
 
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
-                Ok(())
-            }
-        }
-    }
+        let before = self.find_existing_items(&item_ids).await?;
+        self.repo
+            .set_disposed(&item_ids, is_disposed, Utc::now())
+            .await?;
 
-    pub async fn dispose_item(&self, id: Uuid) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let now = Utc::now();
-                let result = sqlx::query(
-                    "UPDATE items SET is_disposed = true, updated_at = $2 WHERE id = $1",
+        let action = if is_disposed {
+            AuditAction::Dispose
+        } else {
+            AuditAction::Undispose
+        };
+        for old in &before {
+            let item = self.get_item(old.id).await?;
+            self.audit
+                .record(
+                    AuditEntity::Item,
+                    &item.id.to_string(),
+                    action,
+                    actor,
+                    snapshot(old),
+                    snapshot(&item),
                 )
-                .bind(id)
-                .bind(now)
-                .execute(pool)
                 .await?;
+        }
 
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
+        Ok(())
+    }
 
-                self.get_item(id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                let now = Utc::now();
-                let result =
-                    sqlx::query("UPDATE items SET is_disposed = 1, updated_at = ?2 WHERE id = ?1")
-                        .bind(id.to_string())
-                        .bind(now)
-                        .execute(pool)
-                        .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
+    /// 複数の物品をまとめてコンテナまたは場所に移動する。1件でも失敗すれば何も移動しない
+    pub async fn bulk_move_items(
+        &self,
+        req: BulkMoveItemsRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<BulkMoveItemsResponse> {
+        let item_ids: Vec<Uuid> = req
+            .ids
+            .iter()
+            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if item_ids.is_empty() {
+            return Err(AppError::BadRequest("ids must not be empty".to_string()));
+        }
+        let has_location = req.location_id.is_some() || req.location.is_some();
+        if req.container_id.is_some() == has_location {
+            return Err(AppError::BadRequest(
+                "Specify either container_id or location (location_id) as the destination".to_string(),
+            ));
+        }
 
-                self.get_item(id).await
+        // 移動先: (storage_type, container_id, storage_location, location_id, 実際の場所)
+        let (storage_type, container_id, storage_location, location_id, to_location) =
+            match &req.container_id {
+                Some(container_id) => {
+                    let container = self.containers.get_container(container_id).await?;
+                    if container.is_disposed {
+                        return Err(AppError::BadRequest(format!(
+                            "Container {} is disposed",
+                            container_id
+                        )));
+                    }
+                    ("container", Some(container.id), None, None, container.location)
+                }
+                None => {
+                    let location = self
+                        .locations
+                        .resolve(req.location_id, req.location.as_deref(), actor)
+                        .await?
+                        .ok_or_else(|| {
+                            AppError::BadRequest("location must not be blank".to_string())
+                        })?;
+                    let path = location.path;
+                    ("location", None, Some(path.clone()), Some(location.id), path)
+                }
+            };
+
+        let hierarchy = self.containers.hierarchy().await?;
+        let mut before = Vec::with_capacity(item_ids.len());
+        let mut transfers = Vec::with_capacity(item_ids.len());
+        for id in &item_ids {
+            if before.iter().any(|item: &Item| item.id == *id) {
+                continue;
             }
+            let item = self.get_item(*id).await?;
+            transfers.push(NewTransfer {
+                entity_type: TransferEntity::Item,
+                entity_id: item.id.to_string(),
+                from_location: hierarchy.item_location(&item),
+                from_container_id: item.container_id.clone().filter(|_| item.storage_type == "container"),
+                to_location: Some(to_location.clone()),
+                to_container_id: container_id.clone(),
+                via_container_id: None,
+            });
+            before.push(item);
         }
-    }
 
-    pub async fn undispose_item(&self, id: Uuid) -> AppResult<Item> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let now = Utc::now();
-                let result = sqlx::query(
-                    "UPDATE items SET is_disposed = false, updated_at = $2 WHERE id = $1",
+        let batch = TransferBatch::new(req.note.as_deref(), actor);
+        let placement = ItemPlacement {
+            storage_type,
+            container_id,
+            storage_location,
+            location_id,
+        };
+        let ids: Vec<Uuid> = before.iter().map(|item| item.id).collect();
+        self.repo
+            .move_items(&ids, &placement, &batch, &transfers)
+            .await?;
+
+        let mut items = Vec::with_capacity(before.len());
+        for old in &before {
+            let item = self.get_item(old.id).await?;
+            self.audit
+                .record(
+                    AuditEntity::Item,
+                    &item.id.to_string(),
+                    AuditAction::Update,
+                    actor,
+                    snapshot(old),
+                    snapshot(&item),
                 )
-                .bind(id)
-                .bind(now)
-                .execute(pool)
                 .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
-
-                self.get_item(id).await
-            }
-            DatabasePool::Sqlite(pool) => {
-                let now = Utc::now();
-                let result =
-                    sqlx::query("UPDATE items SET is_disposed = 0, updated_at = ?2 WHERE id = ?1")
-                        .bind(id.to_string())
-                        .bind(now)
-                        .execute(pool)
-                        .await?;
-
-                if result.rows_affected() == 0 {
-                    return Err(AppError::NotFound(format!("Item with id {} not found", id)));
-                }
-
-                self.get_item(id).await
-            }
+            items.push(item);
         }
+        Ok(BulkMoveItemsResponse {
+            items,
+            batch_id: batch.id,
+            transfers: self.transfers.list_batch(batch.id).await?,
+        })
     }
 
-    pub async fn get_connection_names_suggestions(&self) -> AppResult<Vec<String>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let rows = sqlx::query("SELECT DISTINCT connection_names FROM items WHERE connection_names IS NOT NULL AND connection_names != ''")
-                    .fetch_all(pool)
-                    .await?;
+    pub async fn list_item_transfers(&self, id: Uuid) -> AppResult<Vec<Transfer>> {
+        self.get_item(id).await?;
+        self.transfers
+            .list_for_entity(TransferEntity::Item, &id.to_string())
+            .await
+    }
 
-                let mut suggestions = Vec::new();
-                for row in rows {
-                    if let Some(connection_names_str) =
-                        row.get::<Option<String>, _>("connection_names")
-                    {
-                        if let Ok(names) =
-                            serde_json::from_str::<Vec<String>>(&connection_names_str)
-                        {
-                            suggestions.extend(names);
-                        }
-                    }
+    /// CSV の行を検証し、エラーがなく dry run でもなければ登録・更新する。
+    /// 書き込みは全行の検証が通ってから始める。途中の行で失敗した場合はそこで止め、
+    /// それまでに書き込んだ物品とその行のエラーを返す
+    pub async fn import_items(
+        &self,
+        csv: ItemCsv,
+        mode: ItemImportMode,
+        dry_run: bool,
+        actor: &CurrentUser,
+    ) -> AppResult<ItemImportReport> {
+        let mut errors = csv.errors;
+        let locations = self.locations.list_locations().await?;
+        let mut first_rows: HashMap<String, usize> = HashMap::new();
+        let mut results = Vec::with_capacity(csv.rows.len());
+        let mut targets = Vec::with_capacity(csv.rows.len());
+
+        for row in csv.rows {
+            let mut messages = Vec::new();
+            let mut target = None;
+            if let Some(label_id) = &row.label_id {
+                if let Some(first) = first_rows.get(label_id) {
+                    messages.push((
+                        item_csv::LABEL_ID[0],
+                        format!("Label {} is also used on row {}", label_id, first),
+                    ));
+                } else {
+                    first_rows.insert(label_id.clone(), row.row);
                 }
-
-                // 重複を除去してソート
-                suggestions.sort();
-                suggestions.dedup();
-                Ok(suggestions)
-            }
-            DatabasePool::Sqlite(pool) => {
-                let rows = sqlx::query("SELECT DISTINCT connection_names FROM items WHERE connection_names IS NOT NULL AND connection_names != ''")
-                    .fetch_all(pool)
-                    .await?;
-
-                let mut suggestions = Vec::new();
-                for row in rows {
-                    if let Some(json_str) = row.get::<Option<String>, _>("connection_names") {
-                        if let Ok(names) = serde_json::from_str::<Vec<String>>(&json_str) {
-                            suggestions.extend(names);
+                match self.get_item_by_current_label(label_id).await {
+                    Ok(item) if mode == ItemImportMode::Upsert => target = Some(item),
+                    Ok(item) => messages.push((
+                        item_csv::LABEL_ID[0],
+                        format!("Label {} is already used by item {}", label_id, item.id),
+                    )),
+                    Err(AppError::NotFound(_)) => {
+                        let check = match self.ensure_label_not_retired(label_id, None).await {
+                            Ok(()) => self.labels.ensure_attachable(label_id).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = check {
+                            messages.push((item_csv::LABEL_ID[0], import_error_message(e)?));
                         }
                     }
+                    Err(e) => return Err(e),
                 }
-
-                // 重複を除去してソート
-                suggestions.sort();
-                suggestions.dedup();
-                Ok(suggestions)
             }
-        }
-    }
 
-    pub async fn get_storage_locations_suggestions(&self) -> AppResult<Vec<String>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let rows = sqlx::query("SELECT DISTINCT storage_location FROM items WHERE storage_location IS NOT NULL AND storage_location != ''")
-                    .fetch_all(pool)
-                    .await?;
-
-                let mut suggestions = Vec::new();
-                for row in rows {
-                    if let Some(location_str) = row.get::<Option<String>, _>("storage_location") {
-                        suggestions.push(location_str);
+            let mut container_id = None;
+            let mut storage_location = None;
+            let mut new_location = false;
+            if let Some(place) = row.place.as_deref() {
+                match self.containers.get_container(place).await {
+                    Ok(container) if container.is_disposed => {
+                        messages.push((item_csv::PLACE, format!("Container {} is disposed", place)))
                     }
-                }
-
-                // 重複を除去してソート
-                suggestions.sort();
-                suggestions.dedup();
-                Ok(suggestions)
-            }
-            DatabasePool::Sqlite(pool) => {
-                let rows = sqlx::query("SELECT DISTINCT storage_location FROM items WHERE storage_location IS NOT NULL AND storage_location != ''")
-                    .fetch_all(pool)
-                    .await?;
-
-                let mut suggestions = Vec::new();
-                for row in rows {
-                    if let Some(location_str) = row.get::<Option<String>, _>("storage_location") {
-                        suggestions.push(location_str);
+                    Ok(container) => container_id = Some(container.id),
+                    Err(AppError::NotFound(_)) => {
+                        new_location = !locations.iter().any(|location| location.path == place);
+                        storage_location = Some(place.to_string());
                     }
+                    Err(e) => return Err(e),
                 }
-
-                // 重複を除去してソート
-                suggestions.sort();
-                suggestions.dedup();
-                Ok(suggestions)
             }
-        }
-    }
-
-    pub async fn generate_label_ids(&self, quantity: u32) -> AppResult<Vec<String>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // Get current counter value and increment it atomically
-                let mut tx = pool.begin().await?;
-
-                // Get current counter value
-                let counter_row =
-                    sqlx::query("SELECT current_value FROM label_counter WHERE id = 1")
-                        .fetch_one(&mut *tx)
-                        .await?;
-                let current_value: i64 = counter_row.get("current_value");
-
-                // Check we have enough available IDs (max ZZZZ = 1,679,615)
-                if current_value + quantity as i64 > 1679615 {
-                    return Err(AppError::BadRequest(
-                        "Not enough label IDs available".to_string(),
-                    ));
-                }
 
-                // Generate new label IDs in base-36 format
-                let mut label_ids = Vec::new();
-                for i in 1..=quantity {
-                    let new_number = current_value + i as i64;
-                    // Convert to base-36 and pad to 4 characters
-                    let label_id = format!(
-                        "{:0>4}",
-                        radix_fmt::radix_36(new_number as u32)
-                            .to_string()
-                            .to_uppercase()
-                    );
-                    label_ids.push(label_id);
-                }
-
-                // Update counter
-                let new_counter_value = current_value + quantity as i64;
-                sqlx::query("UPDATE label_counter SET current_value = $1 WHERE id = 1")
-                    .bind(new_counter_value)
-                    .execute(&mut *tx)
-                    .await?;
-
-                tx.commit().await?;
-
-                Ok(label_ids)
-            }
-            DatabasePool::Sqlite(pool) => {
-                // Get current counter value and increment it atomically
-                let mut tx = pool.begin().await?;
-
-                // Get current counter value
-                let counter_row =
-                    sqlx::query("SELECT current_value FROM label_counter WHERE id = 1")
-                        .fetch_one(&mut *tx)
-                        .await?;
-                let current_value: i64 = counter_row.get("current_value");
-
-                // Check we have enough available IDs (max ZZZZ = 1,679,615)
-                if current_value + quantity as i64 > 1679615 {
-                    return Err(AppError::BadRequest(
-                        "Not enough label IDs available".to_string(),
-                    ));
-                }
-
-                // Generate new label IDs in base-36 format
-                let mut label_ids = Vec::new();
-                for i in 1..=quantity {
-                    let new_number = current_value + i as i64;
-                    // Convert to base-36 and pad to 4 characters
-                    let label_id = format!(
-                        "{:0>4}",
-                        radix_fmt::radix_36(new_number as u32)
-                            .to_string()
-                            .to_uppercase()
-                    );
-                    label_ids.push(label_id);
-                }
-
-                // Update counter
-                let new_counter_value = current_value + quantity as i64;
-                sqlx::query("UPDATE label_counter SET current_value = ? WHERE id = 1")
-                    .bind(new_counter_value)
-                    .execute(&mut *tx)
-                    .await?;
-
-                tx.commit().await?;
-
-                Ok(label_ids)
-            }
+            errors.extend(messages.into_iter().map(|(column, message)| ItemImportError {
+                row: row.row,
+                column: Some(column.to_string()),
+                message,
+            }));
+            results.push(ItemImportRowResult {
+                row: row.row,
+                action: if target.is_some() {
+                    ItemImportAction::Update
+                } else {
+                    ItemImportAction::Create
+                },
+                name: row.name,
+                label_id: row.label_id,
+                quantity: row.quantity,
+                container_id,
+                storage_location,
+                new_location,
+                items: Vec::new(),
+            });
+            targets.push((target, row.model_number, row.remarks));
         }
-    }
-
-    pub async fn get_all_labels(&self) -> AppResult<Vec<crate::handlers::labels::LabelInfo>> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // Get all possible 4-digit base-36 labels from 0000 to ZZZZ
-                let mut all_labels = Vec::new();
-
-                // Get used labels from database
-                let used_labels_rows =
-                    sqlx::query("SELECT label_id, name FROM items WHERE LENGTH(label_id) = 4")
-                        .fetch_all(pool)
-                        .await?;
-
-                let mut used_labels_map = std::collections::HashMap::new();
-                for row in used_labels_rows {
-                    let label_id: String = row.get("label_id");
-                    let name: String = row.get("name");
-                    used_labels_map.insert(label_id, name);
-                }
-
-                // Generate all possible labels and check if they're used (0 to ZZZZ in base-36)
-                for i in 0..=1679615u32 {
-                    // 36^4 - 1
-                    let label_id =
-                        format!("{:0>4}", radix_fmt::radix_36(i).to_string().to_uppercase());
-                    let label_info = crate::handlers::labels::LabelInfo {
-                        id: label_id.clone(),
-                        used: used_labels_map.contains_key(&label_id),
-                        item_name: used_labels_map.get(&label_id).cloned(),
-                    };
-                    all_labels.push(label_info);
-                }
-
-                Ok(all_labels)
-            }
-            DatabasePool::Sqlite(pool) => {
-                // Get all possible 4-digit base-36 labels from 0000 to ZZZZ
-                let mut all_labels = Vec::new();
-
-                // Get used labels from database
-                let used_labels_rows =
-                    sqlx::query("SELECT label_id, name FROM items WHERE LENGTH(label_id) = 4")
-                        .fetch_all(pool)
-                        .await?;
-
-                let mut used_labels_map = std::collections::HashMap::new();
-                for row in used_labels_rows {
-                    let label_id: String = row.get("label_id");
-                    let name: String = row.get("name");
-                    used_labels_map.insert(label_id, name);
-                }
-
-                // Generate all possible labels and check if they're used (0 to ZZZZ in base-36)
-                for i in 0..=1679615u32 {
-                    // 36^4 - 1
-                    let label_id =
-                        format!("{:0>4}", radix_fmt::radix_36(i).to_string().to_uppercase());
-                    let label_info = crate::handlers::labels::LabelInfo {
-                        id: label_id.clone(),
-                        used: used_labels_map.contains_key(&label_id),
-                        item_name: used_labels_map.get(&label_id).cloned(),
-                    };
-                    all_labels.push(label_info);
+        errors.sort_by_key(|error| error.row);
+
+        let mut applied_rows = 0;
+        if errors.is_empty() && !dry_run {
+            for (result, (target, model_number, remarks)) in results.iter_mut().zip(targets) {
+                let written = match target {
+                    None => self.import_new_items(result, model_number, remarks, actor).await,
+                    Some(item) => self
+                        .import_existing_item(item, result, model_number, remarks, actor)
+                        .await
+                        .map(|item| result.items.push(item)),
+                };
+                if let Err(e) = written {
+                    errors.push(ItemImportError {
+                        row: result.row,
+                        column: None,
+                        message: import_error_message(e).unwrap_or_else(|e| e.to_string()),
+                    });
+                    break;
                 }
-
-                Ok(all_labels)
+                applied_rows += 1;
             }
         }
-    }
-
-    pub async fn bulk_delete_items(&self, ids: &[String]) -> AppResult<()> {
-        let item_ids: Vec<Uuid> = ids
-            .iter()
-            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string())))
-            .collect::<Result<Vec<_>, _>>()?;
 
-        // Check if any items are on loan
-        let on_loan_check = self.check_items_on_loan(&item_ids).await?;
-        if on_loan_check {
-            return Err(AppError::BadRequest(
-                "One or more items are currently on loan and cannot be deleted.".to_string(),
-            ));
-        }
+        Ok(ItemImportReport {
+            dry_run,
+            mode,
+            applied: errors.is_empty() && !dry_run,
+            applied_rows,
+            total_rows: csv.total_rows,
+            created: results
+                .iter()
+                .filter(|result| result.action == ItemImportAction::Create)
+                .map(|result| result.quantity as usize)
+                .sum(),
+            updated: results
+                .iter()
+                .filter(|result| result.action == ItemImportAction::Update)
+                .count(),
+            rows: results,
+            errors,
+        })
+    }
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let query = "DELETE FROM items WHERE id = ANY($1)";
-                sqlx::query(query)
-                    .bind(&item_ids)
-                    .execute(pool)
-                    .await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-            }
-            DatabasePool::Sqlite(pool) => {
-                let mut tx = pool.begin().await.map_err(|e| {
-                    AppError::InternalServerError(e.to_string())
-                })?;
-                for id in &item_ids {
-                    sqlx::query("DELETE FROM items WHERE id = ?")
-                        .bind(id.to_string())
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-                }
-                tx.commit()
-                    .await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    /// ラベルのない行は個数分のラベルを発行して登録する。登録した物品は1件ずつ `row.items` に加える
+    async fn import_new_items(
+        &self,
+        row: &mut ItemImportRowResult,
+        model_number: Option<String>,
+        remarks: Option<String>,
+        actor: &CurrentUser,
+    ) -> AppResult<()> {
+        let label_ids = match &row.label_id {
+            Some(label_id) => vec![label_id.clone()],
+            None => {
+                self.labels
+                    .generate_batch(DEFAULT_SEQUENCE, row.quantity, Some("qr"), actor)
+                    .await?
+                    .1
             }
+        };
+        let storage_type = if row.container_id.is_some() {
+            "container"
+        } else {
+            "location"
+        };
+        for label_id in label_ids {
+            let req = CreateItemRequest {
+                name: row.name.clone(),
+                label_id,
+                model_number: model_number.clone(),
+                remarks: remarks.clone(),
+                purchase_year: None,
+                purchase_amount: None,
+                durability_years: None,
+                is_depreciation_target: None,
+                connection_names: None,
+                cable_color_pattern: None,
+                storage_location: row.storage_location.clone(),
+                location_id: None,
+                container_id: row.container_id.clone(),
+                storage_type: Some(storage_type.to_string()),
+                qr_code_type: row.label_id.is_none().then(|| "qr".to_string()),
+                image_url: None,
+            };
+            let item = self.create_item(req, actor).await?;
+            row.items.push(item);
         }
-
         Ok(())
     }
 
-    pub async fn bulk_update_disposed_status(
+    /// 空欄の項目は変えない。保管場所が変わる場合は移動として記録する
+    async fn import_existing_item(
         &self,
-        ids: &[String],
-        is_disposed: bool,
-    ) -> AppResult<()> {
-        let item_ids: Vec<Uuid> = ids
-            .iter()
-            .map(|id| Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string())))
-            .collect::<Result<Vec<_>, _>>()?;
+        item: Item,
+        row: &ItemImportRowResult,
+        model_number: Option<String>,
+        remarks: Option<String>,
+        actor: &CurrentUser,
+    ) -> AppResult<Item> {
+        let req = UpdateItemRequest {
+            name: Some(row.name.clone()),
+            model_number,
+            remarks,
+            ..Default::default()
+        };
+        let updated = self.update_item(item.id, req, actor).await?;
 
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let query = "UPDATE items SET is_disposed = $1, updated_at = NOW() WHERE id = ANY($2)";
-                sqlx::query(query)
-                    .bind(is_disposed)
-                    .bind(&item_ids)
-                    .execute(pool)
-                    .await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let in_container = updated.storage_type == "container";
+        let moved = match (&row.container_id, &row.storage_location) {
+            (Some(container_id), _) => {
+                !in_container || updated.container_id.as_ref() != Some(container_id)
             }
-            DatabasePool::Sqlite(pool) => {
-                let mut tx = pool.begin().await.map_err(|e| {
-                    AppError::InternalServerError(e.to_string())
-                })?;
-                for id in &item_ids {
-                    sqlx::query("UPDATE items SET is_disposed = ?, updated_at = ? WHERE id = ?")
-                        .bind(is_disposed)
-                        .bind(Utc::now())
-                        .bind(id.to_string())
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-                }
-                tx.commit()
-                    .await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+            (None, Some(location)) => {
+                in_container || updated.storage_location.as_ref() != Some(location)
             }
+            (None, None) => false,
+        };
+        if !moved {
+            return Ok(updated);
         }
-
-        Ok(())
+        let req = BulkMoveItemsRequest {
+            ids: vec![item.id.to_string()],
+            container_id: row.container_id.clone(),
+            location_id: None,
+            location: row.storage_location.clone(),
+            note: Some("CSV import".to_string()),
+        };
+        self.bulk_move_items(req, actor).await?;
+        self.get_item(item.id).await
     }
 
-    async fn check_items_on_loan(&self, ids: &[Uuid]) -> AppResult<bool> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let query = "SELECT EXISTS(SELECT 1 FROM items WHERE id = ANY($1) AND is_on_loan = TRUE) as has_loan";
-                let result = sqlx::query(query)
-                    .bind(ids)
-                    .fetch_one(pool)
-                    .await
-                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-                Ok(result.get("has_loan"))
-            }
-            DatabasePool::Sqlite(pool) => {
-                // SQLite doesn't support array binding, so we check one by one.
-                for id in ids {
-                    let result = sqlx::query("SELECT is_on_loan FROM items WHERE id = ?")
-                        .bind(id.to_string())
-                        .fetch_optional(pool)
-                        .await
-                        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-
-                    if let Some(row) = result {
-                        if row.get::<bool, _>("is_on_loan") {
-                            return Ok(true);
-                        }
-                    }
-                }
-                Ok(false)
+    /// 一括操作の監査ログ用に、存在する物品だけを取得する
+    async fn find_existing_items(&self, ids: &[Uuid]) -> AppResult<Vec<Item>> {
+        let mut items = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.get_item(*id).await {
+                Ok(item) => items.push(item),
+                Err(AppError::NotFound(_)) => {}
+                Err(e) => return Err(e),
             }
         }
+        Ok(items)
     }
 
-    fn row_to_item(&self, row: sqlx::sqlite::SqliteRow) -> Item {
-        let connection_names: Option<Vec<String>> = row
-            .get::<Option<String>, _>("connection_names")
-            .and_then(|s| serde_json::from_str(&s).ok());
-        let cable_color_pattern: Option<Vec<String>> = row
-            .get::<Option<String>, _>("cable_color_pattern")
-            .and_then(|s| serde_json::from_str(&s).ok());
-        let storage_location: Option<String> = row.get::<Option<String>, _>("storage_location");
-
-        Item {
-            id: row.get::<String, _>("id").parse::<Uuid>().unwrap_or_default(),
-            name: row.get("name"),
-            label_id: row.get("label_id"),
-            model_number: row.get("model_number"),
-            remarks: row.get("remarks"),
-            purchase_year: row.get("purchase_year"),
-            purchase_amount: row.get("purchase_amount"),
-            durability_years: row.get("durability_years"),
-            is_depreciation_target: row.get("is_depreciation_target"),
-            connection_names,
-            cable_color_pattern,
-            storage_location,
-            container_id: row.get("container_id"),
-            storage_type: row
-                .get::<Option<String>, _>("storage_type")
-                .unwrap_or_else(|| "location".to_string()),
-            is_on_loan: row.get("is_on_loan"),
-            qr_code_type: row.get("qr_code_type"),
-            is_disposed: row.get("is_disposed"),
-            image_url: row.get("image_url"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        }
+    async fn check_items_on_loan(&self, ids: &[Uuid]) -> AppResult<bool> {
+        self.repo.any_on_loan(ids).await
     }
+}
 
-    fn row_to_item_postgres(&self, row: sqlx::postgres::PgRow) -> Item {
-        let connection_names: Option<Vec<String>> = row
-            .get::<Option<String>, _>("connection_names")
-            .and_then(|s| serde_json::from_str(&s).ok());
-        let cable_color_pattern: Option<Vec<String>> = row
-            .get::<Option<String>, _>("cable_color_pattern")
-            .and_then(|s| serde_json::from_str(&s).ok());
-        let storage_location: Option<String> = row.get::<Option<String>, _>("storage_location");
-
-        Item {
-            id: row.get("id"),
-            name: row.get("name"),
-            label_id: row.get("label_id"),
-            model_number: row.get("model_number"),
-            remarks: row.get("remarks"),
-            purchase_year: row.get("purchase_year"),
-            purchase_amount: row.get("purchase_amount"),
-            durability_years: row.get("durability_years"),
-            is_depreciation_target: row.get("is_depreciation_target"),
-            connection_names,
-            cable_color_pattern,
-            storage_location,
-            container_id: row.get("container_id"),
-            storage_type: row
-                .get::<Option<String>, _>("storage_type")
-                .unwrap_or_else(|| "location".to_string()),
-            is_on_loan: row.get("is_on_loan"),
-            qr_code_type: row.get("qr_code_type"),
-            is_disposed: row.get("is_disposed"),
-            image_url: row.get("image_url"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+/// 保管場所に関わるフィールド。これらの変更は「移動」として扱う
+const LOCATION_FIELDS: &[&str] = &[
+    "container_id",
+    "storage_location",
+    "storage_type",
+    "location_id",
+];
+
+/// 監査ログの1件を履歴のエントリに変換する。
+/// 更新は画像・保管場所・その他のフィールドに分けて、それぞれ別のエントリにする。
+fn history_entries_from_event(event: AuditEvent) -> Vec<ItemHistoryEntry> {
+    let entry = |kind, changes| ItemHistoryEntry {
+        kind,
+        occurred_at: event.created_at,
+        actor: Some(event.actor.clone()),
+        changes: Some(changes),
+        loan: None,
+    };
+
+    let kind = match event.action.as_str() {
+        "create" => ItemHistoryKind::Created,
+        "dispose" => ItemHistoryKind::Disposed,
+        "undispose" => ItemHistoryKind::Undisposed,
+        "relabel" => ItemHistoryKind::Relabeled,
+        "set_tags" => ItemHistoryKind::TagsChanged,
+        "update" => {
+            let serde_json::Value::Object(changes) = &event.changes else {
+                return Vec::new();
+            };
+
+            let mut image = serde_json::Map::new();
+            let mut location = serde_json::Map::new();
+            let mut other = serde_json::Map::new();
+            for (field, change) in changes {
+                let group = if field == "image_url" {
+                    &mut image
+                } else if LOCATION_FIELDS.contains(&field.as_str()) {
+                    &mut location
+                } else {
+                    &mut other
+                };
+                group.insert(field.clone(), change.clone());
+            }
+
+            return [
+                (ItemHistoryKind::Updated, other),
+                (ItemHistoryKind::ImageChanged, image),
+                (ItemHistoryKind::Moved, location),
+            ]
+            .into_iter()
+            .filter(|(_, changes)| !changes.is_empty())
+            .map(|(kind, changes)| entry(kind, serde_json::Value::Object(changes)))
+            .collect();
         }
+        _ => return Vec::new(),
+    };
+
+    vec![entry(kind, event.changes.clone())]
+}
+
+/// 行の検証で見つかった問題はメッセージにして返し、それ以外のエラーはそのまま返す
+fn import_error_message(e: AppError) -> AppResult<String> {
+    match e {
+        AppError::NotFound(message)
+        | AppError::BadRequest(message)
+        | AppError::Conflict(message)
+        | AppError::InvalidChecksum(message)
+        | AppError::ValidationError(message) => Ok(message),
+        other => Err(other),
     }
 }
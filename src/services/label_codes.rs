@@ -0,0 +1,171 @@
+//! ラベルに印刷するQRコード・バーコード（Code128）の生成。
+//! SVG / PNG / PDF / プリンタ向けの出力はすべてここで作ったモジュール配列から描画する。
+
+use barcoders::sym::code128::Code128;
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::error::{AppError, AppResult};
+
+/// QRコードの周囲に確保する余白（モジュール数）
+pub const QR_QUIET_ZONE: usize = 4;
+/// バーコードの左右に確保する余白（モジュール数）
+pub const BARCODE_QUIET_ZONE: usize = 10;
+
+/// QRコードのモジュール配列（余白を含まない）
+pub struct QrMatrix {
+    pub width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrMatrix {
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+}
+
+pub fn qr_matrix(data: &str) -> AppResult<QrMatrix> {
+    let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+        .map_err(|e| AppError::BadRequest(format!("Cannot encode QR code: {}", e)))?;
+    Ok(QrMatrix {
+        width: code.width(),
+        modules: code
+            .to_colors()
+            .into_iter()
+            .map(|color| color == Color::Dark)
+            .collect(),
+    })
+}
+
+/// Code128（コードセットB）のバー配列。1 が黒いバーを表す
+pub fn code128_bars(data: &str) -> AppResult<Vec<u8>> {
+    if data.is_empty() || !data.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return Err(AppError::BadRequest(
+            "Barcode data must be printable ASCII".to_string(),
+        ));
+    }
+    let code = Code128::new(format!("\u{0181}{}", data))
+        .map_err(|e| AppError::BadRequest(format!("Cannot encode barcode: {}", e)))?;
+    Ok(code.encode())
+}
+
+/// QRコードのSVG。`size` は余白を含めた一辺のピクセル数
+pub fn qr_svg(data: &str, size: u32) -> AppResult<String> {
+    let matrix = qr_matrix(data)?;
+    let total = matrix.width + QR_QUIET_ZONE * 2;
+
+    let mut path = String::new();
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if matrix.is_dark(x, y) {
+                path.push_str(&format!(
+                    "M{} {}h1v1h-1z",
+                    x + QR_QUIET_ZONE,
+                    y + QR_QUIET_ZONE
+                ));
+            }
+        }
+    }
+
+    Ok(format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {total} {total}" shape-rendering="crispEdges">"#,
+            r##"<rect width="{total}" height="{total}" fill="#fff"/>"##,
+            r##"<path d="{path}" fill="#000"/>"##,
+            "</svg>"
+        ),
+        size = size,
+        total = total,
+        path = path
+    ))
+}
+
+/// QRコードのPNG（8bitグレースケール）。モジュールは整数倍で拡大するので、一辺は `size` 以下になる
+pub fn qr_png(data: &str, size: u32) -> AppResult<Vec<u8>> {
+    let matrix = qr_matrix(data)?;
+    let total = matrix.width + QR_QUIET_ZONE * 2;
+    let scale = (size as usize / total).max(1);
+    let edge = total * scale;
+
+    let mut pixels = vec![0xffu8; edge * edge];
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if !matrix.is_dark(x, y) {
+                continue;
+            }
+            for dy in 0..scale {
+                let row = (y + QR_QUIET_ZONE) * scale + dy;
+                let start = row * edge + (x + QR_QUIET_ZONE) * scale;
+                pixels[start..start + scale].fill(0);
+            }
+        }
+    }
+
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, edge as u32, edge as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| AppError::InternalServer(format!("Failed to encode PNG: {}", e)))?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| AppError::InternalServer(format!("Failed to encode PNG: {}", e)))?;
+    }
+    Ok(png_data)
+}
+
+/// Code128バーコードのSVG。バーの下に読み取れる文字列を添える
+pub fn code128_svg(data: &str, module_width: u32, height: u32) -> AppResult<String> {
+    let bars = code128_bars(data)?;
+    let total = bars.len() + BARCODE_QUIET_ZONE * 2;
+    let text_height = (height / 4).max(10);
+
+    let mut path = String::new();
+    let mut x = 0;
+    while x < bars.len() {
+        if bars[x] == 1 {
+            let start = x;
+            while x < bars.len() && bars[x] == 1 {
+                x += 1;
+            }
+            path.push_str(&format!(
+                "M{} 0h{}v{}h-{}z",
+                start + BARCODE_QUIET_ZONE,
+                x - start,
+                height,
+                x - start
+            ));
+        } else {
+            x += 1;
+        }
+    }
+
+    let width = total as u32 * module_width;
+    Ok(format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{full_height}" viewBox="0 0 {width} {full_height}" shape-rendering="crispEdges">"#,
+            r##"<rect width="{width}" height="{full_height}" fill="#fff"/>"##,
+            r##"<path transform="scale({module_width} 1)" d="{path}" fill="#000"/>"##,
+            r##"<text x="{center}" y="{text_y}" font-family="monospace" font-size="{font_size}" text-anchor="middle" fill="#000">{text}</text>"##,
+            "</svg>"
+        ),
+        width = width,
+        full_height = height + text_height,
+        module_width = module_width,
+        path = path,
+        center = width / 2,
+        text_y = height + text_height - 2,
+        font_size = text_height - 2,
+        text = escape_xml(data)
+    ))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
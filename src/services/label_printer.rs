@@ -0,0 +1,325 @@
+//! ラベルプリンタ向けの出力（ZPL / Brother P-touch ラスタ）。
+//! 受付で1枚だけ貼り直す用途を想定し、テープ幅ごとのプリセットで1ラベル分を生成する。
+
+use crate::error::{AppError, AppResult};
+use crate::services::label_codes::{self, BARCODE_QUIET_ZONE};
+
+/// テープ幅ごとの設定
+#[derive(Debug, Clone, Copy)]
+pub struct TapePreset {
+    pub width_mm: u32,
+    /// P-touch のヘッド（128ピン）のうち印字に使うピン数と、その手前の余白ピン数
+    pub brother_pins: usize,
+    pub brother_margin_pins: usize,
+}
+
+impl TapePreset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "12mm" => Some(Self {
+                width_mm: 12,
+                brother_pins: 70,
+                brother_margin_pins: 29,
+            }),
+            "24mm" => Some(Self {
+                width_mm: 24,
+                brother_pins: 128,
+                brother_margin_pins: 0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Zebra（203dpi）向けのZPL。コードはプリンタ内蔵のQR・Code128で描く
+pub fn zpl(label_id: &str, code_type: &str, tape: TapePreset) -> AppResult<String> {
+    const DOTS_PER_MM: u32 = 8;
+    let height = tape.width_mm * DOTS_PER_MM;
+    let margin = DOTS_PER_MM;
+    let inner = height - margin * 2;
+    let text = zpl_field(label_id);
+
+    let (code, code_width, text_y, font) = match code_type {
+        "qr" => {
+            let modules = label_codes::qr_matrix(label_id)?.width as u32;
+            let magnification = (inner / modules).clamp(1, 10);
+            let size = modules * magnification;
+            (
+                format!(
+                    "^FO{},{}^BQN,2,{}^FDMA,{}^FS",
+                    margin, margin, magnification, text
+                ),
+                size,
+                margin + inner / 4,
+                inner / 2,
+            )
+        }
+        "barcode" => {
+            let bars = label_codes::code128_bars(label_id)?;
+            let modules = (bars.len() + BARCODE_QUIET_ZONE * 2) as u32;
+            let bar_height = inner * 3 / 5;
+            (
+                format!(
+                    "^FO{},{}^BY2^BCN,{},N,N,N^FD{}^FS",
+                    margin, margin, bar_height, text
+                ),
+                modules * 2,
+                margin + bar_height + 2,
+                inner - bar_height - 2,
+            )
+        }
+        _ => (String::new(), 0, margin, inner),
+    };
+
+    // QRは右側、バーコードは下側にラベルIDを添える
+    let (text_x, length) = match code_type {
+        "qr" => {
+            let x = margin + code_width + margin;
+            (x, x + font * label_id.chars().count() as u32 + margin)
+        }
+        _ => (
+            margin,
+            margin * 2 + code_width.max(font * label_id.chars().count() as u32),
+        ),
+    };
+
+    Ok(format!(
+        "^XA^CI28^PW{}^LL{}{}^FO{},{}^A0N,{},{}^FD{}^FS^XZ\n",
+        length, height, code, text_x, text_y, font, font, text
+    ))
+}
+
+/// ZPLのフィールドで制御文字として扱われる記号を取り除く
+fn zpl_field(value: &str) -> String {
+    value.chars().filter(|c| !matches!(c, '^' | '~')).collect()
+}
+
+/// Brother P-touch（PT-P700 系, 180dpi, 128ピン）のラスタコマンド列
+pub fn brother_raster(label_id: &str, code_type: &str, tape: TapePreset) -> AppResult<Vec<u8>> {
+    let bitmap = render_bitmap(label_id, code_type, tape.brother_pins)?;
+
+    let mut out = vec![0u8; 100];
+    // 初期化してラスタモードへ切り替える
+    out.extend_from_slice(&[0x1b, 0x40]);
+    out.extend_from_slice(&[0x1b, 0x69, 0x61, 0x01]);
+    // 印刷情報: テープ幅と、ラスタ行数
+    out.extend_from_slice(&[0x1b, 0x69, 0x7a, 0x84, 0x00, tape.width_mm as u8, 0x00]);
+    out.extend_from_slice(&(bitmap.width as u32).to_le_bytes());
+    out.extend_from_slice(&[0x00, 0x00]);
+    // オートカット、チェーン印刷なし、送り余白 2mm
+    out.extend_from_slice(&[0x1b, 0x69, 0x4d, 0x40]);
+    out.extend_from_slice(&[0x1b, 0x69, 0x4b, 0x08]);
+    out.extend_from_slice(&[0x1b, 0x69, 0x64, 0x0e, 0x00]);
+    // TIFF（PackBits）圧縮
+    out.extend_from_slice(&[0x4d, 0x02]);
+
+    // テープの送り方向が画像の横方向になる。1行 = ヘッドの128ピン
+    for x in 0..bitmap.width {
+        let mut line = [0u8; 16];
+        for y in 0..bitmap.height {
+            if bitmap.get(x, y) {
+                let pin = tape.brother_margin_pins + y;
+                line[pin / 8] |= 0x80 >> (pin % 8);
+            }
+        }
+
+        if line.iter().all(|byte| *byte == 0) {
+            out.push(0x5a);
+        } else {
+            let packed = packbits(&line);
+            out.push(0x47);
+            out.extend_from_slice(&(packed.len() as u16).to_le_bytes());
+            out.extend_from_slice(&packed);
+        }
+    }
+
+    // 印刷して排出
+    out.push(0x1a);
+    Ok(out)
+}
+
+fn packbits(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let mut run = 1;
+        while i + run < data.len() && data[i + run] == data[i] && run < 128 {
+            run += 1;
+        }
+        if run > 1 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        let start = i;
+        while i < data.len()
+            && i - start < 128
+            && !(i + 1 < data.len() && data[i] == data[i + 1])
+        {
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+    out
+}
+
+struct Bitmap {
+    width: usize,
+    height: usize,
+    bits: Vec<bool>,
+}
+
+impl Bitmap {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            bits: vec![false; width * height],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.bits[y * self.width + x]
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.bits[row * self.width + column] = true;
+            }
+        }
+    }
+
+    fn draw_text(&mut self, text: &str, x: usize, y: usize, scale: usize) {
+        for (index, c) in text.chars().enumerate() {
+            let glyph = glyph(c);
+            let origin = x + index * 6 * scale;
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0x10 >> column) != 0 {
+                        self.fill(origin + column * scale, y + row * scale, scale, scale);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `height` ドットの帯にコードとラベルIDを並べた画像を作る
+fn render_bitmap(label_id: &str, code_type: &str, height: usize) -> AppResult<Bitmap> {
+    let margin = 4;
+    let inner = height - margin * 2;
+    let text_len = label_id.chars().count();
+
+    match code_type {
+        "qr" => {
+            let matrix = label_codes::qr_matrix(label_id)?;
+            let module = (inner / (matrix.width + 2)).max(1);
+            let size = matrix.width * module;
+            let scale = (inner / 2 / 7).max(1);
+            let text_x = margin * 2 + size + module * 2;
+            let mut bitmap = Bitmap::new(text_x + text_len * 6 * scale + margin, height);
+
+            let offset = (height - size) / 2;
+            for y in 0..matrix.width {
+                for x in 0..matrix.width {
+                    if matrix.is_dark(x, y) {
+                        bitmap.fill(margin + x * module, offset + y * module, module, module);
+                    }
+                }
+            }
+            bitmap.draw_text(label_id, text_x, (height - 7 * scale) / 2, scale);
+            Ok(bitmap)
+        }
+        "barcode" => {
+            let bars = label_codes::code128_bars(label_id)?;
+            let scale = (inner / 4 / 7).max(1);
+            let bar_height = inner - 7 * scale - 2;
+            let module = 2;
+            let width = (bars.len() + BARCODE_QUIET_ZONE * 2) * module;
+            let mut bitmap = Bitmap::new(width.max(text_len * 6 * scale) + margin * 2, height);
+
+            for (index, bar) in bars.iter().enumerate() {
+                if *bar == 1 {
+                    bitmap.fill(
+                        margin + (index + BARCODE_QUIET_ZONE) * module,
+                        margin,
+                        module,
+                        bar_height,
+                    );
+                }
+            }
+            let text_x = margin + width.saturating_sub(text_len * 6 * scale) / 2;
+            bitmap.draw_text(label_id, text_x, margin + bar_height + 2, scale);
+            Ok(bitmap)
+        }
+        _ => {
+            let scale = (inner / 7).max(1);
+            let mut bitmap = Bitmap::new(text_len * 6 * scale + margin * 2, height);
+            bitmap.draw_text(label_id, margin, (height - 7 * scale) / 2, scale);
+            Ok(bitmap)
+        }
+    }
+}
+
+/// 5×7ドットのフォント。ラベルIDに使われる 0-9・A-Z・ハイフンだけを持つ
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        _ => [0x00; 7],
+    }
+}
+
+/// 印字できない文字を含むラベルIDを弾く
+pub fn ensure_printable(label_id: &str) -> AppResult<()> {
+    if label_id.is_empty()
+        || label_id.len() > 32
+        || !label_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(AppError::BadRequest(
+            "Label id must be 1-32 alphanumeric characters".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+//! ラベルIDの採番ルール（接頭辞・桁数・使用文字・チェック文字）。
+
+use crate::error::{AppError, AppResult};
+use crate::models::LabelSequence;
+
+/// 採番ルールを指定しなかったときに使うもの（従来の4桁のラベルID）
+pub const DEFAULT_SEQUENCE: &str = "default";
+
+/// 0-9・A-Z（従来の4桁のラベルIDと同じ）
+pub const DEFAULT_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// 連番として発行できる最大の番号
+pub fn capacity(sequence: &LabelSequence) -> i64 {
+    let base = sequence.alphabet.chars().count() as i64;
+    (0..sequence.width)
+        .try_fold(1i64, |acc, _| acc.checked_mul(base))
+        .map_or(i64::MAX, |total| total - 1)
+}
+
+pub fn format_label(sequence: &LabelSequence, number: i64) -> String {
+    let alphabet: Vec<char> = sequence.alphabet.chars().collect();
+    let base = alphabet.len() as i64;
+
+    let mut digits = Vec::with_capacity(sequence.width as usize);
+    let mut rest = number;
+    for _ in 0..sequence.width {
+        digits.push(alphabet[(rest % base) as usize]);
+        rest /= base;
+    }
+    let body: String = digits.into_iter().rev().collect();
+
+    let mut label = format!("{}{}", sequence.prefix, body);
+    if sequence.check_digit {
+        label.push(check_char(&alphabet, &body));
+    }
+    label
+}
+
+/// その番号で発行したラベルID。チェック文字を付ける前に発行した番号はチェック文字なしの形になる
+pub fn issued_label(sequence: &LabelSequence, number: i64) -> String {
+    let mut label = format_label(sequence, number);
+    let unchecked = sequence.check_digit
+        && sequence
+            .unchecked_until
+            .is_some_and(|until| number <= until);
+    if unchecked {
+        label.pop();
+    }
+    label
+}
+
+/// Luhn mod N のチェック文字。1文字の誤りと隣り合う文字の入れ替えを検出できる
+pub fn check_char(alphabet: &[char], body: &str) -> char {
+    let base = alphabet.len();
+    let mut sum = 0;
+    for (i, c) in body.chars().rev().enumerate() {
+        let value = alphabet.iter().position(|a| *a == c).unwrap_or(0);
+        let addend = if i % 2 == 0 { value * 2 } else { value };
+        sum += addend / base + addend % base;
+    }
+    alphabet[(base - sum % base) % base]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelMatch {
+    /// この採番ルールの形式ではない
+    NoMatch,
+    Valid,
+    /// 形式は合っているがチェック文字が違う
+    BadCheck,
+}
+
+pub fn match_label(sequence: &LabelSequence, label: &str) -> LabelMatch {
+    let Some(rest) = label.strip_prefix(sequence.prefix.as_str()) else {
+        return LabelMatch::NoMatch;
+    };
+    let alphabet: Vec<char> = sequence.alphabet.chars().collect();
+    let chars: Vec<char> = rest.chars().collect();
+    let width = sequence.width as usize;
+    if !chars.iter().all(|c| alphabet.contains(c)) {
+        return LabelMatch::NoMatch;
+    }
+
+    if !sequence.check_digit {
+        return if chars.len() == width {
+            LabelMatch::Valid
+        } else {
+            LabelMatch::NoMatch
+        };
+    }
+
+    if chars.len() == width {
+        // チェック文字を付ける前に発行したラベル。それ以降の番号はチェック文字の読み落とし
+        let legacy = sequence
+            .unchecked_until
+            .is_some_and(|until| parse_number(&alphabet, &chars) <= until);
+        return if legacy {
+            LabelMatch::Valid
+        } else {
+            LabelMatch::BadCheck
+        };
+    }
+    if chars.len() != width + 1 {
+        return LabelMatch::NoMatch;
+    }
+
+    let body: String = chars[..width].iter().collect();
+    if check_char(&alphabet, &body) != chars[width] {
+        return LabelMatch::BadCheck;
+    }
+    LabelMatch::Valid
+}
+
+/// チェック文字を除いたラベルIDの番号（`format_label` の逆）。桁あふれした場合は i64::MAX
+pub fn parse_number(alphabet: &[char], body: &[char]) -> i64 {
+    let base = alphabet.len() as i64;
+    body.iter()
+        .try_fold(0i64, |acc, c| {
+            let value = alphabet.iter().position(|a| a == c).unwrap_or(0) as i64;
+            acc.checked_mul(base)?.checked_add(value)
+        })
+        .unwrap_or(i64::MAX)
+}
+
+/// チェック文字なしの形式（`width` 桁）のラベルIDならその番号
+pub fn unchecked_number(sequence: &LabelSequence, label: &str) -> Option<i64> {
+    let rest = label.strip_prefix(sequence.prefix.as_str())?;
+    let alphabet: Vec<char> = sequence.alphabet.chars().collect();
+    let chars: Vec<char> = rest.chars().collect();
+    if chars.len() != sequence.width as usize || !chars.iter().all(|c| alphabet.contains(c)) {
+        return None;
+    }
+    Some(parse_number(&alphabet, &chars))
+}
+
+/// 採番ルールが受け付けるラベルIDの長さ（接頭辞を除く）
+pub fn id_lengths(width: i32, check_digit: bool, unchecked: bool) -> Vec<i32> {
+    let mut lengths = vec![width + i32::from(check_digit)];
+    if check_digit && unchecked {
+        lengths.push(width);
+    }
+    lengths
+}
+
+/// 採番ルールが受け付けるラベルIDの形
+pub struct LabelForm<'a> {
+    pub prefix: &'a str,
+    pub alphabet: &'a str,
+    /// 接頭辞を除いた長さ（`id_lengths`）
+    pub lengths: Vec<i32>,
+}
+
+/// 両方の形に合うラベルIDがありうるか。一方の接頭辞がもう一方の接頭辞で始まり、
+/// 残りの部分がその使用文字で書けて、長さも合う場合に重なる
+pub fn forms_overlap(a: &LabelForm, b: &LabelForm) -> bool {
+    let (short, long) = if a.prefix.len() <= b.prefix.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let Some(extra) = long.prefix.strip_prefix(short.prefix) else {
+        return false;
+    };
+    if !extra.chars().all(|c| short.alphabet.contains(c))
+        || !long.alphabet.chars().any(|c| short.alphabet.contains(c))
+    {
+        return false;
+    }
+    let extra = extra.chars().count() as i32;
+    long.lengths
+        .iter()
+        .any(|length| short.lengths.contains(&(extra + length)))
+}
+
+/// 新しい採番ルールの接頭辞と使用文字を確認する
+pub fn validate_definition(prefix: &str, alphabet: &str) -> AppResult<()> {
+    if !prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(AppError::BadRequest(
+            "prefix may only contain letters, digits and '-'".to_string(),
+        ));
+    }
+
+    let chars: Vec<char> = alphabet.chars().collect();
+    if chars.len() < 2 || !chars.iter().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::BadRequest(
+            "alphabet must contain at least 2 letters or digits".to_string(),
+        ));
+    }
+    let mut unique = chars.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if unique.len() != chars.len() {
+        return Err(AppError::BadRequest(
+            "alphabet must not contain duplicate characters".to_string(),
+        ));
+    }
+    Ok(())
+}
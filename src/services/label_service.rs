@@ -0,0 +1,1141 @@
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AuditAction, AuditEntity, CreateLabelSequenceRequest, CurrentUser, LabelBatch, LabelFilters,
+    LabelRecord, LabelSequence, LabelStatus, LabelsListResponse,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use crate::services::label_scheme::{self, LabelForm, LabelMatch};
+use chrono::Utc;
+use sqlx::Row;
+use uuid::Uuid;
+
+const LABEL_COLUMNS: &str = r#"
+    l.id, l.status, l.batch_id, b.generated_by, l.item_id, l.container_id, l.generated_at,
+    l.printed_at, l.attached_at, l.voided_at, l.voided_by, l.void_reason, l.updated_at
+"#;
+
+/// ラベル一覧の絞り込み（状態、発行バッチ）。指定されていない条件では絞り込まない
+const LABEL_FILTER_POSTGRES: &str =
+    "WHERE ($1 IS NULL OR l.status = $1) AND ($2 IS NULL OR l.batch_id = $2)";
+const LABEL_FILTER_SQLITE: &str =
+    "WHERE (?1 IS NULL OR l.status = ?1) AND (?2 IS NULL OR l.batch_id = ?2)";
+
+pub struct LabelService {
+    db: DatabasePool,
+    audit: AuditService,
+}
+
+impl LabelService {
+    pub fn new(db: DatabasePool) -> Self {
+        let audit = AuditService::new(db.clone());
+        Self { db, audit }
+    }
+
+    /// 採番ルールから連続したラベルIDを確保し、発行記録と共に保存する
+    pub async fn generate_batch(
+        &self,
+        sequence: &str,
+        quantity: u32,
+        record_type: Option<&str>,
+        actor: &CurrentUser,
+    ) -> AppResult<(LabelBatch, Vec<String>)> {
+        if quantity == 0 {
+            return Err(AppError::BadRequest(
+                "Quantity must be at least 1".to_string(),
+            ));
+        }
+        let sequence = self.get_sequence(sequence).await?;
+        let actor_id = (actor.id != 0).then_some(actor.id);
+        let now = Utc::now();
+
+        let (batch_id, label_ids) = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+
+                let current_value: i64 = sqlx::query(
+                    "SELECT current_value FROM label_sequences WHERE name = $1 FOR UPDATE",
+                )
+                .bind(&sequence.name)
+                .fetch_one(&mut *tx)
+                .await?
+                .get("current_value");
+                let label_ids = allocate(&sequence, current_value, quantity)?;
+
+                sqlx::query(
+                    "UPDATE label_sequences SET current_value = $1, updated_at = $2 WHERE name = $3",
+                )
+                .bind(current_value + quantity as i64)
+                .bind(now)
+                .bind(&sequence.name)
+                .execute(&mut *tx)
+                .await?;
+
+                let batch_id: i64 = sqlx::query(
+                    r#"
+                    INSERT INTO label_batches (
+                        sequence, quantity, first_label, last_label, record_type,
+                        generated_by_id, generated_by, created_at
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&sequence.name)
+                .bind(quantity as i32)
+                .bind(&label_ids[0])
+                .bind(&label_ids[label_ids.len() - 1])
+                .bind(record_type)
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+                .get("id");
+
+                for label_id in &label_ids {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO labels (id, status, batch_id, generated_at, updated_at)
+                        VALUES ($1, $2, $3, $4, $4)
+                        ON CONFLICT (id) DO NOTHING
+                        "#,
+                    )
+                    .bind(label_id)
+                    .bind(LabelStatus::Generated.as_str())
+                    .bind(batch_id)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                tx.commit().await?;
+                (batch_id, label_ids)
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+
+                // 先に書き込みを行い、同時に発行されても同じ範囲を確保しないようにする
+                sqlx::query(
+                    r#"
+                    UPDATE label_sequences
+                    SET current_value = current_value + ?1, updated_at = ?2
+                    WHERE name = ?3
+                    "#,
+                )
+                .bind(quantity as i64)
+                .bind(now)
+                .bind(&sequence.name)
+                .execute(&mut *tx)
+                .await?;
+                let new_value: i64 =
+                    sqlx::query("SELECT current_value FROM label_sequences WHERE name = ?1")
+                        .bind(&sequence.name)
+                        .fetch_one(&mut *tx)
+                        .await?
+                        .get("current_value");
+                let label_ids = allocate(&sequence, new_value - quantity as i64, quantity)?;
+
+                let batch_id: i64 = sqlx::query(
+                    r#"
+                    INSERT INTO label_batches (
+                        sequence, quantity, first_label, last_label, record_type,
+                        generated_by_id, generated_by, created_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&sequence.name)
+                .bind(quantity as i32)
+                .bind(&label_ids[0])
+                .bind(&label_ids[label_ids.len() - 1])
+                .bind(record_type)
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+                .get("id");
+
+                for label_id in &label_ids {
+                    sqlx::query(
+                        r#"
+                        INSERT OR IGNORE INTO labels (id, status, batch_id, generated_at, updated_at)
+                        VALUES (?1, ?2, ?3, ?4, ?4)
+                        "#,
+                    )
+                    .bind(label_id)
+                    .bind(LabelStatus::Generated.as_str())
+                    .bind(batch_id)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                tx.commit().await?;
+                (batch_id, label_ids)
+            }
+        };
+
+        let batch = self.get_batch(batch_id).await?;
+        Ok((batch, label_ids))
+    }
+
+    pub async fn get_batch(&self, id: i64) -> AppResult<LabelBatch> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, sequence, quantity, first_label, last_label, record_type,
+                        generated_by, created_at
+                    FROM label_batches
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Label batch {} not found", id)))?;
+                Ok(self.row_to_batch_postgres(row))
+            }
+            DatabasePool::Sqlite(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT id, sequence, quantity, first_label, last_label, record_type,
+                        generated_by, created_at
+                    FROM label_batches
+                    WHERE id = ?1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Label batch {} not found", id)))?;
+                Ok(self.row_to_batch(row))
+            }
+        }
+    }
+
+    pub async fn list_batches(&self) -> AppResult<Vec<LabelBatch>> {
+        let query = r#"
+            SELECT id, sequence, quantity, first_label, last_label, record_type, generated_by,
+                created_at
+            FROM label_batches
+            ORDER BY id DESC
+        "#;
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_batch_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows.into_iter().map(|row| self.row_to_batch(row)).collect())
+            }
+        }
+    }
+
+    pub async fn get_label(&self, id: &str) -> AppResult<LabelRecord> {
+        self.find_label(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Label {} not found", id)))
+    }
+
+    async fn find_label(&self, id: &str) -> AppResult<Option<LabelRecord>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id WHERE l.id = $1",
+                    LABEL_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+                Ok(row.map(|row| self.row_to_label_postgres(row)))
+            }
+            DatabasePool::Sqlite(pool) => {
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id WHERE l.id = ?1",
+                    LABEL_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+                Ok(row.map(|row| self.row_to_label(row)))
+            }
+        }
+    }
+
+    pub async fn list_labels(
+        &self,
+        filters: &LabelFilters,
+        page: u32,
+        per_page: u32,
+    ) -> AppResult<LabelsListResponse> {
+        let offset = ((page - 1) * per_page) as i64;
+        let limit = per_page as i64;
+
+        let status = filters.status.map(|status| status.as_str());
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id {} ORDER BY l.id LIMIT $3 OFFSET $4",
+                    LABEL_COLUMNS, LABEL_FILTER_POSTGRES
+                ))
+                .bind(status)
+                .bind(filters.batch_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?;
+                let total: i64 = sqlx::query_scalar(&format!(
+                    "SELECT COUNT(*) FROM labels l {}",
+                    LABEL_FILTER_POSTGRES
+                ))
+                .bind(status)
+                .bind(filters.batch_id)
+                .fetch_one(pool)
+                .await?;
+                Ok(LabelsListResponse {
+                    labels: rows
+                        .into_iter()
+                        .map(|row| self.row_to_label_postgres(row))
+                        .collect(),
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id {} ORDER BY l.id LIMIT ?3 OFFSET ?4",
+                    LABEL_COLUMNS, LABEL_FILTER_SQLITE
+                ))
+                .bind(status)
+                .bind(filters.batch_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?;
+                let total: i64 = sqlx::query_scalar(&format!(
+                    "SELECT COUNT(*) FROM labels l {}",
+                    LABEL_FILTER_SQLITE
+                ))
+                .bind(status)
+                .bind(filters.batch_id)
+                .fetch_one(pool)
+                .await?;
+                Ok(LabelsListResponse {
+                    labels: rows.into_iter().map(|row| self.row_to_label(row)).collect(),
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+        }
+    }
+
+    /// 発行（・印刷）されたまま一度も貼られていないラベル。紛失したシールを探すのに使う
+    pub async fn list_unattached(&self, batch_id: Option<i64>) -> AppResult<Vec<LabelRecord>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(&format!(
+                    r#"
+                    SELECT {}
+                    FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id
+                    WHERE l.status IN ('generated', 'printed')
+                      AND l.attached_at IS NULL
+                      AND ($1 IS NULL OR l.batch_id = $1)
+                    ORDER BY l.id
+                    "#,
+                    LABEL_COLUMNS
+                ))
+                .bind(batch_id)
+                .fetch_all(pool)
+                .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_label_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(&format!(
+                    r#"
+                    SELECT {}
+                    FROM labels l LEFT JOIN label_batches b ON b.id = l.batch_id
+                    WHERE l.status IN ('generated', 'printed')
+                      AND l.attached_at IS NULL
+                      AND (?1 IS NULL OR l.batch_id = ?1)
+                    ORDER BY l.id
+                    "#,
+                    LABEL_COLUMNS
+                ))
+                .bind(batch_id)
+                .fetch_all(pool)
+                .await?;
+                Ok(rows.into_iter().map(|row| self.row_to_label(row)).collect())
+            }
+        }
+    }
+
+    /// 無効化されたラベルが含まれていればエラーにする
+    pub async fn ensure_not_voided(&self, label_ids: &[String]) -> AppResult<()> {
+        let voided: Vec<String> = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(
+                    "SELECT id FROM labels WHERE status = 'voided' AND id = ANY($1) ORDER BY id",
+                )
+                .bind(label_ids)
+                .fetch_all(pool)
+                .await?;
+                rows.into_iter().map(|row| row.get("id")).collect()
+            }
+            DatabasePool::Sqlite(pool) => {
+                let query_str = format!(
+                    "SELECT id FROM labels WHERE status = 'voided' AND id IN ({}) ORDER BY id",
+                    label_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+                );
+                let mut query = sqlx::query(&query_str);
+                for label_id in label_ids {
+                    query = query.bind(label_id);
+                }
+                let rows = query.fetch_all(pool).await?;
+                rows.into_iter().map(|row| row.get("id")).collect()
+            }
+        };
+        if !voided.is_empty() {
+            return Err(AppError::Conflict(format!(
+                "Voided labels cannot be printed: {}",
+                voided.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// 印刷したラベルを記録する。無効化されたラベルは印刷させない
+    pub async fn mark_printed(&self, label_ids: &[String]) -> AppResult<()> {
+        if label_ids.is_empty() {
+            return Ok(());
+        }
+        self.ensure_not_voided(label_ids).await?;
+        let now = Utc::now();
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE labels
+                    SET status = CASE WHEN status = 'generated' THEN 'printed' ELSE status END,
+                        printed_at = $1, updated_at = $1
+                    WHERE id = ANY($2)
+                    "#,
+                )
+                .bind(now)
+                .bind(label_ids)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                let query_str = format!(
+                    r#"
+                    UPDATE labels
+                    SET status = CASE WHEN status = 'generated' THEN 'printed' ELSE status END,
+                        printed_at = ?, updated_at = ?
+                    WHERE id IN ({})
+                    "#,
+                    label_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+                );
+                let mut query = sqlx::query(&query_str).bind(now).bind(now);
+                for label_id in label_ids {
+                    query = query.bind(label_id);
+                }
+                query.execute(pool).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// どの採番ルールの形式にも合わないラベルや、無効化されたラベルを物品やコンテナに貼らせない
+    pub async fn ensure_attachable(&self, label_id: &str) -> AppResult<()> {
+        self.verify_label(label_id).await?;
+        if self.sequence_of(label_id).await?.is_none() {
+            let sequences = self.list_sequences().await?;
+            return Err(AppError::BadRequest(format!(
+                "Label {} does not match any label sequence ({})",
+                label_id,
+                sequences
+                    .iter()
+                    .map(|sequence| sequence.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        match self.find_label(label_id).await? {
+            Some(label) if label.status == LabelStatus::Voided => Err(AppError::Conflict(format!(
+                "Label {} has been voided",
+                label_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn attach_to_item(&self, label_id: &str, item_id: Uuid) -> AppResult<()> {
+        let now = Utc::now();
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, item_id, attached_at, updated_at)
+                    VALUES ($1, 'attached', $2, $3, $3)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'attached', item_id = $2, container_id = NULL,
+                        attached_at = $3, updated_at = $3
+                    "#,
+                )
+                .bind(label_id)
+                .bind(item_id)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, item_id, attached_at, updated_at)
+                    VALUES (?1, 'attached', ?2, ?3, ?3)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'attached', item_id = ?2, container_id = NULL,
+                        attached_at = ?3, updated_at = ?3
+                    "#,
+                )
+                .bind(label_id)
+                .bind(item_id.to_string())
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn attach_to_container(&self, label_id: &str) -> AppResult<()> {
+        let now = Utc::now();
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, container_id, attached_at, updated_at)
+                    VALUES ($1, 'attached', $1, $2, $2)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'attached', item_id = NULL, container_id = $1,
+                        attached_at = $2, updated_at = $2
+                    "#,
+                )
+                .bind(label_id)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, container_id, attached_at, updated_at)
+                    VALUES (?1, 'attached', ?1, ?2, ?2)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'attached', item_id = NULL, container_id = ?1,
+                        attached_at = ?2, updated_at = ?2
+                    "#,
+                )
+                .bind(label_id)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 貼られていたラベルを外す。貼り替えなら reassigned、物品の削除なら printed（再利用可能）に戻す
+    pub async fn detach(&self, label_id: &str, status: LabelStatus) -> AppResult<()> {
+        let now = Utc::now();
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE labels
+                    SET status = $1, item_id = NULL, container_id = NULL, updated_at = $2
+                    WHERE id = $3 AND status = 'attached'
+                    "#,
+                )
+                .bind(status.as_str())
+                .bind(now)
+                .bind(label_id)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    UPDATE labels
+                    SET status = ?1, item_id = NULL, container_id = NULL, updated_at = ?2
+                    WHERE id = ?3 AND status = 'attached'
+                    "#,
+                )
+                .bind(status.as_str())
+                .bind(now)
+                .bind(label_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 紛失・破損したラベルを無効にする。貼られているラベルは無効にできない
+    pub async fn void_label(
+        &self,
+        id: &str,
+        reason: Option<String>,
+        actor: &CurrentUser,
+    ) -> AppResult<LabelRecord> {
+        let before = self.find_label(id).await?;
+        match before.as_ref().map(|label| label.status) {
+            Some(LabelStatus::Attached) => {
+                return Err(AppError::Conflict(format!(
+                    "Label {} is attached and cannot be voided",
+                    id
+                )))
+            }
+            Some(LabelStatus::Voided) => {
+                return Err(AppError::Conflict(format!(
+                    "Label {} is already voided",
+                    id
+                )))
+            }
+            _ => {}
+        }
+
+        let now = Utc::now();
+        // 発行記録のないラベル（この機能より前に発行したもの）も無効として記録する
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, voided_at, voided_by, void_reason, updated_at)
+                    VALUES ($1, 'voided', $2, $3, $4, $2)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'voided', voided_at = $2, voided_by = $3, void_reason = $4, updated_at = $2
+                    "#,
+                )
+                .bind(id)
+                .bind(now)
+                .bind(&actor.username)
+                .bind(&reason)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO labels (id, status, voided_at, voided_by, void_reason, updated_at)
+                    VALUES (?1, 'voided', ?2, ?3, ?4, ?2)
+                    ON CONFLICT (id) DO UPDATE
+                    SET status = 'voided', voided_at = ?2, voided_by = ?3, void_reason = ?4, updated_at = ?2
+                    "#,
+                )
+                .bind(id)
+                .bind(now)
+                .bind(&actor.username)
+                .bind(&reason)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        let label = self.get_label(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Label,
+                id,
+                AuditAction::Void,
+                actor,
+                before.as_ref().and_then(snapshot),
+                snapshot(&label),
+            )
+            .await?;
+        Ok(label)
+    }
+
+    pub async fn list_sequences(&self) -> AppResult<Vec<LabelSequence>> {
+        let query = r#"
+            SELECT name, prefix, width, alphabet, check_digit, unchecked_until, current_value, created_at,
+                updated_at
+            FROM label_sequences
+            ORDER BY name
+        "#;
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_sequence_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows.into_iter().map(|row| self.row_to_sequence(row)).collect())
+            }
+        }
+    }
+
+    pub async fn get_sequence(&self, name: &str) -> AppResult<LabelSequence> {
+        let sequence = match &self.db {
+            DatabasePool::Postgres(pool) => sqlx::query(
+                r#"
+                SELECT name, prefix, width, alphabet, check_digit, unchecked_until, current_value,
+                    created_at, updated_at
+                FROM label_sequences
+                WHERE name = $1
+                "#,
+            )
+            .bind(name)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| self.row_to_sequence_postgres(row)),
+            DatabasePool::Sqlite(pool) => sqlx::query(
+                r#"
+                SELECT name, prefix, width, alphabet, check_digit, unchecked_until, current_value,
+                    created_at, updated_at
+                FROM label_sequences
+                WHERE name = ?1
+                "#,
+            )
+            .bind(name)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| self.row_to_sequence(row)),
+        };
+        sequence.ok_or_else(|| AppError::NotFound(format!("Label sequence '{}' not found", name)))
+    }
+
+    /// ラベルIDの形式に合う採番ルール
+    pub async fn sequence_of(&self, label_id: &str) -> AppResult<Option<LabelSequence>> {
+        Ok(self
+            .list_sequences()
+            .await?
+            .into_iter()
+            .find(|sequence| label_scheme::match_label(sequence, label_id) == LabelMatch::Valid))
+    }
+
+    /// 読み取ったラベルIDのチェック文字を確認する。どの採番ルールの形式にも合わないIDはそのまま通す
+    pub async fn verify_label(&self, label_id: &str) -> AppResult<()> {
+        let mut bad_check = false;
+        for sequence in self.list_sequences().await? {
+            match label_scheme::match_label(&sequence, label_id) {
+                LabelMatch::Valid => return Ok(()),
+                LabelMatch::BadCheck => bad_check = true,
+                LabelMatch::NoMatch => {}
+            }
+        }
+        if bad_check {
+            return Err(AppError::InvalidChecksum(format!(
+                "Label {} has a wrong or missing check character",
+                label_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 採番ルールは発行済みのラベルと矛盾しないよう、作成後は変更できない
+    pub async fn create_sequence(
+        &self,
+        req: CreateLabelSequenceRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<LabelSequence> {
+        let alphabet = req
+            .alphabet
+            .unwrap_or_else(|| label_scheme::DEFAULT_ALPHABET.to_string());
+        label_scheme::validate_definition(&req.prefix, &alphabet)?;
+
+        let form = LabelForm {
+            prefix: &req.prefix,
+            alphabet: &alphabet,
+            lengths: label_scheme::id_lengths(req.width, req.check_digit, false),
+        };
+        self.ensure_distinct_form(None, &form).await?;
+
+        let now = Utc::now();
+        let result = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO label_sequences (
+                        name, prefix, width, alphabet, check_digit, current_value, created_at, updated_at
+                    ) VALUES ($1, $2, $3, $4, $5, 0, $6, $6)
+                    "#,
+                )
+                .bind(&req.name)
+                .bind(&req.prefix)
+                .bind(req.width)
+                .bind(&alphabet)
+                .bind(req.check_digit)
+                .bind(now)
+                .execute(pool)
+                .await
+                .map(|_| ())
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO label_sequences (
+                        name, prefix, width, alphabet, check_digit, current_value, created_at, updated_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?6)
+                    "#,
+                )
+                .bind(&req.name)
+                .bind(&req.prefix)
+                .bind(req.width)
+                .bind(&alphabet)
+                .bind(req.check_digit)
+                .bind(now)
+                .execute(pool)
+                .await
+                .map(|_| ())
+            }
+        };
+        if let Err(sqlx::Error::Database(e)) = &result {
+            if e.is_unique_violation() {
+                return Err(AppError::Conflict(format!(
+                    "Label sequence '{}' already exists",
+                    req.name
+                )));
+            }
+        }
+        result?;
+
+        let sequence = self.get_sequence(&req.name).await?;
+        self.audit
+            .record(
+                AuditEntity::LabelSequence,
+                &sequence.name,
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&sequence),
+            )
+            .await?;
+        Ok(sequence)
+    }
+
+    /// 既存の採番ルールにチェック文字を追加する。それまでに発行したIDはチェック文字なしでも使える
+    pub async fn enable_check_digit(
+        &self,
+        name: &str,
+        actor: &CurrentUser,
+    ) -> AppResult<LabelSequence> {
+        let before = self.get_sequence(name).await?;
+        if before.check_digit {
+            return Err(AppError::Conflict(format!(
+                "Label sequence '{}' already uses check characters",
+                name
+            )));
+        }
+        let form = LabelForm {
+            prefix: &before.prefix,
+            alphabet: &before.alphabet,
+            lengths: label_scheme::id_lengths(before.width, true, true),
+        };
+        self.ensure_distinct_form(Some(name), &form).await?;
+
+        // 採番ルールを使わずに登録されたラベルもあるため、発行済みの番号より大きいものも含める
+        let label_ids: Vec<String> = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar("SELECT id FROM labels").fetch_all(pool).await?
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT id FROM labels").fetch_all(pool).await?
+            }
+        };
+        let highest = label_ids
+            .iter()
+            .filter_map(|id| label_scheme::unchecked_number(&before, id))
+            .max()
+            .unwrap_or(0);
+
+        let now = Utc::now();
+        let rows_affected = match &self.db {
+            DatabasePool::Postgres(pool) => sqlx::query(
+                r#"
+                UPDATE label_sequences
+                SET check_digit = $1,
+                    unchecked_until = CASE WHEN current_value > $2 THEN current_value ELSE $2 END,
+                    updated_at = $3
+                WHERE name = $4 AND check_digit = $5
+                "#,
+            )
+            .bind(true)
+            .bind(highest)
+            .bind(now)
+            .bind(name)
+            .bind(false)
+            .execute(pool)
+            .await?
+            .rows_affected(),
+            DatabasePool::Sqlite(pool) => sqlx::query(
+                r#"
+                UPDATE label_sequences
+                SET check_digit = ?1,
+                    unchecked_until = CASE WHEN current_value > ?2 THEN current_value ELSE ?2 END,
+                    updated_at = ?3
+                WHERE name = ?4 AND check_digit = ?5
+                "#,
+            )
+            .bind(true)
+            .bind(highest)
+            .bind(now)
+            .bind(name)
+            .bind(false)
+            .execute(pool)
+            .await?
+            .rows_affected(),
+        };
+        if rows_affected == 0 {
+            return Err(AppError::Conflict(format!(
+                "Label sequence '{}' already uses check characters",
+                name
+            )));
+        }
+
+        let after = self.get_sequence(name).await?;
+        self.audit
+            .record(
+                AuditEntity::LabelSequence,
+                name,
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&after),
+            )
+            .await?;
+        Ok(after)
+    }
+
+    /// 既存の採番ルールの形式にも合うIDを発行すると、どちらのラベルか区別できなくなる
+    async fn ensure_distinct_form(
+        &self,
+        except: Option<&str>,
+        form: &LabelForm<'_>,
+    ) -> AppResult<()> {
+        for sequence in self.list_sequences().await? {
+            if Some(sequence.name.as_str()) == except {
+                continue;
+            }
+            let existing = LabelForm {
+                prefix: &sequence.prefix,
+                alphabet: &sequence.alphabet,
+                lengths: label_scheme::id_lengths(
+                    sequence.width,
+                    sequence.check_digit,
+                    sequence.unchecked_until.is_some(),
+                ),
+            };
+            if label_scheme::forms_overlap(form, &existing) {
+                return Err(AppError::Conflict(format!(
+                    "Label IDs of this form could also match label sequence '{}'",
+                    sequence.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 既定の採番ルールで発行できるすべてのラベルIDと、ほかの採番ルールで発行済みか
+    /// 物品に使われているラベルID
+    pub async fn get_all_labels(&self) -> AppResult<Vec<crate::handlers::labels::LabelInfo>> {
+        let (used_rows, status_rows) = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let used: Vec<(String, String)> = sqlx::query("SELECT label_id, name FROM items")
+                    .fetch_all(pool)
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("label_id"), row.get("name")))
+                    .collect();
+                let statuses: Vec<(String, String)> = sqlx::query("SELECT id, status FROM labels")
+                    .fetch_all(pool)
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("id"), row.get("status")))
+                    .collect();
+                (used, statuses)
+            }
+            DatabasePool::Sqlite(pool) => {
+                let used: Vec<(String, String)> = sqlx::query("SELECT label_id, name FROM items")
+                    .fetch_all(pool)
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("label_id"), row.get("name")))
+                    .collect();
+                let statuses: Vec<(String, String)> = sqlx::query("SELECT id, status FROM labels")
+                    .fetch_all(pool)
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("id"), row.get("status")))
+                    .collect();
+                (used, statuses)
+            }
+        };
+
+        let used_labels_map: std::collections::HashMap<String, String> =
+            used_rows.into_iter().collect();
+        let status_map: std::collections::HashMap<String, LabelStatus> = status_rows
+            .into_iter()
+            .filter_map(|(id, status)| LabelStatus::parse(&status).map(|status| (id, status)))
+            .collect();
+        let label_info = |label_id: String| crate::handlers::labels::LabelInfo {
+            used: used_labels_map.contains_key(&label_id),
+            item_name: used_labels_map.get(&label_id).cloned(),
+            status: status_map.get(&label_id).copied(),
+            id: label_id,
+        };
+
+        let sequences = self.list_sequences().await?;
+        let (defaults, others): (Vec<_>, Vec<_>) = sequences
+            .into_iter()
+            .partition(|sequence| sequence.name == label_scheme::DEFAULT_SEQUENCE);
+
+        let mut labels: Vec<_> = defaults
+            .iter()
+            .flat_map(|sequence| {
+                (0..=label_scheme::capacity(sequence))
+                    .map(|number| label_scheme::issued_label(sequence, number))
+            })
+            .map(&label_info)
+            .collect();
+
+        // ほかの採番ルールは桁数によっては番号が多すぎるので、記録のあるラベルIDだけを並べる
+        let mut recorded: Vec<&String> = used_labels_map
+            .keys()
+            .chain(status_map.keys())
+            .filter(|id| {
+                others.iter().any(|sequence| {
+                    label_scheme::match_label(sequence, id) == LabelMatch::Valid
+                })
+            })
+            .collect();
+        recorded.sort();
+        recorded.dedup();
+        labels.extend(recorded.into_iter().cloned().map(&label_info));
+        Ok(labels)
+    }
+
+    fn row_to_batch(&self, row: sqlx::sqlite::SqliteRow) -> LabelBatch {
+        LabelBatch {
+            id: row.get("id"),
+            sequence: row.get("sequence"),
+            quantity: row.get("quantity"),
+            first_label: row.get("first_label"),
+            last_label: row.get("last_label"),
+            record_type: row.get("record_type"),
+            generated_by: row.get("generated_by"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn row_to_batch_postgres(&self, row: sqlx::postgres::PgRow) -> LabelBatch {
+        LabelBatch {
+            id: row.get("id"),
+            sequence: row.get("sequence"),
+            quantity: row.get("quantity"),
+            first_label: row.get("first_label"),
+            last_label: row.get("last_label"),
+            record_type: row.get("record_type"),
+            generated_by: row.get("generated_by"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    fn row_to_sequence(&self, row: sqlx::sqlite::SqliteRow) -> LabelSequence {
+        LabelSequence {
+            name: row.get("name"),
+            prefix: row.get("prefix"),
+            width: row.get("width"),
+            alphabet: row.get("alphabet"),
+            check_digit: row.get("check_digit"),
+            unchecked_until: row.get("unchecked_until"),
+            current_value: row.get("current_value"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_sequence_postgres(&self, row: sqlx::postgres::PgRow) -> LabelSequence {
+        LabelSequence {
+            name: row.get("name"),
+            prefix: row.get("prefix"),
+            width: row.get("width"),
+            alphabet: row.get("alphabet"),
+            check_digit: row.get("check_digit"),
+            unchecked_until: row.get("unchecked_until"),
+            current_value: row.get("current_value"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_label(&self, row: sqlx::sqlite::SqliteRow) -> LabelRecord {
+        LabelRecord {
+            id: row.get("id"),
+            status: LabelStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(LabelStatus::Generated),
+            batch_id: row.get("batch_id"),
+            generated_by: row.get("generated_by"),
+            item_id: row
+                .get::<Option<String>, _>("item_id")
+                .and_then(|id| id.parse::<Uuid>().ok()),
+            container_id: row.get("container_id"),
+            generated_at: row.get("generated_at"),
+            printed_at: row.get("printed_at"),
+            attached_at: row.get("attached_at"),
+            voided_at: row.get("voided_at"),
+            voided_by: row.get("voided_by"),
+            void_reason: row.get("void_reason"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_label_postgres(&self, row: sqlx::postgres::PgRow) -> LabelRecord {
+        LabelRecord {
+            id: row.get("id"),
+            status: LabelStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(LabelStatus::Generated),
+            batch_id: row.get("batch_id"),
+            generated_by: row.get("generated_by"),
+            item_id: row.get("item_id"),
+            container_id: row.get("container_id"),
+            generated_at: row.get("generated_at"),
+            printed_at: row.get("printed_at"),
+            attached_at: row.get("attached_at"),
+            voided_at: row.get("voided_at"),
+            voided_by: row.get("voided_by"),
+            void_reason: row.get("void_reason"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+/// 採番ルールの現在値の次から `quantity` 個のラベルIDを割り当てる
+fn allocate(sequence: &LabelSequence, current_value: i64, quantity: u32) -> AppResult<Vec<String>> {
+    if current_value + quantity as i64 > label_scheme::capacity(sequence) {
+        return Err(AppError::BadRequest(format!(
+            "Not enough label IDs available in sequence '{}'",
+            sequence.name
+        )));
+    }
+    Ok((1..=quantity as i64)
+        .map(|i| label_scheme::format_label(sequence, current_value + i))
+        .collect())
+}
@@ -0,0 +1,363 @@
+//! ラベルシート（A4の台紙に N×M 面）のPDF生成。
+//! 外部のPDFライブラリは使わず、矩形とテキストだけの小さなPDFを直接書き出す。
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::services::label_codes::{self, BARCODE_QUIET_ZONE, QR_QUIET_ZONE};
+
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// 台紙のレイアウト（単位はmm）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetLayout {
+    pub page_width: f64,
+    pub page_height: f64,
+    pub columns: u32,
+    pub rows: u32,
+    pub cell_width: f64,
+    pub cell_height: f64,
+    pub margin_top: f64,
+    pub margin_left: f64,
+    #[serde(default)]
+    pub gap_x: f64,
+    #[serde(default)]
+    pub gap_y: f64,
+}
+
+impl SheetLayout {
+    /// 市販のラベル用紙に合わせたプリセット
+    pub fn preset(name: &str) -> Option<Self> {
+        let (columns, rows, cell_width, cell_height, margin_top, margin_left, gap_x) = match name {
+            // 12面（86.4×42.3mm）
+            "a4_12" => (2, 6, 86.4, 42.3, 21.6, 18.6, 0.0),
+            // 24面 余白なし（70×37.125mm, A-One 72224 など）
+            "a4_24" => (3, 8, 70.0, 37.125, 0.0, 0.0, 0.0),
+            // 44面（48.3×25.4mm）
+            "a4_44" => (4, 11, 48.3, 25.4, 8.8, 8.4, 0.0),
+            // 65面（38.1×21.2mm）
+            "a4_65" => (5, 13, 38.1, 21.2, 10.7, 4.75, 2.5),
+            _ => return None,
+        };
+        Some(Self {
+            page_width: 210.0,
+            page_height: 297.0,
+            columns,
+            rows,
+            cell_width,
+            cell_height,
+            margin_top,
+            margin_left,
+            gap_x,
+            gap_y: 0.0,
+        })
+    }
+
+    pub fn preset_names() -> &'static [&'static str] {
+        &["a4_12", "a4_24", "a4_44", "a4_65"]
+    }
+
+    pub fn cells_per_page(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
+        let fits_x = self.margin_left
+            + self.cell_width * self.columns as f64
+            + self.gap_x * self.columns.saturating_sub(1) as f64;
+        let fits_y = self.margin_top
+            + self.cell_height * self.rows as f64
+            + self.gap_y * self.rows.saturating_sub(1) as f64;
+
+        if self.columns == 0 || self.rows == 0 || self.columns * self.rows > 500 {
+            return Err(AppError::BadRequest(
+                "Layout must have between 1 and 500 cells".to_string(),
+            ));
+        }
+        if self.cell_width < 10.0 || self.cell_height < 5.0 {
+            return Err(AppError::BadRequest("Label cells are too small".to_string()));
+        }
+        if fits_x > self.page_width + 0.01 || fits_y > self.page_height + 0.01 {
+            return Err(AppError::BadRequest(
+                "Label cells do not fit on the page".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 1面分の内容
+pub struct SheetLabel {
+    pub id: String,
+    /// "qr" / "barcode" / "nothing"
+    pub code_type: String,
+    pub name: Option<String>,
+}
+
+/// ラベルを左上から順に並べたPDFを返す。`skip` 面分は使用済みとして空ける
+pub fn render_sheet_pdf(
+    layout: &SheetLayout,
+    labels: &[SheetLabel],
+    skip: usize,
+) -> AppResult<Vec<u8>> {
+    layout.validate()?;
+
+    let per_page = layout.cells_per_page();
+    let slots = skip + labels.len();
+    let page_count = slots.div_ceil(per_page).max(1);
+
+    let mut pages = vec![String::new(); page_count];
+    for (index, label) in labels.iter().enumerate() {
+        let slot = skip + index;
+        let cell = slot % per_page;
+        let column = (cell % layout.columns as usize) as f64;
+        let row = (cell / layout.columns as usize) as f64;
+
+        let x = layout.margin_left + column * (layout.cell_width + layout.gap_x);
+        let top = layout.margin_top + row * (layout.cell_height + layout.gap_y);
+        draw_label(
+            &mut pages[slot / per_page],
+            label,
+            x * PT_PER_MM,
+            (layout.page_height - top - layout.cell_height) * PT_PER_MM,
+            layout.cell_width * PT_PER_MM,
+            layout.cell_height * PT_PER_MM,
+        )?;
+    }
+
+    Ok(write_pdf(
+        layout.page_width * PT_PER_MM,
+        layout.page_height * PT_PER_MM,
+        &pages,
+    ))
+}
+
+/// 1面を描く。座標はPDFの単位（pt、左下原点）
+fn draw_label(
+    out: &mut String,
+    label: &SheetLabel,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) -> AppResult<()> {
+    let padding = (height * 0.08).min(4.0 * PT_PER_MM);
+    let inner_height = height - padding * 2.0;
+
+    match label.code_type.as_str() {
+        "qr" => {
+            let matrix = label_codes::qr_matrix(&label.id)?;
+            let total = (matrix.width + QR_QUIET_ZONE * 2) as f64;
+            let module = inner_height / total;
+            for row in 0..matrix.width {
+                let mut column = 0;
+                while column < matrix.width {
+                    if !matrix.is_dark(column, row) {
+                        column += 1;
+                        continue;
+                    }
+                    let start = column;
+                    while column < matrix.width && matrix.is_dark(column, row) {
+                        column += 1;
+                    }
+                    out.push_str(&format!(
+                        "{:.2} {:.2} {:.2} {:.2} re\n",
+                        x + padding + (start + QR_QUIET_ZONE) as f64 * module,
+                        y + padding + inner_height - (row + QR_QUIET_ZONE + 1) as f64 * module,
+                        (column - start) as f64 * module,
+                        module
+                    ));
+                }
+            }
+            out.push_str("f\n");
+
+            let text_x = x + padding + inner_height;
+            let text_width = width - (text_x - x) - padding;
+            draw_texts(out, label, text_x, y + padding, text_width, inner_height);
+        }
+        "barcode" => {
+            let bars = label_codes::code128_bars(&label.id)?;
+            let total = (bars.len() + BARCODE_QUIET_ZONE * 2) as f64;
+            let module = (width - padding * 2.0) / total;
+            let bar_height = inner_height * 0.6;
+            let bar_y = y + padding + inner_height - bar_height;
+
+            let mut column = 0;
+            while column < bars.len() {
+                if bars[column] != 1 {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < bars.len() && bars[column] == 1 {
+                    column += 1;
+                }
+                out.push_str(&format!(
+                    "{:.2} {:.2} {:.2} {:.2} re\n",
+                    x + padding + (start + BARCODE_QUIET_ZONE) as f64 * module,
+                    bar_y,
+                    (column - start) as f64 * module,
+                    bar_height
+                ));
+            }
+            out.push_str("f\n");
+
+            draw_texts(
+                out,
+                label,
+                x + padding,
+                y + padding,
+                width - padding * 2.0,
+                inner_height - bar_height,
+            );
+        }
+        _ => draw_texts(
+            out,
+            label,
+            x + padding,
+            y + padding,
+            width - padding * 2.0,
+            inner_height,
+        ),
+    }
+    Ok(())
+}
+
+/// ラベルID（と物品名）を領域の上から順に書く
+fn draw_texts(out: &mut String, label: &SheetLabel, x: f64, y: f64, width: f64, height: f64) {
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    let lines = if label.name.is_some() { 2.0 } else { 1.0 };
+    let id_size = (height / (lines + 0.5)).min(14.0);
+    let id_text = fit_text(&label.id, width, id_size);
+    out.push_str(&format!(
+        "BT /F1 {:.2} Tf {:.2} {:.2} Td <{}> Tj ET\n",
+        id_size,
+        x,
+        y + height - id_size,
+        hex_latin(&id_text)
+    ));
+
+    if let Some(name) = &label.name {
+        let name_size = (id_size * 0.7).min(9.0);
+        let name_text = fit_text(name, width, name_size);
+        out.push_str(&format!(
+            "BT /F2 {:.2} Tf {:.2} {:.2} Td <{}> Tj ET\n",
+            name_size,
+            x,
+            y + height - id_size - name_size * 1.4,
+            hex_utf16(&name_text)
+        ));
+    }
+}
+
+/// 幅に収まるように末尾を「…」で切り詰める。全角は1文字、半角は0.6文字分として概算する
+fn fit_text(text: &str, width: f64, size: f64) -> String {
+    let char_width = |c: char| if c.is_ascii() { 0.6 } else { 1.0 };
+    let max = width / size;
+
+    let total: f64 = text.chars().map(char_width).sum();
+    if total <= max {
+        return text.to_string();
+    }
+
+    let mut used = 1.0;
+    let mut fitted = String::new();
+    for c in text.chars() {
+        used += char_width(c);
+        if used > max {
+            break;
+        }
+        fitted.push(c);
+    }
+    fitted.push('…');
+    fitted
+}
+
+/// Helvetica（WinAnsi）で書ける文字列に変換する
+fn hex_latin(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '…' => 0x85,
+            c if c.is_ascii() && !c.is_ascii_control() => c as u8,
+            _ => b'?',
+        })
+        .map(|byte| format!("{:02X}", byte))
+        .collect()
+}
+
+/// 日本語フォント（UniJIS-UCS2-H）用にUTF-16BEへ変換する。BMP外の文字は「?」にする
+fn hex_utf16(text: &str) -> String {
+    text.chars()
+        .map(|c| if (c as u32) <= 0xFFFF { c as u32 } else { '?' as u32 })
+        .map(|code| format!("{:04X}", code))
+        .collect()
+}
+
+/// ページごとの描画命令からPDFを組み立てる。
+/// 日本語はフォントを埋め込まず、ビューアの標準日本語フォント（HeiseiKakuGo-W5）を使う
+fn write_pdf(page_width: f64, page_height: f64, pages: &[String]) -> Vec<u8> {
+    // 1: Catalog, 2: Pages, 3: Helvetica, 4-5: 日本語フォント, 6以降: ページと内容
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 6 + i * 2).collect();
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type0 /BaseFont /HeiseiKakuGo-W5 /Encoding /UniJIS-UCS2-H /DescendantFonts [5 0 R] >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /CIDFontType0 /BaseFont /HeiseiKakuGo-W5 /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 2 >> /FontDescriptor << /Type /FontDescriptor /FontName /HeiseiKakuGo-W5 /Flags 4 /FontBBox [-92 -250 1010 922] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 737 /StemV 93 >> >>"
+            .to_vec(),
+    ];
+    for (index, content) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                page_width,
+                page_height,
+                page_ids[index] + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content.as_bytes());
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, body) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
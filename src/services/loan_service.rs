@@ -1,599 +1,313 @@
 use crate::db::DatabasePool;
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateLoanRequest, Loan, LoanWithItem, LoansListResponse, ReturnLoanRequest};
-use chrono::Utc;
-use sqlx::Row;
+use crate::config::LoanConfig;
+use crate::models::{
+    AuditAction, AuditEntity, BatchLoanItemResult, BatchLoanRequest, BatchLoanResponse,
+    BatchReturnRequest, CreateLoanRequest, CurrentUser, Loan, LoanFilters, LoansListResponse,
+    OverdueLoanGroup, OverdueLoansResponse, ReturnLoanRequest,
+};
+use crate::repositories::{
+    item_repository, loan_repository, ItemRepository, LoanRepository, NewLoan, Page,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub struct LoanService {
-    db: DatabasePool,
+    repo: Arc<dyn LoanRepository>,
+    items: Arc<dyn ItemRepository>,
+    audit: AuditService,
+    config: LoanConfig,
 }
 
 impl LoanService {
-    pub fn new(db: DatabasePool) -> Self {
-        Self { db }
+    pub fn new(db: DatabasePool, config: LoanConfig) -> Self {
+        Self {
+            repo: loan_repository(&db),
+            items: item_repository(&db),
+            audit: AuditService::new(db),
+            config,
+        }
     }
 
-    pub async fn create_loan(&self, req: CreateLoanRequest) -> AppResult<Loan> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // まず、物品が存在し、貸出可能かチェック
-                let item_row = sqlx::query(
-                    "SELECT id, name, is_on_loan, is_disposed FROM items WHERE id = $1",
-                )
-                .bind(req.item_id)
-                .fetch_optional(pool)
-                .await?;
-
-                let item_row = item_row.ok_or_else(|| {
-                    AppError::NotFound(format!("Item with id {} not found", req.item_id))
-                })?;
-
-                let is_on_loan: Option<bool> = item_row.try_get("is_on_loan").unwrap_or(None);
-                let is_disposed: Option<bool> = item_row.try_get("is_disposed").unwrap_or(None);
-
-                if is_on_loan.unwrap_or(false) {
-                    return Err(AppError::BadRequest("Item is already on loan".to_string()));
-                }
-
-                if is_disposed.unwrap_or(false) {
-                    return Err(AppError::BadRequest(
-                        "Item is disposed and cannot be loaned".to_string(),
-                    ));
-                }
-
-                // 貸出記録を作成
-                let result = sqlx::query(
-                    r#"
-                    INSERT INTO loans (
-                        item_id, student_number, student_name, organization, remarks
-                    ) VALUES ($1, $2, $3, $4, $5)
-                    RETURNING id
-                    "#,
-                )
-                .bind(req.item_id)
-                .bind(&req.student_number)
-                .bind(&req.student_name)
-                .bind(&req.organization)
-                .bind(&req.remarks)
-                .fetch_one(pool)
-                .await?;
-
-                let loan_id: i64 = result.get("id");
-
-                // 物品の貸出状態を更新
-                let now = Utc::now();
-                sqlx::query("UPDATE items SET is_on_loan = true, updated_at = $2 WHERE id = $1")
-                    .bind(req.item_id)
-                    .bind(now)
-                    .execute(pool)
-                    .await?;
-
-                self.get_loan(loan_id).await
+    /// 返却期限の指定がなければ、標準の貸出期間から決める
+    fn resolve_due_date(
+        &self,
+        due_date: Option<DateTime<Utc>>,
+        loan_date: DateTime<Utc>,
+    ) -> AppResult<Option<DateTime<Utc>>> {
+        match due_date {
+            Some(due) if due <= loan_date => Err(AppError::BadRequest(
+                "due_date must be after the loan date".to_string(),
+            )),
+            Some(due) => Ok(Some(due)),
+            None if self.config.default_period_days > 0 => {
+                Ok(Some(loan_date + Duration::days(self.config.default_period_days)))
             }
-            DatabasePool::Sqlite(pool) => {
-                // まず、物品が存在し、貸出可能かチェック
-                let item_row = sqlx::query(
-                    "SELECT id, name, is_on_loan, is_disposed FROM items WHERE id = ?1",
-                )
-                .bind(req.item_id.to_string())
-                .fetch_optional(pool)
-                .await?;
-
-                let item_row = item_row.ok_or_else(|| {
-                    AppError::NotFound(format!("Item with id {} not found", req.item_id))
-                })?;
-
-                let is_on_loan: Option<bool> = item_row.try_get("is_on_loan").unwrap_or(None);
-                let is_disposed: Option<bool> = item_row.try_get("is_disposed").unwrap_or(None);
-
-                if is_on_loan.unwrap_or(false) {
-                    return Err(AppError::BadRequest("Item is already on loan".to_string()));
-                }
+            None => Ok(None),
+        }
+    }
 
-                if is_disposed.unwrap_or(false) {
-                    return Err(AppError::BadRequest(
-                        "Item is disposed and cannot be loaned".to_string(),
-                    ));
-                }
+    pub async fn create_loan(
+        &self,
+        req: CreateLoanRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Loan> {
+        self.insert_loan(req, None, actor).await
+    }
 
-                // 貸出記録を作成
-                let item_id_str = req.item_id.to_string();
-                let result = sqlx::query(
-                    r#"
-                    INSERT INTO loans (
-                        item_id, student_number, student_name, organization, remarks
-                    ) VALUES (?1, ?2, ?3, ?4, ?5)
-                    "#
-                )
-                .bind(&item_id_str)
-                .bind(req.student_number)
-                .bind(req.student_name)
-                .bind(req.organization)
-                .bind(req.remarks)
-                .execute(pool)
-                .await?;
+    /// 予約の受け取りとして貸し出す。その予約自身は重複チェックの対象から外す
+    pub async fn create_loan_for_reservation(
+        &self,
+        req: CreateLoanRequest,
+        reservation_id: i64,
+        actor: &CurrentUser,
+    ) -> AppResult<Loan> {
+        self.insert_loan(req, Some(reservation_id), actor).await
+    }
 
-                // 物品の貸出状態を更新
-                let now = Utc::now();
-                sqlx::query(
-                    "UPDATE items SET is_on_loan = 1, updated_at = ?2 WHERE id = ?1"
-                )
-                .bind(&item_id_str)
-                .bind(now)
-                .execute(pool)
-                .await?;
+    async fn insert_loan(
+        &self,
+        req: CreateLoanRequest,
+        reservation_id: Option<i64>,
+        actor: &CurrentUser,
+    ) -> AppResult<Loan> {
+        let now = Utc::now();
+        let due_date = self.resolve_due_date(req.due_date, now)?;
+
+        let new_loan = NewLoan {
+            student_number: req.student_number,
+            student_name: req.student_name,
+            organization: req.organization,
+            remarks: req.remarks,
+            loan_date: now,
+            due_date,
+        };
+        let Some(loan) = self
+            .repo
+            .insert(req.item_id, &new_loan, reservation_id, actor)
+            .await?
+        else {
+            return Err(self.unavailable_item_error(req.item_id).await);
+        };
+        Ok(loan)
+    }
 
-                let loan_id = result.last_insert_rowid();
-                self.get_loan(loan_id).await
+    /// 貸出状態の更新に失敗した物品について、貸し出せない理由をエラーにする
+    async fn unavailable_item_error(&self, item_id: Uuid) -> AppError {
+        match self.items.find(item_id).await {
+            Ok(None) => AppError::NotFound(format!("Item with id {} not found", item_id)),
+            Ok(Some(item)) if item.is_on_loan != Some(true) && item.is_disposed == Some(true) => {
+                AppError::BadRequest("Item is disposed and cannot be loaned".to_string())
             }
+            // 確認までの間に状態が変わった場合も、他の貸出と競合したものとして扱う
+            Ok(Some(_)) => AppError::Conflict("Item is already on loan".to_string()),
+            Err(e) => e,
         }
     }
 
     pub async fn get_loan(&self, id: i64) -> AppResult<Loan> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, item_id, student_number, student_name, organization,
-                        loan_date, return_date, remarks, created_at, updated_at
-                    FROM loans
-                    WHERE id = $1
-                    "#,
-                )
-                .bind(id)
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Loan with id {} not found", id)))?;
-
-                Ok(self.row_to_loan_postgres(row))
-            }
-            DatabasePool::Sqlite(pool) => {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        id, item_id, student_number, student_name, organization,
-                        loan_date, return_date, remarks, created_at, updated_at
-                    FROM loans
-                    WHERE id = ?1
-                    "#,
-                )
-                .bind(id)
-                .fetch_optional(pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Loan with id {} not found", id)))?;
-
-                Ok(self.row_to_loan(row))
-            }
-        }
+        self.repo
+            .find(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Loan with id {} not found", id)))
     }
 
-   pub async fn get_active_loan_for_item(&self, item_id: &str) -> AppResult<Option<Loan>> {
-       let item_uuid = match Uuid::parse_str(item_id) {
-           Ok(uuid) => uuid,
-           Err(_) => return Err(AppError::BadRequest("Invalid item ID format".to_string())),
-       };
-
-       match &self.db {
-           DatabasePool::Postgres(pool) => {
-               let row = sqlx::query(
-                   r#"
-                   SELECT
-                       id, item_id, student_number, student_name, organization,
-                       loan_date, return_date, remarks, created_at, updated_at
-                   FROM loans
-                   WHERE item_id = $1 AND return_date IS NULL
-                   ORDER BY loan_date DESC
-                   LIMIT 1
-                   "#,
-               )
-               .bind(item_uuid)
-               .fetch_optional(pool)
-               .await?;
-
-               Ok(row.map(|r| self.row_to_loan_postgres(r)))
-           }
-           DatabasePool::Sqlite(pool) => {
-               let row = sqlx::query(
-                   r#"
-                   SELECT
-                       id, item_id, student_number, student_name, organization,
-                       loan_date, return_date, remarks, created_at, updated_at
-                   FROM loans
-                   WHERE item_id = ?1 AND return_date IS NULL
-                   ORDER BY loan_date DESC
-                   LIMIT 1
-                   "#,
-               )
-               .bind(item_id)
-               .fetch_optional(pool)
-               .await?;
+    pub async fn get_active_loan_for_item(&self, item_id: &str) -> AppResult<Option<Loan>> {
+        let item_uuid = match Uuid::parse_str(item_id) {
+            Ok(uuid) => uuid,
+            Err(_) => return Err(AppError::BadRequest("Invalid item ID format".to_string())),
+        };
+        self.repo.find_active(item_uuid).await
+    }
 
-               Ok(row.map(|r| self.row_to_loan(r)))
-           }
-       }
-   }
+    /// 物品の貸出記録を古い順に全件返す
+    pub async fn list_loans_for_item(&self, item_id: Uuid) -> AppResult<Vec<Loan>> {
+        self.repo.list_for_item(item_id).await
+    }
 
     pub async fn list_loans(
         &self,
         page: u32,
         per_page: u32,
-        item_id: Option<i64>,
-        student_number: Option<String>,
-        active_only: Option<bool>,
+        filters: &LoanFilters,
     ) -> AppResult<LoansListResponse> {
-        let offset = ((page - 1) * per_page) as i64;
-        let limit = per_page as i64;
-
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // Build dynamic WHERE query for PostgreSQL
-                let mut where_conditions = Vec::new();
-                let mut param_index = 1;
-
-                if item_id.is_some() {
-                    where_conditions.push(format!("l.item_id = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if student_number.is_some() {
-                    where_conditions.push(format!("l.student_number = ${}", param_index));
-                    param_index += 1;
-                }
-
-                if let Some(active) = active_only {
-                    if active {
-                        where_conditions.push("l.return_date IS NULL".to_string());
-                    } else {
-                        where_conditions.push("l.return_date IS NOT NULL".to_string());
-                    }
-                }
-
-                let where_clause = if where_conditions.is_empty() {
-                    String::new()
-                } else {
-                    format!("WHERE {}", where_conditions.join(" AND "))
-                };
-
-                let query_str = format!(
-                    r#"
-                    SELECT
-                        l.id, l.item_id, l.student_number, l.student_name, l.organization,
-                        l.loan_date, l.return_date, l.remarks, l.created_at, l.updated_at,
-                        i.name as item_name, i.label_id as item_label_id
-                    FROM loans l
-                    INNER JOIN items i ON l.item_id = i.id
-                    {}
-                    ORDER BY l.created_at DESC
-                    LIMIT ${} OFFSET ${}
-                    "#,
-                    where_clause,
-                    param_index,
-                    param_index + 1
-                );
-
-                let count_query_str = format!(
-                    "SELECT COUNT(*) as count FROM loans l INNER JOIN items i ON l.item_id = i.id {}",
-                    where_clause
-                );
-
-                let mut query = sqlx::query(&query_str);
-                let mut count_query = sqlx::query(&count_query_str);
-
-                if let Some(item_id_val) = item_id {
-                    query = query.bind(item_id_val);
-                    count_query = count_query.bind(item_id_val);
-                }
-
-                if let Some(student_number_val) = &student_number {
-                    query = query.bind(student_number_val);
-                    count_query = count_query.bind(student_number_val);
-                }
-
-                query = query.bind(limit).bind(offset);
-
-                let rows = query.fetch_all(pool).await?;
-                let loans: Vec<LoanWithItem> = rows
-                    .into_iter()
-                    .map(|row| self.row_to_loan_with_item_postgres(row))
-                    .collect();
-
-                let count_row = count_query.fetch_one(pool).await?;
-                let total: i64 = count_row.get("count");
-
-                Ok(LoansListResponse {
-                    loans,
-                    total,
-                    page,
-                    per_page,
-                })
-            }
-            DatabasePool::Sqlite(pool) => {
-                // フィルタリング機能を実装
-                let (loans, total) = if item_id.is_none()
-                    && student_number.is_none()
-                    && active_only.is_none()
-                {
-                    // フィルターなし
-                    let rows = sqlx::query(
-                        r#"
-                        SELECT
-                            l.id, l.item_id, l.student_number, l.student_name, l.organization,
-                            l.loan_date, l.return_date, l.remarks, l.created_at, l.updated_at,
-                            i.name as item_name, i.label_id as item_label_id
-                        FROM loans l
-                        INNER JOIN items i ON l.item_id = i.id
-                        ORDER BY l.created_at DESC
-                        LIMIT ?1 OFFSET ?2
-                        "#,
-                    )
-                    .bind(limit)
-                    .bind(offset)
-                    .fetch_all(pool)
-                    .await?;
-
-                    let loans: Vec<LoanWithItem> = rows
-                        .into_iter()
-                        .map(|row| self.row_to_loan_with_item(row))
-                        .collect();
-
-                    let count_row = sqlx::query("SELECT COUNT(*) as count FROM loans")
-                        .fetch_one(pool)
-                        .await?;
-                    let total: i64 = count_row.get("count");
-
-                    (loans, total)
-                } else {
-                    // フィルターあり - 動的クエリを構築
-                    let mut where_conditions = Vec::new();
-
-                    // 物品IDフィルター
-                    if item_id.is_some() {
-                        where_conditions.push("l.item_id = ?".to_string());
-                    }
-
-                    // 学籍番号フィルター
-                    if student_number.is_some() {
-                        where_conditions.push("l.student_number = ?".to_string());
-                    }
-
-                    // アクティブ貸出のみフィルター
-                    if let Some(true) = active_only {
-                        where_conditions.push("l.return_date IS NULL".to_string());
-                    } else if let Some(false) = active_only {
-                        where_conditions.push("l.return_date IS NOT NULL".to_string());
-                    }
-
-                    let where_clause = if where_conditions.is_empty() {
-                        String::new()
-                    } else {
-                        format!("WHERE {}", where_conditions.join(" AND "))
-                    };
-
-                    let query_str = format!(
-                        r#"
-                        SELECT
-                            l.id, l.item_id, l.student_number, l.student_name, l.organization,
-                            l.loan_date, l.return_date, l.remarks, l.created_at, l.updated_at,
-                            i.name as item_name, i.label_id as item_label_id
-                        FROM loans l
-                        INNER JOIN items i ON l.item_id = i.id
-                        {}
-                        ORDER BY l.created_at DESC
-                        LIMIT ? OFFSET ?
-                        "#,
-                        where_clause
-                    );
-
-                    let count_query_str = format!(
-                        "SELECT COUNT(*) as count FROM loans l INNER JOIN items i ON l.item_id = i.id {}",
-                        where_clause
-                    );
-
-                    // パラメーターをバインド
-                    let mut query = sqlx::query(&query_str);
-                    let mut count_query = sqlx::query(&count_query_str);
-
-                    // 物品IDフィルター
-                    if let Some(id) = item_id {
-                        query = query.bind(id);
-                        count_query = count_query.bind(id);
-                    }
-
-                    // 学籍番号フィルター
-                    if let Some(ref number) = student_number {
-                        query = query.bind(number);
-                        count_query = count_query.bind(number);
-                    }
-
-                    // LIMIT/OFFSETをバインド（active_onlyは既にWHERE句に含まれている）
-                    query = query.bind(limit).bind(offset);
-
-                    let rows = query.fetch_all(pool).await?;
-                    let loans: Vec<LoanWithItem> = rows
-                        .into_iter()
-                        .map(|row| self.row_to_loan_with_item(row))
-                        .collect();
-
-                    let count_row = count_query.fetch_one(pool).await?;
-                    let total: i64 = count_row.get("count");
-
-                    (loans, total)
-                };
-
-                Ok(LoansListResponse {
-                    loans,
-                    total,
-                    page,
-                    per_page,
-                })
-            }
-        }
+        let (loans, total) = self
+            .repo
+            .list(filters, Utc::now(), Page::new(page, per_page))
+            .await?;
+        Ok(LoansListResponse {
+            loans,
+            total,
+            page,
+            per_page,
+        })
     }
 
-    pub async fn return_loan(&self, id: i64, req: ReturnLoanRequest) -> AppResult<Loan> {
-        match &self.db {
-            DatabasePool::Postgres(pool) => {
-                // 貸出記録が存在し、未返却かチェック
-                let loan_row =
-                    sqlx::query("SELECT id, item_id, return_date FROM loans WHERE id = $1")
-                        .bind(id)
-                        .fetch_optional(pool)
-                        .await?;
-
-                let loan_row = loan_row
-                    .ok_or_else(|| AppError::NotFound(format!("Loan with id {} not found", id)))?;
-
-                let return_date_check: Option<chrono::DateTime<chrono::Utc>> =
-                    loan_row.try_get("return_date").unwrap_or(None);
-                if return_date_check.is_some() {
-                    return Err(AppError::BadRequest(
-                        "Loan has already been returned".to_string(),
-                    ));
-                }
-
-                let item_id: Uuid = loan_row.get("item_id");
-                let return_date = req.return_date.unwrap_or_else(chrono::Utc::now);
-                let now = chrono::Utc::now();
-
-                // 貸出記録を更新
-                sqlx::query(
-                    "UPDATE loans SET return_date = $1, remarks = $2, updated_at = $3 WHERE id = $4"
-                )
-                .bind(return_date)
-                .bind(&req.remarks)
-                .bind(now)
-                .bind(id)
-                .execute(pool)
-                .await?;
-
-                // 物品の貸出状態を更新
-                sqlx::query("UPDATE items SET is_on_loan = false, updated_at = $1 WHERE id = $2")
-                    .bind(now)
-                    .bind(item_id)
-                    .execute(pool)
-                    .await?;
-
-                // 更新された貸出記録を取得
-                self.get_loan(id).await
+    /// 延滞中の貸出を、借り手（学籍番号・団体）ごとにまとめて返す
+    pub async fn list_overdue_loans(&self) -> AppResult<OverdueLoansResponse> {
+        let loans = self.repo.list_overdue(Utc::now()).await?;
+        let total = loans.len() as i64;
+
+        // 返却期限が古い順に並んでいるので、最も延滞している借り手のグループが先頭に来る
+        let mut groups: Vec<OverdueLoanGroup> = Vec::new();
+        for loan in loans {
+            match groups.iter_mut().find(|group| {
+                group.student_number == loan.student_number
+                    && group.organization == loan.organization
+            }) {
+                Some(group) => group.loans.push(loan),
+                None => groups.push(OverdueLoanGroup {
+                    student_number: loan.student_number.clone(),
+                    student_name: loan.student_name.clone(),
+                    organization: loan.organization.clone(),
+                    loans: vec![loan],
+                }),
             }
-            DatabasePool::Sqlite(pool) => {
-                // 貸出記録が存在し、未返却かチェック
-                let loan_row =
-                    sqlx::query("SELECT id, item_id, return_date FROM loans WHERE id = ?1")
-                        .bind(id)
-                        .fetch_optional(pool)
-                        .await?;
-
-                let loan_row = loan_row
-                    .ok_or_else(|| AppError::NotFound(format!("Loan with id {} not found", id)))?;
-
-                let return_date_check: Option<chrono::NaiveDateTime> =
-                    loan_row.try_get("return_date").unwrap_or(None);
-                if return_date_check.is_some() {
-                    return Err(AppError::BadRequest(
-                        "Loan has already been returned".to_string(),
-                    ));
-                }
+        }
 
-                let return_date = req.return_date.unwrap_or_else(Utc::now);
-                let now = Utc::now();
+        Ok(OverdueLoansResponse { groups, total })
+    }
 
-                // 貸出記録を更新
-                sqlx::query(
-                    "UPDATE loans SET return_date = ?2, remarks = ?3, updated_at = ?4 WHERE id = ?1"
-                )
-                .bind(id)
-                .bind(return_date)
-                .bind(req.remarks)
-                .bind(now)
-                .execute(pool)
-                .await?;
+    pub async fn return_loan(
+        &self,
+        id: i64,
+        req: ReturnLoanRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Loan> {
+        let before = self.get_loan(id).await?;
+        if before.return_date.is_some() {
+            return Err(AppError::Conflict(
+                "Loan has already been returned".to_string(),
+            ));
+        }
 
-                let item_id_str: String = loan_row.try_get("item_id").map_err(|_| {
-                    AppError::InternalServerError("Loan record missing item_id".to_string())
-                })?;
-                let item_id = item_id_str.parse::<Uuid>().map_err(|_| {
-                    AppError::InternalServerError("Invalid item_id format".to_string())
-                })?;
+        let now = Utc::now();
+        let return_date = req.return_date.unwrap_or(now);
+
+        // 未返却の場合だけ更新し、二重に返却されないようにする
+        let loan = self
+            .repo
+            .mark_returned(id, return_date, req.remarks.as_deref(), now)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Loan has already been returned".to_string()))?;
+
+        self.audit
+            .record(
+                AuditEntity::Loan,
+                &loan.id.to_string(),
+                AuditAction::Return,
+                actor,
+                snapshot(&before),
+                snapshot(&loan),
+            )
+            .await?;
+        Ok(loan)
+    }
 
-                // 物品の貸出状態を更新
-                let item_id_str = item_id.to_string();
-                sqlx::query(
-                    "UPDATE items SET is_on_loan = 0, updated_at = ?2 WHERE id = ?1"
-                )
-                .bind(&item_id_str)
-                .bind(now)
-                .execute(pool)
-                .await?;
+    /// 複数の物品を1つのトランザクションでまとめて貸し出す。
+    /// 1件でも貸し出せない物品があれば何も反映せず、物品ごとの結果だけを返す
+    pub async fn create_loans_batch(
+        &self,
+        req: BatchLoanRequest,
+        labels: &HashMap<String, Uuid>,
+        actor: &CurrentUser,
+    ) -> AppResult<BatchLoanResponse> {
+        let now = Utc::now();
+        let due_date = self.resolve_due_date(req.due_date, now)?;
+        let mut results = self.resolve_batch_items(&req.item_ids, &req.label_ids, labels)?;
+
+        let loan = NewLoan {
+            student_number: req.student_number,
+            student_name: req.student_name,
+            organization: req.organization,
+            remarks: req.remarks,
+            loan_date: now,
+            due_date,
+        };
+        let committed = self.repo.insert_batch(&loan, &mut results, actor).await?;
+        Ok(finish_batch(results, committed))
+    }
 
-                self.get_loan(id).await
-            }
-        }
+    /// 複数の物品の貸出を1つのトランザクションでまとめて返却する。
+    /// 1件でも返却できない物品があれば何も反映せず、物品ごとの結果だけを返す
+    pub async fn return_loans_batch(
+        &self,
+        req: BatchReturnRequest,
+        labels: &HashMap<String, Uuid>,
+        actor: &CurrentUser,
+    ) -> AppResult<BatchLoanResponse> {
+        let now = Utc::now();
+        let return_date = req.return_date.unwrap_or(now);
+        let mut results = self.resolve_batch_items(&req.item_ids, &req.label_ids, labels)?;
+
+        let committed = self
+            .repo
+            .return_batch(return_date, req.remarks.as_deref(), now, &mut results, actor)
+            .await?;
+        Ok(finish_batch(results, committed))
     }
 
-    fn row_to_loan(&self, row: sqlx::sqlite::SqliteRow) -> Loan {
-        Loan {
-            id: row.get("id"),
-            item_id: row.get::<String, _>("item_id").parse::<Uuid>().unwrap_or_default(),
-            student_number: row.get("student_number"),
-            student_name: row.get("student_name"),
-            organization: row.get("organization"),
-            loan_date: row.get("loan_date"),
-            return_date: row.get("return_date"),
-            remarks: row.get("remarks"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+    /// 一括処理の対象を物品IDに解決する。見つからないラベルや重複指定はその物品のエラーとして残す
+    /// （`labels` は `ItemService::resolve_labels` で解決したもの）
+    fn resolve_batch_items(
+        &self,
+        item_ids: &[Uuid],
+        label_ids: &[String],
+        labels: &HashMap<String, Uuid>,
+    ) -> AppResult<Vec<BatchLoanItemResult>> {
+        if item_ids.is_empty() && label_ids.is_empty() {
+            return Err(AppError::BadRequest(
+                "item_ids or label_ids must not be empty".to_string(),
+            ));
         }
-    }
 
-    fn row_to_loan_with_item(&self, row: sqlx::sqlite::SqliteRow) -> LoanWithItem {
-        LoanWithItem {
-            id: row.get("id"),
-            item_id: row.get::<String, _>("item_id").parse::<Uuid>().unwrap_or_default(),
-            item_name: row.get("item_name"),
-            item_label_id: row.get("item_label_id"),
-            student_number: row.get("student_number"),
-            student_name: row.get("student_name"),
-            organization: row.get("organization"),
-            loan_date: row.get("loan_date"),
-            return_date: row.get("return_date"),
-            remarks: row.get("remarks"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+        let mut results: Vec<BatchLoanItemResult> = item_ids
+            .iter()
+            .map(|item_id| BatchLoanItemResult {
+                item_id: Some(*item_id),
+                label_id: None,
+                success: false,
+                loan: None,
+                error: None,
+            })
+            .collect();
+        for label_id in label_ids {
+            let item_id = labels.get(label_id).copied();
+            results.push(BatchLoanItemResult {
+                item_id,
+                label_id: Some(label_id.clone()),
+                success: false,
+                loan: None,
+                error: item_id
+                    .is_none()
+                    .then(|| format!("Item with label_id {} not found", label_id)),
+            });
         }
-    }
 
-    fn row_to_loan_postgres(&self, row: sqlx::postgres::PgRow) -> Loan {
-        Loan {
-            id: row.get("id"),
-            item_id: row.get("item_id"),
-            student_number: row.get("student_number"),
-            student_name: row.get("student_name"),
-            organization: row.get("organization"),
-            loan_date: row.get("loan_date"),
-            return_date: row.get("return_date"),
-            remarks: row.get("remarks"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+        let mut seen = HashSet::new();
+        for result in results.iter_mut() {
+            if let Some(item_id) = result.item_id {
+                if !seen.insert(item_id) && result.error.is_none() {
+                    result.error = Some("Item is specified more than once".to_string());
+                }
+            }
         }
+
+        Ok(results)
     }
+}
 
-    fn row_to_loan_with_item_postgres(&self, row: sqlx::postgres::PgRow) -> LoanWithItem {
-        LoanWithItem {
-            id: row.get("id"),
-            item_id: row.get("item_id"),
-            item_name: row.get("item_name"),
-            item_label_id: row.get("item_label_id"),
-            student_number: row.get("student_number"),
-            student_name: row.get("student_name"),
-            organization: row.get("organization"),
-            loan_date: row.get("loan_date"),
-            return_date: row.get("return_date"),
-            remarks: row.get("remarks"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+/// 一括処理の結果をまとめる。取り消された場合は問題のなかった物品にもその旨を記す
+fn finish_batch(mut results: Vec<BatchLoanItemResult>, committed: bool) -> BatchLoanResponse {
+    for result in results.iter_mut() {
+        if !committed && result.error.is_none() {
+            result.error =
+                Some("Not processed because another item in the batch failed".to_string());
         }
+        result.success = result.error.is_none();
     }
+    BatchLoanResponse { committed, results }
 }
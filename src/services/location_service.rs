@@ -0,0 +1,643 @@
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AuditAction, AuditEntity, CreateLocationRequest, CurrentUser, Location, LocationKind,
+    MergeLocationsRequest, MergeLocationsResponse, UpdateLocationRequest,
+};
+use crate::repositories::{insert_audit_postgres, insert_audit_sqlite};
+use crate::services::audit_service::{snapshot, AuditService};
+use chrono::Utc;
+use sqlx::Row;
+use std::collections::HashMap;
+
+/// 場所の名前をつなぐ区切り
+pub const PATH_SEPARATOR: &str = " > ";
+
+pub struct LocationService {
+    db: DatabasePool,
+    audit: AuditService,
+}
+
+impl LocationService {
+    pub fn new(db: DatabasePool) -> Self {
+        let audit = AuditService::new(db.clone());
+        Self { db, audit }
+    }
+
+    pub async fn list_locations(&self) -> AppResult<Vec<Location>> {
+        let query = r#"
+            SELECT id, name, kind, parent_id, created_at, updated_at
+            FROM locations
+        "#;
+        let mut locations = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                rows.into_iter()
+                    .map(|row| self.row_to_location_postgres(row))
+                    .collect::<Vec<_>>()
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                rows.into_iter()
+                    .map(|row| self.row_to_location(row))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let parents: HashMap<i64, (Option<i64>, String)> = locations
+            .iter()
+            .map(|l| (l.id, (l.parent_id, l.name.clone())))
+            .collect();
+        for location in &mut locations {
+            location.path = path_of(&parents, location.id);
+        }
+        locations.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(locations)
+    }
+
+    pub async fn get_location(&self, id: i64) -> AppResult<Location> {
+        self.list_locations()
+            .await?
+            .into_iter()
+            .find(|location| location.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("Location {} not found", id)))
+    }
+
+    pub async fn find_by_path(&self, path: &str) -> AppResult<Option<Location>> {
+        Ok(self
+            .list_locations()
+            .await?
+            .into_iter()
+            .find(|location| location.path == path))
+    }
+
+    /// 物品・コンテナの場所を決める。IDがなければ名前で探し、見つからなければ最上位に作る
+    pub async fn resolve(
+        &self,
+        location_id: Option<i64>,
+        name: Option<&str>,
+        actor: &CurrentUser,
+    ) -> AppResult<Option<Location>> {
+        if let Some(id) = location_id {
+            return self.get_location(id).await.map(Some);
+        }
+        let Some(name) = name.map(str::trim).filter(|name| !name.is_empty()) else {
+            return Ok(None);
+        };
+        if let Some(location) = self.find_by_path(name).await? {
+            return Ok(Some(location));
+        }
+
+        let req = CreateLocationRequest {
+            name: name.to_string(),
+            kind: LocationKind::Room,
+            parent_id: None,
+        };
+        self.create_location(req, actor).await.map(Some)
+    }
+
+    pub async fn create_location(
+        &self,
+        req: CreateLocationRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Location> {
+        let name = req.name.trim().to_string();
+        if name.is_empty() {
+            return Err(AppError::BadRequest("name must not be blank".to_string()));
+        }
+        if let Some(parent_id) = req.parent_id {
+            let parent = self.get_location(parent_id).await?;
+            ensure_kind_order(&parent, req.kind)?;
+        }
+
+        let now = Utc::now();
+        let result = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar::<_, i64>(
+                    r#"
+                    INSERT INTO locations (name, kind, parent_id, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $4)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&name)
+                .bind(req.kind.as_str())
+                .bind(req.parent_id)
+                .bind(now)
+                .fetch_one(pool)
+                .await
+            }
+            DatabasePool::Sqlite(pool) => sqlx::query(
+                r#"
+                INSERT INTO locations (name, kind, parent_id, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?4)
+                "#,
+            )
+            .bind(&name)
+            .bind(req.kind.as_str())
+            .bind(req.parent_id)
+            .bind(now)
+            .execute(pool)
+            .await
+            .map(|result| result.last_insert_rowid()),
+        };
+        let id = result.map_err(|e| duplicate_name(e, &name))?;
+
+        let location = self.get_location(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Location,
+                &location.id.to_string(),
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&location),
+            )
+            .await?;
+        Ok(location)
+    }
+
+    pub async fn update_location(
+        &self,
+        id: i64,
+        req: UpdateLocationRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Location> {
+        let locations = self.list_locations().await?;
+        let before = locations
+            .iter()
+            .find(|location| location.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Location {} not found", id)))?;
+
+        let name = match &req.name {
+            Some(name) if name.trim().is_empty() => {
+                return Err(AppError::BadRequest("name must not be blank".to_string()));
+            }
+            Some(name) => name.trim().to_string(),
+            None => before.name.clone(),
+        };
+        let kind = req.kind.unwrap_or(before.kind);
+        let parent_id = match req.parent_id {
+            Some(parent_id) => parent_id,
+            None => before.parent_id,
+        };
+
+        if let Some(parent_id) = parent_id {
+            let parents: HashMap<i64, (Option<i64>, String)> = locations
+                .iter()
+                .map(|l| (l.id, (l.parent_id, l.name.clone())))
+                .collect();
+            if chain_of(&parents, parent_id).contains(&id) {
+                return Err(AppError::BadRequest(format!(
+                    "Location {} cannot be placed inside itself or one of its own sub-locations",
+                    id
+                )));
+            }
+            let parent = locations
+                .iter()
+                .find(|location| location.id == parent_id)
+                .ok_or_else(|| AppError::NotFound(format!("Location {} not found", parent_id)))?;
+            ensure_kind_order(parent, kind)?;
+        }
+        for child in locations.iter().filter(|l| l.parent_id == Some(id)) {
+            ensure_kind_order(&Location { kind, ..before.clone() }, child.kind)?;
+        }
+
+        let now = Utc::now();
+        let result = match &self.db {
+            DatabasePool::Postgres(pool) => sqlx::query(
+                "UPDATE locations SET name = $1, kind = $2, parent_id = $3, updated_at = $4 WHERE id = $5",
+            )
+            .bind(&name)
+            .bind(kind.as_str())
+            .bind(parent_id)
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|_| ()),
+            DatabasePool::Sqlite(pool) => sqlx::query(
+                "UPDATE locations SET name = ?1, kind = ?2, parent_id = ?3, updated_at = ?4 WHERE id = ?5",
+            )
+            .bind(&name)
+            .bind(kind.as_str())
+            .bind(parent_id)
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|_| ()),
+        };
+        result.map_err(|e| duplicate_name(e, &name))?;
+
+        let location = self.get_location(id).await?;
+        if location.path != before.path {
+            self.sync_names(id).await?;
+        }
+        self.audit
+            .record(
+                AuditEntity::Location,
+                &location.id.to_string(),
+                AuditAction::Update,
+                actor,
+                snapshot(&before),
+                snapshot(&location),
+            )
+            .await?;
+        Ok(location)
+    }
+
+    /// 使われている場所や、下に場所がある場所は削除できない
+    pub async fn delete_location(&self, id: i64, actor: &CurrentUser) -> AppResult<()> {
+        let before = self.get_location(id).await?;
+        let (items, containers, children) = self.usage(id).await?;
+        if items + containers + children > 0 {
+            return Err(AppError::Conflict(format!(
+                "Location {} is still used by {} items, {} containers and {} locations",
+                id, items, containers, children
+            )));
+        }
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query("DELETE FROM locations WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query("DELETE FROM locations WHERE id = ?1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        self.audit
+            .record(
+                AuditEntity::Location,
+                &id.to_string(),
+                AuditAction::Delete,
+                actor,
+                snapshot(&before),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// 重複した場所をまとめる。物品・コンテナ・下の場所を `target_id` に付け替えてから削除する
+    pub async fn merge_locations(
+        &self,
+        req: MergeLocationsRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<MergeLocationsResponse> {
+        let mut source_ids = req.source_ids;
+        source_ids.sort_unstable();
+        source_ids.dedup();
+        if source_ids.is_empty() {
+            return Err(AppError::BadRequest("source_ids must not be empty".to_string()));
+        }
+        if source_ids.contains(&req.target_id) {
+            return Err(AppError::BadRequest(
+                "target_id must not be one of source_ids".to_string(),
+            ));
+        }
+
+        let locations = self.list_locations().await?;
+        let target = locations
+            .iter()
+            .find(|location| location.id == req.target_id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Location {} not found", req.target_id)))?;
+        let mut sources = Vec::with_capacity(source_ids.len());
+        for id in &source_ids {
+            let source = locations
+                .iter()
+                .find(|location| location.id == *id)
+                .cloned()
+                .ok_or_else(|| AppError::NotFound(format!("Location {} not found", id)))?;
+            sources.push(source);
+        }
+        let parents: HashMap<i64, (Option<i64>, String)> = locations
+            .iter()
+            .map(|l| (l.id, (l.parent_id, l.name.clone())))
+            .collect();
+        if chain_of(&parents, target.id)
+            .iter()
+            .any(|ancestor| source_ids.contains(ancestor))
+        {
+            return Err(AppError::BadRequest(
+                "Cannot merge a location into one of its own sub-locations".to_string(),
+            ));
+        }
+        for child in locations.iter().filter(|l| {
+            l.parent_id
+                .is_some_and(|parent| source_ids.contains(&parent))
+        }) {
+            ensure_kind_order(&target, child.kind)?;
+        }
+
+        // まとめた後の親子関係で、物品・コンテナに書く場所の文字列を先に決めておく
+        let mut merged = parents;
+        merged.retain(|id, _| !source_ids.contains(id));
+        for (parent, _) in merged.values_mut() {
+            if parent.is_some_and(|parent| source_ids.contains(&parent)) {
+                *parent = Some(target.id);
+            }
+        }
+        let paths = paths_under(&merged, target.id);
+
+        let now = Utc::now();
+        let (items_moved, containers_moved) = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                let items_moved = sqlx::query(
+                    "UPDATE items SET location_id = $1, updated_at = $2 WHERE location_id = ANY($3)",
+                )
+                .bind(target.id)
+                .bind(now)
+                .bind(&source_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                let containers_moved = sqlx::query(
+                    "UPDATE containers SET location_id = $1, updated_at = $2 WHERE location_id = ANY($3)",
+                )
+                .bind(target.id)
+                .bind(now)
+                .bind(&source_ids)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                sqlx::query(
+                    "UPDATE locations SET parent_id = $1, updated_at = $2 WHERE parent_id = ANY($3)",
+                )
+                .bind(target.id)
+                .bind(now)
+                .bind(&source_ids)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| duplicate_child(e, &target))?;
+                sqlx::query("DELETE FROM locations WHERE id = ANY($1)")
+                    .bind(&source_ids)
+                    .execute(&mut *tx)
+                    .await?;
+                write_paths_postgres(&mut tx, &paths).await?;
+                for source in &sources {
+                    insert_audit_postgres(
+                        &mut tx,
+                        AuditEntity::Location,
+                        &source.id.to_string(),
+                        AuditAction::Merge,
+                        actor,
+                        snapshot(source),
+                        None,
+                    )
+                    .await?;
+                }
+                tx.commit().await?;
+                (items_moved, containers_moved)
+            }
+            DatabasePool::Sqlite(pool) => {
+                let placeholders = source_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+                let mut tx = pool.begin().await?;
+                let mut moved = Vec::with_capacity(2);
+                for table in ["items", "containers"] {
+                    let query = format!(
+                        "UPDATE {} SET location_id = ?, updated_at = ? WHERE location_id IN ({})",
+                        table, placeholders
+                    );
+                    let mut query_builder = sqlx::query(&query).bind(target.id).bind(now);
+                    for id in &source_ids {
+                        query_builder = query_builder.bind(id);
+                    }
+                    moved.push(query_builder.execute(&mut *tx).await?.rows_affected());
+                }
+                let query = format!(
+                    "UPDATE locations SET parent_id = ?, updated_at = ? WHERE parent_id IN ({})",
+                    placeholders
+                );
+                let mut query_builder = sqlx::query(&query).bind(target.id).bind(now);
+                for id in &source_ids {
+                    query_builder = query_builder.bind(id);
+                }
+                query_builder
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| duplicate_child(e, &target))?;
+                let query = format!("DELETE FROM locations WHERE id IN ({})", placeholders);
+                let mut query_builder = sqlx::query(&query);
+                for id in &source_ids {
+                    query_builder = query_builder.bind(id);
+                }
+                query_builder.execute(&mut *tx).await?;
+                write_paths_sqlite(&mut tx, &paths).await?;
+                for source in &sources {
+                    insert_audit_sqlite(
+                        &mut tx,
+                        AuditEntity::Location,
+                        &source.id.to_string(),
+                        AuditAction::Merge,
+                        actor,
+                        snapshot(source),
+                        None,
+                    )
+                    .await?;
+                }
+                tx.commit().await?;
+                (moved[0], moved[1])
+            }
+        };
+
+        Ok(MergeLocationsResponse {
+            location: self.get_location(target.id).await?,
+            merged_ids: source_ids,
+            items_moved,
+            containers_moved,
+        })
+    }
+
+    /// その場所を使っている物品・コンテナ・下の場所の数
+    async fn usage(&self, id: i64) -> AppResult<(i64, i64, i64)> {
+        let row: (i64, i64, i64) = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_as(
+                    r#"
+                    SELECT
+                        (SELECT COUNT(*) FROM items WHERE location_id = $1) AS items,
+                        (SELECT COUNT(*) FROM containers WHERE location_id = $1) AS containers,
+                        (SELECT COUNT(*) FROM locations WHERE parent_id = $1) AS children
+                    "#,
+                )
+                .bind(id)
+                .fetch_one(pool)
+                .await?
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query_as(
+                    r#"
+                    SELECT
+                        (SELECT COUNT(*) FROM items WHERE location_id = ?1) AS items,
+                        (SELECT COUNT(*) FROM containers WHERE location_id = ?1) AS containers,
+                        (SELECT COUNT(*) FROM locations WHERE parent_id = ?1) AS children
+                    "#,
+                )
+                .bind(id)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+        Ok(row)
+    }
+
+    /// 場所とその下の場所を参照している物品・コンテナの場所の文字列を更新する
+    async fn sync_names(&self, id: i64) -> AppResult<()> {
+        let locations = self.list_locations().await?;
+        let parents: HashMap<i64, (Option<i64>, String)> = locations
+            .iter()
+            .map(|l| (l.id, (l.parent_id, l.name.clone())))
+            .collect();
+        let paths = paths_under(&parents, id);
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut conn = pool.acquire().await?;
+                write_paths_postgres(&mut conn, &paths).await
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut conn = pool.acquire().await?;
+                write_paths_sqlite(&mut conn, &paths).await
+            }
+        }
+    }
+
+    fn row_to_location(&self, row: sqlx::sqlite::SqliteRow) -> Location {
+        Location {
+            id: row.get("id"),
+            name: row.get("name"),
+            kind: LocationKind::parse(&row.get::<String, _>("kind")).unwrap_or(LocationKind::Room),
+            parent_id: row.get("parent_id"),
+            path: String::new(),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_location_postgres(&self, row: sqlx::postgres::PgRow) -> Location {
+        Location {
+            id: row.get("id"),
+            name: row.get("name"),
+            kind: LocationKind::parse(&row.get::<String, _>("kind")).unwrap_or(LocationKind::Room),
+            parent_id: row.get("parent_id"),
+            path: String::new(),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+/// 自分から最上位までのID。親子関係が循環していても途中で止まる
+fn chain_of(parents: &HashMap<i64, (Option<i64>, String)>, id: i64) -> Vec<i64> {
+    let mut chain = Vec::new();
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if chain.contains(&id) {
+            break;
+        }
+        let Some((parent, _)) = parents.get(&id) else {
+            break;
+        };
+        chain.push(id);
+        current = *parent;
+    }
+    chain
+}
+
+fn path_of(parents: &HashMap<i64, (Option<i64>, String)>, id: i64) -> String {
+    chain_of(parents, id)
+        .iter()
+        .rev()
+        .filter_map(|id| parents.get(id).map(|(_, name)| name.as_str()))
+        .collect::<Vec<_>>()
+        .join(PATH_SEPARATOR)
+}
+
+/// `id` とその下の場所の (ID, 場所の文字列)
+fn paths_under(parents: &HashMap<i64, (Option<i64>, String)>, id: i64) -> Vec<(i64, String)> {
+    parents
+        .keys()
+        .filter(|location| chain_of(parents, **location).contains(&id))
+        .map(|location| (*location, path_of(parents, *location)))
+        .collect()
+}
+
+/// 物品・コンテナが持っている場所の文字列を `paths` に合わせる
+async fn write_paths_postgres(
+    conn: &mut sqlx::PgConnection,
+    paths: &[(i64, String)],
+) -> AppResult<()> {
+    for (id, path) in paths {
+        sqlx::query("UPDATE items SET storage_location = $1 WHERE location_id = $2")
+            .bind(path)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("UPDATE containers SET location = $1 WHERE location_id = $2")
+            .bind(path)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn write_paths_sqlite(
+    conn: &mut sqlx::SqliteConnection,
+    paths: &[(i64, String)],
+) -> AppResult<()> {
+    for (id, path) in paths {
+        sqlx::query("UPDATE items SET storage_location = ?1 WHERE location_id = ?2")
+            .bind(path)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("UPDATE containers SET location = ?1 WHERE location_id = ?2")
+            .bind(path)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// 建物 > 部屋 > 棚 の順にしか入れられない
+fn ensure_kind_order(parent: &Location, kind: LocationKind) -> AppResult<()> {
+    if parent.kind >= kind {
+        return Err(AppError::BadRequest(format!(
+            "A {} cannot be placed inside a {}",
+            kind.as_str(),
+            parent.kind.as_str()
+        )));
+    }
+    Ok(())
+}
+
+fn duplicate_name(e: sqlx::Error, name: &str) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(format!(
+            "A location named '{}' already exists there",
+            name
+        )),
+        _ => e.into(),
+    }
+}
+
+fn duplicate_child(e: sqlx::Error, target: &Location) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(format!(
+            "'{}' already has a sub-location with the same name; merge those first",
+            target.path
+        )),
+        _ => e.into(),
+    }
+}
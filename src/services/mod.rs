@@ -1,15 +1,37 @@
+pub mod audit_service;
+pub mod auth_service;
+pub mod backup_service;
 pub mod cable_color_service;
 pub mod connector_service;
 pub mod container_service;
+pub mod data_migration_service;
+pub mod item_csv;
 pub mod item_service;
+pub mod label_codes;
+pub mod label_printer;
+pub mod label_scheme;
+pub mod label_service;
+pub mod label_sheet;
 pub mod loan_service;
+pub mod location_service;
+pub mod reservation_service;
+pub mod stocktake_service;
 pub mod storage;
 pub mod tag_service;
+pub mod transfer_service;
 
+pub use audit_service::*;
+pub use auth_service::*;
+pub use backup_service::*;
 pub use cable_color_service::*;
 pub use connector_service::*;
 pub use container_service::*;
+pub use data_migration_service::*;
 pub use item_service::*;
+pub use label_service::*;
 pub use loan_service::*;
+pub use location_service::*;
+pub use reservation_service::*;
+pub use stocktake_service::*;
 pub use storage::StorageService;
 pub use tag_service::*;
@@ -0,0 +1,628 @@
+use crate::config::LoanConfig;
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AuditAction, AuditEntity, AvailabilityConflict, CreateLoanRequest, CreateReservationRequest,
+    CurrentUser, ItemAvailability, Loan, Reservation, ReservationFilters, ReservationStatus,
+    ReservationsListResponse,
+};
+use crate::services::audit_service::{snapshot, AuditService};
+use crate::services::item_service::ItemService;
+use crate::services::loan_service::LoanService;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use uuid::Uuid;
+
+pub struct ReservationService {
+    db: DatabasePool,
+    audit: AuditService,
+    item_service: ItemService,
+    loan_service: LoanService,
+}
+
+impl ReservationService {
+    pub fn new(db: DatabasePool, loan_config: LoanConfig) -> Self {
+        let audit = AuditService::new(db.clone());
+        let item_service = ItemService::new(db.clone());
+        let loan_service = LoanService::new(db.clone(), loan_config);
+        Self {
+            db,
+            audit,
+            item_service,
+            loan_service,
+        }
+    }
+
+    pub async fn create_reservation(
+        &self,
+        req: CreateReservationRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<Reservation> {
+        if req.end_time <= Utc::now() {
+            return Err(AppError::BadRequest(
+                "Reservation must end in the future".to_string(),
+            ));
+        }
+
+        // 物品の存在、廃棄状態、他の予約・貸出との重複をチェック
+        let availability = self
+            .get_availability(req.item_id, req.start_time, req.end_time)
+            .await?;
+        if let Some(conflict) = availability.conflicts.first() {
+            return Err(match conflict {
+                AvailabilityConflict::Disposed => AppError::BadRequest(conflict_message(conflict)),
+                _ => AppError::Conflict(conflict_message(conflict)),
+            });
+        }
+
+        let reservation = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    INSERT INTO reservations (
+                        item_id, student_number, student_name, organization,
+                        start_time, end_time, status, remarks
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(req.item_id)
+                .bind(&req.student_number)
+                .bind(&req.student_name)
+                .bind(&req.organization)
+                .bind(req.start_time)
+                .bind(req.end_time)
+                .bind(ReservationStatus::Reserved.as_str())
+                .bind(&req.remarks)
+                .fetch_one(pool)
+                .await?;
+
+                self.get_reservation(row.get("id")).await
+            }
+            DatabasePool::Sqlite(pool) => {
+                let now = Utc::now();
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO reservations (
+                        item_id, student_number, student_name, organization,
+                        start_time, end_time, status, remarks, created_at, updated_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+                    "#,
+                )
+                .bind(req.item_id.to_string())
+                .bind(&req.student_number)
+                .bind(&req.student_name)
+                .bind(&req.organization)
+                .bind(req.start_time)
+                .bind(req.end_time)
+                .bind(ReservationStatus::Reserved.as_str())
+                .bind(&req.remarks)
+                .bind(now)
+                .execute(pool)
+                .await?;
+
+                self.get_reservation(result.last_insert_rowid()).await
+            }
+        }?;
+
+        self.audit
+            .record(
+                AuditEntity::Reservation,
+                &reservation.id.to_string(),
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&reservation),
+            )
+            .await?;
+        Ok(reservation)
+    }
+
+    pub async fn get_reservation(&self, id: i64) -> AppResult<Reservation> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Reservation with id {} not found", id))
+                })?;
+
+                Ok(self.row_to_reservation_postgres(row))
+            }
+            DatabasePool::Sqlite(pool) => {
+                let row = sqlx::query(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    WHERE id = ?1
+                    "#,
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Reservation with id {} not found", id))
+                })?;
+
+                Ok(self.row_to_reservation(row))
+            }
+        }
+    }
+
+    pub async fn list_reservations(
+        &self,
+        filters: &ReservationFilters,
+        page: u32,
+        per_page: u32,
+    ) -> AppResult<ReservationsListResponse> {
+        let offset = ((page - 1) * per_page) as i64;
+        let limit = per_page as i64;
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut where_conditions = Vec::new();
+                let mut param_index = 1;
+
+                if filters.item_id.is_some() {
+                    where_conditions.push(format!("item_id = ${}", param_index));
+                    param_index += 1;
+                }
+                if filters.student_number.is_some() {
+                    where_conditions.push(format!("student_number = ${}", param_index));
+                    param_index += 1;
+                }
+                if filters.status.is_some() {
+                    where_conditions.push(format!("status = ${}", param_index));
+                    param_index += 1;
+                }
+                // 期間が指定された場合は、その期間と重なる予約を返す
+                if filters.to.is_some() {
+                    where_conditions.push(format!("start_time < ${}", param_index));
+                    param_index += 1;
+                }
+                if filters.from.is_some() {
+                    where_conditions.push(format!("end_time > ${}", param_index));
+                    param_index += 1;
+                }
+
+                let where_clause = if where_conditions.is_empty() {
+                    String::new()
+                } else {
+                    format!("WHERE {}", where_conditions.join(" AND "))
+                };
+
+                let query_str = format!(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    {}
+                    ORDER BY start_time ASC, id ASC
+                    LIMIT ${} OFFSET ${}
+                    "#,
+                    where_clause,
+                    param_index,
+                    param_index + 1
+                );
+                let count_query_str =
+                    format!("SELECT COUNT(*) as count FROM reservations {}", where_clause);
+
+                let mut query = sqlx::query(&query_str);
+                let mut count_query = sqlx::query(&count_query_str);
+
+                if let Some(item_id) = filters.item_id {
+                    query = query.bind(item_id);
+                    count_query = count_query.bind(item_id);
+                }
+                if let Some(student_number) = &filters.student_number {
+                    query = query.bind(student_number);
+                    count_query = count_query.bind(student_number);
+                }
+                if let Some(status) = filters.status {
+                    query = query.bind(status.as_str());
+                    count_query = count_query.bind(status.as_str());
+                }
+                if let Some(to) = filters.to {
+                    query = query.bind(to);
+                    count_query = count_query.bind(to);
+                }
+                if let Some(from) = filters.from {
+                    query = query.bind(from);
+                    count_query = count_query.bind(from);
+                }
+                query = query.bind(limit).bind(offset);
+
+                let rows = query.fetch_all(pool).await?;
+                let reservations = rows
+                    .into_iter()
+                    .map(|row| self.row_to_reservation_postgres(row))
+                    .collect();
+
+                let total: i64 = count_query.fetch_one(pool).await?.get("count");
+
+                Ok(ReservationsListResponse {
+                    reservations,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut where_conditions = Vec::new();
+
+                if filters.item_id.is_some() {
+                    where_conditions.push("item_id = ?");
+                }
+                if filters.student_number.is_some() {
+                    where_conditions.push("student_number = ?");
+                }
+                if filters.status.is_some() {
+                    where_conditions.push("status = ?");
+                }
+                // 期間が指定された場合は、その期間と重なる予約を返す
+                if filters.to.is_some() {
+                    where_conditions.push("start_time < ?");
+                }
+                if filters.from.is_some() {
+                    where_conditions.push("end_time > ?");
+                }
+
+                let where_clause = if where_conditions.is_empty() {
+                    String::new()
+                } else {
+                    format!("WHERE {}", where_conditions.join(" AND "))
+                };
+
+                let query_str = format!(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    {}
+                    ORDER BY start_time ASC, id ASC
+                    LIMIT ? OFFSET ?
+                    "#,
+                    where_clause
+                );
+                let count_query_str =
+                    format!("SELECT COUNT(*) as count FROM reservations {}", where_clause);
+
+                let mut query = sqlx::query(&query_str);
+                let mut count_query = sqlx::query(&count_query_str);
+
+                if let Some(item_id) = filters.item_id {
+                    query = query.bind(item_id.to_string());
+                    count_query = count_query.bind(item_id.to_string());
+                }
+                if let Some(student_number) = &filters.student_number {
+                    query = query.bind(student_number);
+                    count_query = count_query.bind(student_number);
+                }
+                if let Some(status) = filters.status {
+                    query = query.bind(status.as_str());
+                    count_query = count_query.bind(status.as_str());
+                }
+                if let Some(to) = filters.to {
+                    query = query.bind(to);
+                    count_query = count_query.bind(to);
+                }
+                if let Some(from) = filters.from {
+                    query = query.bind(from);
+                    count_query = count_query.bind(from);
+                }
+                query = query.bind(limit).bind(offset);
+
+                let rows = query.fetch_all(pool).await?;
+                let reservations = rows
+                    .into_iter()
+                    .map(|row| self.row_to_reservation(row))
+                    .collect();
+
+                let total: i64 = count_query.fetch_one(pool).await?.get("count");
+
+                Ok(ReservationsListResponse {
+                    reservations,
+                    total,
+                    page,
+                    per_page,
+                })
+            }
+        }
+    }
+
+    pub async fn cancel_reservation(
+        &self,
+        id: i64,
+        actor: &CurrentUser,
+    ) -> AppResult<Reservation> {
+        let before = self.get_reservation(id).await?;
+        if before.status != ReservationStatus::Reserved {
+            return Err(AppError::Conflict(format!(
+                "Reservation is already {}",
+                before.status.as_str()
+            )));
+        }
+
+        self.set_status(id, ReservationStatus::Cancelled, None)
+            .await?;
+        let reservation = self.get_reservation(id).await?;
+
+        self.audit
+            .record(
+                AuditEntity::Reservation,
+                &id.to_string(),
+                AuditAction::Cancel,
+                actor,
+                snapshot(&before),
+                snapshot(&reservation),
+            )
+            .await?;
+        Ok(reservation)
+    }
+
+    /// 受け取り時に予約を貸出に変換する。返却期限は予約の終了時刻になる
+    pub async fn checkout_reservation(&self, id: i64, actor: &CurrentUser) -> AppResult<Loan> {
+        let before = self.get_reservation(id).await?;
+        if before.status != ReservationStatus::Reserved {
+            return Err(AppError::Conflict(format!(
+                "Reservation is already {}",
+                before.status.as_str()
+            )));
+        }
+        if before.end_time <= Utc::now() {
+            return Err(AppError::BadRequest(
+                "Reservation has already ended".to_string(),
+            ));
+        }
+
+        let loan = self
+            .loan_service
+            .create_loan_for_reservation(
+                CreateLoanRequest {
+                    item_id: before.item_id,
+                    student_number: before.student_number.clone(),
+                    student_name: before.student_name.clone(),
+                    organization: before.organization.clone(),
+                    due_date: Some(before.end_time),
+                    remarks: before.remarks.clone(),
+                },
+                id,
+                actor,
+            )
+            .await?;
+
+        self.set_status(id, ReservationStatus::Fulfilled, Some(loan.id))
+            .await?;
+        let reservation = self.get_reservation(id).await?;
+
+        self.audit
+            .record(
+                AuditEntity::Reservation,
+                &id.to_string(),
+                AuditAction::Checkout,
+                actor,
+                snapshot(&before),
+                snapshot(&reservation),
+            )
+            .await?;
+        Ok(loan)
+    }
+
+    /// 指定期間に物品を予約・貸出できるかを返す
+    pub async fn get_availability(
+        &self,
+        item_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> AppResult<ItemAvailability> {
+        if from >= to {
+            return Err(AppError::BadRequest(
+                "'from' must be earlier than 'to'".to_string(),
+            ));
+        }
+
+        let item = self.item_service.get_item(item_id).await?;
+
+        let mut conflicts = Vec::new();
+        if item.is_disposed.unwrap_or(false) {
+            conflicts.push(AvailabilityConflict::Disposed);
+        }
+
+        // 貸出中の場合は返却期限まで（延滞中なら返却されるまで）使えないものとする
+        if let Some(loan) = self
+            .loan_service
+            .get_active_loan_for_item(&item_id.to_string())
+            .await?
+        {
+            let busy_until = loan.due_date.map(|due| due.max(Utc::now()));
+            if busy_until.is_none_or(|until| until > from) {
+                conflicts.push(AvailabilityConflict::Loan {
+                    id: loan.id,
+                    student_number: loan.student_number,
+                    organization: loan.organization,
+                    loan_date: loan.loan_date,
+                    due_date: loan.due_date,
+                });
+            }
+        }
+
+        for reservation in self.find_overlapping(item_id, from, to).await? {
+            conflicts.push(AvailabilityConflict::Reservation {
+                id: reservation.id,
+                student_number: reservation.student_number,
+                organization: reservation.organization,
+                start_time: reservation.start_time,
+                end_time: reservation.end_time,
+            });
+        }
+
+        Ok(ItemAvailability {
+            item_id,
+            from,
+            to,
+            available: conflicts.is_empty(),
+            conflicts,
+        })
+    }
+
+    async fn find_overlapping(
+        &self,
+        item_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> AppResult<Vec<Reservation>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    WHERE item_id = $1 AND status = $2 AND start_time < $3 AND end_time > $4
+                    ORDER BY start_time ASC
+                    "#,
+                )
+                .bind(item_id)
+                .bind(ReservationStatus::Reserved.as_str())
+                .bind(to)
+                .bind(from)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_reservation_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT
+                        id, item_id, student_number, student_name, organization, start_time,
+                        end_time, status, loan_id, remarks, created_at, updated_at
+                    FROM reservations
+                    WHERE item_id = ?1 AND status = ?2 AND start_time < ?3 AND end_time > ?4
+                    ORDER BY start_time ASC
+                    "#,
+                )
+                .bind(item_id.to_string())
+                .bind(ReservationStatus::Reserved.as_str())
+                .bind(to)
+                .bind(from)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_reservation(row))
+                    .collect())
+            }
+        }
+    }
+
+    async fn set_status(
+        &self,
+        id: i64,
+        status: ReservationStatus,
+        loan_id: Option<i64>,
+    ) -> AppResult<()> {
+        let now = Utc::now();
+
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query(
+                    "UPDATE reservations SET status = $2, loan_id = COALESCE($3, loan_id), updated_at = $4 WHERE id = $1",
+                )
+                .bind(id)
+                .bind(status.as_str())
+                .bind(loan_id)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::query(
+                    "UPDATE reservations SET status = ?2, loan_id = COALESCE(?3, loan_id), updated_at = ?4 WHERE id = ?1",
+                )
+                .bind(id)
+                .bind(status.as_str())
+                .bind(loan_id)
+                .bind(now)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn row_to_reservation(&self, row: sqlx::sqlite::SqliteRow) -> Reservation {
+        Reservation {
+            id: row.get("id"),
+            item_id: row.get::<String, _>("item_id").parse::<Uuid>().unwrap_or_default(),
+            student_number: row.get("student_number"),
+            student_name: row.get("student_name"),
+            organization: row.get("organization"),
+            start_time: row.get("start_time"),
+            end_time: row.get("end_time"),
+            status: ReservationStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(ReservationStatus::Reserved),
+            loan_id: row.get("loan_id"),
+            remarks: row.get("remarks"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_reservation_postgres(&self, row: sqlx::postgres::PgRow) -> Reservation {
+        Reservation {
+            id: row.get("id"),
+            item_id: row.get("item_id"),
+            student_number: row.get("student_number"),
+            student_name: row.get("student_name"),
+            organization: row.get("organization"),
+            start_time: row.get("start_time"),
+            end_time: row.get("end_time"),
+            status: ReservationStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(ReservationStatus::Reserved),
+            loan_id: row.get("loan_id"),
+            remarks: row.get("remarks"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+fn conflict_message(conflict: &AvailabilityConflict) -> String {
+    match conflict {
+        AvailabilityConflict::Disposed => "Item is disposed and cannot be reserved".to_string(),
+        AvailabilityConflict::Loan { id, .. } => {
+            format!("Item is on loan during the requested period (loan {})", id)
+        }
+        AvailabilityConflict::Reservation {
+            id,
+            start_time,
+            end_time,
+            ..
+        } => format!(
+            "Item is already reserved from {} to {} (reservation {})",
+            start_time.to_rfc3339(),
+            end_time.to_rfc3339(),
+            id
+        ),
+    }
+}
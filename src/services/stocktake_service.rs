@@ -0,0 +1,907 @@
+use crate::db::DatabasePool;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AuditAction, AuditEntity, CloseStocktakeRequest, CloseStocktakeResponse,
+    CreateStocktakeRequest, CurrentUser, NewTransfer, RecordStocktakeScanRequest,
+    StocktakeItemEntry, StocktakeLoanEntry, StocktakeMisplacedEntry, StocktakeReport,
+    StocktakeScan, StocktakeSession, StocktakeStatus, StocktakeUnexpectedEntry, TransferEntity,
+};
+use crate::repositories::{insert_transfer_postgres, insert_transfer_sqlite, TransferBatch};
+use crate::services::audit_service::{snapshot, AuditService};
+use crate::services::container_service::ContainerService;
+use crate::services::item_service::ItemService;
+use crate::services::location_service::LocationService;
+use chrono::Utc;
+use sqlx::Row;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const SESSION_COLUMNS: &str = r#"
+    id, name, location, status, opened_by, opened_at, closed_by, closed_at, corrections_applied
+"#;
+
+const SCAN_COLUMNS: &str = r#"
+    id, session_id, label_id, item_id, location, container_id, scanned_by, scanned_at
+"#;
+
+/// 照合に使う物品の情報
+struct StockItem {
+    entry: StocktakeItemEntry,
+    is_on_loan: bool,
+    is_disposed: bool,
+}
+
+/// 修正後の保管場所
+struct Placement {
+    storage_type: &'static str,
+    container_id: Option<String>,
+    storage_location: Option<String>,
+    location_id: Option<i64>,
+    /// 移動の記録に残す実際の場所
+    location: String,
+}
+
+/// スキャンした場所
+enum FoundAt {
+    Location(String),
+    Container(String),
+    Unknown,
+}
+
+pub struct StocktakeService {
+    db: DatabasePool,
+    audit: AuditService,
+    item_service: ItemService,
+    container_service: ContainerService,
+    locations: LocationService,
+}
+
+impl StocktakeService {
+    pub fn new(db: DatabasePool) -> Self {
+        let audit = AuditService::new(db.clone());
+        let item_service = ItemService::new(db.clone());
+        let container_service = ContainerService::new(db.clone());
+        let locations = LocationService::new(db.clone());
+        Self {
+            db,
+            audit,
+            item_service,
+            container_service,
+            locations,
+        }
+    }
+
+    pub async fn create_session(
+        &self,
+        req: CreateStocktakeRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<StocktakeSession> {
+        let mut container_ids = req.container_ids;
+        container_ids.sort();
+        container_ids.dedup();
+        if req.location.is_some() != container_ids.is_empty() {
+            return Err(AppError::BadRequest(
+                "Specify either location or container_ids".to_string(),
+            ));
+        }
+        for container_id in &container_ids {
+            self.container_service.get_container(container_id).await?;
+        }
+
+        let now = Utc::now();
+        let actor_id = (actor.id != 0).then_some(actor.id);
+        let id = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                let id: i64 = sqlx::query_scalar(
+                    r#"
+                    INSERT INTO stocktake_sessions (
+                        name, location, status, opened_by_id, opened_by, opened_at
+                    ) VALUES ($1, $2, $3, $4, $5, $6)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&req.name)
+                .bind(&req.location)
+                .bind(StocktakeStatus::Open.as_str())
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?;
+                for container_id in &container_ids {
+                    sqlx::query(
+                        "INSERT INTO stocktake_session_containers (session_id, container_id) VALUES ($1, $2)",
+                    )
+                    .bind(id)
+                    .bind(container_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await?;
+                id
+            }
+            DatabasePool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                let id = sqlx::query(
+                    r#"
+                    INSERT INTO stocktake_sessions (
+                        name, location, status, opened_by_id, opened_by, opened_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                    "#,
+                )
+                .bind(&req.name)
+                .bind(&req.location)
+                .bind(StocktakeStatus::Open.as_str())
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid();
+                for container_id in &container_ids {
+                    sqlx::query(
+                        "INSERT INTO stocktake_session_containers (session_id, container_id) VALUES (?1, ?2)",
+                    )
+                    .bind(id)
+                    .bind(container_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await?;
+                id
+            }
+        };
+
+        let session = self.get_session(id).await?;
+        self.audit
+            .record(
+                AuditEntity::Stocktake,
+                &session.id.to_string(),
+                AuditAction::Create,
+                actor,
+                None,
+                snapshot(&session),
+            )
+            .await?;
+        Ok(session)
+    }
+
+    pub async fn get_session(&self, id: i64) -> AppResult<StocktakeSession> {
+        let session = match &self.db {
+            DatabasePool::Postgres(pool) => sqlx::query(&format!(
+                "SELECT {} FROM stocktake_sessions WHERE id = $1",
+                SESSION_COLUMNS
+            ))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| self.row_to_session_postgres(row)),
+            DatabasePool::Sqlite(pool) => sqlx::query(&format!(
+                "SELECT {} FROM stocktake_sessions WHERE id = ?1",
+                SESSION_COLUMNS
+            ))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| self.row_to_session(row)),
+        };
+        let mut session = session
+            .ok_or_else(|| AppError::NotFound(format!("Stocktake session {} not found", id)))?;
+        session.container_ids = self
+            .session_containers()
+            .await?
+            .remove(&session.id)
+            .unwrap_or_default();
+        Ok(session)
+    }
+
+    pub async fn list_sessions(
+        &self,
+        status: Option<StocktakeStatus>,
+    ) -> AppResult<Vec<StocktakeSession>> {
+        let status = status.map(|status| status.as_str());
+        let mut sessions = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM stocktake_sessions WHERE ($1 IS NULL OR status = $1) \
+                     ORDER BY opened_at DESC, id DESC",
+                    SESSION_COLUMNS
+                ))
+                .bind(status)
+                .fetch_all(pool)
+                .await?;
+                rows.into_iter()
+                    .map(|row| self.row_to_session_postgres(row))
+                    .collect::<Vec<_>>()
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM stocktake_sessions WHERE (?1 IS NULL OR status = ?1) \
+                     ORDER BY opened_at DESC, id DESC",
+                    SESSION_COLUMNS
+                ))
+                .bind(status)
+                .fetch_all(pool)
+                .await?;
+                rows.into_iter()
+                    .map(|row| self.row_to_session(row))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let mut containers = self.session_containers().await?;
+        for session in &mut sessions {
+            session.container_ids = containers.remove(&session.id).unwrap_or_default();
+        }
+        Ok(sessions)
+    }
+
+    /// ラベルの読み取りを記録する。物品として登録されていないラベルもそのまま記録する
+    pub async fn record_scan(
+        &self,
+        session_id: i64,
+        req: RecordStocktakeScanRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<StocktakeScan> {
+        let session = self.get_session(session_id).await?;
+        if session.status != StocktakeStatus::Open {
+            return Err(AppError::Conflict(format!(
+                "Stocktake session {} is closed",
+                session_id
+            )));
+        }
+        if let Some(container_id) = &req.container_id {
+            self.container_service.get_container(container_id).await?;
+        }
+
+        let label_id = req.label_id.trim().to_string();
+        let item_id = match self.item_service.lookup_label(&label_id).await {
+            // ラベルIDは大文字で発行されるので、小文字で読み取られた場合も探す
+            Err(AppError::NotFound(_)) if label_id != label_id.to_uppercase() => {
+                self.item_service
+                    .lookup_label(&label_id.to_uppercase())
+                    .await
+            }
+            result => result,
+        };
+        let item_id = match item_id {
+            Ok(found) => Some(found.item.id),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let now = Utc::now();
+        let actor_id = (actor.id != 0).then_some(actor.id);
+        let id = match &self.db {
+            DatabasePool::Postgres(pool) => {
+                sqlx::query_scalar(
+                    r#"
+                    INSERT INTO stocktake_scans (
+                        session_id, label_id, item_id, location, container_id,
+                        scanned_by_id, scanned_by, scanned_at
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(session_id)
+                .bind(&label_id)
+                .bind(item_id)
+                .bind(&req.location)
+                .bind(&req.container_id)
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .fetch_one(pool)
+                .await?
+            }
+            DatabasePool::Sqlite(pool) => {
+                // items.id と同じハイフン区切りの TEXT として書き込む
+                sqlx::query_scalar(
+                    r#"
+                    INSERT INTO stocktake_scans (
+                        session_id, label_id, item_id, location, container_id,
+                        scanned_by_id, scanned_by, scanned_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(session_id)
+                .bind(&label_id)
+                .bind(item_id.map(Uuid::hyphenated))
+                .bind(&req.location)
+                .bind(&req.container_id)
+                .bind(actor_id)
+                .bind(&actor.username)
+                .bind(now)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+
+        Ok(StocktakeScan {
+            id,
+            session_id,
+            label_id,
+            item_id,
+            location: req.location,
+            container_id: req.container_id,
+            scanned_by: actor.username.clone(),
+            scanned_at: now,
+        })
+    }
+
+    pub async fn list_scans(&self, session_id: i64) -> AppResult<Vec<StocktakeScan>> {
+        self.get_session(session_id).await?;
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM stocktake_scans WHERE session_id = $1 ORDER BY scanned_at, id",
+                    SCAN_COLUMNS
+                ))
+                .bind(session_id)
+                .fetch_all(pool)
+                .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_scan_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM stocktake_scans WHERE session_id = ?1 ORDER BY scanned_at, id",
+                    SCAN_COLUMNS
+                ))
+                .bind(session_id)
+                .fetch_all(pool)
+                .await?;
+                Ok(rows.into_iter().map(|row| self.row_to_scan(row)).collect())
+            }
+        }
+    }
+
+    /// 終了したセッションは終了時点の結果を返す
+    pub async fn get_report(&self, session_id: i64) -> AppResult<StocktakeReport> {
+        let session = self.get_session(session_id).await?;
+        if session.status == StocktakeStatus::Closed {
+            let stored: Option<String> = match &self.db {
+                DatabasePool::Postgres(pool) => {
+                    sqlx::query_scalar("SELECT final_report FROM stocktake_sessions WHERE id = $1")
+                        .bind(session_id)
+                        .fetch_one(pool)
+                        .await?
+                }
+                DatabasePool::Sqlite(pool) => {
+                    sqlx::query_scalar("SELECT final_report FROM stocktake_sessions WHERE id = ?1")
+                        .bind(session_id)
+                        .fetch_one(pool)
+                        .await?
+                }
+            };
+            if let Some(report) = stored.and_then(|s| serde_json::from_str(&s).ok()) {
+                return Ok(report);
+            }
+        }
+        self.build_report(&session).await
+    }
+
+    /// セッションを終了する。`apply_corrections` なら別の場所で見つかった物品の保管場所を直す。
+    /// 終了と修正は1つのトランザクションで行い、修正せずに終了したセッションは
+    /// `apply_corrections` を付けて呼び直せば、終了時の結果のうちまだ直していない物品を直す
+    pub async fn close_session(
+        &self,
+        session_id: i64,
+        req: CloseStocktakeRequest,
+        actor: &CurrentUser,
+    ) -> AppResult<CloseStocktakeResponse> {
+        let before = self.get_session(session_id).await?;
+        let resuming = before.status == StocktakeStatus::Closed;
+        let already_closed = || {
+            AppError::Conflict(format!(
+                "Stocktake session {} is already closed",
+                session_id
+            ))
+        };
+        if resuming && (!req.apply_corrections || before.corrections_applied > 0) {
+            return Err(already_closed());
+        }
+        let report = if resuming {
+            self.get_report(session_id).await?
+        } else {
+            self.build_report(&before).await?
+        };
+
+        let mut moves = Vec::new();
+        if req.apply_corrections {
+            let hierarchy = self.container_service.hierarchy().await?;
+            for entry in &report.wrong_location {
+                let item = self.item_service.get_item(entry.item.item_id).await?;
+                let placement = match (&entry.found_container_id, &entry.found_location) {
+                    (Some(container_id), _) => {
+                        if item.storage_type == "container"
+                            && item.container_id.as_ref() == Some(container_id)
+                        {
+                            continue;
+                        }
+                        let container = self.container_service.get_container(container_id).await?;
+                        Placement {
+                            storage_type: "container",
+                            container_id: Some(container.id),
+                            storage_location: None,
+                            location_id: None,
+                            location: container.location,
+                        }
+                    }
+                    (None, Some(location)) => {
+                        if item.storage_type == "location"
+                            && item.storage_location.as_ref() == Some(location)
+                        {
+                            continue;
+                        }
+                        let location = self
+                            .locations
+                            .resolve(None, Some(location), actor)
+                            .await?
+                            .ok_or_else(|| {
+                            AppError::BadRequest("location must not be blank".to_string())
+                        })?;
+                        Placement {
+                            storage_type: "location",
+                            container_id: None,
+                            storage_location: Some(location.path.clone()),
+                            location_id: Some(location.id),
+                            location: location.path,
+                        }
+                    }
+                    (None, None) => continue,
+                };
+                let transfer = NewTransfer {
+                    entity_type: TransferEntity::Item,
+                    entity_id: item.id.to_string(),
+                    from_location: hierarchy.item_location(&item),
+                    from_container_id: item
+                        .container_id
+                        .clone()
+                        .filter(|_| item.storage_type == "container"),
+                    to_location: Some(placement.location.clone()),
+                    to_container_id: placement.container_id.clone(),
+                    via_container_id: None,
+                };
+                moves.push((item, placement, transfer));
+            }
+            // 呼び直しても直すものがなければ、終了済みとして扱う
+            if resuming && moves.is_empty() {
+                return Err(already_closed());
+            }
+        }
+
+        let report_json = serde_json::to_string(&report)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let corrections_applied = moves.len() as i32;
+        let note = format!("Stocktake: {}", before.name);
+        let batch = TransferBatch::new(Some(&note), actor);
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                let closed = if resuming {
+                    sqlx::query(
+                        r#"
+                        UPDATE stocktake_sessions SET corrections_applied = $1
+                        WHERE id = $2 AND status = $3 AND corrections_applied = 0
+                        "#,
+                    )
+                    .bind(corrections_applied)
+                    .bind(session_id)
+                    .bind(StocktakeStatus::Closed.as_str())
+                    .execute(&mut *tx)
+                    .await?
+                } else {
+                    sqlx::query(
+                        r#"
+                        UPDATE stocktake_sessions
+                        SET status = $1, closed_by = $2, closed_at = $3, final_report = $4,
+                            corrections_applied = $5
+                        WHERE id = $6 AND status = $7
+                        "#,
+                    )
+                    .bind(StocktakeStatus::Closed.as_str())
+                    .bind(&actor.username)
+                    .bind(batch.moved_at)
+                    .bind(&report_json)
+                    .bind(corrections_applied)
+                    .bind(session_id)
+                    .bind(StocktakeStatus::Open.as_str())
+                    .execute(&mut *tx)
+                    .await?
+                };
+                if closed.rows_affected() == 0 {
+                    return Err(already_closed());
+                }
+                for (item, placement, transfer) in &moves {
+                    sqlx::query(
+                        r#"
+                        UPDATE items SET storage_type = $1, container_id = $2, storage_location = $3,
+                            location_id = $4, updated_at = $5
+                        WHERE id = $6
+                        "#,
+                    )
+                    .bind(placement.storage_type)
+                    .bind(&placement.container_id)
+                    .bind(&placement.storage_location)
+                    .bind(placement.location_id)
+                    .bind(batch.moved_at)
+                    .bind(item.id)
+                    .execute(&mut *tx)
+                    .await?;
+                    insert_transfer_postgres(&mut tx, &batch, transfer).await?;
+                }
+                tx.commit().await?;
+            }
+            DatabasePool::Sqlite(pool) => {
+                // 最初に書き込んで書き込みロックを取る
+                let mut tx = pool.begin().await?;
+                let closed = if resuming {
+                    sqlx::query(
+                        r#"
+                        UPDATE stocktake_sessions SET corrections_applied = ?1
+                        WHERE id = ?2 AND status = ?3 AND corrections_applied = 0
+                        "#,
+                    )
+                    .bind(corrections_applied)
+                    .bind(session_id)
+                    .bind(StocktakeStatus::Closed.as_str())
+                    .execute(&mut *tx)
+                    .await?
+                } else {
+                    sqlx::query(
+                        r#"
+                        UPDATE stocktake_sessions
+                        SET status = ?1, closed_by = ?2, closed_at = ?3, final_report = ?4,
+                            corrections_applied = ?5
+                        WHERE id = ?6 AND status = ?7
+                        "#,
+                    )
+                    .bind(StocktakeStatus::Closed.as_str())
+                    .bind(&actor.username)
+                    .bind(batch.moved_at)
+                    .bind(&report_json)
+                    .bind(corrections_applied)
+                    .bind(session_id)
+                    .bind(StocktakeStatus::Open.as_str())
+                    .execute(&mut *tx)
+                    .await?
+                };
+                if closed.rows_affected() == 0 {
+                    return Err(already_closed());
+                }
+                for (item, placement, transfer) in &moves {
+                    sqlx::query(
+                        r#"
+                        UPDATE items SET storage_type = ?1, container_id = ?2, storage_location = ?3,
+                            location_id = ?4, updated_at = ?5
+                        WHERE id = ?6
+                        "#,
+                    )
+                    .bind(placement.storage_type)
+                    .bind(&placement.container_id)
+                    .bind(&placement.storage_location)
+                    .bind(placement.location_id)
+                    .bind(batch.moved_at)
+                    .bind(item.id.to_string())
+                    .execute(&mut *tx)
+                    .await?;
+                    insert_transfer_sqlite(&mut tx, &batch, transfer).await?;
+                }
+                tx.commit().await?;
+            }
+        }
+
+        let mut corrected_item_ids = Vec::with_capacity(moves.len());
+        for (old, _, _) in &moves {
+            let item = self.item_service.get_item(old.id).await?;
+            self.audit
+                .record(
+                    AuditEntity::Item,
+                    &item.id.to_string(),
+                    AuditAction::Update,
+                    actor,
+                    snapshot(old),
+                    snapshot(&item),
+                )
+                .await?;
+            corrected_item_ids.push(item.id);
+        }
+        let session = self.get_session(session_id).await?;
+        self.audit
+            .record(
+                AuditEntity::Stocktake,
+                &session.id.to_string(),
+                if resuming {
+                    AuditAction::Update
+                } else {
+                    AuditAction::Close
+                },
+                actor,
+                snapshot(&before),
+                snapshot(&session),
+            )
+            .await?;
+        Ok(CloseStocktakeResponse {
+            session,
+            report,
+            corrected_item_ids,
+        })
+    }
+
+    /// 登録上の保管場所とスキャン結果を突き合わせる
+    async fn build_report(&self, session: &StocktakeSession) -> AppResult<StocktakeReport> {
+        let items = self.load_items().await?;
+        let hierarchy = self.container_service.hierarchy().await?;
+        let scans = self.list_scans(session.id).await?;
+
+        // 登録上、その場所にあるはずか。入れ子のコンテナは外側のコンテナをたどる
+        let stored_at_location = |item: &StocktakeItemEntry, location: &str| {
+            if item.storage_type == "container" {
+                item.container_id
+                    .as_deref()
+                    .and_then(|id| hierarchy.effective_location(id))
+                    .is_some_and(|l| l == location)
+            } else {
+                item.storage_location.as_deref() == Some(location)
+            }
+        };
+        let stored_in_container = |item: &StocktakeItemEntry, container_id: &str| {
+            item.storage_type == "container"
+                && item
+                    .container_id
+                    .as_deref()
+                    .is_some_and(|id| hierarchy.chain(id).iter().any(|c| c == container_id))
+        };
+        let in_scope = |item: &StocktakeItemEntry| match &session.location {
+            Some(location) => stored_at_location(item, location),
+            None => session
+                .container_ids
+                .iter()
+                .any(|container_id| stored_in_container(item, container_id)),
+        };
+
+        // 同じ物品を何度か読み取った場合は最後のスキャンを使う
+        let mut latest: HashMap<Uuid, &StocktakeScan> = HashMap::new();
+        let mut unknown: HashMap<&str, &StocktakeScan> = HashMap::new();
+        for scan in &scans {
+            match scan.item_id {
+                Some(item_id) => latest.insert(item_id, scan),
+                None => unknown.insert(scan.label_id.as_str(), scan),
+            };
+        }
+
+        let mut report = StocktakeReport {
+            session_id: session.id,
+            generated_at: Utc::now(),
+            expected: 0,
+            found: 0,
+            scans: scans.len() as i64,
+            missing: Vec::new(),
+            wrong_location: Vec::new(),
+            unexpected: Vec::new(),
+            on_loan: Vec::new(),
+        };
+
+        for item in items {
+            let scan = latest.get(&item.entry.item_id).copied();
+            if item.is_disposed {
+                if let Some(scan) = scan {
+                    report.unexpected.push(StocktakeUnexpectedEntry {
+                        label_id: scan.label_id.clone(),
+                        item_id: Some(item.entry.item_id),
+                        reason: "disposed".to_string(),
+                        scanned_by: scan.scanned_by.clone(),
+                        scanned_at: scan.scanned_at,
+                    });
+                }
+                continue;
+            }
+
+            let expected = in_scope(&item.entry);
+            if expected {
+                report.expected += 1;
+            }
+            if item.is_on_loan {
+                if expected || scan.is_some() {
+                    report.on_loan.push(StocktakeLoanEntry {
+                        item: item.entry,
+                        scanned: scan.is_some(),
+                    });
+                }
+                continue;
+            }
+
+            let Some(scan) = scan else {
+                if expected {
+                    report.missing.push(item.entry);
+                }
+                continue;
+            };
+            if expected {
+                report.found += 1;
+            }
+
+            let found_at = match (&scan.container_id, &scan.location) {
+                (Some(container_id), _) => FoundAt::Container(container_id.clone()),
+                (None, Some(location)) => FoundAt::Location(location.clone()),
+                (None, None) => match (&session.location, session.container_ids.as_slice()) {
+                    (Some(location), _) => FoundAt::Location(location.clone()),
+                    (None, [container_id]) => FoundAt::Container(container_id.clone()),
+                    _ => FoundAt::Unknown,
+                },
+            };
+            let (in_place, found_location, found_container_id) = match found_at {
+                FoundAt::Container(container_id) => (
+                    stored_in_container(&item.entry, &container_id),
+                    None,
+                    Some(container_id),
+                ),
+                FoundAt::Location(location) => (
+                    stored_at_location(&item.entry, &location),
+                    Some(location),
+                    None,
+                ),
+                FoundAt::Unknown => (expected, None, None),
+            };
+            if !in_place {
+                report.wrong_location.push(StocktakeMisplacedEntry {
+                    item: item.entry,
+                    found_location,
+                    found_container_id,
+                    scanned_by: scan.scanned_by.clone(),
+                    scanned_at: scan.scanned_at,
+                });
+            }
+        }
+
+        let mut unknown: Vec<&StocktakeScan> = unknown.into_values().collect();
+        unknown.sort_by(|a, b| a.label_id.cmp(&b.label_id));
+        report
+            .unexpected
+            .extend(unknown.into_iter().map(|scan| StocktakeUnexpectedEntry {
+                label_id: scan.label_id.clone(),
+                item_id: None,
+                reason: "unknown_label".to_string(),
+                scanned_by: scan.scanned_by.clone(),
+                scanned_at: scan.scanned_at,
+            }));
+        Ok(report)
+    }
+
+    async fn load_items(&self) -> AppResult<Vec<StockItem>> {
+        let query = r#"
+            SELECT
+                id, name, label_id, storage_type, storage_location, container_id,
+                is_on_loan, is_disposed
+            FROM items
+            ORDER BY label_id
+        "#;
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| StockItem {
+                        entry: StocktakeItemEntry {
+                            item_id: row.get("id"),
+                            label_id: row.get("label_id"),
+                            name: row.get("name"),
+                            storage_type: row
+                                .get::<Option<String>, _>("storage_type")
+                                .unwrap_or_else(|| "location".to_string()),
+                            storage_location: row.get("storage_location"),
+                            container_id: row.get("container_id"),
+                        },
+                        is_on_loan: row.get::<Option<bool>, _>("is_on_loan").unwrap_or(false),
+                        is_disposed: row.get::<Option<bool>, _>("is_disposed").unwrap_or(false),
+                    })
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let rows = sqlx::query(query).fetch_all(pool).await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| StockItem {
+                        entry: StocktakeItemEntry {
+                            item_id: row
+                                .get::<String, _>("id")
+                                .parse::<Uuid>()
+                                .unwrap_or_default(),
+                            label_id: row.get("label_id"),
+                            name: row.get("name"),
+                            storage_type: row
+                                .get::<Option<String>, _>("storage_type")
+                                .unwrap_or_else(|| "location".to_string()),
+                            storage_location: row.get("storage_location"),
+                            container_id: row.get("container_id"),
+                        },
+                        is_on_loan: row.get::<Option<bool>, _>("is_on_loan").unwrap_or(false),
+                        is_disposed: row.get::<Option<bool>, _>("is_disposed").unwrap_or(false),
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    async fn session_containers(&self) -> AppResult<HashMap<i64, Vec<String>>> {
+        let query = r#"
+            SELECT session_id, container_id
+            FROM stocktake_session_containers
+            ORDER BY container_id
+        "#;
+        let rows: Vec<(i64, String)> = match &self.db {
+            DatabasePool::Postgres(pool) => sqlx::query_as(query).fetch_all(pool).await?,
+            DatabasePool::Sqlite(pool) => sqlx::query_as(query).fetch_all(pool).await?,
+        };
+        let mut containers: HashMap<i64, Vec<String>> = HashMap::new();
+        for (session_id, container_id) in rows {
+            containers.entry(session_id).or_default().push(container_id);
+        }
+        Ok(containers)
+    }
+
+    fn row_to_session(&self, row: sqlx::sqlite::SqliteRow) -> StocktakeSession {
+        StocktakeSession {
+            id: row.get("id"),
+            name: row.get("name"),
+            location: row.get("location"),
+            container_ids: Vec::new(),
+            status: StocktakeStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(StocktakeStatus::Open),
+            opened_by: row.get("opened_by"),
+            opened_at: row.get("opened_at"),
+            closed_by: row.get("closed_by"),
+            closed_at: row.get("closed_at"),
+            corrections_applied: row.get("corrections_applied"),
+        }
+    }
+
+    fn row_to_session_postgres(&self, row: sqlx::postgres::PgRow) -> StocktakeSession {
+        StocktakeSession {
+            id: row.get("id"),
+            name: row.get("name"),
+            location: row.get("location"),
+            container_ids: Vec::new(),
+            status: StocktakeStatus::parse(&row.get::<String, _>("status"))
+                .unwrap_or(StocktakeStatus::Open),
+            opened_by: row.get("opened_by"),
+            opened_at: row.get("opened_at"),
+            closed_by: row.get("closed_by"),
+            closed_at: row.get("closed_at"),
+            corrections_applied: row.get("corrections_applied"),
+        }
+    }
+
+    fn row_to_scan(&self, row: sqlx::sqlite::SqliteRow) -> StocktakeScan {
+        StocktakeScan {
+            id: row.get("id"),
+            session_id: row.get("session_id"),
+            label_id: row.get("label_id"),
+            item_id: row
+                .get::<Option<String>, _>("item_id")
+                .and_then(|id| id.parse::<Uuid>().ok()),
+            location: row.get("location"),
+            container_id: row.get("container_id"),
+            scanned_by: row.get("scanned_by"),
+            scanned_at: row.get("scanned_at"),
+        }
+    }
+
+    fn row_to_scan_postgres(&self, row: sqlx::postgres::PgRow) -> StocktakeScan {
+        StocktakeScan {
+            id: row.get("id"),
+            session_id: row.get("session_id"),
+            label_id: row.get("label_id"),
+            item_id: row.get("item_id"),
+            location: row.get("location"),
+            container_id: row.get("container_id"),
+            scanned_by: row.get("scanned_by"),
+            scanned_at: row.get("scanned_at"),
+        }
+    }
+}
@@ -69,6 +69,53 @@ impl StorageService {
             StorageService::Local(storage) => storage.get_url(key),
         }
     }
+
+    /// URL からストレージ内のキーを取り出す。このストレージの URL でなければ None
+    pub fn key_for_url(&self, url: &str) -> Option<String> {
+        let base_url = match self {
+            StorageService::S3(storage) => &storage.base_url,
+            StorageService::Local(storage) => &storage.base_url,
+        };
+        url.strip_prefix(&format!("{}/", base_url))
+            .filter(|key| is_valid_key(key))
+            .map(str::to_string)
+    }
+
+    /// キーのファイルを読む。なければ None
+    pub async fn fetch(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        match self {
+            StorageService::S3(storage) => storage.fetch(key).await,
+            StorageService::Local(storage) => storage.fetch(key).await,
+        }
+    }
+
+    /// キーを指定して保存し、URL を返す（リストア用）
+    pub async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> AppResult<String> {
+        if !is_valid_key(key) {
+            return Err(AppError::BadRequest(format!("Invalid storage key: {}", key)));
+        }
+        match self {
+            StorageService::S3(storage) => storage.put(key, data, content_type).await,
+            StorageService::Local(storage) => storage.put(key, data).await,
+        }
+    }
+
+    /// ファイルが1つもないか
+    pub async fn is_empty(&self) -> AppResult<bool> {
+        match self {
+            StorageService::S3(storage) => storage.is_empty().await,
+            StorageService::Local(storage) => storage.is_empty().await,
+        }
+    }
+}
+
+/// `a/b/c.png` の形の相対パスだけを受け付ける（ストレージの外を指させない）
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.contains('\\')
+        && key
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
 }
 
 #[derive(Clone)]
@@ -200,6 +247,61 @@ impl S3Storage {
     pub fn get_url(&self, key: &str) -> String {
         format!("{}/{}", self.base_url, key)
     }
+
+    pub async fn fetch(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        let output = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => {
+                return Err(AppError::StorageError(format!(
+                    "Failed to read {key} from S3: {e}"
+                )))
+            }
+        };
+        let body = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::StorageError(format!("Failed to read {key} from S3: {e}")))?;
+        Ok(Some(body.into_bytes().to_vec()))
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> AppResult<String> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .body(data.into())
+            .content_type(content_type)
+            .acl(ObjectCannedAcl::PublicRead)
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::error!("S3 upload error details: {:?}", e);
+                AppError::StorageError(format!("Failed to upload to S3: {e}"))
+            })?;
+
+        Ok(self.get_url(key))
+    }
+
+    pub async fn is_empty(&self) -> AppResult<bool> {
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket_name)
+            .max_keys(1)
+            .send()
+            .await
+            .map_err(|e| AppError::StorageError(format!("Failed to list S3 objects: {e}")))?;
+        Ok(output.key_count().unwrap_or(0) == 0)
+    }
 }
 
 #[derive(Clone)]
@@ -325,4 +427,53 @@ impl LocalStorage {
     pub fn get_url(&self, key: &str) -> String {
         format!("{}/{}", self.base_url, key)
     }
+
+    pub async fn fetch(&self, key: &str) -> AppResult<Option<Vec<u8>>> {
+        let file_path = self.base_path.join(key);
+        match fs::read(&file_path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AppError::StorageError(format!(
+                "Failed to read file {}: {}",
+                file_path.display(),
+                e
+            ))),
+        }
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> AppResult<String> {
+        let file_path = self.base_path.join(key);
+        if let Some(parent) = file_path.parent() {
+            self.ensure_directory(parent).await?;
+        }
+        fs::write(&file_path, data).await.map_err(|e| {
+            AppError::StorageError(format!(
+                "Failed to write file {}: {}",
+                file_path.display(),
+                e
+            ))
+        })?;
+
+        Ok(self.get_url(key))
+    }
+
+    /// 空のディレクトリは残っていてもよい（削除しても親ディレクトリが残ることがある）
+    pub async fn is_empty(&self) -> AppResult<bool> {
+        let mut pending = vec![self.base_path.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
 }
@@ -0,0 +1,118 @@
+use crate::db::DatabasePool;
+use crate::error::AppResult;
+use crate::models::{Transfer, TransferEntity};
+use sqlx::Row;
+use uuid::Uuid;
+
+const SELECT_TRANSFERS: &str = r#"
+    SELECT id, batch_id, entity_type, entity_id, from_location, from_container_id,
+           to_location, to_container_id, via_container_id, note, moved_by, moved_at
+    FROM transfers
+"#;
+
+pub struct TransferService {
+    db: DatabasePool,
+}
+
+impl TransferService {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// 物品・コンテナの移動履歴（古い順）
+    pub async fn list_for_entity(
+        &self,
+        entity: TransferEntity,
+        entity_id: &str,
+    ) -> AppResult<Vec<Transfer>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let query = format!(
+                    "{} WHERE entity_type = $1 AND entity_id = $2 ORDER BY moved_at ASC, id ASC",
+                    SELECT_TRANSFERS
+                );
+                let rows = sqlx::query(&query)
+                    .bind(entity.as_str())
+                    .bind(entity_id)
+                    .fetch_all(pool)
+                    .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_transfer_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let query = format!(
+                    "{} WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY moved_at ASC, id ASC",
+                    SELECT_TRANSFERS
+                );
+                let rows = sqlx::query(&query)
+                    .bind(entity.as_str())
+                    .bind(entity_id)
+                    .fetch_all(pool)
+                    .await?;
+                Ok(rows.into_iter().map(|row| self.row_to_transfer(row)).collect())
+            }
+        }
+    }
+
+    pub async fn list_batch(&self, batch_id: Uuid) -> AppResult<Vec<Transfer>> {
+        match &self.db {
+            DatabasePool::Postgres(pool) => {
+                let query = format!("{} WHERE batch_id = $1 ORDER BY id ASC", SELECT_TRANSFERS);
+                let rows = sqlx::query(&query).bind(batch_id).fetch_all(pool).await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|row| self.row_to_transfer_postgres(row))
+                    .collect())
+            }
+            DatabasePool::Sqlite(pool) => {
+                let query = format!("{} WHERE batch_id = ?1 ORDER BY id ASC", SELECT_TRANSFERS);
+                let rows = sqlx::query(&query)
+                    .bind(batch_id.to_string())
+                    .fetch_all(pool)
+                    .await?;
+                Ok(rows.into_iter().map(|row| self.row_to_transfer(row)).collect())
+            }
+        }
+    }
+
+    fn row_to_transfer(&self, row: sqlx::sqlite::SqliteRow) -> Transfer {
+        Transfer {
+            id: row.get("id"),
+            batch_id: row
+                .get::<String, _>("batch_id")
+                .parse::<Uuid>()
+                .unwrap_or_default(),
+            entity_type: TransferEntity::parse(&row.get::<String, _>("entity_type"))
+                .unwrap_or(TransferEntity::Item),
+            entity_id: row.get("entity_id"),
+            from_location: row.get("from_location"),
+            from_container_id: row.get("from_container_id"),
+            to_location: row.get("to_location"),
+            to_container_id: row.get("to_container_id"),
+            via_container_id: row.get("via_container_id"),
+            note: row.get("note"),
+            moved_by: row.get("moved_by"),
+            moved_at: row.get("moved_at"),
+        }
+    }
+
+    fn row_to_transfer_postgres(&self, row: sqlx::postgres::PgRow) -> Transfer {
+        Transfer {
+            id: row.get("id"),
+            batch_id: row.get("batch_id"),
+            entity_type: TransferEntity::parse(&row.get::<String, _>("entity_type"))
+                .unwrap_or(TransferEntity::Item),
+            entity_id: row.get("entity_id"),
+            from_location: row.get("from_location"),
+            from_container_id: row.get("from_container_id"),
+            to_location: row.get("to_location"),
+            to_container_id: row.get("to_container_id"),
+            via_container_id: row.get("via_container_id"),
+            note: row.get("note"),
+            moved_by: row.get("moved_by"),
+            moved_at: row.get("moved_at"),
+        }
+    }
+}
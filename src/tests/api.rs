@@ -0,0 +1,1099 @@
+//! エンドポイントごとのシナリオ。どれも [`assert_same_on_every_backend`] で両方のバックエンドに流す。
+
+use serde_json::json;
+
+use super::fixtures::{id, Fixtures};
+use super::{assert_same_on_every_backend, TestApp, TestDatabase};
+
+#[tokio::test]
+async fn catalog() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+
+        // ケーブル色
+        app.get("/cable_colors").await.expect(200);
+        let red = id(&f.cable_colors[0]);
+        app.get(&format!("/cable_colors/{}", red)).await.expect(200);
+        app.put(
+            &format!("/cable_colors/{}", red),
+            json!({ "description": "電源" }),
+        )
+        .await
+        .expect(200);
+        app.post("/cable_colors", json!({ "name": "red" }))
+            .await
+            .expect(409);
+        app.get("/cable_colors/999999").await.expect(404);
+        app.delete(&format!("/cable_colors/{}", id(&f.cable_colors[1])))
+            .await
+            .expect(204);
+        app.get("/cable_colors?page=1&per_page=1").await.expect(200);
+
+        // コネクタ
+        app.get("/connectors").await.expect(200);
+        let xlr = id(&f.connectors[0]);
+        app.put(
+            &format!("/connectors/{}", xlr),
+            json!({ "gender": "female" }),
+        )
+        .await
+        .expect(200);
+        app.put(&format!("/connectors/{}", xlr), json!({ "gender": "both" }))
+            .await
+            .expect(400);
+        app.put(&format!("/connectors/{}", xlr), json!({ "name": "BNC" }))
+            .await
+            .expect(409);
+        app.delete(&format!("/connectors/{}", id(&f.connectors[1])))
+            .await
+            .expect(204);
+        app.get(&format!("/connectors/{}", id(&f.connectors[1])))
+            .await
+            .expect(404);
+
+        // タグと物品のタグ
+        app.get("/tags").await.expect(200);
+        let mic = id(&f.mic);
+        app.get(&format!("/items/{}/tags", mic)).await.expect(200);
+        app.put(
+            &format!("/items/{}/tags", mic),
+            json!({ "tag_ids": [f.tags[1]["id"], f.tags[0]["id"], f.tags[1]["id"]] }),
+        )
+        .await
+        .expect(200);
+        app.put(
+            &format!("/items/{}/tags", mic),
+            json!({ "tag_ids": [999999] }),
+        )
+        .await
+        .expect(404);
+        app.get("/items/not-a-uuid/tags").await.expect(400);
+        app.put(
+            &format!("/tags/{}", id(&f.tags[0])),
+            json!({ "name": "音響機材" }),
+        )
+        .await
+        .expect(200);
+        app.delete(&format!("/tags/{}", id(&f.tags[1])))
+            .await
+            .expect(204);
+        app.get(&format!("/items/{}/tags", mic)).await.expect(200);
+        app.get(&format!("/tags/{}", id(&f.tags[0])))
+            .await
+            .expect(200);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn items() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let mic = id(&f.mic);
+        let projector = id(&f.projector);
+
+        app.get("/items").await.expect(200);
+        app.get("/items?search=マイク").await.expect(200);
+        app.get("/items?is_on_loan=true").await.expect(200);
+        app.get("/items?storage_type=container").await.expect(200);
+        app.get(&format!("/items?container_id={}", id(&f.rack)))
+            .await
+            .expect(200);
+        app.get("/items?page=2&per_page=2").await.expect(200);
+        app.get(&format!("/items/{}", mic)).await.expect(200);
+        app.get(&format!(
+            "/items/by-label/{}",
+            f.mic["label_id"].as_str().unwrap()
+        ))
+        .await
+        .expect(200);
+        app.get("/items/by-label/NOPE").await.expect(404);
+        app.get("/items/suggestions/connection_names")
+            .await
+            .expect(200);
+        app.get("/items/suggestions/storage_locations")
+            .await
+            .expect(200);
+        app.get(&format!("/items/{}/active-loan", projector))
+            .await
+            .expect(200);
+        app.get(&format!(
+            "/items/{}/availability?from=2030-01-10T09:00:00Z&to=2030-01-10T18:00:00Z",
+            projector
+        ))
+        .await
+        .expect(200);
+
+        app.put(
+            &format!("/items/{}", mic),
+            json!({ "remarks": "予備電池あり", "purchase_year": 2021 }),
+        )
+        .await
+        .expect(200);
+        app.post(
+            "/items",
+            json!({ "name": "重複", "label_id": f.mic["label_id"] }),
+        )
+        .await
+        .expect(409);
+        app.post(&format!("/items/{}/dispose", mic), json!({}))
+            .await
+            .expect(200);
+        app.get("/items?is_disposed=true").await.expect(200);
+        app.post(&format!("/items/{}/undispose", mic), json!({}))
+            .await
+            .expect(200);
+        app.post(
+            &format!("/items/{}/relabel", mic),
+            json!({ "label_id": f.spare_labels[0], "reason": "剥がれた" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!(
+            "/items/by-label/{}",
+            f.mic["label_id"].as_str().unwrap()
+        ))
+        .await
+        .expect(200);
+        // 編集でラベルを変えた場合も貼り替えとして扱い、古いラベルで探せる
+        app.put(
+            &format!("/items/{}", mic),
+            json!({ "label_id": f.spare_labels[1] }),
+        )
+        .await
+        .expect(200);
+        let found = app
+            .get(&format!("/items/by-label/{}", f.spare_labels[0]))
+            .await
+            .expect(200);
+        assert_eq!(found["superseded"], json!(true));
+        assert_eq!(found["label_id"], json!(f.spare_labels[1]));
+
+        app.post(
+            "/items/bulk/move",
+            json!({ "ids": [mic, id(&f.mixer)], "location": "ホール", "note": "本番" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/items/{}/transfers", mic))
+            .await
+            .expect(200);
+        app.put(
+            "/items/bulk/disposed",
+            json!({ "ids": [id(&f.mixer)], "is_disposed": true }),
+        )
+        .await
+        .expect(204);
+        app.get(&format!("/items/{}/history", mic))
+            .await
+            .expect(200);
+        app.get("/items/csv").await.expect(200);
+
+        app.delete(&format!("/items/{}", projector))
+            .await
+            .expect(400);
+        app.delete_with("/items/bulk", json!({ "ids": [id(&f.mixer)] }))
+            .await
+            .expect(204);
+        app.get("/items").await.expect(200);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn containers_and_locations() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let rack = id(&f.rack);
+        let case = id(&f.case);
+
+        app.get("/containers").await.expect(200);
+        app.get("/containers?search=ケース").await.expect(200);
+        app.get("/containers?sort_by=name&sort_order=asc")
+            .await
+            .expect(200);
+        app.get(&format!("/containers/{}", rack)).await.expect(200);
+        app.get(&format!("/containers/{}/tree", rack))
+            .await
+            .expect(200);
+        app.get(&format!("/containers/check/{}", rack))
+            .await
+            .expect(200);
+        app.get("/containers/by-location/倉庫").await.expect(200);
+        app.put(
+            &format!("/containers/{}", case),
+            json!({ "description": "マイク4本" }),
+        )
+        .await
+        .expect(200);
+        app.put(
+            &format!("/containers/{}", rack),
+            json!({ "parent_container_id": case }),
+        )
+        .await
+        .expect(400);
+
+        app.get("/locations").await.expect(200);
+        let building = app
+            .post("/locations", json!({ "name": "1号館", "kind": "building" }))
+            .await
+            .expect(201);
+        let stage = app
+            .post(
+                "/locations",
+                json!({ "name": "舞台袖", "kind": "room", "parent_id": building["id"] }),
+            )
+            .await
+            .expect(201);
+        // 自分の中のコンテナには移動できず、移動の記録も残らない
+        app.post(
+            &format!("/containers/{}/move", rack),
+            json!({ "parent_container_id": case }),
+        )
+        .await
+        .expect(400);
+        let transfers = app
+            .get(&format!("/containers/{}/transfers", rack))
+            .await
+            .expect(200);
+        assert_eq!(transfers, json!([]));
+        app.post(
+            &format!("/containers/{}/move", case),
+            json!({ "location_id": stage["id"], "note": "リハーサル" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/containers/{}/transfers", case))
+            .await
+            .expect(200);
+        app.get(&format!("/items/{}", id(&f.mic))).await.expect(200);
+        let hall = app.get("/locations").await.expect(200);
+        let hall_id = hall
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|location| location["name"] == "ホール")
+            .unwrap()["id"]
+            .clone();
+        app.post(
+            "/locations/merge",
+            json!({ "source_ids": [hall_id], "target_id": stage["id"] }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/locations/{}", id(&stage)))
+            .await
+            .expect(200);
+        app.put(
+            &format!("/locations/{}", id(&stage)),
+            json!({ "name": "下手袖" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/items/{}", id(&f.projector)))
+            .await
+            .expect(200);
+        app.delete(&format!("/locations/{}", id(&building)))
+            .await
+            .expect(409);
+
+        app.delete(&format!("/containers/{}", case))
+            .await
+            .expect(400);
+        app.put(
+            "/containers/bulk/disposed",
+            json!({ "ids": [case], "is_disposed": true }),
+        )
+        .await
+        .expect(204);
+        app.get("/containers?include_disposed=true")
+            .await
+            .expect(200);
+        app.delete_with("/containers/bulk", json!({ "ids": [rack] }))
+            .await
+            .expect(400);
+    })
+    .await;
+}
+
+/// 場所をまとめるときも下の場所の種類の順序を守り、物品の場所と監査ログを一緒に書き込む
+#[tokio::test]
+async fn location_merge_checks_children_and_updates_paths() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let building = app
+            .post("/locations", json!({ "name": "1号館", "kind": "building" }))
+            .await
+            .expect(201);
+        let mut rooms = Vec::new();
+        for name in ["101", "102"] {
+            let room = app
+                .post(
+                    "/locations",
+                    json!({ "name": name, "kind": "room", "parent_id": building["id"] }),
+                )
+                .await
+                .expect(201);
+            rooms.push(room);
+        }
+        let shelf_a = app
+            .post(
+                "/locations",
+                json!({ "name": "棚A", "kind": "shelf", "parent_id": rooms[0]["id"] }),
+            )
+            .await
+            .expect(201);
+        let shelf_b = app
+            .post(
+                "/locations",
+                json!({ "name": "棚B", "kind": "shelf", "parent_id": rooms[1]["id"] }),
+            )
+            .await
+            .expect(201);
+        let item = app
+            .post(
+                "/items",
+                json!({
+                    "name": "延長コード",
+                    "label_id": f.spare_labels[0],
+                    "storage_type": "location",
+                    "location_id": shelf_a["id"],
+                }),
+            )
+            .await
+            .expect(201);
+
+        // 棚の下に棚は置けないので、棚Aのある部屋は棚にまとめられない
+        app.post(
+            "/locations/merge",
+            json!({ "source_ids": [rooms[0]["id"]], "target_id": shelf_b["id"] }),
+        )
+        .await
+        .expect(400);
+        app.get(&format!("/locations/{}", id(&rooms[0])))
+            .await
+            .expect(200);
+
+        app.post(
+            "/locations/merge",
+            json!({ "source_ids": [rooms[0]["id"]], "target_id": rooms[1]["id"] }),
+        )
+        .await
+        .expect(200);
+        let item = app.get(&format!("/items/{}", id(&item))).await.expect(200);
+        assert_eq!(item["storage_location"], json!("1号館 > 102 > 棚A"));
+        let events = app
+            .get(&format!(
+                "/audit?entity_type=location&entity_id={}&action=merge",
+                id(&rooms[0])
+            ))
+            .await
+            .expect(200);
+        assert_eq!(events["total"], json!(1));
+    })
+    .await;
+}
+
+/// PUT で親コンテナを変えても移動として記録し、自分の中のコンテナには入れない
+#[tokio::test]
+async fn container_reparent_on_put_is_recorded_as_move() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let rack = id(&f.rack);
+        let case = id(&f.case);
+        let shelf = app
+            .post("/containers", json!({ "name": "棚", "location": "ホール" }))
+            .await
+            .expect(201)["container"]
+            .clone();
+
+        app.put(
+            &format!("/containers/{}", rack),
+            json!({ "parent_container_id": case }),
+        )
+        .await
+        .expect(400);
+        let transfers = app
+            .get(&format!("/containers/{}/transfers", rack))
+            .await
+            .expect(200);
+        assert_eq!(transfers, json!([]));
+
+        let updated = app
+            .put(
+                &format!("/containers/{}", case),
+                json!({ "parent_container_id": shelf["id"], "description": "マイク4本" }),
+            )
+            .await
+            .expect(200);
+        assert_eq!(updated["container"]["location"], json!("ホール"));
+        assert_eq!(updated["container"]["description"], json!("マイク4本"));
+        let transfers = app
+            .get(&format!("/containers/{}/transfers", case))
+            .await
+            .expect(200);
+        assert_eq!(transfers.as_array().unwrap().len(), 1);
+        assert_eq!(transfers[0]["to_container_id"], shelf["id"]);
+        let transfers = app
+            .get(&format!("/items/{}/transfers", id(&f.mic)))
+            .await
+            .expect(200);
+        assert_eq!(transfers.as_array().unwrap().len(), 1);
+
+        // 場所以外の変更は移動として記録しない
+        app.put(
+            &format!("/containers/{}", case),
+            json!({ "name": "ワイヤレスマイクケース" }),
+        )
+        .await
+        .expect(200);
+        let transfers = app
+            .get(&format!("/containers/{}/transfers", case))
+            .await
+            .expect(200);
+        assert_eq!(transfers.as_array().unwrap().len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn loans_and_reservations() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let mic_label = f.mic["label_id"].as_str().unwrap().to_string();
+        let mixer_label = f.mixer["label_id"].as_str().unwrap().to_string();
+
+        app.get("/loans").await.expect(200);
+        app.get(&format!("/loans/{}", id(&f.loan)))
+            .await
+            .expect(200);
+        app.get("/loans?active_only=true").await.expect(200);
+        app.get("/loans?student_number=24A0001").await.expect(200);
+        app.get("/loans/overdue").await.expect(200);
+        app.post(
+            "/loans",
+            json!({
+                "item_id": f.projector["id"],
+                "student_number": "24A0002",
+                "student_name": "筑波 花子",
+            }),
+        )
+        .await
+        .expect(409);
+
+        app.post(
+            &format!("/loans/by-label/{}", mic_label),
+            json!({
+                "student_number": "24A0002",
+                "student_name": "筑波 花子",
+                "remarks": "ケース付き",
+            }),
+        )
+        .await
+        .expect(201);
+        // 返却時に備考を指定しなければ、貸出時の備考を残す
+        let returned = app
+            .post(&format!("/loans/by-label/{}/return", mic_label), json!({}))
+            .await
+            .expect(200);
+        assert_eq!(returned["remarks"], json!("ケース付き"));
+        app.post(
+            "/loans/batch",
+            json!({
+                "label_ids": [mic_label, mixer_label],
+                "student_number": "24A0003",
+                "student_name": "天久保 次郎",
+                "organization": "音響班",
+            }),
+        )
+        .await
+        .expect(201);
+        app.post(
+            "/loans/batch",
+            json!({
+                "label_ids": [mic_label, "NOPE"],
+                "student_number": "24A0003",
+                "student_name": "天久保 次郎",
+            }),
+        )
+        .await
+        .expect(400);
+        app.post(
+            "/loans/batch/return",
+            json!({ "label_ids": [mic_label, mixer_label] }),
+        )
+        .await
+        .expect(200);
+
+        // 一括処理でも、貼り替え前のラベルは物品に解決し、チェック文字の誤りは 422 にする
+        app.post(&format!("/items/{}/relabel", id(&f.mixer)), json!({}))
+            .await
+            .expect(200);
+        app.post(
+            "/loans/batch",
+            json!({
+                "label_ids": [mixer_label],
+                "student_number": "24A0003",
+                "student_name": "天久保 次郎",
+            }),
+        )
+        .await
+        .expect(201);
+        app.post(
+            "/loans/batch/return",
+            json!({ "label_ids": [mixer_label] }),
+        )
+        .await
+        .expect(200);
+        app.post(
+            "/labels/sequences",
+            json!({ "name": "case", "prefix": "K-", "width": 3, "check_digit": true }),
+        )
+        .await
+        .expect(201);
+        app.post(
+            "/loans/batch/return",
+            json!({ "label_ids": ["K-0010"] }),
+        )
+        .await
+        .expect(422);
+        app.post(&format!("/loans/{}/return", id(&f.loan)), json!({}))
+            .await
+            .expect(200);
+        app.post(&format!("/loans/{}/return", id(&f.loan)), json!({}))
+            .await
+            .expect(409);
+        app.get("/loans/history").await.expect(200);
+
+        let reservation = app
+            .post(
+                "/reservations",
+                json!({
+                    "item_id": f.mic["id"],
+                    "student_number": "24A0004",
+                    "student_name": "桜 三郎",
+                    "start_time": "2030-01-10T09:00:00Z",
+                    "end_time": "2030-01-10T18:00:00Z",
+                }),
+            )
+            .await
+            .expect(201);
+        app.post(
+            "/reservations",
+            json!({
+                "item_id": f.mic["id"],
+                "student_number": "24A0005",
+                "student_name": "春日 四郎",
+                "start_time": "2030-01-10T12:00:00Z",
+                "end_time": "2030-01-10T20:00:00Z",
+            }),
+        )
+        .await
+        .expect(409);
+        app.get("/reservations").await.expect(200);
+        app.get(&format!("/reservations/{}", id(&reservation)))
+            .await
+            .expect(200);
+        app.get(&format!(
+            "/items/{}/availability?from=2030-01-10T00:00:00Z&to=2030-01-11T00:00:00Z",
+            id(&f.mic)
+        ))
+        .await
+        .expect(200);
+        app.post(
+            &format!("/reservations/{}/cancel", id(&reservation)),
+            json!({}),
+        )
+        .await
+        .expect(200);
+        app.get("/reservations?status=cancelled").await.expect(200);
+    })
+    .await;
+}
+
+/// 予約と重なる貸出は何も書き込まずに断り、貸し出したときは監査ログも残す
+#[tokio::test]
+async fn single_loan_checks_reservations_and_records_audit() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        app.post(
+            "/reservations",
+            json!({
+                "item_id": f.mic["id"],
+                "student_number": "24A0004",
+                "student_name": "桜 三郎",
+                "start_time": "2030-01-10T09:00:00Z",
+                "end_time": "2030-01-10T18:00:00Z",
+            }),
+        )
+        .await
+        .expect(201);
+
+        app.post(
+            "/loans",
+            json!({
+                "item_id": f.mic["id"],
+                "student_number": "24A0002",
+                "student_name": "筑波 花子",
+                "due_date": "2030-01-10T12:00:00Z",
+            }),
+        )
+        .await
+        .expect(409);
+        let mic = app.get(&format!("/items/{}", id(&f.mic))).await.expect(200);
+        assert_eq!(mic["is_on_loan"], json!(false));
+        let history = app
+            .get(&format!("/loans?item_id={}", id(&f.mic)))
+            .await
+            .expect(200);
+        assert_eq!(history["total"], json!(0));
+
+        let loan = app
+            .post(
+                "/loans",
+                json!({
+                    "item_id": f.mic["id"],
+                    "student_number": "24A0002",
+                    "student_name": "筑波 花子",
+                    "due_date": "2030-01-09T18:00:00Z",
+                }),
+            )
+            .await
+            .expect(201);
+        let events = app
+            .get(&format!("/audit?entity_type=loan&entity_id={}", id(&loan)))
+            .await
+            .expect(200);
+        assert_eq!(events["total"], json!(1));
+        assert_eq!(events["events"][0]["action"], json!("loan"));
+    })
+    .await;
+}
+
+/// 一部の物品が貸出中のコンテナは、残りの物品だけを貸し出す
+#[tokio::test]
+async fn container_checkout_skips_items_on_loan() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let rack = id(&f.rack);
+        let cable = app
+            .post(
+                "/items",
+                json!({
+                    "name": "XLRケーブル",
+                    "label_id": f.spare_labels[0],
+                    "storage_type": "container",
+                    "container_id": rack,
+                }),
+            )
+            .await
+            .expect(201);
+        let borrower = json!({ "student_number": "24A0002", "student_name": "筑波 花子" });
+        app.post(
+            &format!("/loans/by-label/{}", f.mixer["label_id"].as_str().unwrap()),
+            borrower.clone(),
+        )
+        .await
+        .expect(201);
+
+        let scanned = app.get(&format!("/scan/{}", rack)).await.expect(200);
+        let actions = scanned["actions"].as_array().unwrap();
+        assert!(actions.contains(&json!("checkout_all")));
+        assert!(actions.contains(&json!("return_all")));
+
+        let loaned = app
+            .post(&format!("/loans/by-label/{}", rack), borrower.clone())
+            .await
+            .expect(201);
+        assert_eq!(loaned["committed"], json!(true));
+        assert_eq!(loaned["results"].as_array().unwrap().len(), 1);
+        assert_eq!(loaned["results"][0]["item_id"], cable["id"]);
+
+        let scanned = app.get(&format!("/scan/{}", rack)).await.expect(200);
+        let actions = scanned["actions"].as_array().unwrap();
+        assert!(!actions.contains(&json!("checkout_all")));
+        app.post(&format!("/loans/by-label/{}", rack), borrower)
+            .await
+            .expect(409);
+    })
+    .await;
+}
+
+/// ラベル管理より前からあるコンテナ `K-010` を作る。`labels` に行がないので、採番ルールに
+/// チェック文字を付けるとIDがチェック文字の誤りに見える
+async fn legacy_container(db: &TestDatabase, app: &TestApp) {
+    app.post(
+        "/labels/sequences",
+        json!({ "name": "case", "prefix": "K-", "width": 3 }),
+    )
+    .await
+    .expect(201);
+    app.post(
+        "/containers",
+        json!({ "id": "K-010", "name": "ケース", "location": "倉庫" }),
+    )
+    .await
+    .expect(201);
+    db.execute("DELETE FROM labels WHERE id = 'K-010'").await;
+    app.post("/labels/sequences/case/check-digit", json!({}))
+        .await
+        .expect(200);
+}
+
+/// チェック文字が合わないように見えても、コンテナとして読み取れる
+#[tokio::test]
+async fn scan_finds_legacy_container_before_checking_label() {
+    let db = TestDatabase::sqlite().await;
+    let app = TestApp::new(&db).await;
+    legacy_container(&db, &app).await;
+
+    let scanned = app.get("/scan/K-010").await.expect(200);
+    assert_eq!(scanned["kind"], json!("container"));
+    app.get("/scan/K-011").await.expect(422);
+
+    db.drop().await;
+}
+
+/// ラベルIDでの貸出・返却とIDの確認も、チェック文字の誤りより先にコンテナを探す
+#[tokio::test]
+async fn loans_by_label_find_legacy_container_before_checking_label() {
+    let db = TestDatabase::sqlite().await;
+    let app = TestApp::new(&db).await;
+    legacy_container(&db, &app).await;
+    let label = app
+        .post(
+            "/labels/generate",
+            json!({ "quantity": 1, "record_type": "qr" }),
+        )
+        .await
+        .expect(200)["visible_ids"][0]
+        .clone();
+    app.post(
+        "/items",
+        json!({
+            "name": "マイク",
+            "label_id": label,
+            "storage_type": "container",
+            "container_id": "K-010",
+        }),
+    )
+    .await
+    .expect(201);
+
+    let checked = app.get("/ids/check/K-010").await.expect(200);
+    assert_eq!(checked["found_in"], json!(["containers"]));
+    app.get("/ids/check/K-011").await.expect(422);
+
+    let borrower = json!({ "student_number": "24A0001", "student_name": "雙峰 太郎" });
+    let loaned = app
+        .post("/loans/by-label/K-010", borrower.clone())
+        .await
+        .expect(201);
+    assert_eq!(loaned["committed"], json!(true));
+    app.post("/loans/by-label/K-011", borrower).await.expect(422);
+    let returned = app
+        .post("/loans/by-label/K-010/return", json!({}))
+        .await
+        .expect(200);
+    assert_eq!(returned["committed"], json!(true));
+    app.post("/loans/by-label/K-011/return", json!({}))
+        .await
+        .expect(422);
+
+    db.drop().await;
+}
+
+async fn generate_label(app: &TestApp, sequence: &str) -> String {
+    let generated = app
+        .post(
+            "/labels/generate",
+            json!({ "quantity": 1, "record_type": "qr", "sequence": sequence }),
+        )
+        .await
+        .expect(200);
+    generated["visible_ids"][0].as_str().unwrap().to_string()
+}
+
+/// チェック文字付きで発行したラベルも、チェック文字を付ける前に発行したラベルも一覧に載る
+#[tokio::test]
+async fn label_list_includes_labels_with_check_character() {
+    let db = TestDatabase::sqlite().await;
+    let app = TestApp::new(&db).await;
+    let unchecked = generate_label(&app, "default").await;
+    app.post("/labels/sequences/default/check-digit", json!({}))
+        .await
+        .expect(200);
+    let checked = generate_label(&app, "default").await;
+    app.post(
+        "/labels/sequences",
+        json!({ "name": "cable", "prefix": "C-", "width": 3, "check_digit": true }),
+    )
+    .await
+    .expect(201);
+    let cable = generate_label(&app, "cable").await;
+    assert_eq!(checked.len(), 5);
+
+    let labels = app.get("/labels").await.expect(200);
+    let labels = labels.as_array().unwrap();
+    for label in [&unchecked, &checked, &cable] {
+        let info = labels
+            .iter()
+            .find(|info| info["id"] == json!(label))
+            .unwrap_or_else(|| panic!("{} is not listed", label));
+        assert_eq!(info["status"], json!("generated"));
+    }
+    assert!(!labels.iter().any(|info| info["id"] == json!(checked[..4])));
+
+    db.drop().await;
+}
+
+/// 印刷データの取得ではラベルの状態は変わらず、印刷済みの記録は POST で行う
+#[tokio::test]
+async fn label_print_is_recorded_only_by_post() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let label = generate_label(&app, "default").await;
+        app.post(
+            "/users",
+            json!({ "username": "viewer", "password": "viewer-password", "role": "viewer" }),
+        )
+        .await
+        .expect(201);
+        let mut viewer = app.clone();
+        viewer.login_as("viewer", "viewer-password").await;
+
+        viewer
+            .get(&format!("/labels/{}/print?format=zpl", label))
+            .await
+            .expect(200);
+        let record = app
+            .get(&format!("/labels/{}/record", label))
+            .await
+            .expect(200);
+        assert_eq!(record["status"], json!("generated"));
+        viewer
+            .post(&format!("/labels/{}/print", label), json!({ "format": "zpl" }))
+            .await
+            .expect(403);
+
+        app.post(&format!("/labels/{}/print", label), json!({ "format": "zpl" }))
+            .await
+            .expect(200);
+        let record = app
+            .get(&format!("/labels/{}/record", label))
+            .await
+            .expect(200);
+        assert_eq!(record["status"], json!("printed"));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn labels_and_stocktakes() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let mic_label = f.mic["label_id"].as_str().unwrap().to_string();
+        let spare = f.spare_labels[0].clone();
+
+        app.get("/labels").await.expect(200);
+        app.get("/labels/records").await.expect(200);
+        app.get("/labels/unattached").await.expect(200);
+        app.get("/labels/batches").await.expect(200);
+        app.get("/labels/sequences").await.expect(200);
+        app.post(
+            "/labels/sequences",
+            json!({ "name": "cable", "prefix": "C", "width": 4 }),
+        )
+        .await
+        .expect(201);
+        // "C001" は接頭辞なし・4桁の default のラベルIDとしても読めてしまう
+        app.post(
+            "/labels/sequences",
+            json!({ "name": "short", "prefix": "C", "width": 3, "alphabet": "0123456789" }),
+        )
+        .await
+        .expect(409);
+        // "C0001" は cable のラベルIDとしても読めてしまう
+        app.post(
+            "/labels/sequences",
+            json!({ "name": "cable-sub", "prefix": "C0", "width": 3 }),
+        )
+        .await
+        .expect(409);
+        app.post(
+            "/labels/generate",
+            json!({ "quantity": 2, "record_type": "barcode", "sequence": "cable" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/labels/{}/record", mic_label))
+            .await
+            .expect(200);
+        app.get(&format!("/labels/{}/qr.svg", mic_label))
+            .await
+            .expect(200);
+        app.get(&format!("/labels/{}/barcode.svg", mic_label))
+            .await
+            .expect(200);
+        app.get(&format!("/labels/{}/print?format=zpl", mic_label))
+            .await
+            .expect(200);
+        app.post(&format!("/labels/{}/void", spare), json!({}))
+            .await
+            .expect(200);
+        app.post(&format!("/labels/{}/void", mic_label), json!({}))
+            .await
+            .expect(409);
+        app.get(&format!("/ids/check/{}", mic_label))
+            .await
+            .expect(200);
+        app.get(&format!("/ids/check/{}", f.spare_labels[1]))
+            .await
+            .expect(200);
+        app.get(&format!("/scan/{}", mic_label)).await.expect(200);
+        app.get(&format!("/scan/{}", id(&f.rack))).await.expect(200);
+        app.get("/scan/NOPE").await.expect(404);
+
+        let session = app
+            .post(
+                "/stocktakes",
+                json!({ "name": "前期棚卸し", "container_ids": [id(&f.rack)] }),
+            )
+            .await
+            .expect(201);
+        let session_id = id(&session);
+        app.post(
+            &format!("/stocktakes/{}/scans", session_id),
+            json!({ "label_id": mic_label, "container_id": id(&f.case) }),
+        )
+        .await
+        .expect(201);
+        app.post(
+            &format!("/stocktakes/{}/scans", session_id),
+            json!({ "label_id": f.projector["label_id"], "container_id": id(&f.rack) }),
+        )
+        .await
+        .expect(201);
+        app.post(
+            &format!("/stocktakes/{}/scans", session_id),
+            json!({ "label_id": f.mixer["label_id"], "container_id": id(&f.case) }),
+        )
+        .await
+        .expect(201);
+        app.get("/stocktakes").await.expect(200);
+        app.get(&format!("/stocktakes/{}", session_id))
+            .await
+            .expect(200);
+        let scans = app
+            .get(&format!("/stocktakes/{}/scans", session_id))
+            .await
+            .expect(200);
+        let scanned: Vec<_> = scans
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|scan| scan["item_id"].clone())
+            .collect();
+        assert_eq!(
+            scanned,
+            vec![
+                f.mic["id"].clone(),
+                f.projector["id"].clone(),
+                f.mixer["id"].clone()
+            ]
+        );
+        app.get(&format!("/stocktakes/{}/report", session_id))
+            .await
+            .expect(200);
+        app.post(&format!("/stocktakes/{}/close", session_id), json!({}))
+            .await
+            .expect(200);
+        app.get("/stocktakes?status=closed").await.expect(200);
+
+        // 修正せずに終了したセッションは、呼び直すと見つかった場所に直して移動を記録する
+        let closed = app
+            .post(
+                &format!("/stocktakes/{}/close", session_id),
+                json!({ "apply_corrections": true }),
+            )
+            .await
+            .expect(200);
+        assert_eq!(closed["corrected_item_ids"], json!([f.mixer["id"]]));
+        assert_eq!(closed["session"]["corrections_applied"], json!(1));
+        let mixer = app
+            .get(&format!("/items/{}", id(&f.mixer)))
+            .await
+            .expect(200);
+        assert_eq!(mixer["container_id"], f.case["id"]);
+        let transfers = app
+            .get(&format!("/items/{}/transfers", id(&f.mixer)))
+            .await
+            .expect(200);
+        assert_eq!(transfers.as_array().unwrap().len(), 1);
+        assert_eq!(transfers[0]["to_container_id"], f.case["id"]);
+        app.post(
+            &format!("/stocktakes/{}/close", session_id),
+            json!({ "apply_corrections": true }),
+        )
+        .await
+        .expect(409);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn users_and_audit() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        Fixtures::load(&app).await;
+
+        app.get("/auth/me").await.expect(200);
+        let lender = app
+            .post(
+                "/users",
+                json!({
+                    "username": "lender",
+                    "display_name": "貸出係",
+                    "password": "lender-password",
+                    "role": "lender",
+                }),
+            )
+            .await
+            .expect(201);
+        app.post(
+            "/users",
+            json!({ "username": "lender", "password": "lender-password", "role": "viewer" }),
+        )
+        .await
+        .expect(400);
+        app.get("/users").await.expect(200);
+        app.put(
+            &format!("/users/{}", id(&lender)),
+            json!({ "role": "viewer" }),
+        )
+        .await
+        .expect(200);
+        app.get(&format!("/users/{}", id(&lender)))
+            .await
+            .expect(200);
+        let token = app
+            .post("/auth/tokens", json!({ "name": "受付端末" }))
+            .await
+            .expect(201);
+        app.get("/auth/tokens").await.expect(200);
+        app.delete(&format!("/auth/tokens/{}", id(&token["api_token"])))
+            .await
+            .expect(204);
+        app.delete(&format!("/users/{}", id(&lender)))
+            .await
+            .expect(204);
+
+        app.get("/audit?per_page=100").await.expect(200);
+        app.get("/audit?entity_type=item&action=create")
+            .await
+            .expect(200);
+        app.get("/audit?entity_type=tag").await.expect(200);
+        app.get("/audit?actor=admin&per_page=5&page=2")
+            .await
+            .expect(200);
+    })
+    .await;
+}
@@ -0,0 +1,135 @@
+//! `GET /admin/backup` のアーカイブを `POST /admin/restore` で空の環境に戻すと、
+//! 元と同じ応答と画像が返ることを確かめる。
+
+use std::collections::HashMap;
+use std::io::Read as _;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::data_migration::{microseconds, populate};
+use super::fixtures::id;
+use super::{TestApp, TestDatabase};
+
+const IMAGE: &[u8] = b"\x89PNG\r\n\x1a\n not really a png";
+
+async fn upload_item_image(app: &TestApp, item_id: &str) -> Value {
+    let boundary = "backup-test-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"mic.png\"\r\nContent-Type: image/png\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(IMAGE);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let (status, bytes) = app
+        .send_raw(
+            Method::POST,
+            &format!("/items/{}/image", item_id),
+            &format!("multipart/form-data; boundary={boundary}"),
+            body,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+async fn download_backup(app: &TestApp) -> Vec<u8> {
+    let (status, archive) = app
+        .send_raw(Method::GET, "/admin/backup", "application/json", Vec::new())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    archive
+}
+
+/// アーカイブの中身をパスごとに取り出す
+fn entries(archive: &[u8]) -> HashMap<String, Vec<u8>> {
+    let mut tar = tar::Archive::new(archive);
+    tar.entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            (path, data)
+        })
+        .collect()
+}
+
+async fn restore(app: &TestApp, archive: Vec<u8>) -> (StatusCode, Value) {
+    let (status, body) = app
+        .send_raw(Method::POST, "/admin/restore", "application/x-tar", archive)
+        .await;
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn restored_backup_returns_the_same_responses_and_images() {
+    let source_db = TestDatabase::sqlite().await;
+    let source = TestApp::new(&source_db).await;
+    let f = populate(&source).await;
+    let mic = id(&f.mic);
+    let image_url = upload_item_image(&source, &mic).await["image_url"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let paths = [
+        "/items?per_page=100".to_string(),
+        format!("/items/{}", mic),
+        format!("/containers/{}/tree", id(&f.rack)),
+        "/locations".to_string(),
+        "/loans".to_string(),
+        "/reservations".to_string(),
+        "/tags".to_string(),
+        "/stocktakes".to_string(),
+        "/labels/records?per_page=100".to_string(),
+        "/labels/sequences".to_string(),
+        "/users".to_string(),
+    ];
+    let mut expected = Vec::new();
+    for path in &paths {
+        expected.push(microseconds(&source.get(path).await.expect(200)));
+    }
+
+    let archive = download_backup(&source).await;
+    let files = entries(&archive);
+    let dump: Value = serde_json::from_slice(&files["backup.json"]).unwrap();
+    assert_eq!(dump["format"], json!("hyperdashi-backup"));
+    assert_eq!(dump["version"], json!(1));
+    let key = dump["images"][0]["key"].as_str().unwrap();
+    assert!(image_url.ends_with(key), "{} / {}", image_url, key);
+    assert_eq!(files[&format!("images/{}", key)], IMAGE);
+
+    let target_db = match TestDatabase::postgres().await {
+        Some(db) => db,
+        None => TestDatabase::sqlite().await,
+    };
+    let mut target = TestApp::new(&target_db).await;
+
+    let (status, body) = restore(&target, b"not a tar archive".to_vec()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    let (status, summary) = restore(&target, archive.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", summary);
+    assert_eq!(summary["images"], json!(1));
+    assert_eq!(summary["missing_images"], json!([]));
+
+    // セッションもバックアップの内容に置き換わるので、ログインし直す
+    target.login().await;
+    for (path, expected) in paths.iter().zip(&expected) {
+        let actual = microseconds(&target.get(path).await.expect(200));
+        assert_eq!(&actual, expected, "[{}] {}", target.backend, path);
+    }
+
+    // 戻した環境のバックアップにも同じ画像が入っている
+    let files = entries(&download_backup(&target).await);
+    assert_eq!(files[&format!("images/{}", key)], IMAGE);
+
+    // データが入っている環境には戻さない
+    let (status, body) = restore(&target, archive).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+
+    target_db.drop().await;
+}
@@ -0,0 +1,256 @@
+//! `migrate-data` で写したデータベースが、元と同じ応答を返すことを確かめる。
+//!
+//! SQLite から Postgres（`TEST_DATABASE_URL` がなければ別の SQLite）へ写し、
+//! さらにそこから新しい SQLite へ戻す。
+
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use super::fixtures::{id, Fixtures};
+use super::{TestApp, TestDatabase};
+use crate::db::tables::TABLES;
+use crate::db::DatabasePool;
+use crate::error::AppError;
+use crate::services::DataMigrationService;
+
+/// Postgres は時刻をマイクロ秒までしか持たないので、それより細かい桁は比べない
+static SUB_MICROSECONDS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d{2}:\d{2}:\d{2}\.\d{6})\d+").unwrap());
+
+pub(super) fn microseconds(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(SUB_MICROSECONDS.replace_all(text, "$1").into()),
+        Value::Array(values) => Value::Array(values.iter().map(microseconds).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, v)| (key.clone(), microseconds(v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// 一通りのテーブルに行がある状態にする
+pub(super) async fn populate(app: &TestApp) -> Fixtures {
+    let f = Fixtures::load(app).await;
+    let mic = id(&f.mic);
+
+    app.post(
+        &format!("/items/{}/relabel", mic),
+        json!({ "label_id": f.spare_labels[0], "reason": "剥がれた" }),
+    )
+    .await
+    .expect(200);
+    let building = app
+        .post("/locations", json!({ "name": "1号館", "kind": "building" }))
+        .await
+        .expect(201);
+    let stage = app
+        .post(
+            "/locations",
+            json!({ "name": "舞台袖", "kind": "room", "parent_id": building["id"] }),
+        )
+        .await
+        .expect(201);
+    app.post(
+        &format!("/containers/{}/move", id(&f.case)),
+        json!({ "location_id": stage["id"], "note": "リハーサル" }),
+    )
+    .await
+    .expect(200);
+    app.post(&format!("/loans/{}/return", id(&f.loan)), json!({}))
+        .await
+        .expect(200);
+    app.post(
+        "/reservations",
+        json!({
+            "item_id": f.mic["id"],
+            "student_number": "24A0004",
+            "student_name": "桜 三郎",
+            "start_time": "2030-01-10T09:00:00Z",
+            "end_time": "2030-01-10T18:00:00Z",
+        }),
+    )
+    .await
+    .expect(201);
+    let session = app
+        .post(
+            "/stocktakes",
+            json!({ "name": "前期棚卸し", "container_ids": [id(&f.rack)] }),
+        )
+        .await
+        .expect(201);
+    app.post(
+        &format!("/stocktakes/{}/scans", id(&session)),
+        json!({ "label_id": f.spare_labels[0], "container_id": id(&f.case) }),
+    )
+    .await
+    .expect(201);
+    app.post("/auth/tokens", json!({ "name": "受付端末" }))
+        .await
+        .expect(201);
+    f
+}
+
+#[tokio::test]
+async fn migrated_database_returns_the_same_responses() {
+    let source_db = TestDatabase::sqlite().await;
+    let source = TestApp::new(&source_db).await;
+    let f = populate(&source).await;
+    let mic = id(&f.mic);
+    let paths = [
+        "/items?per_page=100".to_string(),
+        format!("/items/{}", mic),
+        format!("/items/{}/tags", mic),
+        format!("/items/{}/transfers", mic),
+        format!("/containers/{}/tree", id(&f.rack)),
+        "/containers".to_string(),
+        "/locations".to_string(),
+        "/loans".to_string(),
+        "/reservations".to_string(),
+        "/cable_colors".to_string(),
+        "/connectors".to_string(),
+        "/tags".to_string(),
+        "/stocktakes".to_string(),
+        "/labels/records?per_page=100".to_string(),
+        "/labels/batches".to_string(),
+        "/labels/sequences".to_string(),
+        "/users".to_string(),
+        "/audit?per_page=200".to_string(),
+    ];
+    let mut expected = Vec::new();
+    for path in &paths {
+        expected.push(microseconds(&source.get(path).await.expect(200)));
+    }
+
+    let middle_db = match TestDatabase::postgres().await {
+        Some(db) => db,
+        None => TestDatabase::sqlite().await,
+    };
+    let back_db = TestDatabase::sqlite().await;
+    for (from, to) in [(&source_db, &middle_db), (&middle_db, &back_db)] {
+        let counts = DataMigrationService::new(from.pool.clone(), to.pool.clone())
+            .run()
+            .await
+            .unwrap();
+        assert!(counts
+            .iter()
+            .any(|count| count.table == "item_tags" && count.target == 2));
+    }
+
+    // 既にデータがあるデータベースには写さない
+    let again = DataMigrationService::new(source_db.pool.clone(), back_db.pool.clone())
+        .run()
+        .await;
+    assert!(matches!(again, Err(AppError::Conflict(_))), "{:?}", again);
+
+    for db in [&middle_db, &back_db] {
+        let app = TestApp::connect(db).await;
+        for (path, expected) in paths.iter().zip(&expected) {
+            let actual = microseconds(&app.get(path).await.expect(200));
+            assert_eq!(&actual, expected, "[{}] {}", db.backend(), path);
+        }
+
+        // 連番とラベルの採番は写したデータの続きから
+        let tag = app
+            .post("/tags", json!({ "name": "照明" }))
+            .await
+            .expect(201);
+        assert_eq!(tag["id"], json!(3), "[{}]", db.backend());
+        let labels = app
+            .post(
+                "/labels/generate",
+                json!({ "quantity": 1, "record_type": "qr" }),
+            )
+            .await
+            .expect(200);
+        app.post(
+            "/items",
+            json!({ "name": "照明卓", "label_id": labels["visible_ids"][0] }),
+        )
+        .await
+        .expect(201);
+    }
+
+    middle_db.drop().await;
+}
+
+/// マイグレーションが作った列（テーブル名 → 列名と型）。型は Postgres だけ `ColumnKind` に直して比べる
+async fn schema_columns(db: &TestDatabase) -> BTreeMap<String, Vec<(String, Option<String>)>> {
+    let rows: Vec<(String, String, Option<String>)> = match &db.pool {
+        DatabasePool::Postgres(pool) => sqlx::query_as(
+            r#"
+            SELECT table_name::text, column_name::text, data_type::text
+            FROM information_schema.columns
+            WHERE table_schema = current_schema() AND table_name <> '_sqlx_migrations'
+            ORDER BY table_name, ordinal_position
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap(),
+        DatabasePool::Sqlite(pool) => sqlx::query_as(
+            r#"
+            SELECT m.name, p.name, NULL
+            FROM sqlite_master m, pragma_table_info(m.name) p
+            WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND m.name <> '_sqlx_migrations'
+            ORDER BY m.name, p.cid
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap(),
+    };
+    let mut tables: BTreeMap<String, Vec<(String, Option<String>)>> = BTreeMap::new();
+    for (table, column, data_type) in rows {
+        let kind = data_type.map(|data_type| {
+            match data_type.as_str() {
+                "uuid" => "Uuid",
+                "integer" => "Int",
+                "bigint" => "BigInt",
+                "real" => "Real",
+                "boolean" => "Bool",
+                "timestamp with time zone" => "Timestamp",
+                _ => "Text",
+            }
+            .to_string()
+        });
+        tables.entry(table).or_default().push((column, kind));
+    }
+    tables
+}
+
+/// `TABLES` に書いた列が、マイグレーション後のスキーマと一致する（バックアップ・データ移行で列を落とさない）
+#[tokio::test]
+async fn table_list_matches_migrated_schema() {
+    let mut databases = vec![TestDatabase::sqlite().await];
+    databases.extend(TestDatabase::postgres().await);
+    for db in databases {
+        let postgres = matches!(db.pool, DatabasePool::Postgres(_));
+        // 列の並びは問わない
+        let expected: BTreeMap<String, Vec<(String, Option<String>)>> = TABLES
+            .iter()
+            .map(|table| {
+                let mut columns: Vec<_> = table
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let kind = postgres.then(|| format!("{:?}", column.kind));
+                        (column.name.to_string(), kind)
+                    })
+                    .collect();
+                columns.sort();
+                (table.name.to_string(), columns)
+            })
+            .collect();
+        let mut actual = schema_columns(&db).await;
+        for columns in actual.values_mut() {
+            columns.sort();
+        }
+        assert_eq!(actual, expected, "[{}]", db.backend());
+        db.drop().await;
+    }
+}
@@ -0,0 +1,180 @@
+//! シナリオの前提になるデータ。API を通して作るので、作る過程も比較の対象になる。
+
+use serde_json::{json, Value};
+
+use super::TestApp;
+
+pub(crate) struct Fixtures {
+    /// 物品に使っていないラベル
+    pub spare_labels: Vec<String>,
+    pub cable_colors: Vec<Value>,
+    pub connectors: Vec<Value>,
+    pub tags: Vec<Value>,
+    /// 倉庫のラック
+    pub rack: Value,
+    /// ラックの中のケース
+    pub case: Value,
+    /// ケースに入ったマイク
+    pub mic: Value,
+    /// ラックに入ったミキサー
+    pub mixer: Value,
+    /// ホールに置いてあるプロジェクター（貸出中）
+    pub projector: Value,
+    pub loan: Value,
+}
+
+impl Fixtures {
+    pub async fn load(app: &TestApp) -> Self {
+        let labels = app
+            .post(
+                "/labels/generate",
+                json!({ "quantity": 6, "record_type": "qr" }),
+            )
+            .await
+            .expect(200)["visible_ids"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        let mut cable_colors = Vec::new();
+        for (name, hex_code) in [("red", "#ff0000"), ("blue", "#0000a4")] {
+            let color = app
+                .post(
+                    "/cable_colors",
+                    json!({ "name": name, "hex_code": hex_code }),
+                )
+                .await
+                .expect(201);
+            cable_colors.push(color);
+        }
+
+        let mut connectors = Vec::new();
+        for (name, gender) in [("XLR", Some("male")), ("BNC", None)] {
+            let connector = app
+                .post("/connectors", json!({ "name": name, "gender": gender }))
+                .await
+                .expect(201);
+            connectors.push(connector);
+        }
+
+        let mut tags = Vec::new();
+        for (name, color) in [("音響", "#3366ff"), ("映像", "#ff9900")] {
+            let tag = app
+                .post("/tags", json!({ "name": name, "color": color }))
+                .await
+                .expect(201);
+            tags.push(tag);
+        }
+
+        let rack = app
+            .post(
+                "/containers",
+                json!({ "name": "ラック", "location": "倉庫" }),
+            )
+            .await
+            .expect(201)["container"]
+            .clone();
+        let case = app
+            .post(
+                "/containers",
+                json!({ "name": "マイクケース", "parent_container_id": rack["id"] }),
+            )
+            .await
+            .expect(201)["container"]
+            .clone();
+
+        let mic = app
+            .post(
+                "/items",
+                json!({
+                    "name": "ワイヤレスマイク",
+                    "label_id": labels[0],
+                    "model_number": "SM58",
+                    "purchase_year": 2020,
+                    "purchase_amount": 12000.0,
+                    "durability_years": 5,
+                    "is_depreciation_target": false,
+                    "connection_names": ["XLR"],
+                    "cable_color_pattern": ["red", "blue"],
+                    "storage_type": "container",
+                    "container_id": case["id"],
+                }),
+            )
+            .await
+            .expect(201);
+        let mixer = app
+            .post(
+                "/items",
+                json!({
+                    "name": "ミキサー",
+                    "label_id": labels[1],
+                    "connection_names": ["XLR", "BNC"],
+                    "storage_type": "container",
+                    "container_id": rack["id"],
+                }),
+            )
+            .await
+            .expect(201);
+        let projector = app
+            .post(
+                "/items",
+                json!({
+                    "name": "プロジェクター",
+                    "label_id": labels[2],
+                    "remarks": "電源ケーブル付き",
+                    "storage_location": "ホール",
+                }),
+            )
+            .await
+            .expect(201);
+
+        app.put(
+            &format!("/items/{}/tags", mic["id"].as_str().unwrap()),
+            json!({ "tag_ids": [tags[0]["id"]] }),
+        )
+        .await
+        .expect(200);
+        app.put(
+            &format!("/items/{}/tags", projector["id"].as_str().unwrap()),
+            json!({ "tag_ids": [tags[1]["id"]] }),
+        )
+        .await
+        .expect(200);
+
+        let loan = app
+            .post(
+                "/loans",
+                json!({
+                    "item_id": projector["id"],
+                    "student_number": "24A0001",
+                    "student_name": "雙峰 太郎",
+                    "organization": "映像研究会",
+                }),
+            )
+            .await
+            .expect(201);
+
+        Self {
+            spare_labels: labels[3..].to_vec(),
+            cable_colors,
+            connectors,
+            tags,
+            rack,
+            case,
+            mic,
+            mixer,
+            projector,
+            loan,
+        }
+    }
+}
+
+/// JSON の `id` を文字列として取り出す（数値の ID も文字列にする）
+pub(crate) fn id(value: &Value) -> String {
+    match &value["id"] {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
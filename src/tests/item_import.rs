@@ -0,0 +1,153 @@
+//! `POST /items/import` のシナリオ。取り込んだ結果は記録される GET の応答で両方のバックエンドを比べる。
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::fixtures::{id, Fixtures};
+use super::{assert_same_on_every_backend, TestApp, TestDatabase};
+
+async fn import(app: &TestApp, query: &str, csv: Vec<u8>) -> (StatusCode, Value) {
+    let (status, body) = app
+        .send_raw(
+            Method::POST,
+            &format!("/items/import?{}", query),
+            "text/csv",
+            csv,
+        )
+        .await;
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn shift_jis(text: &str) -> Vec<u8> {
+    encoding_rs::SHIFT_JIS.encode(text).0.into_owned()
+}
+
+/// エラーの (行, 列) の一覧
+fn error_cells(report: &Value) -> Vec<(u64, String)> {
+    report["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["row"].as_u64().unwrap(), e["column"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn items_import() {
+    assert_same_on_every_backend(|app: TestApp| async move {
+        let f = Fixtures::load(&app).await;
+        let mic_label = f.mic["label_id"].as_str().unwrap();
+        let spare = &f.spare_labels[0];
+
+        // 書き出した CSV をそのまま更新モードで取り込むと、ラベル列で同じ物品を更新するだけで増えない
+        let exported = app.get("/items/csv").await.expect(200);
+        let (status, report) = import(
+            &app,
+            "mode=upsert",
+            exported.as_str().unwrap().as_bytes().to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", report);
+        assert_eq!(report["errors"], json!([]));
+        assert_eq!(report["created"], json!(0));
+        assert_eq!(report["updated"], json!(3));
+        assert_eq!(report["applied"], json!(true));
+        let places: Vec<_> = report["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| (row["container_id"].clone(), row["storage_location"].clone()))
+            .collect();
+        assert!(places.contains(&(f.case["id"].clone(), Value::Null)));
+        assert!(places.contains(&(Value::Null, json!("ホール"))));
+
+        let csv = format!(
+            "型番,物品名,個数,物品詳細,保管場所,使用用途,使用時期,年間必要数,備考,ラベルID\n\
+             SM58,ワイヤレスマイク B,1,,{rack},,当日,1,予備あり,{mic}\n\
+             ,延長コード,3,\"10m\n黒\",倉庫 > 棚,,当日,1,,\n\
+             ,ケーブル,1,,,,,,,{spare}\n\
+             ,ケーブル,1,,,,,,,{spare}\n\
+             ,,0,,,,,,,\n\
+             ,,,,,,,,,\n",
+            rack = id(&f.rack),
+            mic = mic_label,
+            spare = spare,
+        );
+
+        // 新規登録だけのモードでは、使用中のラベル・重複したラベル・不正な行があれば何も書き込まない
+        let (status, report) = import(&app, "", shift_jis(&csv)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", report);
+        assert_eq!(report["total_rows"], json!(5));
+        assert_eq!(
+            error_cells(&report),
+            [
+                (2, "ラベルID".to_string()),
+                (6, "ラベルID".to_string()),
+                (7, "物品名".to_string()),
+                (7, "個数".to_string()),
+            ]
+        );
+        app.get("/items?per_page=100").await.expect(200);
+
+        // 文字コードを UTF-8 と指定すると Shift_JIS は読めない
+        let (status, _) = import(&app, "encoding=utf-8", shift_jis(&csv)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // ラベルが同じ物品を更新するモード
+        let valid: String = csv
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| ![5, 6].contains(i))
+            .map(|(_, line)| format!("{}\n", line))
+            .collect();
+        let (status, report) =
+            import(&app, "mode=upsert&dry_run=true&encoding=shift_jis", shift_jis(&valid)).await;
+        assert_eq!(status, StatusCode::OK, "{}", report);
+        assert_eq!(report["errors"], json!([]));
+        assert_eq!(report["updated"], json!(1));
+        assert_eq!(report["created"], json!(4));
+        assert_eq!(report["rows"][1]["new_location"], json!(true));
+
+        let bom = [b"\xEF\xBB\xBF".as_slice(), valid.as_bytes()].concat();
+        let (status, report) = import(&app, "mode=upsert", bom).await;
+        assert_eq!(status, StatusCode::OK, "{}", report);
+        assert_eq!(report["applied"], json!(true));
+        assert_eq!(report["rows"][1]["items"].as_array().unwrap().len(), 3);
+
+        let mic = app.get(&format!("/items/{}", id(&f.mic))).await.expect(200);
+        assert_eq!(mic["name"], json!("ワイヤレスマイク B"));
+        assert_eq!(mic["remarks"], json!("予備あり"));
+        assert_eq!(mic["container_id"], f.rack["id"]);
+        app.get(&format!("/items/{}/transfers", id(&f.mic)))
+            .await
+            .expect(200);
+        app.get("/items?per_page=100").await.expect(200);
+        app.get("/locations").await.expect(200);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn items_import_reports_rows_written_before_a_failure() {
+    let db = TestDatabase::sqlite().await;
+    let app = TestApp::new(&db).await;
+    // 既定の連番（36進数4桁、36^4 = 1679616 個）で発行できるラベルを残り2つにする
+    db.execute("UPDATE label_sequences SET current_value = 1679614 WHERE name = 'default'")
+        .await;
+
+    let csv = "物品名,個数\nケーブル,1\n延長コード,2\n変換アダプタ,1\n";
+    let (status, report) = import(&app, "", csv.as_bytes().to_vec()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", report);
+    assert_eq!(report["applied"], json!(false));
+    assert_eq!(report["applied_rows"], json!(1));
+    assert_eq!(report["errors"].as_array().unwrap().len(), 1);
+    assert_eq!(report["errors"][0]["row"], json!(3));
+    assert_eq!(report["rows"][0]["items"].as_array().unwrap().len(), 1);
+    assert_eq!(report["rows"][1]["items"], json!([]));
+    assert_eq!(report["rows"][2]["items"], json!([]));
+
+    let items = app.get("/items?per_page=100").await.expect(200);
+    assert_eq!(items["total"], json!(1));
+
+    db.drop().await;
+}
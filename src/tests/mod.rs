@@ -0,0 +1,464 @@
+//! HTTP API の統合テスト。
+//!
+//! `main.rs` と同じルーターをメモリ上の SQLite で動かし、`TEST_DATABASE_URL` が
+//! 設定されていれば Postgres でも同じシナリオを流して、すべての応答が一致することを
+//! 確かめる。UUID・時刻・トークンのように実行ごとに変わる値は比較の前に置き換える。
+
+mod api;
+mod backup;
+mod data_migration;
+mod fixtures;
+mod item_import;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::sqlite::SqlitePoolOptions;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::config::{
+    AuthConfig, Config, DatabaseConfig, LoanConfig, LocalStorageConfig, ServerConfig,
+    StorageConfig, StorageType,
+};
+use crate::db::DatabasePool;
+
+const ADMIN_USERNAME: &str = "admin";
+const ADMIN_PASSWORD: &str = "admin-password";
+
+/// マイグレーション済みの使い捨てデータベース
+pub(crate) struct TestDatabase {
+    pub pool: DatabasePool,
+    /// Postgres では `TEST_DATABASE_URL` のデータベースの中にスキーマを作って使う
+    schema: Option<String>,
+}
+
+impl TestDatabase {
+    pub async fn sqlite() -> Self {
+        // `sqlite::memory:` は共有キャッシュなので、プール内の接続は同じデータベースを見る
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        Self::migrated(DatabasePool::Sqlite(pool), None).await
+    }
+
+    /// `TEST_DATABASE_URL` が設定されていなければ None
+    pub async fn postgres() -> Option<Self> {
+        let url = std::env::var("TEST_DATABASE_URL").ok()?;
+        let schema = format!("test_{}", Uuid::new_v4().simple());
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema))
+            .execute(&admin)
+            .await
+            .unwrap();
+        admin.close().await;
+
+        let options = PgConnectOptions::from_str(&url)
+            .unwrap()
+            .options([("search_path", schema.as_str())]);
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .unwrap();
+        Some(Self::migrated(DatabasePool::Postgres(pool), Some(schema)).await)
+    }
+
+    async fn migrated(pool: DatabasePool, schema: Option<String>) -> Self {
+        pool.migrate().await.unwrap();
+        Self { pool, schema }
+    }
+
+    pub fn backend(&self) -> &'static str {
+        match self.pool {
+            DatabasePool::Postgres(_) => "postgres",
+            DatabasePool::Sqlite(_) => "sqlite",
+        }
+    }
+
+    /// SQL をそのまま流す（テストの準備用）
+    pub async fn execute(&self, sql: &str) {
+        match &self.pool {
+            DatabasePool::Postgres(pool) => {
+                sqlx::raw_sql(sql).execute(pool).await.unwrap();
+            }
+            DatabasePool::Sqlite(pool) => {
+                sqlx::raw_sql(sql).execute(pool).await.unwrap();
+            }
+        }
+    }
+
+    pub async fn drop(self) {
+        if let (DatabasePool::Postgres(pool), Some(schema)) = (&self.pool, &self.schema) {
+            sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema))
+                .execute(pool)
+                .await
+                .unwrap();
+            pool.close().await;
+        }
+    }
+}
+
+/// 1回のリクエストと応答
+#[derive(Debug, Clone, PartialEq)]
+struct Exchange {
+    request: String,
+    request_body: Option<Value>,
+    status: u16,
+    body: Value,
+}
+
+/// 応答。JSON でなければ `body` は文字列（バイナリなら長さだけ）になる
+pub(crate) struct Response {
+    pub status: StatusCode,
+    pub body: Value,
+    backend: &'static str,
+    request: String,
+}
+
+impl Response {
+    /// ステータスを確かめて本文を返す
+    #[track_caller]
+    pub fn expect(self, status: u16) -> Value {
+        assert_eq!(
+            self.status.as_u16(),
+            status,
+            "[{}] {} -> {}",
+            self.backend,
+            self.request,
+            self.body
+        );
+        self.body
+    }
+}
+
+/// 1つのバックエンドの上で動くアプリケーション
+#[derive(Clone)]
+pub(crate) struct TestApp {
+    pub backend: &'static str,
+    router: Router,
+    token: String,
+    transcript: Arc<Mutex<Vec<Exchange>>>,
+}
+
+impl TestApp {
+    pub async fn new(db: &TestDatabase) -> Self {
+        // 初期データ（ケーブル色）はマイグレーションの履歴がバックエンドごとに違うので消しておく
+        db.execute(match db.pool {
+            DatabasePool::Postgres(_) => "TRUNCATE cable_colors RESTART IDENTITY",
+            DatabasePool::Sqlite(_) => {
+                "DELETE FROM cable_colors; DELETE FROM sqlite_sequence WHERE name = 'cable_colors'"
+            }
+        })
+        .await;
+        Self::connect(db).await
+    }
+
+    /// データベースの中身には手を付けずにアプリケーションを立ち上げる
+    pub async fn connect(db: &TestDatabase) -> Self {
+        let upload_dir = std::env::temp_dir().join(format!("hyperdashi-test-{}", Uuid::new_v4()));
+        let router = crate::app(&test_config(&upload_dir.to_string_lossy()), db.pool.clone())
+            .await
+            .unwrap();
+        let mut app = Self {
+            backend: db.backend(),
+            router,
+            token: String::new(),
+            transcript: Arc::default(),
+        };
+        app.login().await;
+        app
+    }
+
+    /// 管理者としてログインし直す（リストアでセッションが入れ替わったときなど）
+    pub async fn login(&mut self) {
+        self.login_as(ADMIN_USERNAME, ADMIN_PASSWORD).await;
+    }
+
+    /// 指定したユーザーとしてログインし直す
+    pub async fn login_as(&mut self, username: &str, password: &str) {
+        self.token.clear();
+        let login = self
+            .post(
+                "/auth/login",
+                serde_json::json!({ "username": username, "password": password }),
+            )
+            .await
+            .expect(200);
+        self.token = login["token"].as_str().unwrap().to_string();
+    }
+
+    pub async fn get(&self, path: &str) -> Response {
+        self.send(Method::GET, path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> Response {
+        self.send(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> Response {
+        self.send(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> Response {
+        self.send(Method::DELETE, path, None).await
+    }
+
+    pub async fn delete_with(&self, path: &str, body: Value) -> Response {
+        self.send(Method::DELETE, path, Some(body)).await
+    }
+
+    /// JSON 以外の本文を送り、応答の本文をそのまま返す（比較の対象にはしない）
+    pub async fn send_raw(
+        &self,
+        method: Method,
+        path: &str,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(format!("/api/v1{}", path))
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, bytes.to_vec())
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<Value>) -> Response {
+        let uri = format!("/api/v1{}", path);
+        let mut request = Request::builder().method(method.clone()).uri(&uri);
+        if !self.token.is_empty() {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", self.token));
+        }
+        let request = match &body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let response_body = if bytes.is_empty() {
+            Value::Null
+        } else if let Ok(json) = serde_json::from_slice(&bytes) {
+            json
+        } else if let Ok(text) = std::str::from_utf8(&bytes) {
+            Value::String(text.to_string())
+        } else {
+            Value::String(format!("<{} bytes>", bytes.len()))
+        };
+
+        let request = format!("{} {}", method, path);
+        self.transcript.lock().unwrap().push(Exchange {
+            request: request.clone(),
+            request_body: body,
+            status: status.as_u16(),
+            body: response_body.clone(),
+        });
+        Response {
+            status,
+            body: response_body,
+            backend: self.backend,
+            request,
+        }
+    }
+
+    fn normalized_transcript(&self) -> Vec<Exchange> {
+        let mut normalizer = Normalizer::default();
+        self.transcript
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|exchange| Exchange {
+                request: normalizer.text(&exchange.request),
+                request_body: exchange.request_body.as_ref().map(|v| normalizer.value(v)),
+                status: exchange.status,
+                body: normalizer.value(&exchange.body),
+            })
+            .collect()
+    }
+}
+
+fn test_config(upload_dir: &str) -> Config {
+    Config {
+        database: DatabaseConfig { url: String::new() },
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            cors_allowed_origins: Vec::new(),
+        },
+        storage: StorageConfig {
+            storage_type: StorageType::Local,
+            local: Some(LocalStorageConfig {
+                path: upload_dir.to_string(),
+            }),
+            s3: None,
+            max_file_size_mb: 5,
+            max_restore_size_mb: 64,
+        },
+        auth: AuthConfig {
+            admin_username: Some(ADMIN_USERNAME.to_string()),
+            admin_password: Some(ADMIN_PASSWORD.to_string()),
+            ..AuthConfig::default()
+        },
+        loan: LoanConfig::default(),
+    }
+}
+
+static UUID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}")
+        .unwrap()
+});
+static TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?").unwrap()
+});
+
+/// 実行ごとに変わる値を置き換える。UUID は現れた順に番号を振るので、
+/// 同じ UUID を指しているかどうかは比較に残る
+#[derive(Default)]
+struct Normalizer {
+    uuids: HashMap<String, usize>,
+}
+
+impl Normalizer {
+    fn text(&mut self, text: &str) -> String {
+        let text = UUID.replace_all(text, |caps: &regex::Captures| {
+            let next = self.uuids.len() + 1;
+            let n = *self.uuids.entry(caps[0].to_lowercase()).or_insert(next);
+            format!("<uuid {}>", n)
+        });
+        TIMESTAMP.replace_all(&text, "<time>").into_owned()
+    }
+
+    fn value(&mut self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.text(text)),
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.value(v)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, v)| {
+                        let v = match key.as_str() {
+                            "token" | "password_hash" => Value::String("<secret>".to_string()),
+                            _ => self.value(v),
+                        };
+                        (key.clone(), v)
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+/// シナリオを SQLite で流し、Postgres が使えればそこでも流して応答を突き合わせる
+pub(crate) async fn assert_same_on_every_backend<F, Fut>(scenario: F)
+where
+    F: Fn(TestApp) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let sqlite_db = TestDatabase::sqlite().await;
+    let sqlite = TestApp::new(&sqlite_db).await;
+    scenario(sqlite.clone()).await;
+
+    let Some(postgres_db) = TestDatabase::postgres().await else {
+        return;
+    };
+    let postgres = TestApp::new(&postgres_db).await;
+    scenario(postgres.clone()).await;
+
+    let expected = sqlite.normalized_transcript();
+    let actual = postgres.normalized_transcript();
+    for (i, (sqlite, postgres)) in expected.iter().zip(&actual).enumerate() {
+        if let Some(difference) = first_difference(sqlite, postgres) {
+            panic!(
+                "response #{} to {} differs between backends: {}\n--- sqlite\n{}\n--- postgres\n{}",
+                i,
+                sqlite.request,
+                difference,
+                pretty(sqlite),
+                pretty(postgres)
+            );
+        }
+    }
+    assert_eq!(expected.len(), actual.len());
+    postgres_db.drop().await;
+}
+
+fn first_difference(sqlite: &Exchange, postgres: &Exchange) -> Option<String> {
+    if sqlite.request != postgres.request {
+        return Some(format!(
+            "request {} != {}",
+            sqlite.request, postgres.request
+        ));
+    }
+    if sqlite.status != postgres.status {
+        return Some(format!("status {} != {}", sqlite.status, postgres.status));
+    }
+    value_difference("$", &sqlite.body, &postgres.body)
+}
+
+/// 最初に食い違った場所（`$.items[0].name` の形）と両方の値
+fn value_difference(path: &str, sqlite: &Value, postgres: &Value) -> Option<String> {
+    match (sqlite, postgres) {
+        (Value::Object(a), Value::Object(b)) => a
+            .keys()
+            .chain(b.keys().filter(|key| !a.contains_key(*key)))
+            .find_map(|key| {
+                value_difference(
+                    &format!("{}.{}", path, key),
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                )
+            }),
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (a, b))| value_difference(&format!("{}[{}]", path, i), a, b)),
+        (a, b) if a == b => None,
+        (a, b) => Some(format!("{}: {} != {}", path, a, b)),
+    }
+}
+
+fn pretty(exchange: &Exchange) -> String {
+    format!(
+        "{} {}\n{}\n{}",
+        exchange.request,
+        exchange
+            .request_body
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or_default(),
+        exchange.status,
+        serde_json::to_string_pretty(&exchange.body).unwrap()
+    )
+}